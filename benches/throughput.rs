@@ -0,0 +1,62 @@
+//! Throughput of a batch screening with and without statistics, run with
+//! `cargo bench --bench throughput`. Without statistics, the search takes
+//! the path that neither times nor records anything, which this compares
+//! against full statistics kept in memory.
+
+use std::time::{Duration, Instant};
+
+use dqg::{
+    run_with, Graph, InMemoryStatistics, NoStatistics, Settings, Statistics, StatisticsLevel,
+    StatisticsSink,
+};
+
+const ROUNDS: usize = 5;
+
+/// Prisms, i.e. two cycles of the same length with their vertices matched.
+fn batch() -> Vec<Graph> {
+    (3..40)
+        .map(|length| {
+            let edges = (0..length).flat_map(|vertex| {
+                let next = (vertex + 1) % length;
+                [
+                    (vertex, next),
+                    (vertex + length, next + length),
+                    (vertex, vertex + length),
+                ]
+            });
+            Graph::from_edges(2 * length as usize, edges).expect("Invalid prism")
+        })
+        .collect()
+}
+
+fn screen(graphs: &[Graph], statistics: impl Fn(&Graph) -> Box<dyn StatisticsSink>) -> Duration {
+    let start = Instant::now();
+    for graph in graphs {
+        let settings = Settings {
+            iter_powerset: true,
+            ..Default::default()
+        };
+        run_with(graph.clone(), settings, statistics(graph)).expect("Screening failed");
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let graphs = batch();
+    for round in 1..=ROUNDS {
+        let without = screen(&graphs, |_| Box::new(NoStatistics));
+        let with = screen(&graphs, |graph| {
+            Box::new(InMemoryStatistics(Statistics::new(
+                StatisticsLevel::Full,
+                graph.size(),
+            )))
+        });
+        println!(
+            "round {}: {} graphs in {:?} without and {:?} with statistics",
+            round,
+            graphs.len(),
+            without,
+            with
+        );
+    }
+}
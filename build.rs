@@ -0,0 +1,14 @@
+//! Generates the C header of the `capi` feature.
+
+#[cfg(feature = "capi")]
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::generate(&crate_dir)
+        .expect("Couldn't generate the C header")
+        .write_to_file(format!("{}/include/dqg.h", crate_dir));
+}
+
+#[cfg(not(feature = "capi"))]
+fn main() {}
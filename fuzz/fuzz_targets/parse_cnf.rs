@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::parsers::parse_cnf_input;
+
+mod size_limit;
+
+fuzz_target!(|data: &[u8]| {
+    if !size_limit::exceeds_size_limit(data) {
+        let _ = parse_cnf_input(data);
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::parsers::parse_csv_input;
+
+// The graph size is given on the command line for csv files,
+// so the first byte of the input is used as the graph size.
+fuzz_target!(|data: &[u8]| {
+    if let Some((graph_size, csv)) = data.split_first() {
+        let _ = parse_csv_input(*graph_size as usize, csv);
+    }
+});
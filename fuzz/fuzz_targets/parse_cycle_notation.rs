@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::Permutation;
+
+// The first byte of the input is used as the permutation size, the rest
+// as cycle notation. Parsed permutations must be safe to decompose into cycles.
+fuzz_target!(|data: &[u8]| {
+    if let Some((size, notation)) = data.split_first() {
+        if let Ok(notation) = std::str::from_utf8(notation) {
+            if let Ok(mut permutation) =
+                Permutation::from_cycle_notation(notation, Some(*size as usize))
+            {
+                let _ = permutation.get_cycles();
+            }
+        }
+    }
+});
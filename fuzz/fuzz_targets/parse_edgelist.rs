@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::parsers::parse_edgelist_input;
+
+// The graph size is given on the command line for edge lists,
+// so the first byte of the input is used as the graph size.
+fuzz_target!(|data: &[u8]| {
+    if let Some((graph_size, edges)) = data.split_first() {
+        let _ = parse_edgelist_input(*graph_size as usize, edges);
+    }
+});
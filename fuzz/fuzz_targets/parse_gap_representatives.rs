@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::parsers::parse_representatives;
+
+// The permutation size is the graph size in DQG,
+// so the first byte of the input is used as the size.
+fuzz_target!(|data: &[u8]| {
+    if let Some((size, representatives)) = data.split_first() {
+        let _ = parse_representatives(representatives, *size as usize);
+    }
+});
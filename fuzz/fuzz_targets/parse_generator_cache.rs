@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use dqg::parsers::parse_generator_cache;
+
+// The graph size is known before the cache file is read,
+// so the first byte of the input is used as the graph size.
+fuzz_target!(|data: &[u8]| {
+    if let Some((size, cache)) = data.split_first() {
+        if let Ok(cache) = std::str::from_utf8(cache) {
+            if let Some(mut cached) = parse_generator_cache(cache, *size as usize) {
+                cached.generators.iter_mut().for_each(|generator| {
+                    generator.get_cycles();
+                });
+            }
+        }
+    }
+});
@@ -0,0 +1,21 @@
+//! The dreadnaut, txt and CNF inputs declare the size of the graph, which
+//! is allocated before the rest of the input is read. Large sizes are valid,
+//! e.g. for isolated vertices, but only exhaust the memory of the fuzzer.
+
+/// Largest number in an input that is still parsed.
+const MAX_NUMBER: u64 = 1 << 16;
+
+/// Whether the input contains a number above `MAX_NUMBER`, e.g. as its size.
+pub fn exceeds_size_limit(data: &[u8]) -> bool {
+    data.split(|byte| !byte.is_ascii_digit()).any(|digits| {
+        let significant = digits
+            .iter()
+            .position(|digit| *digit != b'0')
+            .map_or(&[][..], |start| &digits[start..]);
+        significant.len() > 6
+            || significant
+                .iter()
+                .fold(0, |number, digit| number * 10 + u64::from(digit - b'0'))
+                > MAX_NUMBER
+    })
+}
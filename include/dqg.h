@@ -0,0 +1,61 @@
+/* Generated by cbindgen from src/capi.rs, don't edit. */
+
+#ifndef DQG_H
+#define DQG_H
+
+#include <stddef.h>
+
+/**
+ * Result of `dqg_check_descriptive`.
+ */
+typedef enum DqgStatus {
+  /**
+   * The quotient of the automorphism group is descriptive.
+   */
+  DQG_STATUS_DESCRIPTIVE = 0,
+  /**
+   * The quotient of the automorphism group isn't descriptive.
+   */
+  DQG_STATUS_NON_DESCRIPTIVE = 1,
+  /**
+   * The graph has no non-trivial automorphisms.
+   */
+  DQG_STATUS_ASYMMETRIC = 2,
+  /**
+   * An edge refers to a vertex outside of the graph or an array is missing.
+   */
+  DQG_STATUS_INVALID_INPUT = 3,
+  /**
+   * The check failed otherwise, e.g. kissat reported an error.
+   */
+  DQG_STATUS_ERROR = 4,
+} DqgStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Check whether the quotient of the automorphism group of the undirected
+ * graph with the vertices 0..vertex_number is descriptive. The automorphisms
+ * respect the colours of the vertices if they are given. Unless the input is
+ * invalid or the check fails, `orbits` is filled with the smallest vertex of
+ * the orbit of each vertex.
+ *
+ * # Safety
+ * `edges` must point to `2 * edge_number` vertices, with the start and end of
+ * each edge next to each other. `colours` must be null or point to
+ * `vertex_number` colours and `orbits` must be null or point to space for
+ * `vertex_number` vertices.
+ */
+enum DqgStatus dqg_check_descriptive(size_t vertex_number,
+                                     const int *edges,
+                                     size_t edge_number,
+                                     const int *colours,
+                                     int *orbits);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* DQG_H */
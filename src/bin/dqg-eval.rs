@@ -0,0 +1,97 @@
+#![warn(rust_2018_idioms)]
+//! Evaluates the logs of the quotientPlanning tool,
+//! independent of the graph input of dqg itself.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+use dqg::{
+    evaluation::{
+        append_feedback, evaluate_log_file, evaluate_logs, expand_log_paths, write_log_csv,
+        LogSchema,
+    },
+    read_graph_file, Error,
+};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "DQG evaluation")]
+struct EvaluationOptions {
+    /// Log files of the quotientPlanning tool. The file names
+    /// may contain the wildcards `*` and `?`, e.g. `logs/*.log`.
+    #[structopt(required = true)]
+    logs: Vec<String>,
+    /// TOML file with the log lines of the quotientPlanning
+    /// tool to look for, if they differ from the defaults.
+    #[structopt(long, parse(from_os_str))]
+    log_schema: Option<PathBuf>,
+    /// Print one csv row per log file and metric instead
+    /// of comparing the metrics to the baseline.
+    #[structopt(long)]
+    csv: bool,
+    /// Append the plan length and time to plan of each metric to this
+    /// csv file, keyed by the graph of the planning task, as feedback
+    /// for `dqg --metric-feedback`.
+    #[structopt(long, parse(from_os_str), requires = "graph")]
+    evaluation_csv: Option<PathBuf>,
+    /// The graph of the planning task the logs belong to.
+    #[structopt(long, parse(from_os_str))]
+    graph: Option<PathBuf>,
+    /// Graph size for graph files which don't contain it.
+    #[structopt(short = "-n", long)]
+    graph_size: Option<usize>,
+}
+
+#[cfg(not(tarpaulin_include))]
+fn run() -> Result<(), Error> {
+    let options = EvaluationOptions::from_args();
+    let schema = match options.log_schema {
+        Some(schema_path) => LogSchema::from_file(&schema_path)?,
+        None => LogSchema::default(),
+    };
+    let graph = match options.graph {
+        Some(graph_path) => Some(read_graph_file(&graph_path, options.graph_size)?),
+        None => None,
+    };
+
+    let mut all_logs = Vec::new();
+    for path in expand_log_paths(&options.logs)? {
+        let reader = BufReader::new(File::open(&path)?);
+        let logs = evaluate_log_file(&mut reader.lines(), &schema);
+        if let (Some(csv_path), Some(graph)) = (options.evaluation_csv.as_ref(), graph.as_ref()) {
+            append_feedback(csv_path, &logs, graph)?;
+        }
+        all_logs.push((path, logs));
+    }
+
+    if options.csv {
+        write_log_csv(&mut io::stdout().lock(), &all_logs)
+    } else {
+        let several = all_logs.len() > 1;
+        for (path, logs) in all_logs {
+            if several {
+                println!("{}:", path.display());
+            }
+            evaluate_logs(logs);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn main() {
+    let exit_code = match run() {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            error.exit_code()
+        }
+    };
+
+    // Exiting directly doesn't flush stdout.
+    std::io::Write::flush(&mut std::io::stdout()).ok();
+    std::process::exit(exit_code);
+}
@@ -0,0 +1,110 @@
+//! C interface for planners that call DQG in-process, built with the `capi`
+//! feature. The header `include/dqg.h` is generated from this file by cbindgen
+//! during the build. Graphs are passed as arrays, so no Rust type crosses the
+//! boundary and nothing needs to be freed by the caller.
+
+use std::{os::raw::c_int, panic, slice};
+
+use crate::{
+    full_quotient::{is_full_quotient_descriptive, DescriptiveOutcome},
+    graph::{Colour, Graph, VertexIndex},
+    quotient::{empty_orbits, Orbits},
+    Error,
+};
+
+/// Result of `dqg_check_descriptive`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DqgStatus {
+    /// The quotient of the automorphism group is descriptive.
+    Descriptive = 0,
+    /// The quotient of the automorphism group isn't descriptive.
+    NonDescriptive = 1,
+    /// The graph has no non-trivial automorphisms.
+    Asymmetric = 2,
+    /// An edge refers to a vertex outside of the graph or an array is missing.
+    InvalidInput = 3,
+    /// The check failed otherwise, e.g. kissat reported an error.
+    Error = 4,
+}
+
+fn check_descriptive(
+    vertex_number: usize,
+    edges: &[VertexIndex],
+    colours: Option<&[Colour]>,
+) -> Result<(DqgStatus, Orbits), Error> {
+    let mut graph = Graph::from_edges(
+        vertex_number,
+        edges.chunks_exact(2).map(|edge| (edge[0], edge[1])),
+    )?;
+    if let Some(colours) = colours {
+        graph.set_colours(colours)?;
+    }
+    graph.minimize();
+
+    Ok(match is_full_quotient_descriptive(&graph)? {
+        DescriptiveOutcome::Asymmetric => (DqgStatus::Asymmetric, empty_orbits(vertex_number)),
+        DescriptiveOutcome::Descriptive { orbits, .. } => (DqgStatus::Descriptive, orbits),
+        DescriptiveOutcome::NonDescriptive { orbits, .. } => (DqgStatus::NonDescriptive, orbits),
+    })
+}
+
+/// Check whether the quotient of the automorphism group of the undirected
+/// graph with the vertices 0..vertex_number is descriptive. The automorphisms
+/// respect the colours of the vertices if they are given. Unless the input is
+/// invalid or the check fails, `orbits` is filled with the smallest vertex of
+/// the orbit of each vertex.
+///
+/// # Safety
+/// `edges` must point to `2 * edge_number` vertices, with the start and end of
+/// each edge next to each other. `colours` must be null or point to
+/// `vertex_number` colours and `orbits` must be null or point to space for
+/// `vertex_number` vertices.
+#[no_mangle]
+pub unsafe extern "C" fn dqg_check_descriptive(
+    vertex_number: usize,
+    edges: *const c_int,
+    edge_number: usize,
+    colours: *const c_int,
+    orbits: *mut c_int,
+) -> DqgStatus {
+    let edges = match edge_number.checked_mul(2) {
+        Some(0) => &[][..],
+        Some(length) if !edges.is_null() => slice::from_raw_parts(edges, length),
+        _ => return DqgStatus::InvalidInput,
+    };
+    let colours = (!colours.is_null()).then(|| slice::from_raw_parts(colours, vertex_number));
+
+    // Unwinding into the caller is undefined behaviour.
+    match panic::catch_unwind(|| check_descriptive(vertex_number, edges, colours)) {
+        Ok(Ok((status, found_orbits))) => {
+            if !orbits.is_null() {
+                slice::from_raw_parts_mut(orbits, vertex_number).copy_from_slice(&found_orbits);
+            }
+            status
+        }
+        Ok(Err(Error::GraphError(_))) => DqgStatus::InvalidInput,
+        Ok(Err(_)) | Err(_) => DqgStatus::Error,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_invalid_input() {
+        let edges = [0, 1, 1, 3];
+        let mut orbits = [-1; 3];
+        let status = unsafe {
+            dqg_check_descriptive(3, edges.as_ptr(), 2, ptr::null(), orbits.as_mut_ptr())
+        };
+        assert_eq!(DqgStatus::InvalidInput, status);
+        assert_eq!([-1; 3], orbits);
+
+        let status =
+            unsafe { dqg_check_descriptive(3, ptr::null(), 1, ptr::null(), ptr::null_mut()) };
+        assert_eq!(DqgStatus::InvalidInput, status);
+    }
+}
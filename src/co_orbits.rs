@@ -0,0 +1,124 @@
+//! How stable the orbit structure is across the quotients of the subsets of
+//! generators: pairs of vertices that share an orbit in most quotients are
+//! a robust part of the symmetry, e.g. to choose a stable abstraction.
+
+use itertools::Itertools;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{encoding::HighLevelEncoding, graph::VertexIndex, quotient::Orbits, Error};
+
+/// Counts for each pair of vertices in how many of the recorded
+/// quotients they are in the same orbit. Only pairs that shared
+/// an orbit at least once are stored, but each orbit adds all of
+/// its pairs, i.e. quadratically many in its size.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CoOrbitFrequencies {
+    quotients: usize,
+    /// Keyed by the smaller vertex first.
+    pairs: HashMap<(VertexIndex, VertexIndex), usize>,
+}
+
+impl CoOrbitFrequencies {
+    /// Count the pairs of vertices in the same orbit of another quotient.
+    pub fn record(&mut self, orbits: &Orbits) {
+        self.quotients += 1;
+        for (_, vertices) in orbits.encode_high() {
+            for pair in vertices.into_iter().tuple_combinations() {
+                *self.pairs.entry(pair).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Number of recorded quotients.
+    pub fn quotients(&self) -> usize {
+        self.quotients
+    }
+
+    /// In how many of the recorded quotients the vertices share an orbit.
+    pub fn count(&self, first: VertexIndex, second: VertexIndex) -> usize {
+        let pair = (first.min(second), first.max(second));
+        self.pairs.get(&pair).copied().unwrap_or(0)
+    }
+
+    /// The share of the recorded quotients in which the vertices share an orbit.
+    pub fn frequency(&self, first: VertexIndex, second: VertexIndex) -> f64 {
+        if self.quotients == 0 {
+            return 0.0;
+        }
+        self.count(first, second) as f64 / self.quotients as f64
+    }
+
+    /// Number of pairs that shared an orbit in any and in all recorded quotients.
+    pub fn summary(&self) -> (usize, usize) {
+        let stable = self
+            .pairs
+            .values()
+            .filter(|count| **count == self.quotients)
+            .count();
+        (self.pairs.len(), stable)
+    }
+
+    /// Write the pairs that shared an orbit at least once as csv, ordered by
+    /// the vertices. Pairs that are missing never shared an orbit.
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writeln!(writer, "vertex_1,vertex_2,count,frequency")?;
+        for ((first, second), count) in self.pairs.iter().sorted() {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                first,
+                second,
+                count,
+                *count as f64 / self.quotients as f64
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write the co-orbit frequencies as csv to the file.
+#[cfg(not(tarpaulin_include))]
+pub fn write_co_orbit_file(path: &Path, frequencies: &CoOrbitFrequencies) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    frequencies.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_co_orbit_frequencies() -> Result<(), Error> {
+        let mut frequencies = CoOrbitFrequencies::default();
+        assert_eq!(0.0, frequencies.frequency(0, 1));
+
+        frequencies.record(&vec![0, 0, 0, 3]);
+        frequencies.record(&vec![0, 1, 0, 3]);
+        frequencies.record(&vec![0, 1, 2, 1]);
+        frequencies.record(&vec![0, 1, 0, 3]);
+
+        assert_eq!(4, frequencies.quotients());
+        assert_eq!(3, frequencies.count(2, 0));
+        assert_eq!(0.75, frequencies.frequency(0, 2));
+        assert_eq!(0.25, frequencies.frequency(1, 3));
+        assert_eq!(0, frequencies.count(0, 3));
+        assert_eq!((4, 0), frequencies.summary());
+
+        let mut output = Vec::new();
+        frequencies.write(&mut output)?;
+        assert_eq!(
+            "vertex_1,vertex_2,count,frequency\n0,1,1,0.25\n0,2,3,0.75\n1,2,1,0.25\n1,3,1,0.25\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+}
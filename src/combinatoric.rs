@@ -0,0 +1,248 @@
+//! Enumeration of the subsets of generators.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{
+    permutation::Permutation,
+    quotient::{count_orbits, generate_orbits_of_subset},
+    Settings,
+};
+
+/// Number of subsets that are queued at once by `QueuedPowerset`.
+pub const QUEUE_BATCH: usize = 1 << 12;
+
+/// Enumerates the non-empty subsets of `0..n` in the same order as
+/// `itertools::powerset` (by size, then lexicographically), but keeps only
+/// the current subset as indices and reuses it instead of allocating each subset.
+#[derive(Debug, Clone)]
+pub struct Powerset {
+    n: usize,
+    min_size: usize,
+    max_size: usize,
+    subset: Vec<usize>,
+    started: bool,
+}
+
+impl Powerset {
+    /// Only enumerate the subsets with at least `min_size` and at most `max_size` elements.
+    pub fn with_sizes(n: usize, min_size: usize, max_size: usize) -> Self {
+        Powerset {
+            n,
+            min_size: min_size.max(1),
+            max_size: max_size.min(n),
+            subset: Vec::with_capacity(n),
+            started: false,
+        }
+    }
+
+    /// The subsets of `n` generators within the size bounds of the settings.
+    pub fn bounded_by(n: usize, settings: &Settings) -> Self {
+        Self::with_sizes(
+            n,
+            settings.min_subset_size,
+            settings.max_subset_size.unwrap_or(n),
+        )
+    }
+
+    /// The next subset in ascending order of its indices,
+    /// borrowed until the next call. None once all subsets were enumerated.
+    pub fn next_subset(&mut self) -> Option<&[usize]> {
+        let size = self.subset.len();
+
+        if !self.started {
+            self.started = true;
+            if self.min_size <= self.max_size {
+                self.subset.extend(0..self.min_size);
+            }
+        } else if size == 0 {
+            // Nothing to enumerate at all.
+            return None;
+        } else if let Some(position) =
+            (0..size).rfind(|position| self.subset[*position] < self.n - size + position)
+        {
+            // Next combination of the same size.
+            self.subset[position] += 1;
+            for next in position + 1..size {
+                self.subset[next] = self.subset[next - 1] + 1;
+            }
+        } else if size < self.max_size {
+            // First combination of the next size.
+            self.subset.clear();
+            self.subset.extend(0..=size);
+        } else {
+            return None;
+        }
+
+        (!self.subset.is_empty()).then_some(self.subset.as_slice())
+    }
+}
+
+/// Pending subsets of generators, ordered by the number of orbits that they
+/// generate with the fewest first, and in the order they were pushed otherwise.
+/// Counting the orbits is cheap compared to encoding and solving the quotient,
+/// so it estimates the metrics: the most promising quotients are checked first
+/// and the quotient found before a timeout is the best one so far.
+#[derive(Debug, Default)]
+pub struct SubsetQueue {
+    queue: BinaryHeap<Reverse<(usize, usize, Vec<usize>)>>,
+    pushed: usize,
+    current: Vec<usize>,
+}
+
+impl SubsetQueue {
+    pub fn push(&mut self, subset: &[usize], orbit_number: usize) {
+        self.queue
+            .push(Reverse((orbit_number, self.pushed, subset.to_vec())));
+        self.pushed += 1;
+    }
+
+    /// The pending subset with the fewest orbits,
+    /// borrowed until the next call. None once the queue is empty.
+    pub fn next_subset(&mut self) -> Option<&[usize]> {
+        let Reverse((_, _, subset)) = self.queue.pop()?;
+        self.current = subset;
+        Some(self.current.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// The subsets of the powerset, queued by their number of orbits in batches
+/// of the powerset order. The first quotients are thus checked before the
+/// orbits of all subsets are counted, e.g. to find some quotient before a timeout.
+#[derive(Debug)]
+pub struct QueuedPowerset {
+    powerset: Powerset,
+    generators: Vec<Permutation>,
+    queue: SubsetQueue,
+    batch: usize,
+}
+
+impl QueuedPowerset {
+    pub fn new(powerset: Powerset, generators: Vec<Permutation>, batch: usize) -> Self {
+        QueuedPowerset {
+            powerset,
+            generators,
+            queue: SubsetQueue::default(),
+            batch: batch.max(1),
+        }
+    }
+
+    /// The pending subset with the fewest orbits of the current batch,
+    /// borrowed until the next call. None once all subsets were checked.
+    pub fn next_subset(&mut self) -> Option<&[usize]> {
+        if self.queue.is_empty() {
+            for _ in 0..self.batch {
+                let Some(subset) = self.powerset.next_subset() else {
+                    break;
+                };
+                let orbits = generate_orbits_of_subset(&mut self.generators, subset);
+                self.queue.push(subset, count_orbits(&orbits));
+            }
+        }
+        self.queue.next_subset()
+    }
+}
+
+/// The subsets of generators that the powerset mode checks,
+/// either in the order of the powerset or by their number of orbits.
+#[derive(Debug)]
+pub enum PendingSubsets {
+    Powerset(Powerset),
+    Queue(QueuedPowerset),
+}
+
+impl PendingSubsets {
+    pub fn next_subset(&mut self) -> Option<&[usize]> {
+        match self {
+            Self::Powerset(powerset) => powerset.next_subset(),
+            Self::Queue(queue) => queue.next_subset(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_powerset_order() {
+        for n in 0..7 {
+            let mut powerset = Powerset::with_sizes(n, 1, n);
+            let mut subsets = Vec::new();
+            while let Some(subset) = powerset.next_subset() {
+                subsets.push(subset.to_vec());
+            }
+            let expected = (0..n).powerset().skip(1).collect_vec();
+            assert_eq!(expected, subsets);
+
+            // Stays exhausted.
+            assert!(powerset.next_subset().is_none());
+        }
+    }
+
+    #[test]
+    fn test_powerset_sizes() {
+        let mut powerset = Powerset::with_sizes(4, 2, 3);
+        let mut subsets = Vec::new();
+        while let Some(subset) = powerset.next_subset() {
+            subsets.push(subset.to_vec());
+        }
+        let expected = (0..4)
+            .powerset()
+            .filter(|subset| (2..=3).contains(&subset.len()))
+            .collect_vec();
+        assert_eq!(expected, subsets);
+
+        assert!(Powerset::with_sizes(4, 3, 2).next_subset().is_none());
+        assert!(Powerset::with_sizes(4, 5, 6).next_subset().is_none());
+        assert_eq!(Some(&[0][..]), Powerset::with_sizes(4, 0, 1).next_subset());
+    }
+
+    #[test]
+    fn test_subset_queue() {
+        let mut queue = SubsetQueue::default();
+        assert!(queue.next_subset().is_none());
+
+        queue.push(&[0], 5);
+        queue.push(&[1], 3);
+        queue.push(&[0, 1], 2);
+        queue.push(&[2], 3);
+
+        let mut subsets = Vec::new();
+        while let Some(subset) = queue.next_subset() {
+            subsets.push(subset.to_vec());
+        }
+        // Ties are checked in the order they were pushed.
+        assert_eq!(vec![vec![0, 1], vec![1], vec![2], vec![0]], subsets);
+    }
+
+    #[test]
+    fn test_queued_powerset() {
+        // (0 1), (0 1 2 3) and (2 3) with 3, 1 and 3 orbits on their own.
+        let generators = vec![
+            Permutation::new(vec![1, 0, 2, 3]).unwrap(),
+            Permutation::new(vec![1, 2, 3, 0]).unwrap(),
+            Permutation::new(vec![0, 1, 3, 2]).unwrap(),
+        ];
+        let mut queued = QueuedPowerset::new(Powerset::with_sizes(3, 1, 3), generators, 2);
+        let mut subsets = Vec::new();
+        while let Some(subset) = queued.next_subset() {
+            subsets.push(subset.to_vec());
+        }
+        // Each batch of two subsets is sorted by the number of orbits on its own.
+        let expected = vec![
+            vec![1],
+            vec![0],
+            vec![0, 1],
+            vec![2],
+            vec![1, 2],
+            vec![0, 2],
+            vec![0, 1, 2],
+        ];
+        assert_eq!(expected, subsets);
+    }
+}
@@ -1,59 +1,268 @@
 //! Different methods to destroy non-descriptive cores.
 
 use itertools::Itertools;
-use std::time::{Duration, Instant};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    debug::print_orbits_nauty_style,
-    do_if_some,
-    encoding::{encode_problem, OrbitEncoding},
-    graph::Graph,
+    debug::{orbits_nauty_style, orbits_with_ids, print_orbits},
+    encoding::{
+        encode_problem, Clause, EncodeOutcome, OrbitEncoding, QuotientGraphEncoding,
+        SATEncodingDictionary,
+    },
+    graph::{Graph, VertexIndex},
+    interrupt::check_interrupted,
     misc::CoreMetric,
     permutation::Permutation,
-    quotient::{compute_generators, empty_orbits, generate_orbits, QuotientGraph},
-    sat_solving::solve_mus_kitten,
-    statistics::QuotientStatistics,
-    time, time_assign, Error, Settings,
+    progress::ProgressEvent,
+    quotient::{
+        compute_generators, empty_orbits, generate_orbits, AutomorphismGraph, Orbits,
+        QuotientGraph, TrivialPartition,
+    },
+    sat_solving::solve_mus_kitten_multiple,
+    statistics::{
+        CoreIteration, QuotientInvariants, QuotientStatistics, Statistics, StatisticsSink,
+    },
+    time, time_assign, Error, Outcome, Settings,
 };
 
+/// What core mode did, printed as a summary at the end.
+#[derive(Debug, Default)]
+struct CoreSummary {
+    initial_generators: Option<usize>,
+    /// Only known if statistics are collected.
+    initial_group_size: Option<f64>,
+    iterations: usize,
+    recoloured_vertices: usize,
+    /// Generators merged into others or removed as their power became the identity.
+    dropped_generators: usize,
+    /// Number of orbits of the descriptive quotient, if one was found.
+    final_orbits: Option<usize>,
+    /// Time spent finding cores with kitten.
+    solver_time: Duration,
+}
+
+impl CoreSummary {
+    /// Record the generators of the original automorphism group.
+    fn log_initial_group(
+        &mut self,
+        generators: usize,
+        settings: &Settings,
+        statistics: &mut dyn StatisticsSink,
+    ) {
+        if self.initial_generators.is_none() {
+            self.initial_generators = Some(generators);
+            settings.report_progress(|| ProgressEvent::GeneratorsComputed { n: generators });
+            self.initial_group_size = statistics
+                .statistics()
+                .map(|statistics| statistics.get_group_size());
+        }
+    }
+
+    fn log_descriptive(&mut self, orbits: &[VertexIndex]) {
+        self.final_orbits = Some(orbits.iter().filter(|orbit| **orbit >= 0).unique().count());
+    }
+}
+
+impl fmt::Display for CoreSummary {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Core mode summary:")?;
+        if let Some(generators) = self.initial_generators {
+            writeln!(f, "  initial generators: {}", generators)?;
+        }
+        if let Some(group_size) = self.initial_group_size {
+            writeln!(f, "  initial group size: {:e}", group_size)?;
+        }
+        writeln!(f, "  iterations: {}", self.iterations)?;
+        writeln!(f, "  recoloured vertices: {}", self.recoloured_vertices)?;
+        writeln!(
+            f,
+            "  merged or removed generators: {}",
+            self.dropped_generators
+        )?;
+        match self.final_orbits {
+            Some(orbits) => writeln!(f, "  final orbits: {}", orbits)?,
+            None => writeln!(f, "  final orbits: no descriptive quotient found")?,
+        }
+        write!(f, "  SAT/MUS time: {:.3}s", self.solver_time.as_secs_f64())
+    }
+}
+
+/// Record the symmetry that the strategy left in this iteration.
+fn log_core_iteration(
+    statistics: &mut dyn StatisticsSink,
+    strategy: CoreMetric,
+    generators: usize,
+    orbits: &[VertexIndex],
+) {
+    statistics.log(|stats| {
+        stats.log_core_iteration(CoreIteration::new(strategy.name(), generators, orbits))
+    });
+}
+
+/// Join cores of the same quotient into a single core,
+/// so that a strategy breaks all of them at once.
+fn join_cores(cores: Vec<Vec<OrbitEncoding>>) -> Vec<OrbitEncoding> {
+    cores
+        .into_iter()
+        .flatten()
+        .sorted_by_key(|(orbit, _)| *orbit)
+        .dedup_by(|(first, _), (second, _)| first == second)
+        .collect()
+}
+
+/// Find up to `cores_per_quotient` cores of the non-descriptive quotient,
+/// log all of them and join them. None if the quotient is descriptive.
+#[cfg(not(tarpaulin_include))]
+fn next_core(
+    formula: impl Iterator<Item = Clause>,
+    quotient_graph: &QuotientGraph,
+    graph: &Graph,
+    dict: SATEncodingDictionary,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<Option<Vec<OrbitEncoding>>, Error> {
+    time!(
+        solver_time,
+        cores,
+        solve_mus_kitten_multiple(
+            formula,
+            quotient_graph,
+            graph,
+            dict,
+            &settings.encoding_options,
+            settings.cores_per_quotient,
+        )?
+    );
+    summary.solver_time += solver_time;
+    let cores = cores.into_iter().map(|core| core.1).collect_vec();
+
+    if cores.is_empty() {
+        return Ok(None);
+    }
+    statistics.log(|stats| stats.log_cores(&cores));
+    let core = join_cores(cores);
+    settings.report_progress(|| ProgressEvent::CoreFound { size: core.len() });
+    Ok(Some(core))
+}
+
 /// Just give every vertex* in the core a new color.
 /// This breaks the core but changes the original graph.
 ///
 /// *Well not really every vertex, but only those
 /// in bigger orbits. We don't need to recolor single vertex orbits.
+/// If `whole_orbits` is set, the first vertex of each orbit is recolored as well.
+/// Returns the number of recolored vertices.
 #[cfg(not(tarpaulin_include))]
-fn recolor_core(graph: &mut Graph, core: &[OrbitEncoding]) -> Result<(), Error> {
+fn recolor_core(
+    graph: &mut Graph,
+    core: &[OrbitEncoding],
+    whole_orbits: bool,
+) -> Result<usize, Error> {
+    let skipped = if whole_orbits { 0 } else { 1 };
+    let mut recolored = 0;
     for orbit in core {
-        for vertex in orbit.1.iter().skip(1) {
+        for vertex in orbit.1.iter().skip(skipped) {
             graph.recolor(*vertex)?;
+            recolored += 1;
         }
     }
 
-    Ok(())
+    Ok(recolored)
 }
 
 #[cfg(not(tarpaulin_include))]
-fn search_with_core_recolor(graph: &mut Graph, settings: &mut Settings) -> Result<(), Error> {
+fn search_with_core_recolor(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<Outcome, Error> {
     let mut generators;
     let mut orbits;
     let mut quotient_graph;
     let mut encoding;
+    // Recoloring doesn't change any edges, so the nauty/Traces graph
+    // only needs new colours in each iteration.
+    let mut automorphism_graph = AutomorphismGraph::from_graph(graph, settings);
+    // Detect iterations that don't change the orbit partition,
+    // as they would only encode and solve the same problem again.
+    let mut previous_orbits = None;
+    let mut last_core: Option<Vec<OrbitEncoding>> = None;
+    let mut escalated = false;
 
-    loop {
+    let outcome = loop {
+        check_interrupted()?;
         let start_time = Instant::now();
         let mut kissat_time = Duration::ZERO;
         let mut core_size = None;
 
-        time_assign!(nauty_time, generators, compute_generators(graph, settings));
+        time_assign!(nauty_time, generators, {
+            automorphism_graph.refresh_colours(graph)?;
+            automorphism_graph.compute_generators(settings, statistics)
+        });
+        summary.log_initial_group(generators.len(), settings, statistics);
+        summary.iterations += 1;
 
         if generators.is_empty() {
+            let orbits = empty_orbits(graph.size());
+            log_core_iteration(statistics, CoreMetric::Recolor, 0, &orbits);
+            summary.log_descriptive(&orbits);
             if settings.output_orbits {
-                print_orbits_nauty_style(empty_orbits(graph.size()), None);
+                print_orbits(orbits, settings, statistics);
+            }
+            // Without any recoloring, the graph was already asymmetric.
+            if previous_orbits.is_none() {
+                break Outcome::Asymmetric;
             }
-            break;
+            break Outcome::DescriptiveFound;
         }
 
         time_assign!(orbit_gen_time, orbits, generate_orbits(&mut generators));
+        log_core_iteration(statistics, CoreMetric::Recolor, generators.len(), &orbits);
+
+        if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+            summary.log_descriptive(&orbits);
+            let quotient_stats = QuotientStatistics::from_trivial_partition(
+                trivial_partition,
+                graph.size(),
+                Some((0..generators.len()).collect()),
+                generators.len(),
+                start_time.elapsed(),
+                orbit_gen_time,
+            );
+            statistics.log(|stats| {
+                stats.log_quotient_statistic(quotient_stats);
+                stats.log_nauty_step(nauty_time);
+                stats.log_iteration();
+                stats.exhausted = true;
+            });
+            if settings.output_orbits {
+                print_orbits(orbits, settings, statistics);
+            }
+            break Outcome::DescriptiveFound;
+        }
+
+        if previous_orbits.as_ref() == Some(&orbits) {
+            match last_core.take() {
+                Some(core) if !escalated => {
+                    // Escalate by also recoloring the orbit representatives.
+                    escalated = true;
+                    summary.recoloured_vertices += recolor_core(graph, &core, true)?;
+                    continue;
+                }
+                _ => {
+                    println!("Orbit partition didn't change after recoloring, stopping.");
+                    break Outcome::NonDescriptiveCore;
+                }
+            }
+        }
+        escalated = false;
+        previous_orbits = Some(orbits.clone());
 
         time!(graph_sort_time, _sorted, graph.sort());
 
@@ -64,25 +273,36 @@ fn search_with_core_recolor(graph: &mut Graph, settings: &mut Settings) -> Resul
         );
         let quotient_size = quotient_graph.quotient_graph.size();
         let (min_orbit_size, max_orbit_size) = quotient_graph.get_orbit_sizes();
+        let invariants = QuotientInvariants::from_quotient_graph(&quotient_graph.quotient_graph);
 
         time_assign!(
             encoding_time,
             encoding,
-            encode_problem(&quotient_graph, graph)
+            encode_problem(&quotient_graph, graph, &settings.encoding_options)?
         );
+        let trivial_encoding = encoding.trivial_reason();
 
-        let descriptive = if let Some((formula, dict)) = encoding {
+        let descriptive = if let EncodeOutcome::Formula(formula, dict) = encoding {
             time!(
                 kitten_time,
                 next_core,
-                solve_mus_kitten(formula, &quotient_graph, graph, dict)?
+                next_core(
+                    formula,
+                    &quotient_graph,
+                    graph,
+                    dict,
+                    settings,
+                    statistics,
+                    summary
+                )?
             );
             kissat_time = kitten_time;
 
             if let Some(core) = next_core {
-                core_size = Some(core.1.len());
+                core_size = Some(core.len());
                 // Break core with recoloring
-                recolor_core(graph, &core.1)?;
+                summary.recoloured_vertices += recolor_core(graph, &core, false)?;
+                last_core = Some(core);
                 false
             } else {
                 //Descriptive
@@ -95,6 +315,8 @@ fn search_with_core_recolor(graph: &mut Graph, settings: &mut Settings) -> Resul
 
         let quotient_handling_time = start_time.elapsed();
         let quotient_stats = QuotientStatistics {
+            generator_subset: Some((0..generators.len()).collect()),
+            subset_size: generators.len(),
             quotient_size,
             core_size,
             max_orbit_size,
@@ -107,8 +329,12 @@ fn search_with_core_recolor(graph: &mut Graph, settings: &mut Settings) -> Resul
             quotient_gen_time,
             encoding_time,
             orbit_sizes: Default::default(),
+            invariants: Some(invariants),
+            trivial_partition: None,
+            trivial_encoding,
+            formula: None,
         };
-        do_if_some(settings.get_stats(), |stats| {
+        statistics.log(|stats| {
             stats.log_quotient_statistic(quotient_stats);
             stats.log_nauty_step(nauty_time);
             stats.log_graph_sorted_step(graph_sort_time);
@@ -116,22 +342,21 @@ fn search_with_core_recolor(graph: &mut Graph, settings: &mut Settings) -> Resul
         });
 
         if descriptive {
-            do_if_some(settings.get_stats(), |stats| stats.exhausted = true);
+            statistics.log(|stats| stats.exhausted = true);
+            summary.log_descriptive(&quotient_graph.orbits);
             if settings.output_orbits {
-                print_orbits_nauty_style(quotient_graph.orbits, None);
+                print_orbits(quotient_graph.orbits, settings, statistics);
             }
-            break;
+            break Outcome::DescriptiveFound;
         }
-    }
+    };
 
-    do_if_some(settings.get_stats(), |stats| {
-        stats.log_end();
-        stats.save_statistics().unwrap();
-    });
-
-    Ok(())
+    Ok(outcome)
 }
 
+/// Give up taking powers of the generators after this many iterations.
+const MAX_POWER_ITERATIONS: usize = 30;
+
 /// Take the power of generators related to the core.
 /// If a generator becomes the identity, it's removed.
 #[cfg(not(tarpaulin_include))]
@@ -156,9 +381,12 @@ fn power_generators(generators: &mut [(Permutation, usize)], core: &[OrbitEncodi
 #[cfg(not(tarpaulin_include))]
 fn search_with_core_power_generators(
     graph: &mut Graph,
-    settings: &mut Settings,
-) -> Result<(), Error> {
-    let mut generators = compute_generators(graph, settings);
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<Outcome, Error> {
+    let mut generators = compute_generators(graph, settings, statistics);
+    summary.log_initial_group(generators.len(), settings, statistics);
     let mut orig_generators = generators
         .iter()
         .cloned()
@@ -171,30 +399,60 @@ fn search_with_core_power_generators(
     let mut encoding;
     let mut counter = 0;
 
-    loop {
+    let outcome = loop {
+        check_interrupted()?;
         if orig_generators.is_empty() {
-            println!("removed all symmetries in {} iterations", counter);
+            let orbits = empty_orbits(graph.size());
+            log_core_iteration(statistics, CoreMetric::PowerGenerators, 0, &orbits);
+            summary.log_descriptive(&orbits);
             if settings.output_orbits {
-                print_orbits_nauty_style(empty_orbits(graph.size()), None);
+                print_orbits(orbits, settings, statistics);
             }
-            return Ok(());
+            if counter == 0 {
+                return Ok(Outcome::Asymmetric);
+            }
+            return Ok(Outcome::DescriptiveFound);
         }
 
+        summary.iterations += 1;
         orbits = generate_orbits(&mut generators);
+        log_core_iteration(
+            statistics,
+            CoreMetric::PowerGenerators,
+            orig_generators.len(),
+            &orbits,
+        );
+        if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+            println!("Trivially descriptive: {}", trivial_partition);
+            summary.log_descriptive(&orbits);
+            if settings.output_orbits {
+                print_orbits(orbits, settings, statistics);
+            }
+            return Ok(Outcome::DescriptiveFound);
+        }
+
         quotient_graph = QuotientGraph::from_graph_orbits(graph, orbits);
-        encoding = encode_problem(&quotient_graph, graph);
+        encoding = encode_problem(&quotient_graph, graph, &settings.encoding_options)?;
 
-        if let Some((formula, dict)) = encoding {
-            let next_core = solve_mus_kitten(formula, &quotient_graph, graph, dict)?;
+        if let EncodeOutcome::Formula(formula, dict) = encoding {
+            let next_core = next_core(
+                formula,
+                &quotient_graph,
+                graph,
+                dict,
+                settings,
+                statistics,
+                summary,
+            )?;
             if let Some(core) = next_core {
-                power_generators(&mut orig_generators, &core.1);
+                power_generators(&mut orig_generators, &core);
             } else {
                 println!("Descriptive");
-                break;
+                break Outcome::DescriptiveFound;
             }
         } else {
             println!("Trivially descriptive");
-            break;
+            break Outcome::DescriptiveFound;
         }
 
         generators = orig_generators
@@ -207,21 +465,25 @@ fn search_with_core_power_generators(
                 power
             })
             .collect_vec();
+        let before = orig_generators.len();
         orig_generators.retain(|(_, n)| *n > 0);
+        summary.dropped_generators += before - orig_generators.len();
 
         counter += 1;
 
-        if counter > 30 {
+        if counter > MAX_POWER_ITERATIONS {
             println!("Too many iterations.");
-            break;
+            break Outcome::NonDescriptiveCore;
         }
-    }
+    };
 
+    if outcome == Outcome::DescriptiveFound {
+        summary.log_descriptive(&quotient_graph.orbits);
+    }
     if settings.output_orbits {
-        print_orbits_nauty_style(quotient_graph.orbits, None);
+        print_orbits(quotient_graph.orbits, settings, statistics);
     }
-    println!("Took {} iterations", counter);
-    Ok(())
+    Ok(outcome)
 }
 
 /// Combine all related generators by composing them in order.
@@ -261,97 +523,634 @@ fn merge_generators(generators: Vec<Permutation>, core: &[OrbitEncoding]) -> Vec
 #[cfg(not(tarpaulin_include))]
 fn search_with_core_merge_generators(
     graph: &mut Graph,
-    settings: &mut Settings,
-) -> Result<(), Error> {
-    let mut generators = compute_generators(graph, settings);
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<Outcome, Error> {
+    let mut generators = compute_generators(graph, settings, statistics);
+    summary.log_initial_group(generators.len(), settings, statistics);
     graph.sort();
     let mut orbits;
     let mut quotient_graph;
     let mut encoding;
     let mut counter = 0;
 
-    loop {
+    let outcome = loop {
+        check_interrupted()?;
         if generators.is_empty() {
-            println!("removed all symmetries in {} iterations", counter);
+            let orbits = empty_orbits(graph.size());
+            log_core_iteration(statistics, CoreMetric::MergeGenerators, 0, &orbits);
+            summary.log_descriptive(&orbits);
             if settings.output_orbits {
-                print_orbits_nauty_style(empty_orbits(graph.size()), None);
+                print_orbits(orbits, settings, statistics);
+            }
+            if counter == 0 {
+                return Ok(Outcome::Asymmetric);
             }
-            return Ok(());
+            return Ok(Outcome::DescriptiveFound);
         }
 
+        summary.iterations += 1;
         orbits = generate_orbits(&mut generators);
+        log_core_iteration(
+            statistics,
+            CoreMetric::MergeGenerators,
+            generators.len(),
+            &orbits,
+        );
+        if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+            println!("Trivially descriptive: {}", trivial_partition);
+            summary.log_descriptive(&orbits);
+            if settings.output_orbits {
+                print_orbits(orbits, settings, statistics);
+            }
+            return Ok(Outcome::DescriptiveFound);
+        }
+
         quotient_graph = QuotientGraph::from_graph_orbits(graph, orbits);
-        encoding = encode_problem(&quotient_graph, graph);
+        encoding = encode_problem(&quotient_graph, graph, &settings.encoding_options)?;
 
-        if let Some((formula, dict)) = encoding {
-            let next_core = solve_mus_kitten(formula, &quotient_graph, graph, dict)?;
+        if let EncodeOutcome::Formula(formula, dict) = encoding {
+            let next_core = next_core(
+                formula,
+                &quotient_graph,
+                graph,
+                dict,
+                settings,
+                statistics,
+                summary,
+            )?;
             if let Some(core) = next_core {
-                generators = merge_generators(generators, &core.1);
+                let before = generators.len();
+                generators = merge_generators(generators, &core);
+                summary.dropped_generators += before - generators.len();
             } else {
                 println!("Descriptive");
-                break;
+                break Outcome::DescriptiveFound;
             }
         } else {
             println!("Trivially descriptive");
-            break;
+            break Outcome::DescriptiveFound;
         }
 
         counter += 1;
-    }
+    };
 
+    summary.log_descriptive(&quotient_graph.orbits);
     if settings.output_orbits {
-        print_orbits_nauty_style(quotient_graph.orbits, None);
+        print_orbits(quotient_graph.orbits, settings, statistics);
     }
-    println!("Took {} iterations", counter);
-    Ok(())
+    Ok(outcome)
+}
+
+/// Time each strategy gets per round in auto mode
+/// before the next strategy takes over.
+const CORE_TIME_SLICE: Duration = Duration::from_millis(100);
+
+/// Orbits of a descriptive quotient or the
+/// non-descriptive core of the quotient.
+enum QuotientCore {
+    Descriptive(Orbits),
+    Core(Vec<OrbitEncoding>),
 }
 
 #[cfg(not(tarpaulin_include))]
-pub fn search_with_core(graph: &mut Graph, settings: &mut Settings) -> Result<(), Error> {
-    match settings.nondescriptive_core {
-        Some(CoreMetric::Recolor) => search_with_core_recolor(graph, settings),
-        Some(CoreMetric::PowerGenerators) => search_with_core_power_generators(graph, settings),
-        Some(CoreMetric::MergeGenerators) => search_with_core_merge_generators(graph, settings),
+fn find_core(
+    graph: &Graph,
+    generators: &mut [Permutation],
+    strategy: CoreMetric,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<QuotientCore, Error> {
+    if generators.is_empty() {
+        let orbits = empty_orbits(graph.size());
+        log_core_iteration(statistics, strategy, 0, &orbits);
+        return Ok(QuotientCore::Descriptive(orbits));
+    }
+
+    let orbits = generate_orbits(generators);
+    log_core_iteration(statistics, strategy, generators.len(), &orbits);
+    if TrivialPartition::detect(&orbits).is_some() {
+        return Ok(QuotientCore::Descriptive(orbits));
+    }
+
+    let quotient_graph = QuotientGraph::from_graph_orbits(graph, orbits);
+    let core = match encode_problem(&quotient_graph, graph, &settings.encoding_options)? {
+        EncodeOutcome::Formula(formula, dict) => next_core(
+            formula,
+            &quotient_graph,
+            graph,
+            dict,
+            settings,
+            statistics,
+            summary,
+        )?,
+        EncodeOutcome::TriviallyDescriptive { .. } => None,
+    };
+
+    Ok(match core {
+        Some(core) => QuotientCore::Core(core),
+        None => QuotientCore::Descriptive(quotient_graph.orbits),
+    })
+}
+
+/// How far a core strategy got. Smaller cores come first,
+/// then fewer remaining generators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CoreProgress {
+    core_size: usize,
+    generators: usize,
+}
+
+impl CoreProgress {
+    fn improves_on(&self, baseline: &CoreProgress) -> bool {
+        self.core_size < baseline.core_size || self.generators < baseline.generators
+    }
+}
+
+enum CoreStep {
+    Progress(CoreProgress),
+    Descriptive(Orbits),
+    GaveUp,
+}
+
+/// State of a single core strategy that can be advanced one iteration at a time.
+enum CoreStrategy {
+    /// Recolors its own copy of the graph.
+    Recolor {
+        graph: Graph,
+        automorphism_graph: Box<AutomorphismGraph>,
+        previous_core: Option<Vec<OrbitEncoding>>,
+    },
+    PowerGenerators {
+        generators: Vec<(Permutation, usize)>,
+        iterations: usize,
+    },
+    MergeGenerators {
+        generators: Vec<Permutation>,
+    },
+}
+
+impl CoreStrategy {
+    fn metric(&self) -> CoreMetric {
+        match self {
+            Self::Recolor { .. } => CoreMetric::Recolor,
+            Self::PowerGenerators { .. } => CoreMetric::PowerGenerators,
+            Self::MergeGenerators { .. } => CoreMetric::MergeGenerators,
+        }
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    fn step(
+        &mut self,
+        graph: &Graph,
+        settings: &Settings,
+        statistics: &mut dyn StatisticsSink,
+        summary: &mut CoreSummary,
+    ) -> Result<CoreStep, Error> {
+        let strategy = self.metric();
+        match self {
+            Self::Recolor {
+                graph: recolored,
+                automorphism_graph,
+                previous_core,
+            } => {
+                automorphism_graph.refresh_colours(recolored)?;
+                let mut generators = automorphism_graph.compute_generators(settings, statistics);
+                let number_of_generators = generators.len();
+
+                match find_core(
+                    recolored,
+                    &mut generators,
+                    strategy,
+                    settings,
+                    statistics,
+                    summary,
+                )? {
+                    QuotientCore::Descriptive(orbits) => Ok(CoreStep::Descriptive(orbits)),
+                    QuotientCore::Core(core) => {
+                        // The same core again means that recoloring all but one
+                        // vertex per orbit wasn't enough, so recolor all of them.
+                        let whole_orbits = previous_core.as_ref() == Some(&core);
+                        summary.recoloured_vertices +=
+                            recolor_core(recolored, &core, whole_orbits)?;
+                        let progress = CoreProgress {
+                            core_size: core.len(),
+                            generators: number_of_generators,
+                        };
+                        *previous_core = Some(core);
+                        Ok(CoreStep::Progress(progress))
+                    }
+                }
+            }
+            Self::PowerGenerators {
+                generators,
+                iterations,
+            } => {
+                if *iterations > MAX_POWER_ITERATIONS {
+                    return Ok(CoreStep::GaveUp);
+                }
+                *iterations += 1;
+
+                let mut powers = generators
+                    .iter_mut()
+                    .map(|(perm, n)| {
+                        let power = perm.nth_power_of(*n);
+                        if power.is_identity() {
+                            *n = 0;
+                        }
+                        power
+                    })
+                    .collect_vec();
+                let before = generators.len();
+                generators.retain(|(_, n)| *n > 0);
+                summary.dropped_generators += before - generators.len();
+                powers.retain(|power| !power.is_identity());
+
+                match find_core(graph, &mut powers, strategy, settings, statistics, summary)? {
+                    QuotientCore::Descriptive(orbits) => Ok(CoreStep::Descriptive(orbits)),
+                    QuotientCore::Core(core) => {
+                        power_generators(generators, &core);
+                        Ok(CoreStep::Progress(CoreProgress {
+                            core_size: core.len(),
+                            generators: generators.len(),
+                        }))
+                    }
+                }
+            }
+            Self::MergeGenerators { generators } => {
+                match find_core(graph, generators, strategy, settings, statistics, summary)? {
+                    QuotientCore::Descriptive(orbits) => Ok(CoreStep::Descriptive(orbits)),
+                    QuotientCore::Core(core) => {
+                        let before = generators.len();
+                        *generators = merge_generators(std::mem::take(generators), &core);
+                        summary.dropped_generators += before - generators.len();
+                        Ok(CoreStep::Progress(CoreProgress {
+                            core_size: core.len(),
+                            generators: generators.len(),
+                        }))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A core strategy together with the progress it made so far.
+struct SlicedStrategy {
+    strategy: CoreStrategy,
+    baseline: Option<CoreProgress>,
+    latest: Option<CoreProgress>,
+    gave_up: bool,
+}
+
+impl SlicedStrategy {
+    fn new(strategy: CoreStrategy) -> Self {
+        SlicedStrategy {
+            strategy,
+            baseline: None,
+            latest: None,
+            gave_up: false,
+        }
+    }
+
+    /// The latest progress if it is better than the first core found.
+    fn improvement(&self) -> Option<CoreProgress> {
+        match (self.baseline, self.latest) {
+            (Some(baseline), Some(latest)) if latest.improves_on(&baseline) => Some(latest),
+            _ => None,
+        }
+    }
+
+    /// Advance the strategy until the time slice is used up or it improved.
+    /// Without a time slice, advance it until it is done.
+    /// Returns the orbits of a descriptive quotient if one was found.
+    #[cfg(not(tarpaulin_include))]
+    fn run(
+        &mut self,
+        graph: &Graph,
+        settings: &Settings,
+        statistics: &mut dyn StatisticsSink,
+        summary: &mut CoreSummary,
+        time_slice: Option<Duration>,
+    ) -> Result<Option<Orbits>, Error> {
+        let slice_start = Instant::now();
+
+        loop {
+            check_interrupted()?;
+            statistics.log(|stats| stats.log_iteration());
+            summary.iterations += 1;
+
+            match self.strategy.step(graph, settings, statistics, summary)? {
+                CoreStep::Progress(progress) => {
+                    self.baseline.get_or_insert(progress);
+                    self.latest = Some(progress);
+                }
+                CoreStep::Descriptive(orbits) => return Ok(Some(orbits)),
+                CoreStep::GaveUp => {
+                    self.gave_up = true;
+                    return Ok(None);
+                }
+            }
+
+            if let Some(time_slice) = time_slice {
+                if slice_start.elapsed() >= time_slice || self.improvement().is_some() {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Index of the strategy with the best improvement, if any improved.
+fn pick_strategy(strategies: &[SlicedStrategy]) -> Option<usize> {
+    strategies
+        .iter()
+        .enumerate()
+        .filter(|(_, sliced)| !sliced.gave_up)
+        .filter_map(|(index, sliced)| Some((index, sliced.improvement()?)))
+        .min_by_key(|(_, improvement)| *improvement)
+        .map(|(index, _)| index)
+}
+
+/// Interleave all core strategies in time slices until one of them makes
+/// progress (i.e. finds a smaller core or gets rid of generators) and
+/// continue only with that one.
+#[cfg(not(tarpaulin_include))]
+fn search_with_core_auto(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    summary: &mut CoreSummary,
+) -> Result<Outcome, Error> {
+    let generators = compute_generators(graph, settings, statistics);
+    summary.log_initial_group(generators.len(), settings, statistics);
+    if generators.is_empty() {
+        let orbits = empty_orbits(graph.size());
+        summary.log_descriptive(&orbits);
+        if settings.output_orbits {
+            print_orbits(orbits, settings, statistics);
+        }
+        return Ok(Outcome::Asymmetric);
+    }
+
+    graph.sort();
+    let mut recolored = graph.clone();
+    let automorphism_graph = Box::new(AutomorphismGraph::from_graph(&mut recolored, settings));
+
+    let mut strategies = vec![
+        SlicedStrategy::new(CoreStrategy::Recolor {
+            graph: recolored,
+            automorphism_graph,
+            previous_core: None,
+        }),
+        SlicedStrategy::new(CoreStrategy::PowerGenerators {
+            generators: generators.iter().cloned().map(|perm| (perm, 1)).collect(),
+            iterations: 0,
+        }),
+        SlicedStrategy::new(CoreStrategy::MergeGenerators { generators }),
+    ];
+
+    let mut rounds = 0;
+    let (metric, orbits) = 'rounds: loop {
+        rounds += 1;
+        check_interrupted()?;
+
+        for sliced in strategies.iter_mut().filter(|sliced| !sliced.gave_up) {
+            let time_slice = Some(CORE_TIME_SLICE);
+            if let Some(orbits) = sliced.run(graph, settings, statistics, summary, time_slice)? {
+                break 'rounds (sliced.strategy.metric(), Some(orbits));
+            }
+        }
+
+        if let Some(index) = pick_strategy(&strategies) {
+            let mut adopted = strategies.swap_remove(index);
+            println!(
+                "Adopted {:?} after {} rounds",
+                adopted.strategy.metric(),
+                rounds
+            );
+            let orbits = adopted.run(graph, settings, statistics, summary, None)?;
+            break (adopted.strategy.metric(), orbits);
+        }
+
+        if strategies.iter().all(|sliced| sliced.gave_up) {
+            println!("All core strategies gave up.");
+            return Ok(Outcome::NonDescriptiveCore);
+        }
+    };
+
+    match orbits {
+        Some(orbits) => {
+            println!("{:?} found a descriptive quotient", metric);
+            summary.log_descriptive(&orbits);
+            if settings.output_orbits {
+                print_orbits(orbits, settings, statistics);
+            }
+            Ok(Outcome::DescriptiveFound)
+        }
+        None => {
+            println!("{:?} gave up.", metric);
+            Ok(Outcome::NonDescriptiveCore)
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn search_with_core(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<Outcome, Error> {
+    let mut summary = CoreSummary::default();
+    let outcome = match settings.nondescriptive_core {
+        Some(CoreMetric::Recolor) => {
+            search_with_core_recolor(graph, settings, statistics, &mut summary)?
+        }
+        Some(CoreMetric::PowerGenerators) => {
+            search_with_core_power_generators(graph, settings, statistics, &mut summary)?
+        }
+        Some(CoreMetric::MergeGenerators) => {
+            search_with_core_merge_generators(graph, settings, statistics, &mut summary)?
+        }
+        Some(CoreMetric::Auto) => search_with_core_auto(graph, settings, statistics, &mut summary)?,
         _ => unreachable!(),
+    };
+    println!("{}", summary);
+
+    statistics.log(Statistics::log_end);
+    statistics.save()?;
+
+    Ok(outcome)
+}
+
+/// Search the smallest non-descriptive core of the quotient induced by all
+/// generators among the subsets of orbits within the configured size bounds.
+#[cfg(not(tarpaulin_include))]
+pub fn search_smallest_core(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<Outcome, Error> {
+    let mut generators = compute_generators(graph, settings, statistics);
+    graph.sort();
+    settings.report_progress(|| ProgressEvent::GeneratorsComputed {
+        n: generators.len(),
+    });
+    if generators.is_empty() {
+        return Ok(Outcome::Asymmetric);
     }
+
+    let quotient_graph = QuotientGraph::from_graph_orbits(graph, generate_orbits(&mut generators));
+    let orbit_number = quotient_graph.iter_orbits().count();
+    let max_size = settings.max_core_size.unwrap_or(orbit_number);
+    let core = quotient_graph.search_non_descriptive_core(
+        graph,
+        settings.min_core_size..=max_size,
+        settings.is_deterministic(),
+    )?;
+
+    let outcome = match core {
+        Some(QuotientGraphEncoding(_, core)) => {
+            settings.report_progress(|| ProgressEvent::CoreFound { size: core.len() });
+            // Vertices outside of the core belong to no orbit.
+            let mut core_orbits = vec![-1; graph.size()];
+            for (orbit, vertices) in core.iter() {
+                for vertex in vertices {
+                    core_orbits[*vertex as usize] = *orbit;
+                }
+            }
+            let core_orbits = match settings.vertex_ids.as_deref() {
+                Some(vertex_ids) => orbits_with_ids(core_orbits, vertex_ids),
+                None => orbits_nauty_style(core_orbits),
+            };
+            println!("Smallest non-descriptive core ({} orbits):", core.len());
+            println!("{}", core_orbits);
+            Outcome::NonDescriptiveCore
+        }
+        // Each quotient with a core is non-descriptive itself.
+        None if settings.min_core_size <= orbit_number && max_size >= orbit_number => {
+            println!("The quotient is descriptive.");
+            Outcome::DescriptiveFound
+        }
+        None => {
+            println!(
+                "No non-descriptive core with {} to {} orbits.",
+                settings.min_core_size, max_size
+            );
+            Outcome::Done
+        }
+    };
+
+    statistics.log(Statistics::log_end);
+    statistics.save()?;
+    Ok(outcome)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::statistics::NoStatistics;
+
+    #[test]
+    fn test_join_cores() {
+        let cores = vec![
+            vec![(3, vec![3, 4]), (5, vec![5, 6])],
+            vec![(0, vec![0, 1]), (3, vec![3, 4])],
+        ];
+        assert_eq!(
+            vec![(0, vec![0, 1]), (3, vec![3, 4]), (5, vec![5, 6])],
+            join_cores(cores)
+        );
+    }
 
     #[test]
     fn test_merge_generators() {
         let generators = vec![
-            Permutation::new(vec![0, 1, 2, 3, 5, 4]),
-            Permutation::new(vec![0, 2, 1, 3, 4, 5]),
-            Permutation::new(vec![0, 2, 1, 4, 3, 5]),
-            Permutation::new(vec![0, 5, 2, 4, 3, 1]),
+            Permutation::new(vec![0, 1, 2, 3, 5, 4]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 3, 4, 5]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 4, 3, 5]).unwrap(),
+            Permutation::new(vec![0, 5, 2, 4, 3, 1]).unwrap(),
         ];
         let core = vec![(3, vec![3, 4])];
 
         let expected = vec![
-            Permutation::new(vec![0, 2, 5, 3, 4, 1]),
-            Permutation::new(vec![0, 1, 2, 3, 5, 4]),
-            Permutation::new(vec![0, 2, 1, 3, 4, 5]),
+            Permutation::new(vec![0, 2, 5, 3, 4, 1]).unwrap(),
+            Permutation::new(vec![0, 1, 2, 3, 5, 4]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 3, 4, 5]).unwrap(),
         ];
         let merged = merge_generators(generators, &core);
         assert_eq!(expected, merged);
 
         let generators = vec![
-            Permutation::new(vec![0, 1, 2, 3, 5, 4]),
-            Permutation::new(vec![0, 2, 1, 3, 4, 5]),
-            Permutation::new(vec![0, 2, 1, 4, 3, 5]),
-            Permutation::new(vec![0, 5, 3, 2, 4, 1]),
+            Permutation::new(vec![0, 1, 2, 3, 5, 4]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 3, 4, 5]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 4, 3, 5]).unwrap(),
+            Permutation::new(vec![0, 5, 3, 2, 4, 1]).unwrap(),
         ];
         let core = vec![(3, vec![3, 4])];
 
         let expected = vec![
-            Permutation::new(vec![0, 1, 2, 3, 5, 4]),
-            Permutation::new(vec![0, 2, 1, 3, 4, 5]),
-            Permutation::new(vec![0, 5, 3, 2, 4, 1]),
+            Permutation::new(vec![0, 1, 2, 3, 5, 4]).unwrap(),
+            Permutation::new(vec![0, 2, 1, 3, 4, 5]).unwrap(),
+            Permutation::new(vec![0, 5, 3, 2, 4, 1]).unwrap(),
         ];
         let merged = merge_generators(generators, &core);
         assert_eq!(expected, merged);
     }
+
+    #[test]
+    fn test_core_summary() {
+        let mut summary = CoreSummary {
+            iterations: 3,
+            recoloured_vertices: 4,
+            solver_time: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        summary.log_initial_group(2, &Settings::default(), &mut NoStatistics);
+        summary.log_initial_group(1, &Settings::default(), &mut NoStatistics);
+        assert_eq!(
+            "Core mode summary:
+  initial generators: 2
+  iterations: 3
+  recoloured vertices: 4
+  merged or removed generators: 0
+  final orbits: no descriptive quotient found
+  SAT/MUS time: 1.500s",
+            summary.to_string()
+        );
+
+        summary.log_descriptive(&[0, 0, 2, 3, 2]);
+        assert_eq!(Some(3), summary.final_orbits);
+    }
+
+    #[test]
+    fn test_pick_strategy() {
+        let progress = |core_size, generators| CoreProgress {
+            core_size,
+            generators,
+        };
+        let sliced = |baseline, latest, gave_up| SlicedStrategy {
+            strategy: CoreStrategy::MergeGenerators {
+                generators: Vec::new(),
+            },
+            baseline: Some(baseline),
+            latest: Some(latest),
+            gave_up,
+        };
+
+        assert!(progress(2, 5).improves_on(&progress(3, 5)));
+        assert!(progress(4, 4).improves_on(&progress(3, 5)));
+        assert!(!progress(3, 5).improves_on(&progress(3, 5)));
+
+        let strategies = vec![
+            sliced(progress(3, 5), progress(3, 5), false),
+            sliced(progress(3, 5), progress(3, 4), false),
+            sliced(progress(3, 5), progress(2, 5), false),
+            sliced(progress(3, 5), progress(1, 1), true),
+        ];
+        assert_eq!(Some(2), pick_strategy(&strategies));
+        assert_eq!(None, pick_strategy(&strategies[..1]));
+    }
 }
@@ -1,21 +1,22 @@
 //! Debug facilities.
 use flussab_cnf::cnf::{write_clause, write_header, Header};
 use itertools::Itertools;
-use kissat_rs::Literal;
 use nom::error::{VerboseError, VerboseErrorKind};
 use std::{
+    collections::BTreeMap,
     fmt::{self, Debug, Display},
     io::{self, Write},
     time::Duration,
 };
 
 use crate::{
-    encoding::{Clause, HighLevelEncoding, QuotientGraphEncoding},
-    graph::{Graph, GraphError, VertexIndex},
+    encoding::{Clause, HighLevelEncoding, Literal},
+    graph::{Colour, GraphError, VertexIndex, DEFAULT_COLOR},
     parser::{BinParseError, ParseError},
     permutation::Permutation,
     quotient::Orbits,
-    statistics::{OrbitStatistics, Statistics},
+    statistics::{CoreFrequencies, OrbitStatistics, Statistics, StatisticsSink},
+    Outcome, Settings,
 };
 
 // Error types and From<...> implementations
@@ -38,10 +39,32 @@ pub enum Error {
     ParseError(Vec<VerboseErrorKind>),
     #[error("Error while parsing graph from command line")]
     CLIParseError(io::Error),
+    #[cfg(feature = "native")]
     #[error("Error while calling Kissat")]
     KissatError(kissat_rs::Error),
-    #[error("Unknown metric used")]
+    #[error("Unknown metric {0}")]
     MetricError(MetricError),
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+    #[error("{0} failed with {1}")]
+    ExternalToolError(&'static str, std::process::ExitStatus),
+    #[error("Unexpected output from GAP: {0}")]
+    GapError(String),
+    #[error("Interrupted by a signal")]
+    Interrupted,
+    #[error("The graph changed after {0} was derived from it")]
+    StaleGraph(&'static str),
+}
+
+impl Error {
+    /// Exit code of the process if a run fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ParseError(_) | Self::ConfigError(_) => Outcome::PARSE_ERROR_EXIT_CODE,
+            Self::Interrupted => Outcome::INTERRUPTED_EXIT_CODE,
+            _ => Outcome::OTHER_ERROR_EXIT_CODE,
+        }
+    }
 }
 
 impl From<GraphError> for Error {
@@ -92,6 +115,7 @@ impl<'a> From<nom::Err<BinParseError<'a>>> for Error {
     }
 }
 
+#[cfg(feature = "native")]
 impl From<kissat_rs::Error> for Error {
     #[cfg(not(tarpaulin_include))]
     fn from(ke: kissat_rs::Error) -> Self {
@@ -99,6 +123,14 @@ impl From<kissat_rs::Error> for Error {
     }
 }
 
+/// The SAT solver without the `native` feature can't fail.
+#[cfg(not(feature = "native"))]
+impl From<std::convert::Infallible> for Error {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
 impl From<io::Error> for Error {
     #[cfg(not(tarpaulin_include))]
     fn from(ie: io::Error) -> Self {
@@ -115,6 +147,17 @@ impl From<MetricError> for Error {
 
 // Custom debug methods
 
+impl fmt::Debug for CoreFrequencies {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoreFrequencies")
+            .field("number_of_cores", &self.number_of_cores)
+            .field("number_of_quotients", &self.number_of_quotients)
+            .field("vertex_frequencies", &self.sorted_frequencies())
+            .finish()
+    }
+}
+
 impl fmt::Debug for OrbitStatistics {
     #[cfg(not(tarpaulin_include))]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -166,28 +209,266 @@ pub fn write_formula_dimacs(
     writer.flush().map_err(Error::from)
 }
 
+/// Comments that map a DIMACS formula back to the orbits without the dictionary.
+pub struct DimacsComments<'a> {
+    /// Orbit and vertex of each variable, indexed by the variable.
+    pub pairs: &'a [(VertexIndex, VertexIndex)],
+    /// Number of leading clauses that encode the transversal constraints.
+    /// All other clauses encode the descriptive constraints.
+    pub transversal_clauses: usize,
+}
+
+/// Same as `write_formula_dimacs`, but the variables are listed with their
+/// orbit/vertex pair before the header (`c var <variable> orbit <orbit> vertex <vertex>`)
+/// and each block of clauses is preceded by a comment with its origin
+/// (`c transversal orbit <orbit>` or `c descriptive`).
 #[cfg(not(tarpaulin_include))]
-pub fn print_orbits_nauty_style(orbits: Orbits, statistics: Option<&Statistics>) {
-    // This is necessary to give a correct
-    // start point for the output.
-    let runtime = if let Some(statistics) = statistics {
-        statistics.start_time.elapsed()
-    } else {
-        Duration::ZERO
+pub fn write_formula_dimacs_commented(
+    writer: &mut impl Write,
+    formula: &[Clause],
+    variable_number: usize,
+    comments: &DimacsComments<'_>,
+) -> Result<(), Error> {
+    let mut writer = flussab::DeferredWriter::from_write(writer);
+    for (variable, (orbit, vertex)) in comments.pairs.iter().enumerate().skip(1) {
+        let comment = if *orbit < 0 {
+            format!("c var {} auxiliary\n", variable)
+        } else {
+            format!("c var {} orbit {} vertex {}\n", variable, orbit, vertex)
+        };
+        writer.write_all_defer_err(comment.as_bytes());
+    }
+
+    let header = Header {
+        var_count: variable_number,
+        clause_count: formula.len(),
     };
-    println!("cpu time = {:.6} seconds", runtime.as_secs_f64());
+    write_header(&mut writer, header);
+
+    let mut last_orbit = None;
+    for (index, clause) in formula.iter().enumerate() {
+        if index < comments.transversal_clauses {
+            let orbit = clause
+                .first()
+                .and_then(|literal| comments.pairs.get(literal.unsigned_abs() as usize))
+                .map(|(orbit, _)| *orbit)
+                // Clauses between auxiliary variables stay with their orbit.
+                .filter(|orbit| *orbit >= 0);
+            if orbit.is_some() && orbit != last_orbit {
+                if let Some(orbit) = orbit {
+                    writer
+                        .write_all_defer_err(format!("c transversal orbit {}\n", orbit).as_bytes());
+                }
+                last_orbit = orbit;
+            }
+        } else if index == comments.transversal_clauses {
+            writer.write_all_defer_err(b"c descriptive\n");
+        }
+        write_clause(&mut writer, clause);
+    }
 
+    writer.flush().map_err(Error::from)
+}
+
+/// Orbits in the format used by dreadnaut, e.g. `0 3 (2); 1 2 (2); 4; `.
+pub fn orbits_nauty_style(orbits: Orbits) -> String {
     orbits
         .encode_high()
         .into_iter()
-        .for_each(|(orbit, members)| {
+        .map(|(orbit, members)| {
             if members.len() > 1 {
-                members.iter().for_each(|member| print!("{} ", member));
-                print!("({}); ", members.len());
+                format!("{} ({}); ", members.iter().join(" "), members.len())
+            } else {
+                format!("{}; ", orbit)
+            }
+        })
+        .collect()
+}
+
+/// Same as `orbits_nauty_style`, but each vertex is written
+/// with its original id from an input with sparse ids.
+pub fn orbits_with_ids(orbits: Orbits, vertex_ids: &[VertexIndex]) -> String {
+    orbits
+        .encode_high()
+        .into_iter()
+        .map(|(_, members)| {
+            let ids = members
+                .iter()
+                .map(|member| vertex_ids[*member as usize])
+                .sorted_unstable()
+                .collect_vec();
+            if ids.len() > 1 {
+                format!("{} ({}); ", ids.iter().join(" "), ids.len())
+            } else {
+                format!("{}; ", ids[0])
+            }
+        })
+        .collect()
+}
+
+/// Same as `orbits_nauty_style` (or `orbits_with_ids`), but with one line per
+/// colour class that starts with the colour. An orbit whose vertices have
+/// different colours, e.g. because the colours were ignored, gets a line for
+/// the combination of the colours, which are then separated by slashes.
+pub fn orbits_by_colour(
+    orbits: Orbits,
+    colours: &[Colour],
+    vertex_ids: Option<&[VertexIndex]>,
+) -> String {
+    let mut classes = BTreeMap::<Vec<Colour>, String>::new();
+    for (_, members) in orbits.encode_high() {
+        let orbit_colours = members
+            .iter()
+            .map(|member| colours[*member as usize])
+            .sorted_unstable()
+            .dedup()
+            .collect_vec();
+        let members = match vertex_ids {
+            Some(vertex_ids) => members
+                .iter()
+                .map(|member| vertex_ids[*member as usize])
+                .sorted_unstable()
+                .collect_vec(),
+            None => members,
+        };
+        let orbit = if members.len() > 1 {
+            format!("{} ({}); ", members.iter().join(" "), members.len())
+        } else {
+            format!("{}; ", members[0])
+        };
+        classes.entry(orbit_colours).or_default().push_str(&orbit);
+    }
+
+    classes
+        .into_iter()
+        .map(|(colours, orbits)| {
+            let colours = colours
+                .iter()
+                .map(|colour| match *colour {
+                    DEFAULT_COLOR => "default".to_string(),
+                    colour => colour.to_string(),
+                })
+                .join("/");
+            format!("colour {}: {}", colours, orbits)
+        })
+        .join("\n")
+}
+
+/// Line length that dreadnaut wraps its output at by default.
+const DREADNAUT_LINE_LENGTH: usize = 78;
+
+/// Orbits exactly as the `o` command of dreadnaut prints them
+/// (`putorbits` in nauty with `labelorg` 0 and the default line length):
+/// runs of at least three consecutive vertices are written as ranges,
+/// each orbit is followed by its size (if not a singleton) and a semicolon.
+pub fn orbits_dreadnaut_style(orbits: Orbits) -> String {
+    let mut orbits = orbits.encode_high();
+    orbits.sort_unstable_by_key(|(_, members)| members[0]);
+
+    let mut output = String::new();
+    let mut line_length = 0;
+
+    for (_, members) in orbits {
+        let mut position = 0;
+        while position < members.len() {
+            // Extend the run of consecutive vertices as far as possible.
+            let mut end = position;
+            while end + 1 < members.len() && members[end + 1] == members[end] + 1 {
+                end += 1;
+            }
+
+            let token = if end >= position + 2 {
+                format!("{}:{}", members[position], members[end])
             } else {
-                print!("{}; ", orbit);
+                end = position;
+                members[position].to_string()
+            };
+            // Same line wrapping as nauty, which wraps sets
+            // one column earlier than the orbit sizes.
+            if line_length + token.len() + 1 >= DREADNAUT_LINE_LENGTH - 1 {
+                output.push_str("\n   ");
+                line_length = 3;
+            }
+            output.push(' ');
+            output.push_str(&token);
+            line_length += token.len() + 1;
+
+            position = end + 1;
+        }
+
+        if members.len() > 1 {
+            let size = format!(" ({})", members.len());
+            if line_length + size.len() + 1 >= DREADNAUT_LINE_LENGTH {
+                output.push_str("\n   ");
+                line_length = 3;
             }
-        });
+            output.push_str(&size);
+            line_length += size.len();
+        }
+        output.push(';');
+        line_length += 1;
+    }
+
+    output.push('\n');
+    output
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn print_orbits_dreadnaut_style(orbits: Orbits) {
+    print!("{}", orbits_dreadnaut_style(orbits));
+    std::io::stdout()
+        .flush()
+        .expect("Why would stdout not be flushed?");
+}
+
+/// Print the orbits in the format chosen by the settings.
+#[cfg(not(tarpaulin_include))]
+pub fn print_orbits(orbits: Orbits, settings: &Settings, statistics: &mut dyn StatisticsSink) {
+    if settings.strict_orbits {
+        print_orbits_dreadnaut_style(orbits);
+    } else {
+        print_orbits_nauty_style(
+            orbits,
+            settings.vertex_ids.as_deref(),
+            settings.orbit_colours.as_deref(),
+            statistics.statistics().map(|stats| &*stats),
+        );
+    }
+}
+
+/// Print the subset of generators that induced the printed orbits,
+/// unless the orbits are printed exactly like dreadnaut does.
+#[cfg(not(tarpaulin_include))]
+pub fn print_generator_subset(generators: &[Permutation], subset: &[usize], settings: &Settings) {
+    if settings.strict_orbits {
+        return;
+    }
+    println!("induced by the generators {}:", subset.iter().join(" "));
+    for index in subset {
+        println!("{}", generators[*index]);
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn print_orbits_nauty_style(
+    orbits: Orbits,
+    vertex_ids: Option<&[VertexIndex]>,
+    colours: Option<&[Colour]>,
+    statistics: Option<&Statistics>,
+) {
+    // This is necessary to give a correct
+    // start point for the output.
+    let runtime = if let Some(statistics) = statistics {
+        statistics.start_time.elapsed()
+    } else {
+        Duration::ZERO
+    };
+    println!("cpu time = {:.6} seconds", runtime.as_secs_f64());
+    match (colours, vertex_ids) {
+        (Some(colours), _) => print!("{}", orbits_by_colour(orbits, colours, vertex_ids)),
+        (None, Some(vertex_ids)) => print!("{}", orbits_with_ids(orbits, vertex_ids)),
+        (None, None) => print!("{}", orbits_nauty_style(orbits)),
+    }
 
     // Force new line and flush everything out.
     println!();
@@ -217,35 +498,6 @@ pub fn print_generator(mut generator: Permutation) {
     println!();
 }
 
-#[cfg(not(tarpaulin_include))]
-pub fn _print_dot(quotient_encoding: QuotientGraphEncoding, graph: &Graph) -> Result<(), Error> {
-    println!("graph graphname {{");
-
-    let colors = vec!["red", "green", "blue", "black", "yellow", "orange"]; // I don't expect to print more than 4 orbits at a time with one color per orbit.
-
-    let mut vertices_in_core = quotient_encoding
-        .1
-        .iter()
-        .flat_map(|(_, vertices)| vertices)
-        .cloned()
-        .collect::<Vec<VertexIndex>>();
-    vertices_in_core.sort_unstable();
-
-    for (orbit, color) in quotient_encoding.1.iter().zip(colors) {
-        for vertex in orbit.1.iter() {
-            println!("{:?} [color={:?}];", vertex, color);
-            for end in graph.get_vertex(*vertex)?.edges_to.iter() {
-                if vertex < end && vertices_in_core.binary_search(end).is_ok() {
-                    println!("{:?} -- {:?};", vertex, end);
-                }
-            }
-        }
-    }
-
-    println!("}}");
-    Ok(())
-}
-
 // Custom formatter for debug printing
 
 #[cfg(not(tarpaulin_include))]
@@ -363,3 +615,94 @@ macro_rules! get_line_recognize {
         eof::<$crate::parser::Input<'_>, $crate::parser::ParseError<'_>>(res)?;
     };
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_formula_dimacs_commented() {
+        // Orbits {0, 1} and {2} with an edge from 0 to 2.
+        let pairs = vec![(-1, -1), (0, 0), (0, 1), (2, 2)];
+        let formula = vec![vec![1, 2], vec![3], vec![-2]];
+        let comments = DimacsComments {
+            pairs: &pairs,
+            transversal_clauses: 2,
+        };
+
+        let mut written = Vec::new();
+        write_formula_dimacs_commented(&mut written, &formula, 3, &comments).unwrap();
+        assert_eq!(
+            "c var 1 orbit 0 vertex 0\n\
+             c var 2 orbit 0 vertex 1\n\
+             c var 3 orbit 2 vertex 2\n\
+             p cnf 3 3\n\
+             c transversal orbit 0\n\
+             1 2 0\n\
+             c transversal orbit 2\n\
+             3 0\n\
+             c descriptive\n\
+             -2 0\n",
+            String::from_utf8(written).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_orbits_dreadnaut_style() {
+        assert_eq!(
+            " 0:3 (4); 4 5 (2); 6;\n",
+            orbits_dreadnaut_style(vec![0, 0, 0, 0, 4, 4, 6])
+        );
+        assert_eq!(
+            " 0 2 4 (3); 1 3 (2);\n",
+            orbits_dreadnaut_style(vec![0, 1, 0, 1, 0])
+        );
+        assert_eq!(
+            " 0:3 5 (5); 4;\n",
+            orbits_dreadnaut_style(vec![0, 0, 0, 0, 4, 0])
+        );
+
+        let singletons = (0..30).collect();
+        assert_eq!(
+            " 0; 1; 2; 3; 4; 5; 6; 7; 8; 9; 10; 11; 12; 13; 14; 15; 16; 17; 18; 19; 20;
+    21; 22; 23; 24; 25; 26; 27; 28; 29;\n",
+            orbits_dreadnaut_style(singletons)
+        );
+    }
+
+    #[test]
+    fn test_orbits_nauty_style() {
+        assert_eq!(
+            "0 1 2 3 (4); 4 5 (2); 6; ",
+            orbits_nauty_style(vec![0, 0, 0, 0, 4, 4, 6])
+        );
+    }
+
+    #[test]
+    fn test_orbits_with_ids() {
+        assert_eq!(
+            "3 42 (2); 7 10 (2); 43; ",
+            orbits_with_ids(vec![0, 1, 1, 0, 4], &[3, 7, 10, 42, 43])
+        );
+    }
+
+    #[test]
+    fn test_orbits_by_colour() {
+        let orbits = vec![0, 1, 0, 1, 4, 5];
+        let colours = [2, 0, 2, 0, 0, DEFAULT_COLOR];
+        assert_eq!(
+            "colour 0: 1 3 (2); 4; \ncolour 2: 0 2 (2); \ncolour default: 5; ",
+            orbits_by_colour(orbits.clone(), &colours, None)
+        );
+        assert_eq!(
+            "colour 0: 7 42 (2); 43; \ncolour 2: 3 10 (2); \ncolour default: 44; ",
+            orbits_by_colour(orbits, &colours, Some(&[3, 7, 10, 42, 43, 44]))
+        );
+
+        // Orbits of ignored colours mix them.
+        assert_eq!(
+            "colour 0/1: 0 1 (2); ",
+            orbits_by_colour(vec![0, 0], &[1, 0], None)
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Simple DPLL solver that replaces kissat without the `native` feature,
+//! e.g. in the browser. It has the interface of the kissat bindings,
+//! but is only meant for the small formulas of a demo.
+
+use itertools::Itertools;
+use std::{collections::HashMap, convert::Infallible};
+
+use crate::encoding::Literal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assignment {
+    True,
+    False,
+}
+
+/// Unlike kissat, this solver can't fail.
+pub struct Solver;
+
+impl Solver {
+    pub fn decide_formula<I, C>(formula: I) -> Result<bool, Infallible>
+    where
+        I: Iterator<Item = C>,
+        C: IntoIterator<Item = Literal>,
+    {
+        Ok(Self::solve_formula(formula)?.is_some())
+    }
+
+    /// A satisfying assignment of each variable, or None if there is none.
+    pub fn solve_formula<I, C>(
+        formula: I,
+    ) -> Result<Option<HashMap<Literal, Option<Assignment>>>, Infallible>
+    where
+        I: Iterator<Item = C>,
+        C: IntoIterator<Item = Literal>,
+    {
+        let clauses = formula
+            .map(|clause| clause.into_iter().collect_vec())
+            .collect_vec();
+        let variable_number = clauses
+            .iter()
+            .flatten()
+            .map(|literal| literal.unsigned_abs() as usize)
+            .max()
+            .unwrap_or(0);
+        let mut values = vec![None; variable_number + 1];
+        if !dpll(&clauses, &mut values) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            values
+                .into_iter()
+                .enumerate()
+                .skip(1)
+                .map(|(variable, value)| {
+                    let assignment = value.map(|value| {
+                        if value {
+                            Assignment::True
+                        } else {
+                            Assignment::False
+                        }
+                    });
+                    (variable as Literal, assignment)
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn literal_value(values: &[Option<bool>], literal: Literal) -> Option<bool> {
+    values[literal.unsigned_abs() as usize].map(|value| value == (literal > 0))
+}
+
+/// Assign the last literal of each clause whose other literals are false.
+/// Returns false on a conflict, i.e. a clause with only false literals.
+fn propagate(
+    clauses: &[Vec<Literal>],
+    values: &mut [Option<bool>],
+    trail: &mut Vec<(usize, bool)>,
+) -> bool {
+    loop {
+        let mut changed = false;
+        for clause in clauses {
+            let mut unassigned = None;
+            let mut unassigned_number = 0;
+            let mut satisfied = false;
+            for literal in clause {
+                match literal_value(values, *literal) {
+                    Some(true) => {
+                        satisfied = true;
+                        break;
+                    }
+                    Some(false) => (),
+                    None => {
+                        unassigned_number += 1;
+                        unassigned = Some(*literal);
+                    }
+                }
+            }
+            if satisfied {
+                continue;
+            }
+            match (unassigned_number, unassigned) {
+                (0, _) => return false,
+                (1, Some(literal)) => {
+                    let variable = literal.unsigned_abs() as usize;
+                    values[variable] = Some(literal > 0);
+                    trail.push((variable, false));
+                    changed = true;
+                }
+                _ => (),
+            }
+        }
+        if !changed {
+            return true;
+        }
+    }
+}
+
+/// Chronological backtracking over the variables in order. The trail
+/// holds each assigned variable and whether it was a decision.
+fn dpll(clauses: &[Vec<Literal>], values: &mut [Option<bool>]) -> bool {
+    let mut trail = Vec::new();
+    loop {
+        if propagate(clauses, values, &mut trail) {
+            match (1..values.len()).find(|variable| values[*variable].is_none()) {
+                Some(variable) => {
+                    values[variable] = Some(true);
+                    trail.push((variable, true));
+                }
+                None => return true,
+            }
+            continue;
+        }
+
+        // Undo the assignments up to the last decision and flip it.
+        loop {
+            match trail.pop() {
+                Some((variable, true)) => {
+                    values[variable] = Some(false);
+                    trail.push((variable, false));
+                    break;
+                }
+                Some((variable, false)) => values[variable] = None,
+                None => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn satisfies(
+        formula: &[Vec<Literal>],
+        assignment: &HashMap<Literal, Option<Assignment>>,
+    ) -> bool {
+        formula.iter().all(|clause| {
+            clause.iter().any(|literal| {
+                let expected = if *literal > 0 {
+                    Assignment::True
+                } else {
+                    Assignment::False
+                };
+                assignment.get(&literal.abs()) == Some(&Some(expected))
+            })
+        })
+    }
+
+    #[test]
+    fn test_dpll() -> Result<(), Infallible> {
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![-3, -2], vec![-1, -2]];
+        let assignment = Solver::solve_formula(formula.iter().cloned())?.unwrap();
+        assert!(satisfies(&formula, &assignment));
+
+        // All four combinations of 1 and 2 are excluded.
+        let unsatisfiable = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]];
+        assert!(!Solver::decide_formula(unsatisfiable.into_iter())?);
+        assert!(!Solver::decide_formula(vec![vec![]].into_iter())?);
+        assert!(Solver::decide_formula(
+            Vec::<Vec<Literal>>::new().into_iter()
+        )?);
+        Ok(())
+    }
+
+    /// Pigeons in two holes, variable 2 * pigeon + hole + 1.
+    fn pigeonhole(pigeons: Literal) -> Vec<Vec<Literal>> {
+        let variable = |pigeon: Literal, hole: Literal| 2 * pigeon + hole + 1;
+        let mut formula = (0..pigeons)
+            .map(|pigeon| vec![variable(pigeon, 0), variable(pigeon, 1)])
+            .collect_vec();
+        for hole in 0..2 {
+            for (first, second) in (0..pigeons).tuple_combinations() {
+                formula.push(vec![-variable(first, hole), -variable(second, hole)]);
+            }
+        }
+        formula
+    }
+
+    #[test]
+    fn test_dpll_pigeonhole() -> Result<(), Infallible> {
+        assert!(!Solver::decide_formula(pigeonhole(3).into_iter())?);
+
+        let formula = pigeonhole(2);
+        let assignment = Solver::solve_formula(formula.iter().cloned())?.unwrap();
+        assert!(satisfies(&formula, &assignment));
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use custom_debug_derive::Debug;
+
+use crate::{
+    graph::{Generation, Graph, VertexIndex},
+    Error,
+};
+
+use super::low_level::non_edge_positions;
+
+/// How many orbit vertices and non-edge positions the cache holds
+/// at most, i.e. a few hundred MiB.
+const DEFAULT_CAPACITY: usize = 1 << 24;
+
+/// The non-edges between a pair of orbits, each given by its sorted vertices.
+#[derive(Debug)]
+struct CacheEntry {
+    start_orbit_elements: Box<[VertexIndex]>,
+    end_orbit_elements: Box<[VertexIndex]>,
+    positions: Box<[(usize, usize)]>,
+}
+
+impl CacheEntry {
+    fn size(&self) -> usize {
+        self.start_orbit_elements.len() + self.end_orbit_elements.len() + self.positions.len()
+    }
+}
+
+/// Caches the non-edges between pairs of orbits across several quotients.
+/// Subsets of generators often share orbits, so the expensive edge lookups
+/// can be reused. The entries are keyed by the sorted vertex lists of the
+/// orbits and store positions within these lists, which are remapped to
+/// the literals of the current dictionary on reuse.
+///
+/// The clauses of the orbits themselves aren't cached, as they need no
+/// lookups in the graph and are cheaper to rebuild than to remap.
+///
+/// Once the entries exceed the capacity, the cache starts over, as the
+/// orbits of later, larger subsets of generators rarely repeat earlier ones.
+#[derive(Debug)]
+pub struct EncodingCache {
+    /// Entries by the hash of their orbit pair, colliding pairs share a bucket.
+    /// This way, lookups hash the borrowed orbits instead of copying them.
+    #[debug(skip)]
+    non_edges: HashMap<u64, Vec<CacheEntry>>,
+    #[debug(skip)]
+    hash_state: RandomState,
+    size: usize,
+    capacity: usize,
+    hits: usize,
+    /// Of the graph that the entries were computed for.
+    #[debug(skip)]
+    graph_generation: Option<Generation>,
+}
+
+impl Default for EncodingCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl EncodingCache {
+    /// Empty cache that holds at most `capacity` orbit vertices and positions.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            non_edges: HashMap::new(),
+            hash_state: RandomState::new(),
+            size: 0,
+            capacity,
+            hits: 0,
+            graph_generation: None,
+        }
+    }
+
+    /// Fail if the entries were computed for another graph or before the
+    /// edges of this one changed. The first graph is remembered until `clear`.
+    pub fn check_graph(&mut self, graph: &Graph) -> Result<(), Error> {
+        match self.graph_generation {
+            Some(generation) => generation.check_edges(graph, "the encoding cache"),
+            None => {
+                self.graph_generation = Some(graph.generation());
+                Ok(())
+            }
+        }
+    }
+
+    fn hash_orbits(
+        &self,
+        start_orbit_elements: &[VertexIndex],
+        end_orbit_elements: &[VertexIndex],
+    ) -> u64 {
+        let mut hasher = self.hash_state.build_hasher();
+        start_orbit_elements.hash(&mut hasher);
+        end_orbit_elements.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn non_edge_positions(
+        &mut self,
+        start_orbit_elements: &[VertexIndex],
+        end_orbit_elements: &[VertexIndex],
+        original_graph: &Graph,
+    ) -> &[(usize, usize)] {
+        let hash = self.hash_orbits(start_orbit_elements, end_orbit_elements);
+
+        let cached = self.non_edges.get(&hash).and_then(|bucket| {
+            bucket.iter().position(|entry| {
+                *entry.start_orbit_elements == *start_orbit_elements
+                    && *entry.end_orbit_elements == *end_orbit_elements
+            })
+        });
+        if let Some(index) = cached {
+            self.hits += 1;
+            return &self.non_edges[&hash][index].positions;
+        }
+
+        let entry = CacheEntry {
+            start_orbit_elements: start_orbit_elements.into(),
+            end_orbit_elements: end_orbit_elements.into(),
+            positions: non_edge_positions(start_orbit_elements, end_orbit_elements, original_graph)
+                .into_boxed_slice(),
+        };
+        if self.size + entry.size() > self.capacity {
+            self.non_edges.clear();
+            self.size = 0;
+        }
+        self.size += entry.size();
+
+        let bucket = self.non_edges.entry(hash).or_default();
+        bucket.push(entry);
+        &bucket[bucket.len() - 1].positions
+    }
+
+    /// Forget all entries, e.g. before switching to another graph,
+    /// but keep the allocated memory.
+    pub fn clear(&mut self) {
+        self.non_edges.clear();
+        self.size = 0;
+        self.hits = 0;
+        self.graph_generation = None;
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_non_edge_positions() -> Result<(), Error> {
+        let graph = Graph::from_edges(4, vec![(0, 2), (1, 3)])?;
+        let mut cache = EncodingCache::with_capacity(8);
+
+        let expected = non_edge_positions(&[0, 1], &[2, 3], &graph);
+        assert_eq!(expected, cache.non_edge_positions(&[0, 1], &[2, 3], &graph));
+        assert_eq!(expected, cache.non_edge_positions(&[0, 1], &[2, 3], &graph));
+        assert_eq!(1, cache.hits());
+
+        // The mirrored pair is another entry, which doesn't fit anymore.
+        let mirrored = non_edge_positions(&[2, 3], &[0, 1], &graph);
+        assert_eq!(mirrored, cache.non_edge_positions(&[2, 3], &[0, 1], &graph));
+        assert_eq!(6, cache.size);
+        cache.non_edge_positions(&[0, 1], &[2, 3], &graph);
+        assert_eq!(1, cache.hits());
+
+        Ok(())
+    }
+}
@@ -1,24 +1,42 @@
 use std::collections::HashMap;
 
 use custom_debug_derive::Debug;
-use kissat_rs::Literal;
 
+use super::{Literal, OrbitEncoding};
 use crate::graph::VertexIndex;
 
 const MAX_LITERAL: Literal = 2i32.pow(28) - 1;
 
+/// Source of the literals for the SAT encoding. Besides the dictionary,
+/// this allows tools that embed the descriptiveness constraints in their
+/// own formulas to map orbit/vertex pairs to their own variables.
+pub trait LiteralAllocator {
+    /// The literal to which an orbit/vertex pair is mapped,
+    /// always the same one for the same pair.
+    fn lookup_pairing(&mut self, orbit: VertexIndex, vertex: VertexIndex) -> Literal;
+    /// A fresh literal that isn't paired with any orbit/vertex pair.
+    fn new_auxiliary_literal(&mut self) -> Literal;
+}
+
 #[derive(Debug)]
 pub struct SATEncodingDictionary {
     literal_counter: Literal,
+    transversal_clauses: usize,
     #[debug(skip)]
     literal_map: HashMap<i64, Literal>,
+    /// The pairing of each literal, indexed by the literal.
+    /// None for auxiliary literals and the unused literal 0.
+    #[debug(skip)]
+    literal_pairings: Vec<Option<i64>>,
 }
 
 impl Default for SATEncodingDictionary {
     fn default() -> Self {
         SATEncodingDictionary {
             literal_counter: 1,
+            transversal_clauses: 0,
             literal_map: HashMap::new(),
+            literal_pairings: vec![None],
         }
     }
 }
@@ -33,6 +51,7 @@ impl SATEncodingDictionary {
         } else {
             let literal = self.get_new_literal();
             self.literal_map.insert(pairing_result, literal);
+            self.literal_pairings[literal as usize] = Some(pairing_result);
             literal
         }
     }
@@ -48,6 +67,28 @@ impl SATEncodingDictionary {
         (orbit as i32, vertex)
     }
 
+    /// Pair the vertices of the orbits with literals in canonical order, i.e.
+    /// sorted by orbit and then by vertex, before the encodings look them up.
+    /// The numbering then depends neither on the order of the lookups nor on
+    /// auxiliary literals in between, so that the formulas of different runs
+    /// can be diffed.
+    pub fn allocate_canonical(&mut self, orbits: &[OrbitEncoding]) {
+        let mut pairs = orbits
+            .iter()
+            .flat_map(|(orbit, vertices)| vertices.iter().map(move |vertex| (*orbit, *vertex)))
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        for (orbit, vertex) in pairs {
+            self.lookup_pairing(orbit, vertex);
+        }
+    }
+
+    /// A fresh literal that isn't paired with any orbit/vertex pair,
+    /// e.g. for the auxiliary variables of an at most one encoding.
+    pub fn new_auxiliary_literal(&mut self) -> Literal {
+        self.get_new_literal()
+    }
+
     fn get_new_literal(&mut self) -> Literal {
         let new_literal = self.literal_counter;
 
@@ -55,22 +96,83 @@ impl SATEncodingDictionary {
         debug_assert!(new_literal < MAX_LITERAL);
 
         self.literal_counter += 1;
+        self.literal_pairings.push(None);
         new_literal
     }
 
+    /// Forget all pairings but keep the allocated memory.
+    pub fn clear(&mut self) {
+        self.literal_counter = 1;
+        self.transversal_clauses = 0;
+        self.literal_map.clear();
+        self.literal_pairings.truncate(1);
+    }
+
+    /// Number of leading clauses of the encoded formula
+    /// that encode the transversal constraints.
+    pub fn transversal_clauses(&self) -> usize {
+        self.transversal_clauses
+    }
+
+    pub fn set_transversal_clauses(&mut self, transversal_clauses: usize) {
+        self.transversal_clauses = transversal_clauses;
+    }
+
     pub fn variable_number(&self) -> usize {
         self.literal_counter as usize
     }
 
-    pub fn destroy(mut self) -> Vec<(VertexIndex, VertexIndex)> {
-        let mut pairs = vec![(-1, -1); self.literal_counter as usize];
-        for (pairing, literal) in self.literal_map.drain() {
-            pairs[literal as usize] = Self::unpair(pairing);
+    /// The orbit/vertex pair of a literal, regardless of its sign,
+    /// or None for auxiliary and unknown literals.
+    pub fn lookup_literal(&self, literal: Literal) -> Option<(VertexIndex, VertexIndex)> {
+        self.literal_pairings
+            .get(literal.unsigned_abs() as usize)
+            .copied()
+            .flatten()
+            .map(Self::unpair)
+    }
+
+    /// Number of orbit/vertex pairs, i.e. without auxiliary literals.
+    pub fn len(&self) -> usize {
+        self.literal_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.literal_map.is_empty()
+    }
+
+    /// The paired literals with their orbit/vertex pair in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (Literal, (VertexIndex, VertexIndex))> + '_ {
+        self.literal_pairings
+            .iter()
+            .enumerate()
+            .filter_map(|(literal, pairing)| {
+                pairing.map(|pairing| (literal as Literal, Self::unpair(pairing)))
+            })
+    }
+
+    /// The orbit/vertex pair of each literal, indexed by the literal,
+    /// e.g. to comment the variables of a DIMACS export. Auxiliary
+    /// literals (and the unused literal 0) map to (-1, -1).
+    pub fn pairs(&self) -> Vec<(VertexIndex, VertexIndex)> {
+        let mut pairs = vec![(-1, -1); self.variable_number()];
+        for (literal, pair) in self.iter() {
+            pairs[literal as usize] = pair;
         }
         pairs
     }
 }
 
+impl LiteralAllocator for SATEncodingDictionary {
+    fn lookup_pairing(&mut self, orbit: VertexIndex, vertex: VertexIndex) -> Literal {
+        SATEncodingDictionary::lookup_pairing(self, orbit, vertex)
+    }
+
+    fn new_auxiliary_literal(&mut self) -> Literal {
+        SATEncodingDictionary::new_auxiliary_literal(self)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -89,4 +191,67 @@ mod test {
         assert_eq!(0x12345678, orbit);
         assert_eq!(0x01234567, vertex);
     }
+
+    #[test]
+    fn test_clear() {
+        let mut dict = SATEncodingDictionary::default();
+        assert_eq!(1, dict.lookup_pairing(3, 4));
+        assert_eq!(2, dict.lookup_pairing(3, 5));
+
+        dict.clear();
+        assert_eq!(1, dict.variable_number());
+        assert_eq!(1, dict.lookup_pairing(3, 5));
+    }
+
+    #[test]
+    fn test_auxiliary_literal() {
+        let mut dict = SATEncodingDictionary::default();
+        assert_eq!(1, dict.lookup_pairing(3, 4));
+        assert_eq!(2, dict.new_auxiliary_literal());
+        assert_eq!(3, dict.lookup_pairing(3, 5));
+        assert_eq!(vec![(-1, -1), (3, 4), (-1, -1), (3, 5)], dict.pairs());
+    }
+
+    #[test]
+    fn test_lookup_literal() {
+        let mut dict = SATEncodingDictionary::default();
+        assert!(dict.is_empty());
+        assert_eq!(1, dict.lookup_pairing(3, 4));
+        assert_eq!(2, dict.new_auxiliary_literal());
+        assert_eq!(3, dict.lookup_pairing(1, 5));
+
+        assert_eq!(Some((3, 4)), dict.lookup_literal(1));
+        assert_eq!(Some((1, 5)), dict.lookup_literal(-3));
+        assert_eq!(None, dict.lookup_literal(0));
+        assert_eq!(None, dict.lookup_literal(2));
+        assert_eq!(None, dict.lookup_literal(4));
+        assert_eq!(2, dict.len());
+        assert_eq!(
+            vec![(1, (3, 4)), (3, (1, 5))],
+            dict.iter().collect::<Vec<_>>()
+        );
+
+        dict.clear();
+        assert_eq!(None, dict.lookup_literal(1));
+        assert!(dict.is_empty());
+        assert_eq!(1, dict.lookup_pairing(1, 5));
+        assert_eq!(Some((1, 5)), dict.lookup_literal(1));
+    }
+
+    #[test]
+    fn test_allocate_canonical() {
+        let orbits = vec![(2, vec![5, 2]), (0, vec![3, 0]), (1, vec![1])];
+        let mut dict = SATEncodingDictionary::default();
+        dict.allocate_canonical(&orbits);
+        assert_eq!(
+            vec![(-1, -1), (0, 0), (0, 3), (1, 1), (2, 2), (2, 5)],
+            dict.pairs()
+        );
+
+        // Later lookups and auxiliary literals don't change the numbering.
+        assert_eq!(6, dict.new_auxiliary_literal());
+        assert_eq!(5, dict.lookup_pairing(2, 5));
+        assert_eq!(1, dict.lookup_pairing(0, 0));
+        assert_eq!(7, dict.variable_number());
+    }
 }
@@ -63,6 +63,6 @@ impl HighLevelEncoding for QuotientGraph {
     type HighLevelRepresentation = QuotientGraphEncoding;
 
     fn encode_high(&self) -> Self::HighLevelRepresentation {
-        QuotientGraphEncoding(self.quotient_graph.encode_high(), self.orbits.encode_high())
+        self.encoding().clone()
     }
 }
@@ -1,17 +1,23 @@
-use crate::graph::Graph;
+use std::collections::HashSet;
+
+use crate::{
+    graph::{Graph, VertexIndex},
+    EncodingOptions,
+};
 
 use super::{
-    encoding_dict::SATEncodingDictionary,
+    encoding_cache::EncodingCache,
+    encoding_dict::LiteralAllocator,
     high_level::{EdgeEncoding, OrbitEncoding, QuotientGraphEncoding},
-    Formula,
+    Formula, Literal,
 };
 
 pub trait SATEncoding {
-    fn encode_sat(&self, dict: &mut SATEncodingDictionary, original_graph: &Graph) -> Formula;
+    fn encode_sat<A: LiteralAllocator>(&self, dict: &mut A, original_graph: &Graph) -> Formula;
 }
 
 impl SATEncoding for OrbitEncoding {
-    fn encode_sat(&self, dict: &mut SATEncodingDictionary, _original_graph: &Graph) -> Formula {
+    fn encode_sat<A: LiteralAllocator>(&self, dict: &mut A, _original_graph: &Graph) -> Formula {
         // This is actually the encoding that a valid transversal
         // can only choose one element from the orbit.
 
@@ -22,7 +28,7 @@ impl SATEncoding for OrbitEncoding {
         // - ladder: however this works, 3(n-1) binary clauses, n-1 ternary clauses, n-1 aux vars
         // - matrix: how the heck does this even, 2*sqrt(n) aux vars, 1 n-ary clause, 1 sqrt(n)-ary clause, 1 n/sqrt(n)-ary clause, 2n+4*sqrt(n)+O(fourth root n) binary clauses
 
-        // For now we use pairwise encoding, because it's easy to implement
+        // Here we only encode AT LEAST ONE, AT MOST ONE is optional (see `encode_at_most_one`).
         // Disjunction of all vertex-in-orbit pairs to encode AT LEAST ONE
         // ---------------------------------------------------------------
         // \/ vi for all vi in the orbit
@@ -37,59 +43,315 @@ impl SATEncoding for OrbitEncoding {
     }
 }
 
-impl SATEncoding for QuotientGraphEncoding {
-    fn encode_sat(&self, dict: &mut SATEncodingDictionary, original_graph: &Graph) -> Formula {
+/// Pairwise AT MOST ONE constraint for the vertices of an orbit.
+/// for all i,j (~xi || ~xj), size = (n^2-n)/2
+pub fn encode_at_most_one_pairwise<A: LiteralAllocator>(
+    orbit: &OrbitEncoding,
+    dict: &mut A,
+) -> Formula {
+    let (orbit, orbit_elements) = orbit;
+    let mut formula = Vec::new();
+
+    for (position, first_element) in orbit_elements.iter().enumerate() {
+        let first_literal = dict.lookup_pairing(*orbit, *first_element);
+        for second_element in &orbit_elements[position + 1..] {
+            let second_literal = dict.lookup_pairing(*orbit, *second_element);
+            formula.push(vec![-first_literal, -second_literal]);
+        }
+    }
+
+    formula
+}
+
+/// Ladder AT MOST ONE constraint for the vertices of an orbit with n-1 aux vars,
+/// where the aux var si means that one of the first i vertices is picked.
+/// xi => si, si => si+1, si => ~xi+1, size = 3n-4 binary clauses
+/// The channelling clauses of the full ladder aren't needed for AT MOST ONE.
+pub fn encode_at_most_one_ladder<A: LiteralAllocator>(
+    orbit: &OrbitEncoding,
+    dict: &mut A,
+) -> Formula {
+    let (orbit, orbit_elements) = orbit;
+    let mut formula = Vec::new();
+    let mut previous_step: Option<Literal> = None;
+
+    for (position, element) in orbit_elements.iter().enumerate() {
+        let literal = dict.lookup_pairing(*orbit, *element);
+        if let Some(previous_step) = previous_step {
+            formula.push(vec![-literal, -previous_step]);
+        }
+        if position + 1 < orbit_elements.len() {
+            let step = dict.new_auxiliary_literal();
+            formula.push(vec![-literal, step]);
+            if let Some(previous_step) = previous_step {
+                formula.push(vec![-previous_step, step]);
+            }
+            previous_step = Some(step);
+        }
+    }
+
+    formula
+}
+
+/// Binary AT MOST ONE constraint for the vertices of an orbit with ceil(ld n)
+/// aux vars, which hold the position of the picked vertex in binary.
+/// xi => bj if bit j of i is set and xi => ~bj otherwise, size = n*ceil(ld n)
+pub fn encode_at_most_one_binary<A: LiteralAllocator>(
+    orbit: &OrbitEncoding,
+    dict: &mut A,
+) -> Formula {
+    let (orbit, orbit_elements) = orbit;
+    let bit_number = match orbit_elements.len() {
+        0 | 1 => 0,
+        size => (usize::BITS - (size - 1).leading_zeros()) as usize,
+    };
+    let bits = (0..bit_number)
+        .map(|_| dict.new_auxiliary_literal())
+        .collect::<Vec<_>>();
+    let mut formula = Vec::with_capacity(orbit_elements.len() * bit_number);
+
+    for (position, element) in orbit_elements.iter().enumerate() {
+        let literal = dict.lookup_pairing(*orbit, *element);
+        for (bit_position, bit) in bits.iter().enumerate() {
+            if position & (1 << bit_position) == 0 {
+                formula.push(vec![-literal, -bit]);
+            } else {
+                formula.push(vec![-literal, *bit]);
+            }
+        }
+    }
+
+    formula
+}
+
+/// AT MOST ONE constraint for the vertices of an orbit, encoded
+/// pairwise, with the ladder or binary by the size of the orbit.
+pub fn encode_at_most_one<A: LiteralAllocator>(
+    orbit: &OrbitEncoding,
+    dict: &mut A,
+    options: &EncodingOptions,
+) -> Formula {
+    let size = orbit.1.len();
+    if size >= options.binary_threshold {
+        encode_at_most_one_binary(orbit, dict)
+    } else if size >= options.ladder_threshold {
+        encode_at_most_one_ladder(orbit, dict)
+    } else {
+        encode_at_most_one_pairwise(orbit, dict)
+    }
+}
+
+/// Positions (i, j) of all pairs of vertices from the two orbits
+/// that are not connected by an edge in the original graph.
+pub fn non_edge_positions(
+    start_orbit_elements: &[VertexIndex],
+    end_orbit_elements: &[VertexIndex],
+    original_graph: &Graph,
+) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+
+    // for all vertices v1 in o1
+    for (start_position, start_orbit_element) in start_orbit_elements.iter().enumerate() {
+        // for all vertices v2 in o2
+        for (end_position, end_orbit_element) in end_orbit_elements.iter().enumerate() {
+            // If the edge (v1,v2) for the two picked vertices exists
+            // in the original graph, we do not need to encode it.
+            if !original_graph.lookup_edge(start_orbit_element, end_orbit_element) {
+                positions.push((start_position, end_position));
+            }
+        }
+    }
+
+    positions
+}
+
+/// Which vertices of the two orbits aren't connected to any vertex of the other orbit,
+/// i.e. appear in a non-edge with every vertex of the other orbit.
+fn isolated_positions(
+    non_edge_positions: &[(usize, usize)],
+    start_orbit_size: usize,
+    end_orbit_size: usize,
+) -> (Vec<bool>, Vec<bool>) {
+    let mut start_non_edges = vec![0; start_orbit_size];
+    let mut end_non_edges = vec![0; end_orbit_size];
+
+    for (start_position, end_position) in non_edge_positions {
+        start_non_edges[*start_position] += 1;
+        end_non_edges[*end_position] += 1;
+    }
+
+    (
+        start_non_edges
+            .into_iter()
+            .map(|count| count == end_orbit_size)
+            .collect(),
+        end_non_edges
+            .into_iter()
+            .map(|count| count == start_orbit_size)
+            .collect(),
+    )
+}
+
+/// The descriptiveness constraints of the quotient edge between the two orbits:
+/// a transversal may not pick two vertices from them that aren't connected
+/// in the original graph. The non-edges between the orbits are given by
+/// their positions in the orbits, see `non_edge_positions`.
+pub fn encode_edge_constraints<A: LiteralAllocator>(
+    dict: &mut A,
+    start_orbit: &OrbitEncoding,
+    end_orbit: &OrbitEncoding,
+    non_edge_positions: &[(usize, usize)],
+    options: &EncodingOptions,
+) -> Formula {
+    let (start_orbit, start_orbit_elements) = start_orbit;
+    let (end_orbit, end_orbit_elements) = end_orbit;
+    let mut formula = Vec::new();
+
+    // A vertex without any neighbour in the other orbit can never be picked,
+    // which a single unit clause expresses instead of one binary clause
+    // for each vertex of the other orbit.
+    let (start_isolated, end_isolated) = if options.degree_implied {
+        isolated_positions(
+            non_edge_positions,
+            start_orbit_elements.len(),
+            end_orbit_elements.len(),
+        )
+    } else {
+        (
+            vec![false; start_orbit_elements.len()],
+            vec![false; end_orbit_elements.len()],
+        )
+    };
+    for (start_element, _) in start_orbit_elements
+        .iter()
+        .zip(&start_isolated)
+        .filter(|(_, isolated)| **isolated)
+    {
+        formula.push(vec![-dict.lookup_pairing(*start_orbit, *start_element)]);
+    }
+    for (end_element, _) in end_orbit_elements
+        .iter()
+        .zip(&end_isolated)
+        .filter(|(_, isolated)| **isolated)
+    {
+        formula.push(vec![-dict.lookup_pairing(*end_orbit, *end_element)]);
+    }
+
+    for &(start_position, end_position) in non_edge_positions {
+        if start_isolated[start_position] || end_isolated[end_position] {
+            continue;
+        }
+
+        let start_orbit_relation =
+            dict.lookup_pairing(*start_orbit, start_orbit_elements[start_position]);
+        let end_orbit_relation = dict.lookup_pairing(*end_orbit, end_orbit_elements[end_position]);
+
+        // If there is an edge in the quotient graph,
+        // the transversal needs to pick vertices from
+        // the related orbits that are also connected in G.
+        // We don't actually need to encode this for existing edges
+        // in G but only for non-existing ones. We also don't need
+        // the edge in the quotient graph, as it also exists.
+        // ------------------------------------------------
+        // (o1,o2) && (o1, v1) && (o2,v2) => False
+        // ~(o1, v1) || ~(o2,v2)
+        let clause = match (
+            options.propagate_singletons,
+            start_orbit_elements.len() == 1,
+            end_orbit_elements.len() == 1,
+        ) {
+            // The only vertex of a singleton orbit is always picked.
+            (true, true, false) => vec![-end_orbit_relation],
+            (true, false, true) => vec![-start_orbit_relation],
+            _ => vec![-start_orbit_relation, -end_orbit_relation],
+        };
+        formula.push(clause);
+    }
+
+    formula
+}
+
+impl QuotientGraphEncoding {
+    /// Same as `encode_sat`, but with the given encoding options.
+    /// If a cache is given, the non-edges between orbits that were
+    /// already computed for earlier quotients are reused.
+    pub fn encode_sat_with<A: LiteralAllocator>(
+        &self,
+        dict: &mut A,
+        original_graph: &Graph,
+        options: &EncodingOptions,
+        cache: Option<&mut EncodingCache>,
+    ) -> Formula {
+        self.encode_edges(dict, original_graph, options, cache)
+    }
+
+    fn encode_edges<A: LiteralAllocator>(
+        &self,
+        dict: &mut A,
+        original_graph: &Graph,
+        options: &EncodingOptions,
+        mut cache: Option<&mut EncodingCache>,
+    ) -> Formula {
         // This is actually the encoding that edges between two
         // vertices (i.e. two orbits) of a quotient graph is preserved
         // when the transversal chooses two vertices from the orbits.
         let QuotientGraphEncoding(quotient_edges, orbits) = self;
         let mut formula = Vec::new();
 
+        // The edge (o2,o1) yields the same clauses as (o1,o2), just mirrored.
+        // Not so for digraphs, where (o2,o1) asks for the reverse arcs.
+        let edge_set: HashSet<(VertexIndex, VertexIndex)> =
+            if options.mirrored_edges || original_graph.is_declared_directed() {
+                HashSet::new()
+            } else {
+                quotient_edges.iter().map(EdgeEncoding::get_edge).collect()
+            };
+
         // for all (o1,o2) edges in the quotient graph G\O (i.e. o1, o2 in O)
         for (start_orbit, end_orbit) in quotient_edges.iter().map(EdgeEncoding::get_edge) {
-            let start_orbit_elements = {
+            if start_orbit > end_orbit && edge_set.contains(&(end_orbit, start_orbit)) {
+                continue;
+            }
+
+            let start_orbit = {
                 let index = orbits.binary_search_by(|(orbit,_)| orbit.cmp(&start_orbit)) .expect(
                     "The edges were computed from the orbits, how can there be no fitting orbit?",
                 );
-                &orbits[index].1
+                &orbits[index]
             };
-            let end_orbit_elements =
+            let end_orbit =
                 {
                     let index = orbits.binary_search_by(|(orbit,_)| orbit.cmp(&end_orbit)) .expect(
                     "The edges were computed from the orbits, how can there be no fitting orbit?",
                 );
-                    &orbits[index].1
+                    &orbits[index]
                 };
 
-            // for all vertices v1 in o1
-            for start_orbit_element in start_orbit_elements {
-                // for all vertices v2 in o2
-                'end: for end_orbit_element in end_orbit_elements {
-                    // If the edge (v1,v2) for the two picked vertices exists
-                    // in the original graph, we do not need to encode it.
-                    if original_graph.lookup_edge(start_orbit_element, end_orbit_element) {
-                        continue 'end;
-                    }
-
-                    let start_orbit_relation =
-                        dict.lookup_pairing(start_orbit, *start_orbit_element);
-                    let end_orbit_relation = dict.lookup_pairing(end_orbit, *end_orbit_element);
-
-                    // If there is an edge in the quotient graph,
-                    // the transversal needs to pick vertices from
-                    // the related orbits that are also connected in G.
-                    // We don't actually need to encode this for existing edges
-                    // in G but only for non-existing ones. We also don't need
-                    // the edge in the quotient graph, as it also exists.
-                    // ------------------------------------------------
-                    // (o1,o2) && (o1, v1) && (o2,v2) => False
-                    // ~(o1, v1) || ~(o2,v2)
-                    let clause = vec![-start_orbit_relation, -end_orbit_relation];
-                    formula.push(clause);
+            let computed;
+            let non_edge_positions = match cache.as_deref_mut() {
+                Some(cache) => {
+                    cache.non_edge_positions(&start_orbit.1, &end_orbit.1, original_graph)
                 }
-            }
+                None => {
+                    computed = non_edge_positions(&start_orbit.1, &end_orbit.1, original_graph);
+                    &computed
+                }
+            };
+            formula.extend(encode_edge_constraints(
+                dict,
+                start_orbit,
+                end_orbit,
+                non_edge_positions,
+                options,
+            ));
         }
 
         formula
     }
 }
+
+impl SATEncoding for QuotientGraphEncoding {
+    fn encode_sat<A: LiteralAllocator>(&self, dict: &mut A, original_graph: &Graph) -> Formula {
+        self.encode_sat_with(dict, original_graph, &EncodingOptions::default(), None)
+    }
+}
@@ -2,45 +2,159 @@
 //! needed to encode the descriptive quotient problem
 //! as a CNF formula which can then be decided by a SAT solver.
 
-use kissat_rs::Literal;
+#[cfg(feature = "native")]
+pub use kissat_rs::Literal;
+/// Same as the literals of kissat, which isn't available without `native`.
+#[cfg(not(feature = "native"))]
+pub type Literal = i32;
+
+mod encoding_cache;
+pub use encoding_cache::EncodingCache;
 
 mod encoding_dict;
-pub use encoding_dict::SATEncodingDictionary;
+pub use encoding_dict::{LiteralAllocator, SATEncodingDictionary};
 
 mod high_level;
 pub use high_level::{EdgeEncoding, HighLevelEncoding, OrbitEncoding, QuotientGraphEncoding};
 
 mod low_level;
-pub use low_level::SATEncoding;
+pub use low_level::{
+    encode_at_most_one, encode_at_most_one_binary, encode_at_most_one_ladder,
+    encode_at_most_one_pairwise, encode_edge_constraints, non_edge_positions, SATEncoding,
+};
 
-use crate::{graph::Graph, quotient::QuotientGraph};
+use crate::{graph::Graph, quotient::QuotientGraph, EncodingOptions, Error, TransversalEncoding};
 
 pub type Clause = Vec<Literal>;
 pub type Formula = Vec<Clause>;
 
+/// Why a quotient is descriptive without solving anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrivialReason {
+    /// The quotient has no edges between different orbits.
+    NoQuotientEdges,
+    /// Each vertex is adjacent to all vertices of the orbits that its
+    /// orbit has quotient edges to, so every transversal is consistent.
+    CompleteQuotientEdges,
+}
+
+/// The encoding of a quotient, unless it is trivially descriptive.
+pub enum EncodeOutcome<I> {
+    TriviallyDescriptive { reason: TrivialReason },
+    Formula(I, SATEncodingDictionary),
+}
+
+impl<I> EncodeOutcome<I> {
+    /// The formula and its dictionary, None for trivially descriptive quotients.
+    pub fn formula(self) -> Option<(I, SATEncodingDictionary)> {
+        match self {
+            Self::TriviallyDescriptive { .. } => None,
+            Self::Formula(formula, dict) => Some((formula, dict)),
+        }
+    }
+
+    pub fn trivial_reason(&self) -> Option<TrivialReason> {
+        match self {
+            Self::TriviallyDescriptive { reason } => Some(*reason),
+            Self::Formula(..) => None,
+        }
+    }
+}
+
 /// Encode the decision problem whether a set of generators
-/// induces a descriptive quotient graph into SAT.
-#[allow(clippy::needless_collect)]
+/// induces a descriptive quotient graph into SAT. Fails if the
+/// edges of the graph changed since the quotient was built.
 pub fn encode_problem(
     quotient_graph: &QuotientGraph,
     original_graph: &Graph,
-) -> Option<(impl Iterator<Item = Clause>, SATEncodingDictionary)> {
-    let mut dict = SATEncodingDictionary::default();
+    options: &EncodingOptions,
+) -> Result<EncodeOutcome<impl Iterator<Item = Clause>>, Error> {
+    encode_problem_with(
+        quotient_graph,
+        original_graph,
+        options,
+        None,
+        SATEncodingDictionary::default(),
+    )
+}
+
+/// Same as `encode_problem`, but reuses parts of the encoding
+/// from earlier quotients through the cache.
+pub fn encode_problem_cached(
+    quotient_graph: &QuotientGraph,
+    original_graph: &Graph,
+    options: &EncodingOptions,
+    cache: &mut EncodingCache,
+) -> Result<EncodeOutcome<impl Iterator<Item = Clause>>, Error> {
+    encode_problem_with(
+        quotient_graph,
+        original_graph,
+        options,
+        Some(cache),
+        SATEncodingDictionary::default(),
+    )
+}
 
-    let QuotientGraphEncoding(quotient_edges, orbits) = quotient_graph.encode_high();
+/// Same as `encode_problem_cached`, but fills the given dictionary, e.g. one
+/// that was cleared after an earlier quotient to reuse its allocation.
+pub fn encode_problem_reusing(
+    quotient_graph: &QuotientGraph,
+    original_graph: &Graph,
+    options: &EncodingOptions,
+    cache: &mut EncodingCache,
+    dict: SATEncodingDictionary,
+) -> Result<EncodeOutcome<impl Iterator<Item = Clause>>, Error> {
+    encode_problem_with(quotient_graph, original_graph, options, Some(cache), dict)
+}
+
+#[allow(clippy::needless_collect)]
+fn encode_problem_with(
+    quotient_graph: &QuotientGraph,
+    original_graph: &Graph,
+    options: &EncodingOptions,
+    mut cache: Option<&mut EncodingCache>,
+    mut dict: SATEncodingDictionary,
+) -> Result<EncodeOutcome<impl Iterator<Item = Clause>>, Error> {
+    quotient_graph.check_graph(original_graph)?;
+    if let Some(cache) = cache.as_deref_mut() {
+        cache.check_graph(original_graph)?;
+    }
+
+    let quotient_encoding = quotient_graph.encoding();
+    let QuotientGraphEncoding(quotient_edges, orbits) = quotient_encoding;
+    let has_quotient_edges = !quotient_edges.is_empty();
+    dict.allocate_canonical(orbits);
 
     let transversal_encoding = orbits
         .iter()
-        .flat_map(|orbit| orbit.encode_sat(&mut dict, original_graph))
+        .flat_map(|orbit| {
+            let mut orbit_formula = orbit.encode_sat(&mut dict, original_graph);
+            match options.transversal {
+                TransversalEncoding::AtLeastOne => {}
+                TransversalEncoding::Pairwise => {
+                    orbit_formula.extend(encode_at_most_one_pairwise(orbit, &mut dict))
+                }
+                TransversalEncoding::AtMostOne => {
+                    orbit_formula.extend(encode_at_most_one(orbit, &mut dict, options))
+                }
+            }
+            orbit_formula
+        })
         .collect::<Formula>();
+    dict.set_transversal_clauses(transversal_encoding.len());
 
     let descriptive_constraint_encoding =
-        QuotientGraphEncoding(quotient_edges, orbits).encode_sat(&mut dict, original_graph);
+        quotient_encoding.encode_sat_with(&mut dict, original_graph, options, cache);
 
     if descriptive_constraint_encoding.is_empty() {
-        None
+        let reason = if has_quotient_edges {
+            TrivialReason::CompleteQuotientEdges
+        } else {
+            TrivialReason::NoQuotientEdges
+        };
+        Ok(EncodeOutcome::TriviallyDescriptive { reason })
     } else {
-        Some((
+        Ok(EncodeOutcome::Formula(
             transversal_encoding
                 .into_iter()
                 .chain(descriptive_constraint_encoding),
@@ -51,12 +165,18 @@ pub fn encode_problem(
 
 #[cfg(test)]
 mod test {
-    use crate::{encoding::high_level::EdgeEncoding, graph::GraphError, Error};
+    use itertools::Itertools;
+
+    use crate::{
+        encoding::high_level::EdgeEncoding,
+        graph::{GraphError, VertexIndex},
+        Error,
+    };
 
     use super::*;
 
     #[test]
-    fn test_encode_problem_trivial() -> Result<(), GraphError> {
+    fn test_encode_problem_trivial() -> Result<(), Error> {
         // 0 -- 1 -- 2 where 0 and 2 are in the same orbit
         let mut graph = Graph::new_ordered(3);
         graph.add_arc(0, 1)?;
@@ -64,13 +184,25 @@ mod test {
         let orbits = vec![0, 1, 0];
         let quotient_graph = QuotientGraph::from_graph_orbits(&graph, orbits);
 
-        let formula = encode_problem(&quotient_graph, &graph);
-        assert!(formula.is_none());
+        let formula = encode_problem(&quotient_graph, &graph, &EncodingOptions::default())?;
+        assert_eq!(
+            Some(TrivialReason::CompleteQuotientEdges),
+            formula.trivial_reason()
+        );
+
+        // Two isolated vertices in one orbit.
+        let graph = Graph::new_ordered(2);
+        let quotient_graph = QuotientGraph::from_graph_orbits(&graph, vec![0, 0]);
+        let formula = encode_problem(&quotient_graph, &graph, &EncodingOptions::default())?;
+        assert_eq!(
+            Some(TrivialReason::NoQuotientEdges),
+            formula.trivial_reason()
+        );
         Ok(())
     }
 
     #[test]
-    fn test_encode_problem_nontrivial() -> Result<(), GraphError> {
+    fn test_encode_problem_nontrivial() -> Result<(), Error> {
         //0-1-2-3, where 1 and 2 are in the same (fake) orbit.
         let mut graph = Graph::new_ordered(4);
         graph.add_edge(0, 1)?;
@@ -100,9 +232,10 @@ mod test {
             vec![-4, -2],
         ];
 
-        let formula = encode_problem(&quotient, &graph);
-        assert!(formula.is_some());
+        let formula = encode_problem(&quotient, &graph, &EncodingOptions::default())?;
+        assert!(formula.trivial_reason().is_none());
         assert!(formula
+            .formula()
             .unwrap()
             .0
             .zip(expected.into_iter())
@@ -111,6 +244,137 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_encode_problem_options() -> Result<(), Error> {
+        //0-1-2-3, where 1 and 2 are in the same (fake) orbit.
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.add_edge(2, 3)?;
+
+        let fake_orbits = vec![0, 1, 1, 3];
+        let quotient = QuotientGraph::from_graph_orbits(&graph, fake_orbits);
+        let options = EncodingOptions {
+            transversal: TransversalEncoding::Pairwise,
+            mirrored_edges: false,
+            propagate_singletons: true,
+            degree_implied: false,
+            cnf_comments: false,
+            ..Default::default()
+        };
+
+        let expected: Formula = vec![
+            vec![1],
+            vec![2, 3],
+            // Not both 1 and 2 in orbit 1
+            vec![-2, -3],
+            vec![4],
+            // 0 is always picked, so 2 can't be picked for orbit 1
+            vec![-3],
+            // 3 is always picked, so 1 can't be picked for orbit 1
+            vec![-2],
+        ];
+
+        let formula = encode_problem(&quotient, &graph, &options)?
+            .formula()
+            .unwrap()
+            .0;
+        assert_eq!(expected, formula.collect_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_problem_degree_implied() -> Result<(), Error> {
+        // 0-2-1 and 3, where {0,1} and {2,3} are (fake) orbits.
+        // 3 has no neighbour in {0,1} and can never be picked.
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 2)?;
+        graph.add_edge(1, 2)?;
+
+        let fake_orbits = vec![0, 0, 2, 2];
+        let quotient = QuotientGraph::from_graph_orbits(&graph, fake_orbits);
+        let options = EncodingOptions {
+            transversal: TransversalEncoding::Pairwise,
+            mirrored_edges: false,
+            propagate_singletons: false,
+            degree_implied: true,
+            cnf_comments: false,
+            ..Default::default()
+        };
+
+        let expected: Formula = vec![
+            vec![1, 2],
+            vec![-1, -2],
+            vec![3, 4],
+            vec![-3, -4],
+            // Instead of [-1, -4] and [-2, -4]
+            vec![-4],
+        ];
+
+        let formula = encode_problem(&quotient, &graph, &options)?
+            .formula()
+            .unwrap()
+            .0;
+        assert_eq!(expected, formula.collect_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_problem_cached() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(6);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.add_edge(2, 3)?;
+        graph.add_edge(3, 4)?;
+        graph.add_edge(4, 5)?;
+
+        let mut cache = EncodingCache::default();
+        for orbits in [vec![0, 1, 1, 3, 3, 5], vec![0, 1, 1, 3, 4, 4]] {
+            let quotient = QuotientGraph::from_graph_orbits(&graph, orbits);
+            let expected = encode_problem(&quotient, &graph, &EncodingOptions::default())?
+                .formula()
+                .unwrap()
+                .0
+                .collect_vec();
+            let cached =
+                encode_problem_cached(&quotient, &graph, &EncodingOptions::default(), &mut cache)?
+                    .formula()
+                    .unwrap()
+                    .0
+                    .collect_vec();
+            assert_eq!(expected, cached);
+        }
+        // The orbits 0 and {1,2} are part of both quotients.
+        assert_eq!(2, cache.hits());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_problem_digraph_keeps_mirrored_edges() -> Result<(), Error> {
+        // 0 -> 2 and 3 -> 1, where {0,1} and {2,3} are (fake) orbits.
+        // The quotient arcs in both directions forbid different pairs.
+        let mut graph = Graph::new_ordered(4);
+        graph.set_directed(true);
+        graph.connect(0, 2)?;
+        graph.connect(3, 1)?;
+
+        let quotient = QuotientGraph::from_graph_orbits(&graph, vec![0, 0, 2, 2]);
+        let encode = |mirrored_edges| {
+            let options = EncodingOptions {
+                mirrored_edges,
+                ..Default::default()
+            };
+            encode_problem(&quotient, &graph, &options)
+                .map(|outcome| outcome.formula().unwrap().0.collect_vec())
+        };
+        assert_eq!(encode(true)?, encode(false)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_encode_graph_edges() -> Result<(), Error> {
         let mut graph = Graph::new_ordered(5);
@@ -172,6 +436,190 @@ mod test {
         assert_eq!(at_least_one, formula);
     }
 
+    /// Whether the clauses allow picking exactly the given vertices of
+    /// the orbit for some assignment of the auxiliary variables.
+    fn allows_picking(formula: &Formula, picked: &[Literal], variable_number: usize) -> bool {
+        let auxiliary = (1..variable_number as Literal)
+            .filter(|variable| !picked.contains(variable) && !picked.contains(&-variable))
+            .collect_vec();
+        (0..1u64 << auxiliary.len()).any(|auxiliary_assignment| {
+            let value =
+                |literal: Literal| match picked.iter().find(|pick| pick.abs() == literal.abs()) {
+                    Some(pick) => *pick == literal,
+                    None => {
+                        let position = auxiliary
+                            .iter()
+                            .position(|aux| *aux == literal.abs())
+                            .unwrap();
+                        (auxiliary_assignment >> position & 1 == 1) == (literal > 0)
+                    }
+                };
+            formula
+                .iter()
+                .all(|clause| clause.iter().copied().any(value))
+        })
+    }
+
+    #[test]
+    fn test_at_most_one_encodings() {
+        let orbit_encoding: OrbitEncoding = (0, vec![0, 1, 2, 3, 4]);
+        let encodings: [fn(&OrbitEncoding, &mut SATEncodingDictionary) -> Formula; 3] = [
+            encode_at_most_one_pairwise,
+            encode_at_most_one_ladder,
+            encode_at_most_one_binary,
+        ];
+        // Pairwise: 10 clauses, ladder: 3*5-4 clauses and 4 aux vars,
+        // binary: 5*3 clauses and 3 aux vars.
+        let sizes = [(10, 6), (11, 10), (15, 9)];
+
+        for (encode, (clause_number, variable_number)) in encodings.iter().zip(sizes) {
+            let mut dict = SATEncodingDictionary::default();
+            let literals = (0..5)
+                .map(|vertex| dict.lookup_pairing(0, vertex))
+                .collect_vec();
+            let formula = encode(&orbit_encoding, &mut dict);
+            assert_eq!(clause_number, formula.len());
+            assert_eq!(variable_number, dict.variable_number());
+
+            for picked_set in 0..1 << literals.len() {
+                let picked = literals
+                    .iter()
+                    .enumerate()
+                    .map(|(position, literal)| {
+                        if picked_set >> position & 1 == 1 {
+                            *literal
+                        } else {
+                            -literal
+                        }
+                    })
+                    .collect_vec();
+                let picked_number = (picked_set as u32).count_ones();
+                assert_eq!(
+                    picked_number <= 1,
+                    allows_picking(&formula, &picked, variable_number)
+                );
+            }
+        }
+    }
+
+    /// Allocates literals from an offset, like a tool
+    /// that embeds the constraints in its own formula.
+    #[derive(Default)]
+    struct OffsetAllocator(Vec<(VertexIndex, VertexIndex)>);
+
+    impl LiteralAllocator for OffsetAllocator {
+        fn lookup_pairing(&mut self, orbit: VertexIndex, vertex: VertexIndex) -> Literal {
+            let position = match self.0.iter().position(|pair| *pair == (orbit, vertex)) {
+                Some(position) => position,
+                None => {
+                    self.0.push((orbit, vertex));
+                    self.0.len() - 1
+                }
+            };
+            100 + position as Literal
+        }
+
+        fn new_auxiliary_literal(&mut self) -> Literal {
+            self.0.push((-1, -1));
+            99 + self.0.len() as Literal
+        }
+    }
+
+    #[test]
+    fn test_literal_allocator() -> Result<(), GraphError> {
+        // 0-2 and 1-3, where {0,1} and {2,3} are (fake) orbits.
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 2)?;
+        graph.add_edge(1, 3)?;
+        graph.sort();
+
+        let start_orbit: OrbitEncoding = (0, vec![0, 1]);
+        let end_orbit: OrbitEncoding = (2, vec![2, 3]);
+        let mut allocator = OffsetAllocator::default();
+
+        let mut formula = start_orbit.encode_sat(&mut allocator, &graph);
+        formula.extend(encode_at_most_one_ladder(&start_orbit, &mut allocator));
+        let non_edges = non_edge_positions(&start_orbit.1, &end_orbit.1, &graph);
+        formula.extend(encode_edge_constraints(
+            &mut allocator,
+            &start_orbit,
+            &end_orbit,
+            &non_edges,
+            &EncodingOptions::default(),
+        ));
+
+        let expected: Formula = vec![
+            vec![100, 101],
+            // Ladder with the auxiliary literal 102
+            vec![-100, 102],
+            vec![-101, -102],
+            // Not 0 with 3 and not 1 with 2
+            vec![-100, -103],
+            vec![-101, -104],
+        ];
+        assert_eq!(expected, formula);
+        Ok(())
+    }
+
+    #[test]
+    fn test_at_most_one_thresholds() {
+        let options = EncodingOptions {
+            transversal: TransversalEncoding::AtMostOne,
+            ladder_threshold: 3,
+            binary_threshold: 5,
+            ..Default::default()
+        };
+        let clause_number = |size: VertexIndex| {
+            let mut dict = SATEncodingDictionary::default();
+            encode_at_most_one(&(0, (0..size).collect()), &mut dict, &options).len()
+        };
+        // Pairwise, ladder and binary.
+        assert_eq!(1, clause_number(2));
+        assert_eq!(8, clause_number(4));
+        assert_eq!(15, clause_number(5));
+    }
+
+    #[test]
+    fn test_encode_stale_graph() -> Result<(), Error> {
+        let mut graph = Graph::from_edges(4, vec![(0, 1), (1, 2), (2, 3)])?;
+        let quotient = QuotientGraph::from_graph_orbits(&graph, vec![0, 1, 1, 3]);
+        let mut cache = EncodingCache::default();
+        encode_problem_cached(&quotient, &graph, &EncodingOptions::default(), &mut cache)?;
+
+        // Recolouring keeps the quotient and the cache.
+        graph.set_colours(&[1, 2, 2, 1])?;
+        encode_problem_cached(&quotient, &graph, &EncodingOptions::default(), &mut cache)?;
+
+        // The cache only belongs to the first graph until it is cleared.
+        let other = Graph::from_edges(4, vec![(0, 1), (1, 2), (2, 3)])?;
+        let other_quotient = QuotientGraph::from_graph_orbits(&other, vec![0, 1, 1, 3]);
+        assert!(matches!(
+            encode_problem_cached(
+                &other_quotient,
+                &other,
+                &EncodingOptions::default(),
+                &mut cache
+            ),
+            Err(Error::StaleGraph(_))
+        ));
+        cache.clear();
+        encode_problem_cached(
+            &other_quotient,
+            &other,
+            &EncodingOptions::default(),
+            &mut cache,
+        )?;
+
+        graph.add_edge(0, 3)?;
+        graph.sort();
+        assert!(matches!(
+            encode_problem(&quotient, &graph, &EncodingOptions::default()),
+            Err(Error::StaleGraph(_))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_encode_graph() {
         let mut graph = Graph::new_ordered(4);
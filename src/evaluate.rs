@@ -1,20 +1,67 @@
 //! Log evaluation mode that allows to
 //! evaluate the logs of the quotientPlanning
-//! tool run as experiments.
+//! tool run as experiments. Used by the
+//! separate `dqg-eval` binary.
 
+use serde::Deserialize;
 use std::{
     cmp::Ordering,
     fmt::Display,
-    io::{BufRead, Lines},
+    fs::{read_dir, read_to_string},
+    io::{BufRead, Lines, Write},
     iter::Peekable,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use crate::{
     parser::{Input, ParseError},
-    MetricUsed,
+    Error, MetricUsed,
 };
 
+/// The lines of the quotientPlanning log that mark the events of a run.
+/// Newer versions of the tool might word them differently,
+/// so they can be overwritten by a TOML file (see `LogSchema::from_file`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSchema {
+    /// Prefix of the line with the plan length, which directly follows it.
+    pub valid_plan: String,
+    pub not_solved: String,
+    pub no_symmetries: String,
+    pub no_covering_instantiations: String,
+    pub quotient_plan: String,
+    pub concrete_plan: String,
+    /// Number of integer columns before the timings in the tool stats line.
+    pub tool_stats_skipped_columns: usize,
+}
+
+impl Default for LogSchema {
+    fn default() -> Self {
+        LogSchema {
+            valid_plan: "Plan is valid and it is of length ".to_string(),
+            not_solved: "The problem was not solved! Plan can't be valid!".to_string(),
+            no_symmetries: "No symmetries found, exiting!!".to_string(),
+            no_covering_instantiations: "No covering instantiations, exiting!!".to_string(),
+            quotient_plan: "Quotient problem plan:".to_string(),
+            concrete_plan: "Concrete problem plan:".to_string(),
+            tool_stats_skipped_columns: 11,
+        }
+    }
+}
+
+impl LogSchema {
+    /// Read a schema from a TOML file. Left out entries keep their default.
+    #[cfg(not(tarpaulin_include))]
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        Self::from_toml(&read_to_string(path)?)
+    }
+
+    fn from_toml(schema: &str) -> Result<Self, Error> {
+        toml::from_str(schema).map_err(|error| Error::ConfigError(error.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum PlanResult {
     ValidPlan(usize),
@@ -40,6 +87,31 @@ struct ToolStats {
     inst_find_time: f64,
 }
 
+impl ToolStats {
+    /// Time the planner took without symmetries.
+    fn baseline_time(&self) -> f64 {
+        [self.translation_time, self.search_time]
+            .iter()
+            .filter(|time| **time > 0.)
+            .sum()
+    }
+
+    /// Time the quotient approach took to plan, i.e. all timings except
+    /// the ones of the baseline. Negative timings mark steps that weren't run.
+    fn time_to_plan(&self) -> f64 {
+        [
+            self.quotient_translation_time,
+            self.quotient_search_time,
+            self.symm_det_time,
+            self.colouring_time,
+            self.inst_find_time,
+        ]
+        .iter()
+        .filter(|time| **time > 0.)
+        .sum()
+    }
+}
+
 impl Display for ToolStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -64,6 +136,32 @@ pub struct Log {
     tool_stats: ToolStats,
 }
 
+impl Log {
+    pub fn metric(&self) -> MetricUsed {
+        self.metric
+    }
+
+    /// Length of the concrete plan found with the quotient, if there was one.
+    pub fn plan_length(&self) -> Option<usize> {
+        match self.quotient_result {
+            QuotientResult::QuotientConcretePlans(_, PlanResult::ValidPlan(length)) => Some(length),
+            _ => None,
+        }
+    }
+
+    pub fn time_to_plan(&self) -> f64 {
+        self.tool_stats.time_to_plan()
+    }
+
+    /// Length of the plan found without symmetries, if there was one.
+    pub fn baseline_plan_length(&self) -> Option<usize> {
+        match self.default_result {
+            PlanResult::ValidPlan(length) => Some(length),
+            PlanResult::NotSolved => None,
+        }
+    }
+}
+
 impl PartialEq for Log {
     fn eq(&self, other: &Self) -> bool {
         self.metric == other.metric
@@ -74,26 +172,22 @@ impl PartialEq for Log {
 
 impl Eq for Log {}
 
-fn evaluate_plan_result<'a>(line: Input<'a>) -> Option<PlanResult> {
+fn evaluate_plan_result<'a>(line: Input<'a>, schema: &LogSchema) -> Option<PlanResult> {
     use nom::{
         branch::alt, bytes::complete::tag, character::complete::digit1, combinator::map,
         sequence::preceded,
     };
-    let valid_tag =
-        tag::<Input<'a>, Input<'a>, ParseError<'a>>("Plan is valid and it is of length ");
+    let valid_tag = tag::<&str, Input<'a>, ParseError<'a>>(schema.valid_plan.as_str());
     let valid_parser = map(preceded(valid_tag, digit1), |length: &str| {
         PlanResult::ValidPlan(length.parse().unwrap())
     });
-    let not_solved_parser = map(
-        tag("The problem was not solved! Plan can't be valid!"),
-        |_| PlanResult::NotSolved,
-    );
+    let not_solved_parser = map(tag(schema.not_solved.as_str()), |_| PlanResult::NotSolved);
     alt((valid_parser, not_solved_parser))(line)
         .ok()
         .map(|(_, plan_result)| plan_result)
 }
 
-fn evaluate_tool_stats<'a>(line: &'a str) -> Option<ToolStats> {
+fn evaluate_tool_stats<'a>(line: &'a str, schema: &LogSchema) -> Option<ToolStats> {
     use nom::{
         character::complete::{char, i32, multispace0},
         combinator::recognize,
@@ -104,7 +198,8 @@ fn evaluate_tool_stats<'a>(line: &'a str) -> Option<ToolStats> {
     let int_parser =
         terminated::<Input<'a>, i32, Input<'a>, ParseError<'a>, _, _>(i32, multispace0);
     let float_parser = terminated(recognize(tuple((i32, char('.'), i32))), multispace0);
-    let uninteresting_parser = many_m_n(11, 11, int_parser);
+    let skipped_columns = schema.tool_stats_skipped_columns;
+    let uninteresting_parser = many_m_n(skipped_columns, skipped_columns, int_parser);
     let interesting_parser = many_m_n(7, 7, float_parser);
 
     preceded(uninteresting_parser, interesting_parser)(line)
@@ -120,7 +215,10 @@ fn evaluate_tool_stats<'a>(line: &'a str) -> Option<ToolStats> {
         })
 }
 
-fn evaluate_log<B: BufRead>(peekable: &mut Peekable<&mut Lines<B>>) -> Option<Log> {
+fn evaluate_log<B: BufRead>(
+    peekable: &mut Peekable<&mut Lines<B>>,
+    schema: &LogSchema,
+) -> Option<Log> {
     let metric = peekable.find_map(|line| {
         line.unwrap()
             .strip_suffix(':')
@@ -128,35 +226,36 @@ fn evaluate_log<B: BufRead>(peekable: &mut Peekable<&mut Lines<B>>) -> Option<Lo
     })?;
     let tool_stats = peekable
         .next()
-        .and_then(|line| evaluate_tool_stats(line.as_ref().unwrap()))
+        .and_then(|line| evaluate_tool_stats(line.as_ref().unwrap(), schema))
         .unwrap_or_default();
-    let default_result = peekable.find_map(|line| evaluate_plan_result(line.unwrap().as_str()))?;
+    let default_result =
+        peekable.find_map(|line| evaluate_plan_result(line.unwrap().as_str(), schema))?;
 
     let mut quotient_result = QuotientResult::TimedOut;
     let mut quotient_next = false;
 
     loop {
         if peekable
-            .next_if(|line| line.as_ref().unwrap() == "No symmetries found, exiting!!")
+            .next_if(|line| line.as_ref().unwrap() == &schema.no_symmetries)
             .is_some()
         {
             quotient_result = QuotientResult::NoActionSymmetry;
             break;
         } else if peekable
-            .next_if(|line| line.as_ref().unwrap() == "No covering instantiations, exiting!!")
+            .next_if(|line| line.as_ref().unwrap() == &schema.no_covering_instantiations)
             .is_some()
         {
             quotient_result = QuotientResult::Nondescriptive;
             break;
         } else if peekable
-            .next_if(|line| line.as_ref().unwrap() == "Quotient problem plan:")
+            .next_if(|line| line.as_ref().unwrap() == &schema.quotient_plan)
             .is_some()
         {
             quotient_result =
                 QuotientResult::QuotientConcretePlans(PlanResult::NotSolved, PlanResult::NotSolved);
             quotient_next = true;
         } else if peekable
-            .next_if(|line| line.as_ref().unwrap() == "Concrete problem plan:")
+            .next_if(|line| line.as_ref().unwrap() == &schema.concrete_plan)
             .is_some()
         {
             quotient_next = false;
@@ -171,7 +270,7 @@ fn evaluate_log<B: BufRead>(peekable: &mut Peekable<&mut Lines<B>>) -> Option<Lo
             quotient_result = QuotientResult::TimedOut;
             break;
         } else if let Some(plan_result) =
-            evaluate_plan_result(peekable.peek().unwrap().as_ref().unwrap().as_str())
+            evaluate_plan_result(peekable.peek().unwrap().as_ref().unwrap().as_str(), schema)
         {
             if quotient_next {
                 quotient_result =
@@ -202,11 +301,11 @@ fn evaluate_log<B: BufRead>(peekable: &mut Peekable<&mut Lines<B>>) -> Option<Lo
 }
 
 #[cfg(not(tarpaulin_include))]
-pub fn evaluate_log_file<B: BufRead>(file_as_lines: &mut Lines<B>) -> Vec<Log> {
+pub fn evaluate_log_file<B: BufRead>(file_as_lines: &mut Lines<B>, schema: &LogSchema) -> Vec<Log> {
     let mut logs = Vec::new();
     let mut peekable = file_as_lines.peekable();
 
-    while let Some(log) = evaluate_log(&mut peekable) {
+    while let Some(log) = evaluate_log(&mut peekable, schema) {
         logs.push(log);
     }
 
@@ -242,11 +341,11 @@ fn print_eval_results(
     baseline: &PlanResult,
     standard: &QuotientResult,
     standard_result: &Ordering,
-    result: Option<(QuotientResult, ToolStats)>,
+    result: &Option<(QuotientResult, ToolStats)>,
     name: &str,
 ) {
     if let Some((result, stats)) = result {
-        let other_result = compare_results(baseline, &result);
+        let other_result = compare_results(baseline, result);
         match other_result.cmp(standard_result) {
             Ordering::Greater => println!("Success! {:?} for {}: {}", result, name, stats),
             Ordering::Equal => {
@@ -255,7 +354,7 @@ fn print_eval_results(
                     if let QuotientResult::QuotientConcretePlans(_, PlanResult::ValidPlan(m)) =
                         result
                     {
-                        if *n > m {
+                        if n > m {
                             println!("Success! {:?} for {}: {}", result, name, stats);
                             return;
                         }
@@ -271,6 +370,21 @@ fn print_eval_results(
     }
 }
 
+/// The metric that found a valid concrete plan in the least time.
+fn fastest_metric<'a>(
+    results: &[(&'a str, &Option<(QuotientResult, ToolStats)>)],
+) -> Option<(&'a str, f64)> {
+    results
+        .iter()
+        .filter_map(|(name, result)| match result {
+            Some((QuotientResult::QuotientConcretePlans(_, PlanResult::ValidPlan(_)), stats)) => {
+                Some((*name, stats.time_to_plan()))
+            }
+            _ => None,
+        })
+        .min_by(|(_, left), (_, right)| left.total_cmp(right))
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn evaluate_logs(logs: Vec<Log>) {
     let mut baseline = None;
@@ -292,20 +406,141 @@ pub fn evaluate_logs(logs: Vec<Log>) {
     }
 
     if let Some(baseline) = baseline {
-        if let Some(standard) = standard {
-            let standard_result = compare_results(&baseline, &standard.0);
+        if let Some((standard_quotient, standard_stats)) = &standard {
+            let standard_result = compare_results(&baseline, standard_quotient);
             println!(
                 "Baseline: {:?} Standard: {:?}, {}",
-                baseline, standard.0, standard.1
+                baseline, standard_quotient, standard_stats
             );
 
-            print_eval_results(&baseline, &standard.0, &standard_result, least, "Least");
-            print_eval_results(&baseline, &standard.0, &standard_result, biggest, "Biggest");
-            print_eval_results(&baseline, &standard.0, &standard_result, sparse, "Sparse");
+            print_eval_results(
+                &baseline,
+                standard_quotient,
+                &standard_result,
+                &least,
+                "Least",
+            );
+            print_eval_results(
+                &baseline,
+                standard_quotient,
+                &standard_result,
+                &biggest,
+                "Biggest",
+            );
+            print_eval_results(
+                &baseline,
+                standard_quotient,
+                &standard_result,
+                &sparse,
+                "Sparse",
+            );
+
+            let results = [
+                ("Standard", &standard),
+                ("Least", &least),
+                ("Biggest", &biggest),
+                ("Sparse", &sparse),
+            ];
+            for (name, result) in results.iter() {
+                if let Some((_, stats)) = result {
+                    println!("Time to plan for {}: {:.6}", name, stats.time_to_plan());
+                }
+            }
+            println!(
+                "Time to plan for Baseline: {:.6}",
+                standard_stats.baseline_time()
+            );
+            match fastest_metric(&results) {
+                Some((name, time)) => println!("Fastest: {} ({:.6})", name, time),
+                None => println!("Fastest: none found a valid plan"),
+            }
         }
     }
 }
 
+/// Whether the file name matches the pattern, in which `*`
+/// stands for any sequence of characters and `?` for any one.
+fn matches_pattern(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => {
+            matches_pattern(rest, name)
+                || (!name.is_empty() && matches_pattern(pattern, &name[1..]))
+        }
+        (Some(('?', rest)), Some((_, name_rest))) => matches_pattern(rest, name_rest),
+        (Some((expected, rest)), Some((found, name_rest))) => {
+            expected == found && matches_pattern(rest, name_rest)
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// The log files given by the paths, where the file names may contain the
+/// wildcards `*` and `?`, e.g. `logs/*.log`. Matches are sorted by name.
+pub fn expand_log_paths(patterns: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let file_pattern = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.contains(['*', '?']) => name.chars().collect::<Vec<_>>(),
+            _ => {
+                paths.push(path.to_path_buf());
+                continue;
+            }
+        };
+
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut matches = Vec::new();
+        for entry in read_dir(directory)? {
+            let entry = entry?;
+            let name = entry
+                .file_name()
+                .to_string_lossy()
+                .chars()
+                .collect::<Vec<_>>();
+            if entry.file_type()?.is_file() && matches_pattern(&file_pattern, &name) {
+                matches.push(directory.join(entry.file_name()));
+            }
+        }
+        if matches.is_empty() {
+            return Err(Error::ConfigError(format!(
+                "No log file matches {}",
+                pattern
+            )));
+        }
+        matches.sort();
+        paths.append(&mut matches);
+    }
+    Ok(paths)
+}
+
+/// Write one csv row for each metric in the logs of each file.
+pub fn write_log_csv(writer: &mut impl Write, logs: &[(PathBuf, Vec<Log>)]) -> Result<(), Error> {
+    let optional = |value: Option<usize>| value.map_or_else(String::new, |value| value.to_string());
+
+    writeln!(
+        writer,
+        "file,metric,baseline_plan_length,plan_length,time_to_plan"
+    )?;
+    for (path, logs) in logs {
+        for log in logs {
+            writeln!(
+                writer,
+                "{},{},{},{},{:.6}",
+                path.display(),
+                log.metric().name(),
+                optional(log.baseline_plan_length()),
+                optional(log.plan_length()),
+                log.time_to_plan()
+            )?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use std::io::Cursor;
@@ -327,23 +562,29 @@ mod test {
         let plan_result1 = "Plan is valid and it is of length 36";
         assert_eq!(
             Some(PlanResult::ValidPlan(36)),
-            evaluate_plan_result(plan_result1),
+            evaluate_plan_result(plan_result1, &LogSchema::default()),
         );
 
         let plan_result2 = "The problem was not solved! Plan can't be valid!";
         assert_eq!(
             Some(PlanResult::NotSolved),
-            evaluate_plan_result(plan_result2),
+            evaluate_plan_result(plan_result2, &LogSchema::default()),
         );
 
         let plan_result3 = "Covering instantiation set size is: 2";
-        assert_eq!(None, evaluate_plan_result(plan_result3));
+        assert_eq!(
+            None,
+            evaluate_plan_result(plan_result3, &LogSchema::default())
+        );
     }
 
     #[test]
     fn test_evaluate_tool_stats() {
         let tool_stats = "6464 4482 418 400 109151 -1 10261 -1 98 1 0 4.101270 25.530000 -1.000000 23.060000 12.913098 0.000000 0.010000";
-        assert_eq!(Some(TEST_STATS), evaluate_tool_stats(tool_stats));
+        assert_eq!(
+            Some(TEST_STATS),
+            evaluate_tool_stats(tool_stats, &LogSchema::default())
+        );
     }
 
     #[test]
@@ -374,7 +615,7 @@ Primary cover size: 9
 No covering instantiations, exiting!!";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::Standard,
             default_result: PlanResult::ValidPlan(36),
@@ -423,7 +664,7 @@ Number of var orbits added to the quotient problem goal state = 2
 No symmetries found, exiting!!";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::BiggestOrbits,
             default_result: PlanResult::ValidPlan(5),
@@ -470,7 +711,7 @@ Concrete problem plan:
 Plan is valid and it is of length 36";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::LeastOrbits,
             default_result: PlanResult::ValidPlan(194),
@@ -520,7 +761,7 @@ Concrete problem plan:
 The problem was not solved! Plan can't be valid!";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::LeastOrbits,
             default_result: PlanResult::NotSolved,
@@ -605,7 +846,7 @@ Current action is 1452
 Plan is valid and it is of length 12";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::Sparsity,
             default_result: PlanResult::ValidPlan(36),
@@ -654,7 +895,7 @@ Plan is valid and it is of length 36
 sparsity:";
         let mut lines = Cursor::new(raw).lines();
         let mut peekable = (&mut lines).peekable();
-        let log = evaluate_log(&mut peekable);
+        let log = evaluate_log(&mut peekable, &LogSchema::default());
         let expected_log = Some(Log {
             metric: MetricUsed::LeastOrbits,
             default_result: PlanResult::NotSolved,
@@ -663,4 +904,110 @@ sparsity:";
         });
         assert_eq!(expected_log, log);
     }
+
+    #[test]
+    fn test_evaluate_log_schema() -> Result<(), Error> {
+        let schema = LogSchema::from_toml(
+            r#"
+valid_plan = "Found plan of length "
+no_symmetries = "No symmetries."
+tool_stats_skipped_columns = 2
+"#,
+        )?;
+        assert_eq!(LogSchema::default().not_solved, schema.not_solved);
+
+        let raw = "standard:
+1 2 4.101270 25.530000 -1.000000 23.060000 12.913098 0.000000 0.010000
+Found plan of length 7
+No symmetries.";
+        let mut lines = Cursor::new(raw).lines();
+        let mut peekable = (&mut lines).peekable();
+        let log = evaluate_log(&mut peekable, &schema);
+        let expected_log = Some(Log {
+            metric: MetricUsed::Standard,
+            default_result: PlanResult::ValidPlan(7),
+            quotient_result: QuotientResult::NoActionSymmetry,
+            tool_stats: TEST_STATS,
+        });
+        assert_eq!(expected_log, log);
+
+        assert!(LogSchema::from_toml("unknown = \"\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fastest_metric() {
+        let plans = QuotientResult::QuotientConcretePlans(
+            PlanResult::ValidPlan(3),
+            PlanResult::ValidPlan(5),
+        );
+        let slow = Some((
+            plans,
+            ToolStats {
+                inst_find_time: 2.,
+                ..TEST_STATS
+            },
+        ));
+        let fast = Some((plans, TEST_STATS));
+        let fastest_but_unsolved = Some((
+            QuotientResult::Nondescriptive,
+            ToolStats {
+                symm_det_time: 0.,
+                ..TEST_STATS
+            },
+        ));
+
+        assert!((TEST_STATS.time_to_plan() - 35.983098).abs() < 1e-9);
+        assert!((TEST_STATS.baseline_time() - 29.63127).abs() < 1e-9);
+
+        let results = [
+            ("Slow", &slow),
+            ("Fast", &fast),
+            ("Unsolved", &fastest_but_unsolved),
+            ("Missing", &None),
+        ];
+        let (name, time) = fastest_metric(&results).unwrap();
+        assert_eq!("Fast", name);
+        assert!((time - TEST_STATS.time_to_plan()).abs() < 1e-9);
+        assert_eq!(None, fastest_metric(&results[2..]));
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        let matches = |pattern: &str, name: &str| {
+            matches_pattern(
+                &pattern.chars().collect::<Vec<_>>(),
+                &name.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(matches("*.log", "run1.log"));
+        assert!(matches("run?.log", "run1.log"));
+        assert!(matches("*", ""));
+        assert!(matches("r*1*.log", "run1.log"));
+        assert!(!matches("*.log", "run1.txt"));
+        assert!(!matches("run?.log", "run10.log"));
+    }
+
+    #[test]
+    fn test_write_log_csv() -> Result<(), Error> {
+        let logs = vec![(
+            PathBuf::from("logs/run1.log"),
+            vec![Log {
+                metric: MetricUsed::Sparsity,
+                default_result: PlanResult::ValidPlan(36),
+                quotient_result: QuotientResult::Nondescriptive,
+                tool_stats: TEST_STATS,
+            }],
+        )];
+        let mut written = Vec::new();
+        write_log_csv(&mut written, &logs)?;
+        assert_eq!(
+            format!(
+                "file,metric,baseline_plan_length,plan_length,time_to_plan\nlogs/run1.log,sparsity,36,,{:.6}\n",
+                TEST_STATS.time_to_plan()
+            ),
+            String::from_utf8(written).unwrap()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,503 @@
+//! Reproducible experiments described by a TOML config file.
+//! Each combination of input, metric, backend and strategy is run
+//! as a separate DQG process so that runs can't influence each other.
+//!
+//! Example config:
+//! ```toml
+//! inputs = ["graphs/a.dre", "graphs/b.txt"]
+//! repetitions = 3
+//! output_directory = "results"
+//! timeout = 60
+//! arguments = ["-s"]
+//!
+//! [matrix]
+//! metrics = ["standard", "least_orbits"]
+//! backends = ["nauty", "traces"]
+//! strategies = ["powerset", "auto"]
+//! ```
+
+use serde::Deserialize;
+use std::{
+    env::{current_dir, current_exe},
+    fmt,
+    fs::{create_dir_all, read_to_string, File},
+    io::{BufWriter, Write},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    interrupt::{check_interrupted, forward_interrupts, is_interrupted},
+    misc::CoreMetric,
+    Error, MetricUsed, Outcome,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Nauty,
+    Traces,
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nauty" => Ok(Self::Nauty),
+            "traces" => Ok(Self::Traces),
+            _ => Err(Error::ConfigError(format!("Unknown backend {}", s))),
+        }
+    }
+}
+
+/// How to search for descriptive quotients in a single run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Strategy {
+    /// Only check the quotient of all generators.
+    All,
+    Powerset,
+    SearchGroup,
+    Gap,
+    LatticeWalk,
+    Core(CoreMetric),
+}
+
+impl FromStr for Strategy {
+    type Err = Error;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "powerset" => Ok(Self::Powerset),
+            "search_group" => Ok(Self::SearchGroup),
+            "gap" => Ok(Self::Gap),
+            "lattice_walk" => Ok(Self::LatticeWalk),
+            _ => s
+                .parse()
+                .map(Self::Core)
+                .map_err(|_| Error::ConfigError(format!("Unknown strategy {}", s))),
+        }
+    }
+}
+
+fn default_repetitions() -> usize {
+    1
+}
+
+fn default_output_directory() -> PathBuf {
+    PathBuf::from("experiment")
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Matrix {
+    #[serde(default)]
+    metrics: Vec<String>,
+    #[serde(default)]
+    backends: Vec<String>,
+    #[serde(default)]
+    strategies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExperimentConfig {
+    inputs: Vec<PathBuf>,
+    #[serde(default = "default_repetitions")]
+    repetitions: usize,
+    /// Per-run output and the results table go here.
+    #[serde(default = "default_output_directory")]
+    output_directory: PathBuf,
+    /// Timeout in seconds for each run.
+    timeout: Option<u64>,
+    /// Additional arguments passed to each run.
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default)]
+    matrix: Matrix,
+}
+
+/// A single DQG run of the experiment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExperimentRun {
+    input: PathBuf,
+    /// Of the input in the config, as inputs may share a file name.
+    input_index: usize,
+    metric: Option<String>,
+    backend: Backend,
+    strategy: Strategy,
+    repetition: usize,
+}
+
+impl ExperimentRun {
+    fn arguments(&self, config: &ExperimentConfig) -> Vec<String> {
+        let mut arguments = config.arguments.clone();
+
+        if let Some(metric) = &self.metric {
+            arguments.push("--metric".to_string());
+            arguments.push(metric.clone());
+        }
+        if self.backend == Backend::Traces {
+            arguments.push("-t".to_string());
+        }
+        match &self.strategy {
+            Strategy::All => (),
+            Strategy::Powerset => arguments.push("-p".to_string()),
+            Strategy::SearchGroup => arguments.push("-g".to_string()),
+            Strategy::Gap => arguments.push("--gap-mode".to_string()),
+            Strategy::LatticeWalk => arguments.push("--lattice-walk".to_string()),
+            Strategy::Core(metric) => {
+                arguments.push("-q".to_string());
+                arguments.push(metric.name().to_string());
+            }
+        }
+        if let Some(timeout) = config.timeout {
+            arguments.push("--timeout".to_string());
+            arguments.push(timeout.to_string());
+        }
+        // Relative to the directory of the run.
+        arguments.push("--statistics-file".to_string());
+        arguments.push("statistics.dqg".to_string());
+
+        arguments.push(self.input.to_string_lossy().into_owned());
+        arguments
+    }
+
+    /// Name of the directory that this run works in, i.e. where its output,
+    /// statistics and the files of DQG and its external tools are written to.
+    fn directory_name(&self) -> String {
+        format!(
+            "{}_{}_{}_{:?}_{}_{}",
+            self.input_index,
+            self.input
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default(),
+            self.metric.as_deref().unwrap_or("standard"),
+            self.backend,
+            self.strategy,
+            self.repetition
+        )
+        .to_lowercase()
+    }
+}
+
+impl fmt::Display for Strategy {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Powerset => write!(f, "powerset"),
+            Self::SearchGroup => write!(f, "search_group"),
+            Self::Gap => write!(f, "gap"),
+            Self::LatticeWalk => write!(f, "lattice_walk"),
+            Self::Core(metric) => write!(f, "{}", metric.name()),
+        }
+    }
+}
+
+/// All runs of the experiment, i.e. the cross product of inputs,
+/// metrics, backends, strategies and repetitions.
+/// Empty matrix dimensions fall back to the default of DQG.
+fn expand_matrix(config: &ExperimentConfig) -> Result<Vec<ExperimentRun>, Error> {
+    let metrics = if config.matrix.metrics.is_empty() {
+        vec![None]
+    } else {
+        config
+            .matrix
+            .metrics
+            .iter()
+            .map(|metric| {
+                metric.parse::<MetricUsed>()?;
+                Ok(Some(metric.clone()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?
+    };
+    let backends = if config.matrix.backends.is_empty() {
+        vec![Backend::Nauty]
+    } else {
+        config
+            .matrix
+            .backends
+            .iter()
+            .map(|backend| backend.parse())
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let strategies = if config.matrix.strategies.is_empty() {
+        vec![Strategy::All]
+    } else {
+        config
+            .matrix
+            .strategies
+            .iter()
+            .map(|strategy| strategy.parse())
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut runs = Vec::new();
+    for (input_index, input) in config.inputs.iter().enumerate() {
+        for metric in metrics.iter() {
+            for backend in backends.iter() {
+                for strategy in strategies.iter() {
+                    for repetition in 0..config.repetitions {
+                        runs.push(ExperimentRun {
+                            input: input.clone(),
+                            input_index,
+                            metric: metric.clone(),
+                            backend: *backend,
+                            strategy: strategy.clone(),
+                            repetition,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(runs)
+}
+
+fn parse_config(config: &str) -> Result<ExperimentConfig, Error> {
+    toml::from_str(config).map_err(|error| Error::ConfigError(error.to_string()))
+}
+
+/// Human readable meaning of the exit code of a run.
+fn describe_exit_code(exit_code: Option<i32>) -> String {
+    const OUTCOMES: [Outcome; 4] = [
+        Outcome::ExhaustedWithoutDescriptive,
+        Outcome::NonDescriptiveCore,
+        Outcome::Asymmetric,
+        Outcome::Timeout,
+    ];
+
+    match exit_code {
+        None => "killed".to_string(),
+        Some(0) => "success".to_string(),
+        Some(Outcome::PARSE_ERROR_EXIT_CODE) => "parse error".to_string(),
+        Some(Outcome::OTHER_ERROR_EXIT_CODE) => "error".to_string(),
+        Some(code) => OUTCOMES
+            .iter()
+            .find(|outcome| outcome.exit_code() == code)
+            .map_or_else(|| "unknown".to_string(), |outcome| format!("{:?}", outcome)),
+    }
+}
+
+struct RunResult {
+    exit_code: Option<i32>,
+    time: Duration,
+}
+
+fn write_results_table(
+    writer: &mut impl Write,
+    results: &[(ExperimentRun, RunResult)],
+) -> Result<(), Error> {
+    writeln!(
+        writer,
+        "input,metric,backend,strategy,repetition,exit_code,outcome,seconds"
+    )?;
+    for (run, result) in results {
+        writeln!(
+            writer,
+            "{},{},{:?},{},{},{},{},{:.6}",
+            run.input.display(),
+            run.metric.as_deref().unwrap_or("standard"),
+            run.backend,
+            run.strategy,
+            run.repetition,
+            result
+                .exit_code
+                .map_or_else(String::new, |code| code.to_string()),
+            describe_exit_code(result.exit_code),
+            result.time.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
+
+/// Run the experiment described by the config file and write
+/// the consolidated results into `results.csv` in the output directory.
+/// Relative paths in the config are relative to the config file.
+/// Each run works in its own directory in the output directory.
+#[cfg(not(tarpaulin_include))]
+pub fn run_experiment(config_path: &Path) -> Result<Outcome, Error> {
+    let mut config = parse_config(&read_to_string(config_path)?)?;
+    // The runs don't work in the current directory.
+    let base_directory = current_dir()?.join(config_path.parent().unwrap_or_else(|| Path::new("")));
+    config.output_directory = base_directory.join(&config.output_directory);
+    for input in config.inputs.iter_mut() {
+        *input = base_directory.join(&input);
+    }
+
+    let runs = expand_matrix(&config)?;
+    create_dir_all(&config.output_directory)?;
+    let executable = current_exe()?;
+
+    let number_of_runs = runs.len();
+    let mut results = Vec::with_capacity(number_of_runs);
+    for (number, run) in runs.into_iter().enumerate() {
+        // The results of the finished runs are still written.
+        if is_interrupted() {
+            break;
+        }
+        println!(
+            "[{}/{}] {}",
+            number + 1,
+            number_of_runs,
+            run.directory_name()
+        );
+
+        let run_directory = config.output_directory.join(run.directory_name());
+        create_dir_all(&run_directory)?;
+        let output_file = File::create(run_directory.join("dqg.out"))?;
+        let start_time = Instant::now();
+        let mut child = Command::new(&executable)
+            .args(run.arguments(&config))
+            .current_dir(&run_directory)
+            .stdout(output_file.try_clone()?)
+            .stderr(output_file)
+            // Signals of the terminal only reach the run by forwarding.
+            .process_group(0)
+            .spawn()?;
+        // The run stops after its current quotient and saves its statistics.
+        let forwarding = forward_interrupts(&child);
+        let status = child.wait()?;
+        drop(forwarding);
+        let result = RunResult {
+            exit_code: status.code(),
+            time: start_time.elapsed(),
+        };
+
+        results.push((run, result));
+    }
+
+    let mut table = Vec::new();
+    write_results_table(&mut table, &results)?;
+    print!("{}", String::from_utf8_lossy(&table));
+
+    let mut results_file =
+        BufWriter::new(File::create(config.output_directory.join("results.csv"))?);
+    results_file.write_all(&table)?;
+    results_file.flush()?;
+
+    check_interrupted()?;
+    Ok(Outcome::Done)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_matrix() -> Result<(), Error> {
+        let config = parse_config(
+            r#"
+inputs = ["a.dre", "b.txt"]
+repetitions = 2
+timeout = 10
+
+[matrix]
+metrics = ["least_orbits", "sparsity"]
+backends = ["nauty", "traces"]
+strategies = ["powerset", "auto"]
+"#,
+        )?;
+
+        let runs = expand_matrix(&config)?;
+        assert_eq!(2 * 2 * 2 * 2 * 2, runs.len());
+
+        let run = &runs[3];
+        assert_eq!(PathBuf::from("a.dre"), run.input);
+        assert_eq!(Strategy::Core(CoreMetric::Auto), run.strategy);
+        assert_eq!(1, run.repetition);
+        assert_eq!(
+            vec![
+                "--metric",
+                "least_orbits",
+                "-q",
+                "auto",
+                "--timeout",
+                "10",
+                "--statistics-file",
+                "statistics.dqg",
+                "a.dre"
+            ],
+            run.arguments(&config)
+        );
+        assert_eq!("0_a_least_orbits_nauty_auto_1", run.directory_name());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_matrix_defaults() -> Result<(), Error> {
+        let config = parse_config("inputs = [\"a.dre\"]")?;
+        let runs = expand_matrix(&config)?;
+        assert_eq!(1, runs.len());
+        assert_eq!(
+            vec!["--statistics-file", "statistics.dqg", "a.dre"],
+            runs[0].arguments(&config)
+        );
+        assert_eq!(PathBuf::from("experiment"), config.output_directory);
+
+        // Inputs with the same name don't share a directory.
+        let config = parse_config("inputs = [\"a/graph.dre\", \"b/graph.dre\"]")?;
+        let runs = expand_matrix(&config)?;
+        assert_ne!(runs[0].directory_name(), runs[1].directory_name());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(parse_config("inputs = [\"a.dre\"]\nunknown = 1").is_err());
+
+        let config =
+            parse_config("inputs = [\"a.dre\"]\n[matrix]\nbackends = [\"bliss\"]").unwrap();
+        assert!(expand_matrix(&config).is_err());
+        let config = parse_config("inputs = [\"a.dre\"]\n[matrix]\nmetrics = [\"best\"]").unwrap();
+        assert!(expand_matrix(&config).is_err());
+    }
+
+    #[test]
+    fn test_results_table() -> Result<(), Error> {
+        let run = ExperimentRun {
+            input: PathBuf::from("a.dre"),
+            input_index: 0,
+            metric: None,
+            backend: Backend::Traces,
+            strategy: Strategy::Powerset,
+            repetition: 0,
+        };
+        let results = vec![
+            (
+                run.clone(),
+                RunResult {
+                    exit_code: Some(3),
+                    time: Duration::from_millis(1500),
+                },
+            ),
+            (
+                run,
+                RunResult {
+                    exit_code: None,
+                    time: Duration::ZERO,
+                },
+            ),
+        ];
+
+        let mut output = Vec::new();
+        write_results_table(&mut output, &results)?;
+        assert_eq!(
+            "input,metric,backend,strategy,repetition,exit_code,outcome,seconds
+a.dre,standard,Traces,powerset,0,3,ExhaustedWithoutDescriptive,1.500000
+a.dre,standard,Traces,powerset,0,,killed,0.000000
+",
+            String::from_utf8(output).unwrap()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Memory of the external tools that DQG runs, i.e. the kissat binary for
+//! the probes and kitten for the cores. GAP limits itself by its `-o` option.
+//! Without the `native` feature, there are neither limits nor reports.
+
+use std::process::Command;
+#[cfg(feature = "native")]
+use std::{io, os::unix::process::CommandExt};
+
+use crate::misc::MemorySize;
+
+/// Limit the address space of the process of the command, if there is a
+/// limit. Tools that exceed it fail to allocate and exit with an error.
+#[cfg(feature = "native")]
+pub fn limit_memory(command: &mut Command, limit: Option<MemorySize>) -> &mut Command {
+    if let Some(limit) = limit {
+        let bytes = limit.bytes() as libc::rlim_t;
+        let rlimit = libc::rlimit {
+            rlim_cur: bytes,
+            rlim_max: bytes,
+        };
+        // Safety: setrlimit is async-signal-safe and the closure allocates nothing.
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setrlimit(libc::RLIMIT_AS, &rlimit) == 0 {
+                    Ok(())
+                } else {
+                    Err(io::Error::last_os_error())
+                }
+            });
+        }
+    }
+    command
+}
+
+#[cfg(not(feature = "native"))]
+pub fn limit_memory(command: &mut Command, _limit: Option<MemorySize>) -> &mut Command {
+    command
+}
+
+/// The largest resident set size in KiB of any external tool that has
+/// terminated so far, None if the system doesn't report it.
+#[cfg(feature = "native")]
+pub fn peak_external_memory() -> Option<u64> {
+    // Safety: rusage is plain data that getrusage fills.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        return None;
+    }
+    let max_rss = usage.ru_maxrss as u64;
+    // macOS reports bytes instead of KiB.
+    Some(if cfg!(target_os = "macos") {
+        max_rss / 1024
+    } else {
+        max_rss
+    })
+}
+
+#[cfg(not(feature = "native"))]
+pub fn peak_external_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(feature = "native")]
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limit_memory() -> Result<(), io::Error> {
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -v"]);
+        let output = limit_memory(&mut command, "64M".parse().ok()).output()?;
+        // ulimit -v reports KiB.
+        assert_eq!("65536", String::from_utf8_lossy(&output.stdout).trim());
+        assert!(peak_external_memory().is_some_and(|peak| peak > 0));
+        Ok(())
+    }
+}
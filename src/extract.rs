@@ -0,0 +1,119 @@
+//! Extraction of the subgraph of the input graph that is induced
+//! by some orbits, to inspect the structure behind them.
+
+use itertools::Itertools;
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{
+    graph::{Graph, VertexIndex},
+    quotient::{compute_generators, empty_orbits, generate_orbits},
+    statistics::StatisticsSink,
+    writer::write_dreadnaut_file,
+    Error, Settings,
+};
+
+/// A subgraph induced by some orbits.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OrbitSubgraph {
+    pub graph: Graph,
+    /// The vertex of the input graph for each vertex of the subgraph.
+    pub vertices: Vec<VertexIndex>,
+}
+
+/// The subgraph induced by the orbits that contain the selected vertices.
+/// Orbits are named by their smallest vertex, so that an orbit selects itself.
+pub fn orbit_subgraph(
+    graph: &Graph,
+    orbits: &[VertexIndex],
+    selected: &[VertexIndex],
+) -> Result<OrbitSubgraph, Error> {
+    let selected_orbits = selected
+        .iter()
+        .map(|vertex| {
+            orbits
+                .get(*vertex as usize)
+                .copied()
+                .ok_or_else(|| Error::ConfigError(format!("No vertex {} to extract", vertex)))
+        })
+        .collect::<Result<BTreeSet<_>, _>>()?;
+
+    let vertices = orbits
+        .iter()
+        .enumerate()
+        .filter(|(_, orbit)| selected_orbits.contains(orbit))
+        .map(|(vertex, _)| vertex as VertexIndex)
+        .collect_vec();
+    let graph = graph.induce_relabeled_subgraph(&vertices)?;
+
+    Ok(OrbitSubgraph { graph, vertices })
+}
+
+/// Write the subgraph induced by the orbits of the automorphism group that
+/// contain the selected vertices in dreadnaut syntax to the given path.
+#[cfg(not(tarpaulin_include))]
+pub fn extract_orbits(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    selected: &[VertexIndex],
+    path: &Path,
+) -> Result<(), Error> {
+    let mut generators = compute_generators(graph, settings, statistics);
+    graph.sort();
+    let orbits = if generators.is_empty() {
+        empty_orbits(graph.size())
+    } else {
+        generate_orbits(&mut generators)
+    };
+
+    let subgraph = orbit_subgraph(graph, &orbits, selected)?;
+    write_dreadnaut_file(path, &subgraph.graph, false)?;
+
+    // Inputs with sparse vertex ids name the vertices by them.
+    let vertices = match settings.vertex_ids.as_deref() {
+        Some(vertex_ids) => subgraph
+            .vertices
+            .iter()
+            .map(|vertex| vertex_ids[*vertex as usize])
+            .collect_vec(),
+        None => subgraph.vertices,
+    };
+    println!(
+        "Wrote the subgraph induced by {} vertices to {}",
+        vertices.len(),
+        path.display()
+    );
+    println!("Vertices in the input: {}", vertices.iter().join(" "));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_orbit_subgraph() -> Result<(), Error> {
+        // A path 0-1-2-3 with a pendant vertex 4 at 1 and the colours 0, 0, 1, 1, 0.
+        let mut graph = Graph::new_ordered(5);
+        graph.set_colours(&[0, 0, 1, 1, 0])?;
+        for (start, end) in [(0, 1), (1, 2), (2, 3), (1, 4)] {
+            graph.add_edge(start, end)?;
+        }
+        let orbits = vec![0, 1, 2, 3, 0];
+
+        let subgraph = orbit_subgraph(&graph, &orbits, &[1, 4])?;
+        assert_eq!(vec![0, 1, 4], subgraph.vertices);
+        let mut expected = Graph::new_ordered(3);
+        expected.set_colours(&[0, 0, 0])?;
+        expected.add_edge(0, 1)?;
+        expected.add_edge(1, 2)?;
+        assert_eq!(expected, subgraph.graph);
+
+        let subgraph = orbit_subgraph(&graph, &orbits, &[2, 3])?;
+        assert_eq!(vec![2, 3], subgraph.vertices);
+        assert!(subgraph.graph.lookup_edge(&0, &1));
+
+        assert!(orbit_subgraph(&graph, &orbits, &[5]).is_err());
+        Ok(())
+    }
+}
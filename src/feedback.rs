@@ -0,0 +1,230 @@
+//! Feedback from the evaluation of planning runs to the metric choice.
+//!
+//! `dqg-eval` with `--evaluation-csv` appends one row per metric to a csv file:
+//! the fingerprint and size of the graph of the planning task, the metric, the
+//! length of the concrete plan found with the quotient (empty if there was none)
+//! and the time to plan. `--metric-feedback` picks the metric for a new graph
+//! from the rows of the same graph or, if there are none, of the graphs closest
+//! in size.
+
+use itertools::Itertools;
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{evaluate::Log, graph::Graph, Error, MetricUsed};
+
+const FEEDBACK_HEADER: &str = "graph_hash,graph_size,metric,plan_length,time_to_plan";
+
+/// Graphs only count as similar if their sizes differ by at most this factor.
+const SIMILAR_SIZE_FACTOR: usize = 2;
+
+/// Outcome of a planning run with one metric on one graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackRow {
+    graph_hash: u64,
+    graph_size: usize,
+    metric: MetricUsed,
+    plan_length: Option<usize>,
+    time_to_plan: f64,
+}
+
+impl FeedbackRow {
+    fn from_log(log: &Log, graph: &Graph) -> Self {
+        FeedbackRow {
+            graph_hash: graph.fingerprint(),
+            graph_size: graph.size(),
+            metric: log.metric(),
+            plan_length: log.plan_length(),
+            time_to_plan: log.time_to_plan(),
+        }
+    }
+}
+
+impl fmt::Display for FeedbackRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:016x},{},{},{},{:.6}",
+            self.graph_hash,
+            self.graph_size,
+            self.metric.name(),
+            self.plan_length
+                .map_or_else(String::new, |length| length.to_string()),
+            self.time_to_plan
+        )
+    }
+}
+
+impl FromStr for FeedbackRow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::ConfigError(format!("Invalid evaluation feedback row {}", s));
+        let Some((graph_hash, graph_size, metric, plan_length, time_to_plan)) =
+            s.split(',').collect_tuple()
+        else {
+            return Err(invalid());
+        };
+
+        Ok(FeedbackRow {
+            graph_hash: u64::from_str_radix(graph_hash, 16).map_err(|_| invalid())?,
+            graph_size: graph_size.parse().map_err(|_| invalid())?,
+            metric: metric.parse().map_err(|_| invalid())?,
+            plan_length: match plan_length {
+                "" => None,
+                length => Some(length.parse().map_err(|_| invalid())?),
+            },
+            time_to_plan: time_to_plan.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Parse the rows of an evaluation csv. Repeated headers from appended files are skipped.
+pub fn parse_feedback(input: &str) -> Result<Vec<FeedbackRow>, Error> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && *line != FEEDBACK_HEADER)
+        .map(str::parse)
+        .collect()
+}
+
+/// Append the outcome of each metric in the logs to the evaluation csv.
+#[cfg(not(tarpaulin_include))]
+pub fn append_feedback(path: &Path, logs: &[Log], graph: &Graph) -> Result<(), Error> {
+    let is_new = fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    if is_new {
+        writeln!(writer, "{}", FEEDBACK_HEADER)?;
+    }
+    for log in logs {
+        writeln!(writer, "{}", FeedbackRow::from_log(log, graph))?;
+    }
+    writer.flush().map_err(Error::from)
+}
+
+/// The metric that most often led to a valid plan, then to the shortest plans and
+/// then to the least time to plan. Only the rows of the same graph count or, if
+/// there are none, those of the similar graphs closest in size.
+pub fn choose_metric(
+    rows: &[FeedbackRow],
+    graph_hash: u64,
+    graph_size: usize,
+) -> Option<MetricUsed> {
+    let same_graph = rows
+        .iter()
+        .filter(|row| row.graph_hash == graph_hash)
+        .collect_vec();
+    let matching = if same_graph.is_empty() {
+        let distance = |row: &&FeedbackRow| row.graph_size.abs_diff(graph_size);
+        let similar = rows.iter().filter(|row| {
+            row.graph_size <= graph_size * SIMILAR_SIZE_FACTOR
+                && graph_size <= row.graph_size * SIMILAR_SIZE_FACTOR
+        });
+        let closest = similar.clone().map(|row| distance(&row)).min()?;
+        similar.filter(|row| distance(row) == closest).collect_vec()
+    } else {
+        same_graph
+    };
+
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+    matching
+        .into_iter()
+        .into_group_map_by(|row| row.metric)
+        .into_iter()
+        .map(|(metric, rows)| {
+            let lengths = rows
+                .iter()
+                .filter_map(|row| row.plan_length)
+                .map(|length| length as f64)
+                .collect_vec();
+            let valid = lengths.len() as f64 / rows.len() as f64;
+            let times = rows.iter().map(|row| row.time_to_plan).collect_vec();
+            (metric, valid, mean(&lengths), mean(&times))
+        })
+        .min_by(|left, right| {
+            right
+                .1
+                .total_cmp(&left.1)
+                .then(left.2.total_cmp(&right.2))
+                .then(left.3.total_cmp(&right.3))
+                .then(left.0.name().cmp(right.0.name()))
+        })
+        .map(|(metric, ..)| metric)
+}
+
+/// The metric for the graph according to the evaluation csv, if it has feedback for similar graphs.
+#[cfg(not(tarpaulin_include))]
+pub fn metric_from_feedback(path: &Path, graph: &Graph) -> Result<Option<MetricUsed>, Error> {
+    let rows = parse_feedback(&fs::read_to_string(path)?)?;
+    let metric = choose_metric(&rows, graph.fingerprint(), graph.size());
+    match metric {
+        Some(metric) => eprintln!("Using metric {} from evaluation feedback", metric.name()),
+        None => eprintln!("No evaluation feedback for graphs like this one"),
+    }
+    Ok(metric)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(
+        graph_hash: u64,
+        graph_size: usize,
+        metric: MetricUsed,
+        plan_length: Option<usize>,
+    ) -> FeedbackRow {
+        FeedbackRow {
+            graph_hash,
+            graph_size,
+            metric,
+            plan_length,
+            time_to_plan: 1.5,
+        }
+    }
+
+    #[test]
+    fn test_feedback_roundtrip() -> Result<(), Error> {
+        let rows = vec![
+            row(0xab, 10, MetricUsed::Sparsity, Some(12)),
+            row(0xab, 10, MetricUsed::Standard, None),
+        ];
+        let csv = format!("{}\n{}\n{}\n", FEEDBACK_HEADER, rows[0], rows[1]);
+        assert_eq!(
+            "00000000000000ab,10,sparsity,12,1.500000",
+            rows[0].to_string()
+        );
+        assert_eq!(rows, parse_feedback(&csv)?);
+
+        assert!(parse_feedback("ab,10,unknown,12,1.5").is_err());
+        assert!(parse_feedback("ab,10,sparsity,12").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_metric() {
+        let rows = vec![
+            // Same graph: least_orbits finds shorter plans than sparsity.
+            row(1, 10, MetricUsed::Sparsity, Some(12)),
+            row(1, 10, MetricUsed::LeastOrbits, Some(8)),
+            row(1, 10, MetricUsed::Standard, None),
+            // Other graphs: only biggest_orbit found a plan for the closest one.
+            row(2, 18, MetricUsed::BiggestOrbits, Some(30)),
+            row(2, 18, MetricUsed::LeastOrbits, None),
+            row(3, 25, MetricUsed::Sparsity, Some(5)),
+        ];
+
+        assert_eq!(Some(MetricUsed::LeastOrbits), choose_metric(&rows, 1, 10));
+        assert_eq!(Some(MetricUsed::BiggestOrbits), choose_metric(&rows, 4, 16));
+        assert_eq!(Some(MetricUsed::Sparsity), choose_metric(&rows, 4, 24));
+        assert_eq!(None, choose_metric(&rows, 4, 100));
+        assert_eq!(None, choose_metric(&[], 1, 10));
+    }
+}
@@ -0,0 +1,87 @@
+//! One-call check of the quotient induced by the whole automorphism group,
+//! for library callers that need none of the search modes.
+
+use crate::{
+    encoding::{encode_problem, EncodeOutcome, HighLevelEncoding},
+    graph::{Graph, VertexIndex},
+    quotient::{compute_generators, generate_orbits, Orbits, QuotientGraph},
+    sat_solving::{solve_mus_kitten, solve_validate},
+    statistics::NoStatistics,
+    Error, NautyTraces, Settings,
+};
+
+/// Whether the quotient of the full automorphism group is descriptive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptiveOutcome {
+    /// The graph has no non-trivial automorphisms.
+    Asymmetric,
+    /// The quotient is descriptive. The transversal holds one (orbit, vertex)
+    /// pair for each orbit such that the picked vertices induce the quotient.
+    Descriptive {
+        orbits: Orbits,
+        transversal: Vec<(VertexIndex, VertexIndex)>,
+    },
+    /// The quotient is not descriptive. The core consists of the orbits (with
+    /// their vertices) that already have no consistent transversal on their own.
+    NonDescriptive {
+        orbits: Orbits,
+        core: Vec<(VertexIndex, Vec<VertexIndex>)>,
+    },
+}
+
+/// Compute the automorphism group of the graph with nauty, respecting its colours,
+/// and check whether the quotient induced by all generators is descriptive.
+#[cfg(not(tarpaulin_include))]
+pub fn is_full_quotient_descriptive(graph: &Graph) -> Result<DescriptiveOutcome, Error> {
+    let mut graph = graph.clone();
+    let settings = Settings {
+        colored_graph: true,
+        nauyt_or_traces: if graph.is_sparse() {
+            NautyTraces::SparseNauty
+        } else {
+            NautyTraces::Nauty
+        },
+        ..Default::default()
+    };
+
+    let mut generators = compute_generators(&mut graph, &settings, &mut NoStatistics);
+    if generators.is_empty() {
+        return Ok(DescriptiveOutcome::Asymmetric);
+    }
+    // Sort the graph to allow easier lookup for edges.
+    graph.sort();
+
+    let orbits = generate_orbits(&mut generators);
+    let quotient_graph = QuotientGraph::from_graph_orbits(&graph, orbits);
+    let options = &settings.encoding_options;
+
+    let transversal = match encode_problem(&quotient_graph, &graph, options)? {
+        EncodeOutcome::Formula(formula, dict) => solve_validate(formula, dict)?,
+        // Without edges between the orbits, every transversal is consistent.
+        EncodeOutcome::TriviallyDescriptive { .. } => Some(
+            quotient_graph
+                .iter_orbits()
+                .map(|(orbit, vertices)| (orbit, vertices[0]))
+                .collect(),
+        ),
+    };
+
+    if let Some(mut transversal) = transversal {
+        // The encoding may pick more than one vertex of an orbit.
+        transversal.dedup_by_key(|(orbit, _)| *orbit);
+        return Ok(DescriptiveOutcome::Descriptive {
+            orbits: quotient_graph.orbits,
+            transversal,
+        });
+    }
+
+    let (formula, dict) = encode_problem(&quotient_graph, &graph, options)?
+        .formula()
+        .expect("Non-descriptive quotient without encoding!");
+    let core = solve_mus_kitten(formula, &quotient_graph, &graph, dict, options)?
+        .map_or_else(|| quotient_graph.encode_high().1, |core| core.1);
+    Ok(DescriptiveOutcome::NonDescriptive {
+        orbits: quotient_graph.orbits,
+        core,
+    })
+}
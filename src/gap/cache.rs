@@ -0,0 +1,203 @@
+//! Cache of the conjugacy class representatives computed by GAP.
+//!
+//! The cache file is append-only: first one `r` line per representative with the
+//! images of its generators (separated by `|`), then one `c` line per checked
+//! class with its index and whether its quotient is descriptive. A restarted run
+//! of the same graph and generators neither calls GAP again nor rechecks classes.
+
+use itertools::Itertools;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    graph::{fnv1a, Graph},
+    permutation::Permutation,
+    Error,
+};
+
+/// The generators of each conjugacy class representative.
+pub type Representatives = Vec<Vec<Permutation>>;
+
+/// Stable hash of the graph and the generators of its automorphism
+/// group. The check results depend on both, so both are part of the key.
+pub fn cache_key(graph: &Graph, generators: &[Permutation]) -> u64 {
+    let mut hash = graph.fingerprint();
+    fnv1a(&mut hash, generators.len() as i64);
+    for generator in generators {
+        generator
+            .raw
+            .iter()
+            .for_each(|image| fnv1a(&mut hash, *image as i64));
+    }
+    hash
+}
+
+pub fn cache_path(key: u64) -> PathBuf {
+    PathBuf::from(format!("./dqg_gap_{:016x}.cache", key))
+}
+
+/// The cache file while GAP still streams representatives into it.
+/// Only renamed to the cache path once GAP output all of them.
+pub fn partial_cache_path(key: u64) -> PathBuf {
+    cache_path(key).with_extension("partial")
+}
+
+/// Parse the cache file. Returns None if it is malformed or belongs to a graph of
+/// another size. A truncated last line (e.g. from a killed run) is ignored.
+pub fn parse_cache(input: &str, size: usize) -> Option<(Representatives, Vec<Option<bool>>)> {
+    let mut representatives = Vec::new();
+    let mut results = Vec::new();
+
+    let lines = input.split_inclusive('\n');
+    for line in lines.filter(|line| line.ends_with('\n')) {
+        match line.trim_end().split_once(' ') {
+            Some(("r", generators)) => {
+                if !results.is_empty() {
+                    return None;
+                }
+                let representative = generators
+                    .split('|')
+                    .map(|images| Permutation::from_images_str(images, size))
+                    .collect::<Option<_>>()?;
+                representatives.push(representative);
+            }
+            Some(("c", result)) => {
+                results.resize(representatives.len(), None);
+                let (index, descriptive) = result.split_once(' ')?;
+                let index: usize = index.parse().ok()?;
+                *results.get_mut(index)? = Some(descriptive == "1");
+            }
+            _ => return None,
+        }
+    }
+
+    results.resize(representatives.len(), None);
+    Some((representatives, results))
+}
+
+pub fn write_representatives(
+    writer: &mut impl Write,
+    representatives: &[Vec<Permutation>],
+) -> Result<(), Error> {
+    for representative in representatives {
+        let generators = representative
+            .iter()
+            .map(Permutation::images_string)
+            .join("|");
+        writeln!(writer, "r {}", generators)?;
+    }
+    Ok(())
+}
+
+pub fn write_result(writer: &mut impl Write, index: usize, descriptive: bool) -> Result<(), Error> {
+    writeln!(writer, "c {} {}", index, descriptive as u8).map_err(Error::from)
+}
+
+/// The check results of the representatives of one group, backed by its cache file.
+#[derive(Debug)]
+pub struct RepresentativeCache {
+    file: File,
+    results: Vec<Option<bool>>,
+}
+
+impl RepresentativeCache {
+    /// Load the representatives and check results of an earlier run, if there was one.
+    #[cfg(not(tarpaulin_include))]
+    pub fn load(path: &Path, size: usize) -> Result<Option<(Self, Representatives)>, Error> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        let Some((representatives, results)) = parse_cache(&content, size) else {
+            eprintln!(
+                "Ignoring malformed GAP cache file {}, recomputing it.",
+                path.display()
+            );
+            return Ok(None);
+        };
+        // Later results are appended after the last complete line.
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(content.rfind('\n').map_or(0, |end| end + 1) as u64)?;
+        let cache = RepresentativeCache { file, results };
+        Ok(Some((cache, representatives)))
+    }
+
+    /// Start a new cache file for the freshly computed representatives.
+    #[cfg(not(tarpaulin_include))]
+    pub fn create(path: &Path, representatives: &[Vec<Permutation>]) -> Result<Self, Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_representatives(&mut writer, representatives)?;
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        Ok(RepresentativeCache {
+            file,
+            results: vec![None; representatives.len()],
+        })
+    }
+
+    /// Whether the quotient of the representative with the given index
+    /// is descriptive, if an earlier run already checked it.
+    pub fn result(&self, index: usize) -> Option<bool> {
+        self.results.get(index).copied().flatten()
+    }
+
+    /// Store the check result right away, so that it survives the run being killed.
+    #[cfg(not(tarpaulin_include))]
+    pub fn record(&mut self, index: usize, descriptive: bool) -> Result<(), Error> {
+        self.results[index] = Some(descriptive);
+        write_result(&mut self.file, index, descriptive)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let representatives = vec![
+            vec![Permutation::new(vec![1, 0, 2]).unwrap()],
+            vec![
+                Permutation::new(vec![1, 0, 2]).unwrap(),
+                Permutation::new(vec![0, 2, 1]).unwrap(),
+            ],
+        ];
+        let mut written = Vec::new();
+        write_representatives(&mut written, &representatives).unwrap();
+        let mut content = String::from_utf8(written).unwrap();
+        assert_eq!("r 1 0 2\nr 1 0 2|0 2 1\n", content);
+
+        content.push_str("c 1 0\n");
+        // Truncated by a killed run.
+        content.push_str("c 0");
+        let (parsed, results) = parse_cache(&content, 3).unwrap();
+        assert_eq!(representatives, parsed);
+        assert_eq!(vec![None, Some(false)], results);
+
+        // Another graph size or a broken entry invalidate the cache.
+        assert!(parse_cache(&content, 4).is_none());
+        assert!(parse_cache("r 1 1 2\n", 3).is_none());
+        assert!(parse_cache("c 5 1\n", 3).is_none());
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut path = Graph::new_ordered(3);
+        path.add_edge(0, 1).unwrap();
+        path.add_edge(1, 2).unwrap();
+        let mut triangle = path.clone();
+        triangle.add_edge(0, 2).unwrap();
+        let generators = vec![Permutation::new(vec![2, 1, 0]).unwrap()];
+
+        assert_eq!(
+            cache_key(&path, &generators),
+            cache_key(&path.clone(), &generators.clone())
+        );
+        assert_ne!(
+            cache_key(&path, &generators),
+            cache_key(&triangle, &generators)
+        );
+        assert_ne!(cache_key(&path, &generators), cache_key(&path, &[]));
+    }
+}
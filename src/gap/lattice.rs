@@ -0,0 +1,227 @@
+//! Walk down the subgroup lattice from the full automorphism group
+//! until the quotient induced by the subgroup is descriptive.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    debug::print_orbits,
+    encoding::{encode_problem, EncodeOutcome, OrbitEncoding},
+    graph::{Graph, VertexIndex},
+    interrupt::check_interrupted,
+    permutation::Permutation,
+    quotient::{empty_orbits, generate_orbits, Orbits, QuotientGraph, TrivialPartition},
+    sat_solving::solve_mus_kitten,
+    statistics::StatisticsSink,
+    Error, Outcome, Settings,
+};
+
+use super::{
+    call_gap, parse_representatives, print::write_maximal_subgroups_input, DEFAULT_GAP_MEMORY,
+};
+
+/// Number of core orbits that are split into several orbits by the subgroup orbits.
+fn split_core_orbits(core: &[OrbitEncoding], subgroup_orbits: &[VertexIndex]) -> usize {
+    core.iter()
+        .filter(|(_, members)| {
+            members.iter().any(|member| {
+                subgroup_orbits[*member as usize] != subgroup_orbits[members[0] as usize]
+            })
+        })
+        .count()
+}
+
+fn number_of_orbits(orbits: &[VertexIndex]) -> usize {
+    orbits
+        .iter()
+        .enumerate()
+        .filter(|(vertex, orbit)| *vertex == **orbit as usize)
+        .count()
+}
+
+/// Pick the maximal subgroup to descend into. Subgroups that split more orbits
+/// of the non-descriptive core are preferred, as the core can't appear in their
+/// quotients anymore. Ties are broken by the number of orbits to keep the
+/// quotient as small as possible.
+fn pick_subgroup(
+    core: &[OrbitEncoding],
+    subgroups: Vec<(Vec<Permutation>, Orbits)>,
+) -> Option<(Vec<Permutation>, Orbits)> {
+    subgroups.into_iter().min_by_key(|(_, orbits)| {
+        (
+            std::cmp::Reverse(split_core_orbits(core, orbits)),
+            number_of_orbits(orbits),
+        )
+    })
+}
+
+#[cfg(not(tarpaulin_include))]
+fn report_descriptive_subgroup(
+    mut group: Vec<Permutation>,
+    graph: &Graph,
+    depth: usize,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) {
+    println!(
+        "Maximal descriptive subgroup found at depth {} with {} generators:",
+        depth,
+        group.len()
+    );
+    for generator in group.iter() {
+        println!("{}", generator);
+    }
+
+    if settings.output_orbits {
+        let orbits = if group.is_empty() {
+            empty_orbits(graph.size())
+        } else {
+            generate_orbits(&mut group)
+        };
+        print_orbits(orbits, settings, statistics);
+    }
+}
+
+/// Starting from the group generated by the generators, descend into maximal
+/// subgroups (computed by GAP) until the induced quotient is descriptive.
+/// The non-descriptive core of each quotient decides which subgroup to take.
+#[cfg(not(tarpaulin_include))]
+pub fn lattice_walk(
+    graph: &Graph,
+    generators: Vec<Permutation>,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<Outcome, Error> {
+    if generators.is_empty() {
+        return Ok(Outcome::Asymmetric);
+    }
+
+    let mut group = generators;
+    let mut depth = 0;
+    let mut gap_time = Duration::ZERO;
+
+    loop {
+        check_interrupted()?;
+        statistics.log(|stats| stats.log_iteration());
+
+        // The trivial group always induces a descriptive quotient.
+        if group.is_empty() {
+            break;
+        }
+
+        let orbits = generate_orbits(&mut group);
+        if TrivialPartition::detect(&orbits).is_some() {
+            break;
+        }
+
+        let quotient = QuotientGraph::from_graph_orbits(graph, orbits);
+        let core = match encode_problem(&quotient, graph, &settings.encoding_options)? {
+            EncodeOutcome::Formula(formula, dict) => {
+                solve_mus_kitten(formula, &quotient, graph, dict, &settings.encoding_options)?
+            }
+            EncodeOutcome::TriviallyDescriptive { .. } => None,
+        };
+
+        let core = match core {
+            Some(core) => core.1,
+            None => break,
+        };
+
+        write_maximal_subgroups_input(group.clone())?;
+        let before_gap_time = Instant::now();
+        let gap_out = call_gap(settings.gap_memory.unwrap_or(DEFAULT_GAP_MEMORY))?;
+        gap_time += before_gap_time.elapsed();
+
+        let subgroups = match gap_out {
+            Some(gap_out) if gap_out.iter().any(|byte| !byte.is_ascii_whitespace()) => {
+                parse_representatives(&gap_out, graph.size())?
+            }
+            // Only trivial maximal subgroups.
+            Some(_) => Vec::new(),
+            None => {
+                println!("GAP failed to compute the maximal subgroups, stopping.");
+                statistics.log(|stats| {
+                    stats.log_gap_done(gap_time);
+                    stats.log_end();
+                });
+                statistics.save()?;
+                return Ok(Outcome::NonDescriptiveCore);
+            }
+        };
+
+        let subgroups = subgroups
+            .into_iter()
+            .map(|mut subgroup| {
+                let orbits = generate_orbits(&mut subgroup);
+                (subgroup, orbits)
+            })
+            .collect();
+
+        group = match pick_subgroup(&core, subgroups) {
+            Some((subgroup, _)) => subgroup,
+            None => Vec::new(),
+        };
+        depth += 1;
+    }
+
+    report_descriptive_subgroup(group, graph, depth, settings, statistics);
+
+    statistics.log(|stats| {
+        stats.exhausted = true;
+        stats.log_gap_done(gap_time);
+        stats.log_end();
+    });
+    statistics.save()?;
+
+    Ok(Outcome::DescriptiveFound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pick_subgroup() {
+        let size = 6;
+        // Core with the orbits {0,1} and {2,3}.
+        let core = vec![(0, vec![0, 1]), (2, vec![2, 3])];
+
+        // Keeps both core orbits.
+        let keeps_core =
+            vec![Permutation::from_cycles(vec![vec![0, 1], vec![2, 3]], size).unwrap()];
+        // Splits {2,3} and keeps {0,1} and {4,5}.
+        let splits_one =
+            vec![Permutation::from_cycles(vec![vec![0, 1], vec![4, 5]], size).unwrap()];
+        // Splits both core orbits, but has more orbits.
+        let splits_both = vec![Permutation::from_cycles(vec![vec![4, 5]], size).unwrap()];
+        // Splits both core orbits with fewer orbits.
+        let splits_both_coarse =
+            vec![Permutation::from_cycles(vec![vec![0, 2], vec![1, 3]], size).unwrap()];
+
+        let subgroups = vec![
+            keeps_core,
+            splits_one,
+            splits_both,
+            splits_both_coarse.clone(),
+        ]
+        .into_iter()
+        .map(|mut subgroup| {
+            let orbits = generate_orbits(&mut subgroup);
+            (subgroup, orbits)
+        })
+        .collect();
+
+        let (picked, _) = pick_subgroup(&core, subgroups).unwrap();
+        assert_eq!(splits_both_coarse, picked);
+
+        assert!(pick_subgroup(&core, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_split_core_orbits() {
+        let core = vec![(0, vec![0, 1]), (2, vec![2, 3])];
+        assert_eq!(0, split_core_orbits(&core, &[0, 0, 2, 2]));
+        assert_eq!(1, split_core_orbits(&core, &[0, 0, 2, 3]));
+        assert_eq!(2, split_core_orbits(&core, &[0, 1, 2, 3]));
+        assert_eq!(4, number_of_orbits(&[0, 1, 2, 3]));
+    }
+}
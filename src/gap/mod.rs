@@ -1,95 +1,333 @@
+use itertools::Itertools;
 use std::{
-    process::{Command, Stdio},
-    time::Instant,
+    io::Read,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    debug::print_orbits_nauty_style, graph::Graph, permutation::Permutation,
-    quotient::generate_orbits, statistics::Statistics, Error,
+    debug::print_orbits,
+    encoding::EncodingCache,
+    graph::Graph,
+    interrupt::{check_interrupted, is_interrupted},
+    permutation::Permutation,
+    quotient::{generate_orbits, Orbits},
+    statistics::{Statistics, StatisticsSink},
+    timed_out, EncodingOptions, Error, MemorySize, Outcome, Settings,
 };
 
 mod print;
 use print::write_gap_input;
+pub use print::write_group;
 
 mod parser;
-use parser::parse_representatives;
+pub use parser::parse_representatives;
+
+mod cache;
+use cache::{cache_key, cache_path, RepresentativeCache, Representatives};
+
+mod stream;
+use stream::{stream_classes, StreamedClasses};
 
 mod search;
 use search::{check_class, check_class_stats};
 
+mod lattice;
+pub use lattice::lattice_walk;
+
+mod verify;
+use verify::verify_representatives;
+
 pub static GAP_IN_FILE: &str = "./dqg.g";
 
+/// How a run of GAP ended.
+enum GapRun {
+    Finished(Vec<u8>),
+    Failed,
+    DeadlineExceeded,
+}
+
+/// How many lines of unexpected GAP output to show.
+const GAP_OUTPUT_LINES_SHOWN: usize = 5;
+
+/// How long to wait between checks whether GAP has finished.
+const GAP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Memory limit of GAP for the conjugacy classes if none is configured.
+const CLASSES_GAP_MEMORY: MemorySize = MemorySize::gibibytes(16);
+
+/// Memory limit of GAP if none is configured, e.g. for the maximal subgroups.
+pub const DEFAULT_GAP_MEMORY: MemorySize = MemorySize::gibibytes(4);
+
+/// Start GAP on the input file with the given memory limit and its output piped.
+#[cfg(not(tarpaulin_include))]
+fn spawn_gap(memory: MemorySize) -> Result<Child, Error> {
+    Command::new("gap")
+        .arg("-b")
+        .arg("-o")
+        .arg(memory.to_string())
+        .arg("--nointeract")
+        .arg(GAP_IN_FILE)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(Error::from)
+}
+
+/// The error for GAP output that is no list of representatives.
+fn unexpected_output(gap_out: &[u8]) -> Error {
+    let gap_out = String::from_utf8_lossy(gap_out);
+    Error::GapError(format!(
+        "expected one list of generators per class, got:\n{}",
+        gap_out.lines().take(GAP_OUTPUT_LINES_SHOWN).join("\n")
+    ))
+}
+
+/// Run GAP on the input file with the given memory limit
+/// and kill it if it didn't finish before the deadline
+/// or the run was interrupted.
+#[cfg(not(tarpaulin_include))]
+fn call_gap_until(memory: MemorySize, deadline: Option<Duration>) -> Result<GapRun, Error> {
+    let start_time = Instant::now();
+    let mut gap = spawn_gap(memory)?;
+
+    // Read the output concurrently so that GAP never blocks on a full pipe.
+    let mut stdout = gap.stdout.take().expect("GAP stdout is piped!");
+    let reader = thread::spawn(move || {
+        let mut gap_out = Vec::new();
+        stdout.read_to_end(&mut gap_out).map(|_| gap_out)
+    });
+
+    let status = loop {
+        if let Some(status) = gap.try_wait()? {
+            break status;
+        }
+        if is_interrupted() {
+            // GAP may have exited in the meantime.
+            let _ = gap.kill();
+            gap.wait()?;
+            return Err(Error::Interrupted);
+        }
+        if timed_out(start_time, deadline) {
+            gap.kill()?;
+            gap.wait()?;
+            return Ok(GapRun::DeadlineExceeded);
+        }
+        thread::sleep(GAP_POLL_INTERVAL);
+    };
+
+    let gap_out = reader.join().expect("GAP output reader panicked!")?;
+    if status.success() {
+        Ok(GapRun::Finished(gap_out))
+    } else {
+        Ok(GapRun::Failed)
+    }
+}
+
+/// Run GAP on the input file with the given memory
+/// limit and return its output if it succeeded.
+#[cfg(not(tarpaulin_include))]
+fn call_gap(memory: MemorySize) -> Result<Option<Vec<u8>>, Error> {
+    match call_gap_until(memory, None)? {
+        GapRun::Finished(gap_out) => Ok(Some(gap_out)),
+        GapRun::Failed | GapRun::DeadlineExceeded => Ok(None),
+    }
+}
+
+/// The conjugacy class representatives, if they are known.
+enum ClassRepresentatives {
+    Found(RepresentativeCache, Representatives),
+    GapFailed,
+    DeadlineExceeded,
+}
+
+/// The conjugacy class representatives from the cache of an earlier run, if there was one.
+#[cfg(not(tarpaulin_include))]
+fn cached_representatives(
+    graph: &Graph,
+    key: u64,
+) -> Result<Option<(RepresentativeCache, Representatives)>, Error> {
+    let cached = RepresentativeCache::load(&cache_path(key), graph.size())?;
+    if let Some((_, representatives)) = cached.as_ref() {
+        verify_representatives(graph, representatives)?;
+    }
+    Ok(cached)
+}
+
+/// The conjugacy class representatives of the group generated by the generators,
+/// either from the cache of an earlier run or computed by GAP before the deadline.
+#[cfg(not(tarpaulin_include))]
+fn class_representatives(
+    graph: &Graph,
+    generators: Vec<Permutation>,
+    memory: MemorySize,
+    deadline: Option<Duration>,
+) -> Result<ClassRepresentatives, Error> {
+    let key = cache_key(graph, &generators);
+    if let Some((cache, representatives)) = cached_representatives(graph, key)? {
+        return Ok(ClassRepresentatives::Found(cache, representatives));
+    }
+
+    write_gap_input(generators)?;
+    let gap_out = match call_gap_until(memory, deadline)? {
+        GapRun::Finished(gap_out) => gap_out,
+        GapRun::Failed => return Ok(ClassRepresentatives::GapFailed),
+        GapRun::DeadlineExceeded => return Ok(ClassRepresentatives::DeadlineExceeded),
+    };
+    let representatives =
+        parse_representatives(&gap_out, graph.size()).map_err(|_| unexpected_output(&gap_out))?;
+    verify_representatives(graph, &representatives)?;
+    let cache = RepresentativeCache::create(&cache_path(key), &representatives)?;
+    Ok(ClassRepresentatives::Found(cache, representatives))
+}
+
+/// Search the conjugacy classes of subgroups for a descriptive quotient.
+/// None if GAP missed the deadline and the caller should search the subsets
+/// of generators instead.
 #[cfg(not(tarpaulin_include))]
 pub fn gap_mode(
     graph: &Graph,
     mut generators: Vec<Permutation>,
-    statistics: &mut Option<Statistics>,
-) -> Result<(), Error> {
-    if let Some(stats) = statistics {
-        return gap_mode_statistics(graph, generators, stats);
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<Option<Outcome>, Error> {
+    let encoding_options = &settings.encoding_options;
+    if let Some(stats) = statistics.statistics() {
+        let outcome = gap_mode_statistics(
+            graph,
+            generators,
+            encoding_options,
+            settings.gap_memory.unwrap_or(DEFAULT_GAP_MEMORY),
+            settings.gap_deadline,
+            stats,
+        )?;
+        // The fallback search saves the statistics once it is done.
+        if let Some((outcome, orbits)) = outcome {
+            if let Some(orbits) = orbits.filter(|_| settings.output_orbits) {
+                print_orbits(orbits, settings, statistics);
+            }
+            statistics.save()?;
+            return Ok(Some(outcome));
+        }
+        return Ok(None);
     }
 
-    // Early exit if full quotient is descriptive.
-    let full_orbits = generate_orbits(&mut generators);
-    if check_class(graph, full_orbits.clone())? {
-        print_orbits_nauty_style(full_orbits, None);
-        return Ok(());
+    if generators.is_empty() {
+        return Ok(Some(Outcome::Asymmetric));
     }
 
-    write_gap_input(generators)?;
+    // The conjugacy class representatives share many orbits.
+    let mut encoding_cache = EncodingCache::default();
 
-    let gap = Command::new("gap")
-        .arg("-b")
-        .arg("-o")
-        .arg("16G")
-        .arg("--nointeract")
-        .arg(GAP_IN_FILE)
-        .stdout(Stdio::piped())
-        .spawn()?;
+    // Early exit if full quotient is descriptive.
+    let full_orbits = generate_orbits(&mut generators);
+    if check_class(
+        graph,
+        full_orbits.clone(),
+        encoding_options,
+        &mut encoding_cache,
+    )? {
+        if settings.output_orbits {
+            print_orbits(full_orbits, settings, statistics);
+        }
+        return Ok(Some(Outcome::DescriptiveFound));
+    }
 
-    let gap_out = gap.wait_with_output()?;
+    let key = cache_key(graph, &generators);
+    let Some((mut cache, representatives)) = cached_representatives(graph, key)? else {
+        // Check the classes while GAP still prints them.
+        return match stream_classes(
+            graph,
+            generators,
+            settings.gap_memory.unwrap_or(CLASSES_GAP_MEMORY),
+            settings,
+            key,
+        )? {
+            StreamedClasses::Descriptive(orbits) => {
+                if settings.output_orbits {
+                    print_orbits(orbits, settings, statistics);
+                }
+                Ok(Some(Outcome::DescriptiveFound))
+            }
+            StreamedClasses::Exhausted | StreamedClasses::GapFailed => {
+                Ok(Some(Outcome::ExhaustedWithoutDescriptive))
+            }
+            StreamedClasses::DeadlineExceeded => {
+                eprintln!("GAP missed its deadline, searching the subsets of generators instead.");
+                Ok(None)
+            }
+        };
+    };
 
-    if gap_out.status.success() {
-        let representatives = parse_representatives(&gap_out.stdout, graph.size())?;
-        for mut representative in representatives {
-            let orbits = generate_orbits(&mut representative);
-            if check_class(graph, orbits.clone())? {
-                print_orbits_nauty_style(orbits, None);
-                break;
+    for (index, mut representative) in representatives.into_iter().enumerate() {
+        check_interrupted()?;
+        let orbits = generate_orbits(&mut representative);
+        let descriptive = match cache.result(index) {
+            Some(descriptive) => descriptive,
+            None => {
+                let descriptive =
+                    check_class(graph, orbits.clone(), encoding_options, &mut encoding_cache)?;
+                cache.record(index, descriptive)?;
+                descriptive
             }
+        };
+        if descriptive {
+            if settings.output_orbits {
+                print_orbits(orbits, settings, statistics);
+            }
+            return Ok(Some(Outcome::DescriptiveFound));
         }
     }
 
-    Ok(())
+    Ok(Some(Outcome::ExhaustedWithoutDescriptive))
 }
 
 #[cfg(not(tarpaulin_include))]
 fn gap_mode_statistics(
     graph: &Graph,
     generators: Vec<Permutation>,
+    encoding_options: &EncodingOptions,
+    memory: MemorySize,
+    deadline: Option<Duration>,
     statistics: &mut Statistics,
-) -> Result<(), Error> {
+) -> Result<Option<(Outcome, Option<Orbits>)>, Error> {
+    let mut encoding_cache = EncodingCache::default();
+    let mut outcome = Outcome::Asymmetric;
+    let mut descriptive_orbits = None;
+
     if !generators.is_empty() {
-        write_gap_input(generators)?;
+        outcome = Outcome::ExhaustedWithoutDescriptive;
         let before_gap_time = Instant::now();
 
-        let gap = Command::new("gap")
-            .arg("-b")
-            .arg("-o")
-            .arg("4G")
-            .arg("--nointeract")
-            .arg(GAP_IN_FILE)
-            .stdout(Stdio::piped())
-            .spawn()?;
-
-        let gap_out = gap.wait_with_output()?;
+        let representatives = class_representatives(graph, generators, memory, deadline)?;
         statistics.log_gap_done(before_gap_time.elapsed());
 
-        if gap_out.status.success() {
-            let representatives = parse_representatives(&gap_out.stdout, graph.size())?;
-            for mut representative in representatives {
-                if check_class_stats(graph, &mut representative, statistics)? {
-                    //print_orbits_nauty_style(orbits, Some(statistics));
+        if let ClassRepresentatives::DeadlineExceeded = representatives {
+            statistics.log_gap_fallback();
+            return Ok(None);
+        }
+
+        if let ClassRepresentatives::Found(mut cache, representatives) = representatives {
+            for (index, mut representative) in representatives.into_iter().enumerate() {
+                check_interrupted()?;
+                // Classes checked by an earlier run are not logged again.
+                let descriptive = match cache.result(index) {
+                    Some(descriptive) => descriptive,
+                    None => {
+                        let descriptive = check_class_stats(
+                            graph,
+                            &mut representative,
+                            statistics,
+                            encoding_options,
+                            &mut encoding_cache,
+                        )?;
+                        cache.record(index, descriptive)?;
+                        descriptive
+                    }
+                };
+                if descriptive {
+                    descriptive_orbits = Some(generate_orbits(&mut representative));
+                    outcome = Outcome::DescriptiveFound;
                     break;
                 }
             }
@@ -97,6 +335,8 @@ fn gap_mode_statistics(
     }
 
     statistics.exhausted = true;
+    statistics.log_encoding_cache_hits(encoding_cache.hits());
     statistics.log_end();
-    statistics.save_statistics()
+
+    Ok(Some((outcome, descriptive_orbits)))
 }
@@ -7,19 +7,23 @@ use crate::{
 
 use nom::{
     character::complete::{char, i32, line_ending, multispace0},
-    combinator::map,
+    combinator::{all_consuming, map, map_opt, verify},
     multi::{many1, separated_list1},
     sequence::{delimited, preceded, tuple},
 };
 
-fn parse_cycle(input: BinInput<'_>) -> BinParseResult<'_, Vec<VertexIndex>> {
-    let cycle = separated_list1(char(','), preceded(multispace0, map(i32, |i| i - 1)));
+/// GAP points start at 1, so each point is shifted
+/// by one and has to be inside the permutation.
+fn parse_cycle(input: BinInput<'_>, size: usize) -> BinParseResult<'_, Vec<VertexIndex>> {
+    let point = verify(i32, |i| *i >= 1 && *i as usize <= size);
+    let cycle = separated_list1(char(','), preceded(multispace0, map(point, |i| i - 1)));
     preceded(multispace0, delimited(char('('), cycle, char(')')))(input)
 }
 
 fn parse_permutation(input: BinInput<'_>, size: usize) -> BinParseResult<'_, Permutation> {
-    map(many1(parse_cycle), |cycles| {
-        Permutation::from_cycles(cycles, size)
+    // GAP never outputs a point twice, but malformed output must not become a permutation.
+    map_opt(many1(|input| parse_cycle(input, size)), |cycles| {
+        Permutation::from_cycles(cycles, size).ok()
     })(input)
 }
 
@@ -43,6 +47,17 @@ pub fn parse_representatives(
         .map_err(Error::from)
 }
 
+/// Parse the generators of a single representative, surrounded by whitespace.
+pub fn parse_representative(input: BinInput<'_>, size: usize) -> Result<Vec<Permutation>, Error> {
+    all_consuming(delimited(
+        multispace0,
+        |input| parse_generators(input, size),
+        multispace0,
+    ))(input)
+    .map(|(_, representative)| representative)
+    .map_err(Error::from)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -52,9 +67,12 @@ mod test {
         let cycle = "(1, 11,        13)".as_bytes();
 
         let expected = vec![0, 10, 12];
-        let (_, parsed) = parse_cycle(cycle)?;
+        let (_, parsed) = parse_cycle(cycle, 13)?;
         assert_eq!(expected, parsed);
 
+        assert!(parse_cycle(cycle, 12).is_err());
+        assert!(parse_cycle("(0, 1)".as_bytes(), 13).is_err());
+
         Ok(())
     }
 
@@ -66,10 +84,13 @@ mod test {
         let cycles = vec![vec![0, 1, 2, 3], vec![22, 33, 4]];
         let size = 48;
 
-        let expected = Permutation::from_cycles(cycles, size);
+        let expected = Permutation::from_cycles(cycles, size).unwrap();
         let (_, parsed) = parse_permutation(permutation, size)?;
         assert_eq!(expected, parsed);
 
+        // A point in two cycles isn't a permutation.
+        assert!(parse_permutation("(1,2)(2,3)".as_bytes(), 3).is_err());
+
         Ok(())
     }
 
@@ -81,9 +102,9 @@ mod test {
         .as_bytes();
         let size = 1000;
         let cycles1 = vec![vec![65, 45, 53, 1], vec![11, 22]];
-        let permutation1 = Permutation::from_cycles(cycles1, size);
+        let permutation1 = Permutation::from_cycles(cycles1, size).unwrap();
         let cycles2 = vec![vec![66, 20, 566, 64]];
-        let permutation2 = Permutation::from_cycles(cycles2, size);
+        let permutation2 = Permutation::from_cycles(cycles2, size).unwrap();
 
         let expected = vec![permutation1, permutation2];
         let (_, parsed) = parse_generators(permutations, size)?;
@@ -107,17 +128,17 @@ mod test {
             vec![3, 19],
             vec![4, 20],
         ];
-        let permutation1 = Permutation::from_cycles(cycles1, size);
+        let permutation1 = Permutation::from_cycles(cycles1, size).unwrap();
         let cycles2 = vec![
             vec![8, 16, 24],
             vec![9, 17, 25],
             vec![10, 18, 26],
             vec![11, 19, 27],
         ];
-        let permutation2 = Permutation::from_cycles(cycles2, size);
+        let permutation2 = Permutation::from_cycles(cycles2, size).unwrap();
         let repr1 = vec![permutation1, permutation2];
         let cycles3 = vec![vec![0, 16], vec![1, 17], vec![2, 18]];
-        let permutation3 = Permutation::from_cycles(cycles3, size);
+        let permutation3 = Permutation::from_cycles(cycles3, size).unwrap();
         let repr2 = vec![permutation3];
 
         let expected = vec![repr1, repr2];
@@ -126,4 +147,23 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_representative() -> Result<(), Error> {
+        let representative = "
+[ (1, 2)(3, 4),
+  (2, 3) ]
+"
+        .as_bytes();
+        let expected = vec![
+            Permutation::from_cycles(vec![vec![0, 1], vec![2, 3]], 4).unwrap(),
+            Permutation::from_cycles(vec![vec![1, 2]], 4).unwrap(),
+        ];
+        assert_eq!(expected, parse_representative(representative, 4)?);
+
+        assert!(parse_representative("[ (1, 2) ] [ (2, 3) ]".as_bytes(), 4).is_err());
+        assert!(parse_representative("[ (1, 5) ]".as_bytes(), 4).is_err());
+
+        Ok(())
+    }
 }
@@ -34,16 +34,23 @@ fn write_permutation_gap(
     Ok(())
 }
 
+/// Define the group `g` generated by the permutations, with the vertices
+/// numbered from 1 as GAP does.
+#[cfg(not(tarpaulin_include))]
+pub fn write_group(writer: &mut impl Write, permutations: Vec<Permutation>) -> Result<(), Error> {
+    write!(writer, "g:=Group([")?;
+    for mut permutation in permutations {
+        write_permutation_gap(writer, &mut permutation)?;
+        writeln!(writer, ",")?;
+    }
+    writeln!(writer, "]);;").map_err(Error::from)
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn write_gap_input(permutations: Vec<Permutation>) -> Result<(), Error> {
     let mut gap_in_file = BufWriter::new(File::create(GAP_IN_FILE)?);
 
-    write!(gap_in_file, "g:=Group([")?;
-    for mut permutation in permutations {
-        write_permutation_gap(&mut gap_in_file, &mut permutation)?;
-        writeln!(gap_in_file, ",")?;
-    }
-    writeln!(gap_in_file, "]);;")?;
+    write_group(&mut gap_in_file, permutations)?;
     writeln!(
         gap_in_file,
         r#"
@@ -56,3 +63,24 @@ od;;"#
     )
     .map_err(Error::from)
 }
+
+/// Let GAP print the generators of a representative of each class of
+/// maximal subgroups. Trivial subgroups are left out.
+#[cfg(not(tarpaulin_include))]
+pub fn write_maximal_subgroups_input(permutations: Vec<Permutation>) -> Result<(), Error> {
+    let mut gap_in_file = BufWriter::new(File::create(GAP_IN_FILE)?);
+
+    write_group(&mut gap_in_file, permutations)?;
+    writeln!(
+        gap_in_file,
+        r#"
+m:=MaximalSubgroupClassReps(g);;
+for h in m do
+    if not IsTrivial(h) then
+        Print(GeneratorsOfGroup(h));
+        Print("\n");
+    fi;
+od;;"#
+    )
+    .map_err(Error::from)
+}
@@ -1,20 +1,31 @@
 use std::time::Instant;
 
 use crate::{
-    encoding::encode_problem,
+    encoding::{encode_problem_cached, EncodeOutcome, EncodingCache},
     graph::Graph,
     graph::VertexIndex,
     permutation::Permutation,
-    quotient::{generate_orbits, QuotientGraph},
+    quotient::{generate_orbits, QuotientGraph, TrivialPartition},
     sat_solving::solve,
-    statistics::{QuotientStatistics, Statistics},
-    time, Error,
+    statistics::{FormulaStatistics, QuotientInvariants, QuotientStatistics, Statistics},
+    time, EncodingOptions, Error,
 };
 
 #[cfg(not(tarpaulin_include))]
-pub fn check_class(graph: &Graph, representative_orbits: Vec<VertexIndex>) -> Result<bool, Error> {
+pub fn check_class(
+    graph: &Graph,
+    representative_orbits: Vec<VertexIndex>,
+    encoding_options: &EncodingOptions,
+    encoding_cache: &mut EncodingCache,
+) -> Result<bool, Error> {
+    if TrivialPartition::detect(&representative_orbits).is_some() {
+        return Ok(true);
+    }
+
     let quotient = QuotientGraph::from_graph_orbits(graph, representative_orbits);
-    if let Some((formula, _)) = encode_problem(&quotient, graph) {
+    if let EncodeOutcome::Formula(formula, _) =
+        encode_problem_cached(&quotient, graph, encoding_options, encoding_cache)?
+    {
         solve(formula)
     } else {
         Ok(true)
@@ -26,6 +37,8 @@ pub fn check_class_stats(
     graph: &Graph,
     representative_group: &mut [Permutation],
     statistics: &mut Statistics,
+    encoding_options: &EncodingOptions,
+    encoding_cache: &mut EncodingCache,
 ) -> Result<bool, Error> {
     let start_time = Instant::now();
 
@@ -35,6 +48,19 @@ pub fn check_class_stats(
         generate_orbits(representative_group)
     );
 
+    if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+        statistics.log_quotient_statistic(QuotientStatistics::from_trivial_partition(
+            trivial_partition,
+            graph.size(),
+            None,
+            representative_group.len(),
+            start_time.elapsed(),
+            orbit_gen_time,
+        ));
+        statistics.log_iteration();
+        return Ok(true);
+    }
+
     time!(
         quotient_gen_time,
         quotient,
@@ -44,14 +70,25 @@ pub fn check_class_stats(
 
     let min_max_orbit_size = quotient.get_orbit_sizes();
     let (min_orbit_size, max_orbit_size) = min_max_orbit_size;
+    let invariants = QuotientInvariants::from_quotient_graph(&quotient.quotient_graph);
+
+    time!(
+        encoding_time,
+        formula,
+        encode_problem_cached(&quotient, graph, encoding_options, encoding_cache)?
+    );
 
-    time!(encoding_time, formula, encode_problem(&quotient, graph));
+    let trivial_encoding = formula.trivial_reason();
+    let formula = formula
+        .formula()
+        .map(|(formula, _)| formula.collect::<Vec<_>>());
+    let formula_stats = formula.as_deref().map(FormulaStatistics::from_formula);
 
     time!(
         kissat_time,
         descriptive,
-        if let Some((formula, _)) = formula {
-            solve(formula)
+        if let Some(formula) = formula {
+            solve(formula.into_iter())
         } else {
             Ok(true)
         }
@@ -60,6 +97,8 @@ pub fn check_class_stats(
     let result = matches!(descriptive, Ok(true));
 
     let quotient_stats = QuotientStatistics {
+        generator_subset: None,
+        subset_size: representative_group.len(),
         quotient_size,
         core_size: None,
         max_orbit_size,
@@ -72,6 +111,10 @@ pub fn check_class_stats(
         quotient_gen_time,
         encoding_time,
         orbit_sizes: Default::default(),
+        invariants: Some(invariants),
+        trivial_partition: None,
+        trivial_encoding,
+        formula: formula_stats,
     };
     statistics.log_quotient_statistic(quotient_stats);
     statistics.log_iteration();
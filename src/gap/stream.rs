@@ -0,0 +1,324 @@
+//! Checks the conjugacy classes while GAP still prints their representatives.
+//!
+//! One thread parses the representatives from the output of GAP and hands them
+//! to the workers over a bounded channel, so that only a few representatives are
+//! in memory at once, even for groups with very many classes. The first
+//! descriptive class stops GAP, the parser and the workers.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    process::{Child, ChildStdout},
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use super::{
+    cache::{cache_path, partial_cache_path, write_representatives, write_result},
+    parser::parse_representative,
+    print::write_gap_input,
+    search::check_class,
+    spawn_gap, unexpected_output,
+    verify::verify_representative,
+    GAP_POLL_INTERVAL,
+};
+use crate::{
+    encoding::EncodingCache,
+    graph::Graph,
+    interrupt::check_interrupted,
+    permutation::Permutation,
+    quotient::{generate_orbits, Orbits},
+    timed_out, EncodingOptions, Error, MemorySize, Settings,
+};
+
+/// How the streamed check of the conjugacy classes ended.
+pub enum StreamedClasses {
+    Descriptive(Orbits),
+    Exhausted,
+    GapFailed,
+    DeadlineExceeded,
+}
+
+/// Representatives waiting in the channel for each worker.
+const CLASSES_PER_WORKER: usize = 2;
+
+/// A representative and its index in the output of GAP.
+type Class = (usize, Vec<Permutation>);
+
+/// The index of a checked class and its orbits if its quotient is descriptive.
+type ClassResult = Result<(usize, Option<Orbits>), Error>;
+
+/// The next representative in the output of GAP, which may span several lines.
+/// None at the end of the output.
+pub fn next_representative(
+    reader: &mut impl BufRead,
+    size: usize,
+) -> Result<Option<Vec<Permutation>>, Error> {
+    let mut gap_out = Vec::new();
+    reader.read_until(b']', &mut gap_out)?;
+    if gap_out.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    parse_representative(&gap_out, size)
+        .map(Some)
+        .map_err(|_| unexpected_output(&gap_out))
+}
+
+/// Parse, verify and write each representative to the partial cache file before
+/// sending it to the workers. The number of representatives, or None if the
+/// workers stopped before GAP was done.
+#[cfg(not(tarpaulin_include))]
+fn send_representatives(
+    graph: &Graph,
+    reader: &mut impl BufRead,
+    partial_path: &Path,
+    class_sender: SyncSender<Class>,
+) -> Result<Option<usize>, Error> {
+    let mut cache = BufWriter::new(File::create(partial_path)?);
+    let mut index = 0;
+    while let Some(representative) = next_representative(reader, graph.size())? {
+        verify_representative(graph, index, &representative)?;
+        write_representatives(&mut cache, slice::from_ref(&representative))?;
+        if class_sender.send((index, representative)).is_err() {
+            return Ok(None);
+        }
+        index += 1;
+    }
+    cache.flush()?;
+    Ok(Some(index))
+}
+
+#[cfg(not(tarpaulin_include))]
+fn parse_stream(
+    graph: &Graph,
+    stdout: ChildStdout,
+    partial_path: &Path,
+    class_sender: SyncSender<Class>,
+) -> Result<Option<usize>, Error> {
+    let mut reader = BufReader::new(stdout);
+    let parsed = send_representatives(graph, &mut reader, partial_path, class_sender);
+    // Let GAP finish even after a parse error, so that
+    // its exit status tells whether it failed itself.
+    io::copy(&mut reader, &mut io::sink())?;
+    parsed
+}
+
+#[cfg(not(tarpaulin_include))]
+fn check_stream(
+    graph: &Graph,
+    class_receiver: &Mutex<Option<Receiver<Class>>>,
+    result_sender: Sender<ClassResult>,
+    stop: &AtomicBool,
+    encoding_options: &EncodingOptions,
+) {
+    // The representatives of one group share many orbits.
+    let mut encoding_cache = EncodingCache::default();
+
+    while !stop.load(Ordering::Relaxed) {
+        let next = match class_receiver
+            .lock()
+            .expect("Class channel poisoned!")
+            .as_ref()
+        {
+            Some(class_receiver) => class_receiver.recv().ok(),
+            None => None,
+        };
+        let Some((index, mut representative)) = next else {
+            break;
+        };
+
+        let orbits = generate_orbits(&mut representative);
+        let result = check_class(graph, orbits.clone(), encoding_options, &mut encoding_cache)
+            .map(|descriptive| (index, descriptive.then_some(orbits)));
+        if result_sender.send(result).is_err() {
+            break;
+        }
+    }
+}
+
+/// Collect the check results until GAP and the workers are done (None), the first
+/// descriptive class was found or GAP missed the deadline. Fails once interrupted.
+#[cfg(not(tarpaulin_include))]
+fn collect_results(
+    gap: &mut Child,
+    result_receiver: &Receiver<ClassResult>,
+    results: &mut Vec<Option<bool>>,
+    settings: &Settings,
+    start_time: Instant,
+) -> Result<Option<StreamedClasses>, Error> {
+    let mut workers_done = false;
+    loop {
+        check_interrupted()?;
+        if workers_done {
+            thread::sleep(GAP_POLL_INTERVAL);
+        } else {
+            match result_receiver.recv_timeout(GAP_POLL_INTERVAL) {
+                Ok(result) => {
+                    let (index, orbits) = result?;
+                    if results.len() <= index {
+                        results.resize(index + 1, None);
+                    }
+                    results[index] = Some(orbits.is_some());
+                    if let Some(orbits) = orbits {
+                        return Ok(Some(StreamedClasses::Descriptive(orbits)));
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => workers_done = true,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+
+        let gap_done = gap.try_wait()?.is_some();
+        if gap_done && workers_done {
+            return Ok(None);
+        }
+        if !gap_done && timed_out(start_time, settings.gap_deadline) {
+            return Ok(Some(StreamedClasses::DeadlineExceeded));
+        }
+    }
+}
+
+/// Run GAP on the generators and check each conjugacy class as soon as GAP printed
+/// its representative. If GAP printed all of them, they are cached with the results.
+#[cfg(not(tarpaulin_include))]
+pub fn stream_classes(
+    graph: &Graph,
+    generators: Vec<Permutation>,
+    memory: MemorySize,
+    settings: &Settings,
+    key: u64,
+) -> Result<StreamedClasses, Error> {
+    // With several workers, the first descriptive class depends on the scheduling.
+    let workers = settings
+        .gap_workers
+        .unwrap_or_else(|| {
+            if settings.is_deterministic() {
+                1
+            } else {
+                thread::available_parallelism().map_or(1, usize::from)
+            }
+        })
+        .max(1);
+    let partial_path = partial_cache_path(key);
+
+    write_gap_input(generators)?;
+    let start_time = Instant::now();
+    let mut gap = spawn_gap(memory)?;
+    let stdout = gap.stdout.take().expect("GAP stdout is piped!");
+
+    let (class_sender, class_receiver) = mpsc::sync_channel(CLASSES_PER_WORKER * workers);
+    let class_receiver = Mutex::new(Some(class_receiver));
+    let (result_sender, result_receiver) = mpsc::channel();
+    let stop = AtomicBool::new(false);
+    let mut results = Vec::new();
+
+    let (collected, status, parsed) = thread::scope(|scope| {
+        let partial_path = partial_path.as_path();
+        let parser = scope.spawn(move || parse_stream(graph, stdout, partial_path, class_sender));
+        for _ in 0..workers {
+            let (class_receiver, stop) = (&class_receiver, &stop);
+            let result_sender = result_sender.clone();
+            let encoding_options = &settings.encoding_options;
+            scope.spawn(move || {
+                check_stream(graph, class_receiver, result_sender, stop, encoding_options)
+            });
+        }
+        drop(result_sender);
+
+        let collected = collect_results(
+            &mut gap,
+            &result_receiver,
+            &mut results,
+            settings,
+            start_time,
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        if !matches!(collected, Ok(None)) {
+            // GAP may have exited in the meantime, which is just as good.
+            let _ = gap.kill();
+        }
+        // Unblocks the parser if the channel is full and the workers already stopped.
+        class_receiver
+            .lock()
+            .expect("Class channel poisoned!")
+            .take();
+        let status = gap.wait();
+        let parsed = parser.join().expect("GAP output parser panicked!");
+        (collected, status, parsed)
+    });
+
+    let discard_partial = || {
+        let _ = fs::remove_file(&partial_path);
+    };
+    match collected {
+        Ok(None) => {}
+        Ok(Some(stopped)) => {
+            discard_partial();
+            return Ok(stopped);
+        }
+        Err(error) => {
+            discard_partial();
+            return Err(error);
+        }
+    }
+    if !status?.success() {
+        discard_partial();
+        return Ok(StreamedClasses::GapFailed);
+    }
+    let classes = match parsed {
+        Ok(classes) => classes.expect("The workers only stop early on a descriptive class!"),
+        Err(error) => {
+            discard_partial();
+            return Err(error);
+        }
+    };
+    debug_assert_eq!(classes, results.len());
+
+    let mut cache = OpenOptions::new().append(true).open(&partial_path)?;
+    for (index, descriptive) in results.into_iter().enumerate() {
+        if let Some(descriptive) = descriptive {
+            write_result(&mut cache, index, descriptive)?;
+        }
+    }
+    fs::rename(&partial_path, cache_path(key))?;
+    Ok(StreamedClasses::Exhausted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_next_representative() -> Result<(), Error> {
+        let gap_out = "[ (1, 2)(3, 4),
+  (2, 3) ]
+[ (1, 2) ]
+";
+        let mut reader = Cursor::new(gap_out.as_bytes());
+        let first = vec![
+            Permutation::from_cycles(vec![vec![0, 1], vec![2, 3]], 4).unwrap(),
+            Permutation::from_cycles(vec![vec![1, 2]], 4).unwrap(),
+        ];
+        let second = vec![Permutation::from_cycles(vec![vec![0, 1]], 4).unwrap()];
+        assert_eq!(Some(first), next_representative(&mut reader, 4)?);
+        assert_eq!(Some(second), next_representative(&mut reader, 4)?);
+        assert_eq!(None, next_representative(&mut reader, 4)?);
+
+        let mut truncated = Cursor::new("[ (1, 2)(3,".as_bytes());
+        assert!(matches!(
+            next_representative(&mut truncated, 4),
+            Err(Error::GapError(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! GAP only sees the abstract generators and not the graph, so its
+//! representatives are checked to be automorphisms of the coloured graph
+//! before their orbits are used.
+
+use crate::{
+    graph::{Graph, VertexIndex},
+    permutation::Permutation,
+    Error,
+};
+
+/// The reason why the permutation is not an automorphism
+/// of the (sorted) graph, None if it is one.
+pub fn automorphism_violation(graph: &Graph, generator: &Permutation) -> Option<String> {
+    if generator.len() != graph.size() {
+        return Some(format!(
+            "it permutes {} points but the graph has {} vertices",
+            generator.len(),
+            graph.size()
+        ));
+    }
+
+    let image = |vertex: VertexIndex| generator.raw[vertex as usize];
+    let colour_change = graph
+        .vertices
+        .iter()
+        .find(|vertex| graph.vertices[image(vertex.index) as usize].colour != vertex.colour);
+    if let Some(vertex) = colour_change {
+        return Some(format!(
+            "it maps vertex {} to vertex {} of another colour",
+            vertex.index,
+            image(vertex.index)
+        ));
+    }
+
+    graph
+        .iterate_edges()
+        .find(|(start, end)| !graph.lookup_edge(&image(*start), &image(*end)))
+        .map(|(start, end)| {
+            format!(
+                "it maps the edge ({}, {}) to the non-edge ({}, {})",
+                start,
+                end,
+                image(start),
+                image(end)
+            )
+        })
+}
+
+/// Check that each generator of the representative with
+/// the given index is an automorphism of the graph.
+pub fn verify_representative(
+    graph: &Graph,
+    index: usize,
+    representative: &[Permutation],
+) -> Result<(), Error> {
+    for generator in representative {
+        if let Some(violation) = automorphism_violation(graph, generator) {
+            return Err(Error::GapError(format!(
+                "generator {} of class representative {} is no automorphism of the graph: {}",
+                generator, index, violation
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Check that each generator of each representative is an automorphism of the graph.
+pub fn verify_representatives(
+    graph: &Graph,
+    representatives: &[Vec<Permutation>],
+) -> Result<(), Error> {
+    representatives
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, representative)| verify_representative(graph, index, representative))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_automorphism_violation() {
+        let mut path = Graph::new_ordered(3);
+        path.add_edge(0, 1).unwrap();
+        path.add_edge(1, 2).unwrap();
+        path.sort();
+
+        let flip = Permutation::new(vec![2, 1, 0]).unwrap();
+        assert!(automorphism_violation(&path, &flip).is_none());
+        assert!(verify_representatives(&path, &[vec![flip.clone()]]).is_ok());
+
+        let swap = Permutation::new(vec![1, 0, 2]).unwrap();
+        assert!(automorphism_violation(&path, &swap).is_some());
+        assert!(verify_representatives(&path, &[vec![flip.clone()], vec![swap]]).is_err());
+
+        assert!(automorphism_violation(&path, &Permutation::new(vec![1, 0]).unwrap()).is_some());
+
+        path.recolor(0).unwrap();
+        assert!(automorphism_violation(&path, &flip).is_some());
+    }
+}
@@ -0,0 +1,205 @@
+//! Sidecar files with the generators of the automorphism group of an input
+//! graph, so that repeated runs on the same input skip nauty/Traces.
+//!
+//! The file starts with an `n` line with the graph size and the number of
+//! generators, followed by an optional `s` line with the group size and one
+//! `p` line per generator with its images. Its name contains a hash of the
+//! graph and the backend, so that each graph of an input file has its own
+//! cache. Generators read from a cache are only used if they are
+//! automorphisms of the graph.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    graph::{fnv1a, Graph, VertexIndex, DEFAULT_COLOR},
+    permutation::Permutation,
+    Error, NautyTraces,
+};
+
+/// The generators of an automorphism group and the size of the group, if known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedGenerators {
+    pub generators: Vec<Permutation>,
+    pub group_size: Option<f64>,
+}
+
+/// Stable hash of the graph, whether its colours are part of the automorphisms
+/// and the backend, as all of them determine the generators.
+pub fn generator_cache_key(graph: &Graph, colored_graph: bool, backend: NautyTraces) -> u64 {
+    let mut hash = graph.fingerprint();
+    fnv1a(&mut hash, colored_graph as i64);
+    fnv1a(&mut hash, backend as i64);
+    hash
+}
+
+/// The cache file next to the input file.
+pub fn generator_cache_path(input: &Path, key: u64) -> PathBuf {
+    let mut file_name = input.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{:016x}.generators", key));
+    input.with_file_name(file_name)
+}
+
+/// Parse the cache file. Returns None if it is malformed, e.g. truncated,
+/// or belongs to a graph of another size.
+pub fn parse_generator_cache(input: &str, size: usize) -> Option<CachedGenerators> {
+    let mut lines = input.lines();
+    let (cached_size, number) = lines.next()?.strip_prefix("n ")?.split_once(' ')?;
+    if cached_size.parse::<usize>().ok()? != size {
+        return None;
+    }
+    let number: usize = number.parse().ok()?;
+
+    let mut generators = Vec::with_capacity(number);
+    let mut group_size = None;
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("s", group)) if generators.is_empty() && group_size.is_none() => {
+                group_size = Some(group.parse().ok()?);
+            }
+            Some(("p", images)) => generators.push(Permutation::from_images_str(images, size)?),
+            _ => return None,
+        }
+    }
+
+    (generators.len() == number).then_some(CachedGenerators {
+        generators,
+        group_size,
+    })
+}
+
+pub fn write_generator_cache(
+    writer: &mut impl Write,
+    size: usize,
+    cached: &CachedGenerators,
+) -> Result<(), Error> {
+    writeln!(writer, "n {} {}", size, cached.generators.len())?;
+    if let Some(group_size) = cached.group_size {
+        writeln!(writer, "s {}", group_size)?;
+    }
+    for generator in cached.generators.iter() {
+        writeln!(writer, "p {}", generator.images_string())?;
+    }
+    Ok(())
+}
+
+/// Whether each generator maps the edges onto edges and, if the
+/// colours are part of the automorphisms, each vertex onto one
+/// of the same colour.
+pub fn are_automorphisms(graph: &Graph, generators: &[Permutation], colored_graph: bool) -> bool {
+    let mut colours = vec![DEFAULT_COLOR; graph.size()];
+    for vertex in graph.vertices.iter() {
+        colours[vertex.index as usize] = vertex.colour;
+    }
+    let edges: HashSet<_> = graph.iterate_edges().collect();
+
+    generators.iter().all(|generator| {
+        let image = |vertex: VertexIndex| generator.raw[vertex as usize];
+        generator.raw.len() == graph.size()
+            && (!colored_graph
+                || (0..graph.size() as VertexIndex)
+                    .all(|vertex| colours[image(vertex) as usize] == colours[vertex as usize]))
+            && edges
+                .iter()
+                .all(|(start, end)| edges.contains(&(image(*start), image(*end))))
+    })
+}
+
+/// Load the cached generators of the graph, if there are valid ones.
+#[cfg(not(tarpaulin_include))]
+pub fn load_generators(
+    path: &Path,
+    graph: &Graph,
+    colored_graph: bool,
+) -> Option<CachedGenerators> {
+    let content = fs::read_to_string(path).ok()?;
+    let cached = parse_generator_cache(&content, graph.size())
+        .filter(|cached| are_automorphisms(graph, &cached.generators, colored_graph));
+    if cached.is_none() {
+        eprintln!(
+            "Ignoring invalid generator cache file {}, recomputing it.",
+            path.display()
+        );
+    }
+    cached
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn store_generators(path: &Path, size: usize, cached: &CachedGenerators) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_generator_cache(&mut writer, size, cached)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path_graph() -> Graph {
+        let mut path = Graph::new_ordered(3);
+        path.add_edge(0, 1).unwrap();
+        path.add_edge(1, 2).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_generator_cache_roundtrip() {
+        let cached = CachedGenerators {
+            generators: vec![
+                Permutation::new(vec![1, 0, 2]).unwrap(),
+                Permutation::new(vec![0, 2, 1]).unwrap(),
+            ],
+            group_size: Some(6.0),
+        };
+        let mut written = Vec::new();
+        write_generator_cache(&mut written, 3, &cached).unwrap();
+        let content = String::from_utf8(written).unwrap();
+        assert_eq!("n 3 2\ns 6\np 1 0 2\np 0 2 1\n", content);
+        assert_eq!(Some(cached), parse_generator_cache(&content, 3));
+
+        let trivial = parse_generator_cache("n 3 0\n", 3).unwrap();
+        assert!(trivial.generators.is_empty());
+        assert_eq!(None, trivial.group_size);
+
+        // Another graph size, a missing generator or a broken one invalidate the cache.
+        assert!(parse_generator_cache(&content, 4).is_none());
+        assert!(parse_generator_cache("n 3 2\np 1 0 2\n", 3).is_none());
+        assert!(parse_generator_cache("n 3 1\np 1 1 2\n", 3).is_none());
+        assert!(parse_generator_cache("n 3 1\np 1 0 2\ns 2\n", 3).is_none());
+    }
+
+    #[test]
+    fn test_are_automorphisms() {
+        let mut path = path_graph();
+        let flip = vec![Permutation::new(vec![2, 1, 0]).unwrap()];
+        let swap = vec![Permutation::new(vec![1, 0, 2]).unwrap()];
+        assert!(are_automorphisms(&path, &flip, true));
+        assert!(!are_automorphisms(&path, &swap, false));
+        assert!(!are_automorphisms(
+            &path,
+            &[Permutation::new(vec![1, 0]).unwrap()],
+            false
+        ));
+
+        path.recolor(0).unwrap();
+        assert!(!are_automorphisms(&path, &flip, true));
+        assert!(are_automorphisms(&path, &flip, false));
+    }
+
+    #[test]
+    fn test_generator_cache_path() {
+        let path = path_graph();
+        let key = generator_cache_key(&path, false, NautyTraces::Nauty);
+        assert_ne!(key, generator_cache_key(&path, true, NautyTraces::Nauty));
+        assert_ne!(key, generator_cache_key(&path, false, NautyTraces::Traces));
+        assert_eq!(
+            PathBuf::from(format!("dir/graph.dre.{:016x}.generators", key)),
+            generator_cache_path(Path::new("dir/graph.dre"), key)
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Small modifications of a loaded graph, e.g. from an edit script,
+//! to study how they change the orbits and the descriptiveness.
+
+use super::{Colour, Graph, GraphError, VertexIndex};
+
+/// A single modification of a graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphEdit {
+    /// Add the edge, or the arc to a declared digraph.
+    AddEdge(VertexIndex, VertexIndex),
+    /// Remove the edge, or the arc from a declared digraph.
+    RemoveEdge(VertexIndex, VertexIndex),
+    /// Give the vertex the colour.
+    Recolour(VertexIndex, Colour),
+}
+
+impl GraphEdit {
+    /// The same edit of the vertices mapped by the function,
+    /// e.g. from their ids in the input to their indices.
+    pub fn map_vertices<F, E>(self, mut map: F) -> Result<Self, E>
+    where
+        F: FnMut(VertexIndex) -> Result<VertexIndex, E>,
+    {
+        Ok(match self {
+            Self::AddEdge(start, end) => Self::AddEdge(map(start)?, map(end)?),
+            Self::RemoveEdge(start, end) => Self::RemoveEdge(map(start)?, map(end)?),
+            Self::Recolour(vertex, colour) => Self::Recolour(map(vertex)?, colour),
+        })
+    }
+}
+
+impl Graph {
+    /// Apply the edit through the editing methods of the graph.
+    /// Returns false if it didn't change anything, i.e. the removed
+    /// edge wasn't there. Adding an edge twice adds a duplicate edge,
+    /// which `minimize` removes.
+    pub fn apply_edit(&mut self, edit: GraphEdit) -> Result<bool, GraphError> {
+        match edit {
+            GraphEdit::AddEdge(start, end) => {
+                // Arcs of digraphs are only checked at their start.
+                self.get_vertex(end)?;
+                self.connect(start, end)?;
+                Ok(true)
+            }
+            GraphEdit::RemoveEdge(start, end) => {
+                self.get_vertex(end)?;
+                self.disconnect(start, end)
+            }
+            GraphEdit::Recolour(vertex, colour) => {
+                self.set_colour(vertex, colour)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_edit() -> Result<(), GraphError> {
+        let mut graph = Graph::from_edges(4, vec![(0, 1), (1, 2)])?;
+        let generation = graph.generation();
+
+        assert!(graph.apply_edit(GraphEdit::AddEdge(2, 3))?);
+        assert!(graph.apply_edit(GraphEdit::RemoveEdge(1, 0))?);
+        assert!(!graph.apply_edit(GraphEdit::RemoveEdge(0, 3))?);
+        assert!(graph.apply_edit(GraphEdit::Recolour(3, 1))?);
+        assert!(generation.check_edges(&graph, "test").is_err());
+
+        let expected = Graph::from_edges(4, vec![(1, 2), (2, 3)])?;
+        graph.minimize();
+        assert_eq!(expected.number_edges(), graph.number_edges());
+        assert_eq!(
+            expected.iterate_edges().collect::<Vec<_>>(),
+            graph.iterate_edges().collect::<Vec<_>>()
+        );
+        assert_eq!(1, graph.get_vertex(3)?.colour);
+        assert!(graph.validate().is_empty());
+
+        assert_eq!(
+            Err(GraphError(4)),
+            graph.apply_edit(GraphEdit::AddEdge(0, 4))
+        );
+
+        // Digraphs only lose the arc in the given direction.
+        let mut digraph = Graph::from_edges(2, vec![(0, 1)])?;
+        digraph.set_directed(true);
+        assert!(digraph.apply_edit(GraphEdit::RemoveEdge(0, 1))?);
+        assert_eq!(vec![(1, 0)], digraph.iterate_edges().collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_vertices() {
+        let ids = [3, 7, 9];
+        let compact = |id| {
+            ids.iter()
+                .position(|vertex_id| *vertex_id == id)
+                .map(|index| index as VertexIndex)
+                .ok_or(id)
+        };
+        assert_eq!(
+            Ok(GraphEdit::AddEdge(0, 2)),
+            GraphEdit::AddEdge(3, 9).map_vertices(compact)
+        );
+        assert_eq!(
+            Ok(GraphEdit::Recolour(1, 9)),
+            GraphEdit::Recolour(7, 9).map_vertices(compact)
+        );
+        assert_eq!(Err(4), GraphEdit::RemoveEdge(3, 4).map_vertices(compact));
+    }
+}
@@ -1,6 +1,81 @@
 use custom_debug_derive::Debug;
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use super::{Colour, GraphError, VertexIndex, DEFAULT_COLOR};
+use crate::Error;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Feed the value into the FNV-1a hash.
+pub fn fnv1a(hash: &mut u64, value: i64) {
+    for byte in value.to_le_bytes() {
+        *hash ^= byte as u64;
+        *hash = hash.wrapping_mul(FNV_PRIME);
+    }
+}
+
+/// Source of the generations of all graphs, so that no two
+/// graphs that were changed independently share a generation.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The state of a graph that structures derived from it, e.g. the nauty graph
+/// or the quotient, record to detect that they became stale. Each change to the
+/// edges or to the colours and order of the vertices draws a new number, so a
+/// clone shares the generation of the original until one of them is changed.
+/// Changes through the public `vertices` and `state` aren't tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generation {
+    edges: u64,
+    vertices: u64,
+}
+
+impl Generation {
+    fn new() -> Self {
+        let generation = next_generation();
+        Generation {
+            edges: generation,
+            vertices: generation,
+        }
+    }
+
+    /// Fail unless the graph still has the edges of this generation,
+    /// naming the derived structure that would be stale otherwise.
+    pub fn check_edges(&self, graph: &Graph, derived: &'static str) -> Result<(), Error> {
+        if self.edges == graph.generation().edges {
+            Ok(())
+        } else {
+            Err(Error::StaleGraph(derived))
+        }
+    }
+}
+
+/// Like the adjacency matrix, the generation never makes two graphs unequal.
+#[derive(Clone)]
+struct GenerationCounter(Generation);
+
+impl Default for GenerationCounter {
+    fn default() -> Self {
+        GenerationCounter(Generation::new())
+    }
+}
+
+impl PartialEq for GenerationCounter {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for GenerationCounter {}
 
 #[derive(std::fmt::Debug, PartialEq, Eq, Clone)]
 pub enum GraphState {
@@ -12,6 +87,39 @@ pub enum GraphState {
     SparseSorted,
 }
 
+/// Adjacency matrix with one bitset row per vertex position, built by
+/// `Graph::choose_representation` for dense graphs. It only caches the
+/// edge lists, so it never makes two graphs with the same edges unequal.
+#[derive(Clone, Default)]
+struct AdjacencyMatrix(Option<(usize, Vec<u64>)>);
+
+impl AdjacencyMatrix {
+    fn from_vertices(vertices: &[Vertex], position: impl Fn(VertexIndex) -> Option<usize>) -> Self {
+        let row_words = vertices.len().div_ceil(64);
+        let mut bits = vec![0u64; row_words * vertices.len()];
+        for (start, vertex) in vertices.iter().enumerate() {
+            for end in vertex.edges_to.iter().filter_map(|end| position(*end)) {
+                bits[start * row_words + end / 64] |= 1 << (end % 64);
+            }
+        }
+        AdjacencyMatrix(Some((row_words, bits)))
+    }
+
+    /// None if the matrix wasn't built.
+    fn contains(&self, start: usize, end: usize) -> Option<bool> {
+        let (row_words, bits) = self.0.as_ref()?;
+        Some(bits[start * row_words + end / 64] & (1 << (end % 64)) != 0)
+    }
+}
+
+impl PartialEq for AdjacencyMatrix {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for AdjacencyMatrix {}
+
 /// Fixed size graph.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Graph {
@@ -21,6 +129,46 @@ pub struct Graph {
     #[debug(skip)]
     pub state: GraphState,
     max_color: Colour,
+    #[debug(skip)]
+    adjacency_matrix: AdjacencyMatrix,
+    /// Declared as a digraph, e.g. by the `d` directive of dreadnaut.
+    directed: bool,
+    #[debug(skip)]
+    generation: GenerationCounter,
+}
+
+/// Inconsistencies found by `Graph::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphIssue {
+    /// The index is negative, used by more than one vertex or
+    /// doesn't match the position of the vertex in an ordered graph.
+    InvalidVertex(VertexIndex),
+    /// The edge ends in a vertex that isn't part of the graph.
+    DanglingEdge(VertexIndex, VertexIndex),
+    /// The edge is only stored for one direction.
+    MissingReverseEdge(VertexIndex, VertexIndex),
+    DuplicateEdge(VertexIndex, VertexIndex),
+    /// Colours are never negative.
+    InvalidColour(VertexIndex, Colour),
+}
+
+impl fmt::Display for GraphIssue {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidVertex(vertex) => write!(f, "invalid vertex index {}", vertex),
+            Self::DanglingEdge(start, end) => {
+                write!(f, "edge {}-{} ends in an unknown vertex", start, end)
+            }
+            Self::MissingReverseEdge(start, end) => {
+                write!(f, "edge {}-{} has no reverse edge", start, end)
+            }
+            Self::DuplicateEdge(start, end) => write!(f, "duplicate edge {}-{}", start, end),
+            Self::InvalidColour(vertex, colour) => {
+                write!(f, "vertex {} has invalid colour {}", vertex, colour)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,11 +187,55 @@ impl Graph {
         self.edge_number
     }
 
+    pub fn generation(&self) -> Generation {
+        self.generation.0
+    }
+
+    pub(super) fn edges_changed(&mut self) {
+        self.generation.0.edges = next_generation();
+    }
+
+    pub(super) fn vertices_changed(&mut self) {
+        self.generation.0.vertices = next_generation();
+    }
+
     pub fn is_sparse(&self) -> bool {
         // A complete graph has n(n-1)/2 edges for n vertices.
         // We draw the line between sparse and dense at half
         // of the possible edges in a complete graph.
-        self.edge_number < self.size * (self.size - 1) / 4
+        self.edge_number < self.size * self.size.saturating_sub(1) / 4
+    }
+
+    /// Keep the sorted adjacency lists of sparse graphs for edge lookups, but
+    /// add an adjacency matrix to dense graphs, e.g. dense quotients of sparse
+    /// inputs, so that `lookup_edge` takes constant time. Changes to the edges
+    /// or the order of the vertices drop the matrix again.
+    pub fn choose_representation(&mut self) {
+        self.adjacency_matrix = if self.is_sparse() {
+            AdjacencyMatrix::default()
+        } else {
+            AdjacencyMatrix::from_vertices(&self.vertices, |index| self.position(index))
+        };
+    }
+
+    /// Whether `lookup_edge` uses an adjacency matrix.
+    pub fn has_adjacency_matrix(&self) -> bool {
+        self.adjacency_matrix.0.is_some()
+    }
+
+    /// Position of the vertex in the vertex list.
+    pub(super) fn position(&self, index: VertexIndex) -> Option<usize> {
+        match self.state {
+            GraphState::IndexOrdered => Some(index as usize).filter(|index| *index < self.size),
+            GraphState::SparseSorted => self
+                .vertices
+                .binary_search_by(|vertex| vertex.index.cmp(&index))
+                .ok(),
+            _ => self
+                .vertices
+                .iter()
+                .position(|vertex| vertex.index == index),
+        }
     }
 
     #[inline]
@@ -64,7 +256,91 @@ impl Graph {
             edge_number: 0,
             state: GraphState::IndexOrdered,
             max_color: -1,
+            adjacency_matrix: AdjacencyMatrix::default(),
+            directed: false,
+            generation: GenerationCounter::default(),
+        }
+    }
+
+    /// Build an index ordered graph with `n` vertices from undirected edges.
+    /// The degrees are counted first, so that each adjacency list is
+    /// allocated once instead of growing with every `add_edge`.
+    pub fn from_edges<I>(n: usize, edges: I) -> Result<Self, GraphError>
+    where
+        I: IntoIterator<Item = (VertexIndex, VertexIndex)>,
+    {
+        let edges = edges.into_iter().collect::<Vec<_>>();
+
+        let mut degrees = vec![0usize; n];
+        for (start, end) in edges.iter() {
+            for vertex in [*start, *end] {
+                // Negative indices wrap around and are out of bounds as well.
+                *degrees.get_mut(vertex as usize).ok_or(GraphError(vertex))? += 1;
+            }
         }
+
+        let mut vertices = degrees
+            .into_iter()
+            .enumerate()
+            .map(|(index, degree)| Vertex {
+                index: index as VertexIndex,
+                edges_to: Vec::with_capacity(degree),
+                colour: DEFAULT_COLOR,
+            })
+            .collect::<Vec<_>>();
+        for (start, end) in edges.iter() {
+            vertices[*start as usize].add_edge(*end);
+            vertices[*end as usize].add_edge(*start);
+        }
+
+        Ok(Graph {
+            vertices,
+            size: n,
+            edge_number: 2 * edges.len(),
+            state: GraphState::IndexOrdered,
+            max_color: -1,
+            adjacency_matrix: AdjacencyMatrix::default(),
+            directed: false,
+            generation: GenerationCounter::default(),
+        })
+    }
+
+    /// Build an index ordered graph from the adjacency list of each vertex.
+    /// The lists are taken as arcs, i.e. undirected edges have to be in both lists.
+    pub fn from_adjacency_lists<I>(adjacency_lists: I) -> Result<Self, GraphError>
+    where
+        I: IntoIterator<Item = Vec<VertexIndex>>,
+    {
+        let adjacency_lists = adjacency_lists.into_iter().collect::<Vec<_>>();
+        let size = adjacency_lists.len();
+        let mut edge_number = 0;
+
+        let vertices = adjacency_lists
+            .into_iter()
+            .enumerate()
+            .map(|(index, edges_to)| {
+                if let Some(end) = edges_to.iter().find(|end| **end as usize >= size) {
+                    return Err(GraphError(*end));
+                }
+                edge_number += edges_to.len();
+                Ok(Vertex {
+                    index: index as VertexIndex,
+                    edges_to,
+                    colour: DEFAULT_COLOR,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Graph {
+            vertices,
+            size,
+            edge_number,
+            state: GraphState::IndexOrdered,
+            max_color: -1,
+            adjacency_matrix: AdjacencyMatrix::default(),
+            directed: false,
+            generation: GenerationCounter::default(),
+        })
     }
 
     pub fn new_with_indices(indices: &[VertexIndex], is_sorted: bool) -> Self {
@@ -82,11 +358,17 @@ impl Graph {
                 GraphState::Chaos
             },
             max_color: -1,
+            adjacency_matrix: AdjacencyMatrix::default(),
+            directed: false,
+            generation: GenerationCounter::default(),
         }
     }
 
     pub fn set_vertex(&mut self, new_vertex: Vertex) -> Result<(), GraphError> {
         use GraphState::*;
+        self.adjacency_matrix = AdjacencyMatrix::default();
+        self.edges_changed();
+        self.vertices_changed();
         self.update_max_color(new_vertex.colour);
         let index = new_vertex.index;
         if self.state == IndexOrdered {
@@ -108,18 +390,29 @@ impl Graph {
     }
 
     pub fn get_vertex(&self, index: VertexIndex) -> Result<&Vertex, GraphError> {
-        match self.state {
-            GraphState::IndexOrdered => self.vertices.get(index as usize).ok_or(GraphError(index)),
-            GraphState::SparseSorted => Ok(&self.vertices[self
-                .vertices
-                .binary_search_by(|vertex| vertex.index.cmp(&index))
-                .unwrap()]),
-            _ => self
-                .vertices
-                .iter()
-                .find(|vertex| vertex.index == index)
-                .ok_or(GraphError(index)),
-        }
+        self.position(index)
+            .map(|position| &self.vertices[position])
+            .ok_or(GraphError(index))
+    }
+
+    /// Number of arcs that start at the vertex, i.e. its
+    /// degree if the graph stores both directions of each edge.
+    pub fn degree(&self, index: VertexIndex) -> Result<usize, GraphError> {
+        Ok(self.get_vertex(index)?.edges_to.len())
+    }
+
+    /// Ends of the arcs that start at the vertex, sorted
+    /// unless edges were added since the last sort.
+    pub fn neighbours(&self, index: VertexIndex) -> Result<&[VertexIndex], GraphError> {
+        Ok(&self.get_vertex(index)?.edges_to)
+    }
+
+    pub fn max_degree(&self) -> usize {
+        self.vertices
+            .iter()
+            .map(|vertex| vertex.edges_to.len())
+            .max()
+            .unwrap_or(0)
     }
 
     fn get_vertex_mut(&mut self, index: VertexIndex) -> Result<&mut Vertex, GraphError> {
@@ -144,8 +437,10 @@ impl Graph {
     }
 
     pub fn add_arc(&mut self, start: VertexIndex, end: VertexIndex) -> Result<(), GraphError> {
+        self.adjacency_matrix = AdjacencyMatrix::default();
         self.get_vertex_mut(start)?.add_edge(end);
         self.edge_number += 1;
+        self.edges_changed();
         Ok(())
     }
 
@@ -155,7 +450,60 @@ impl Graph {
         Ok(())
     }
 
+    /// Add an arc to a declared digraph and an edge otherwise.
+    pub fn connect(&mut self, start: VertexIndex, end: VertexIndex) -> Result<(), GraphError> {
+        if self.directed {
+            self.add_arc(start, end)
+        } else {
+            self.add_edge(start, end)
+        }
+    }
+
+    /// Remove all arcs from start to end. Returns whether there were any.
+    pub fn remove_arc(&mut self, start: VertexIndex, end: VertexIndex) -> Result<bool, GraphError> {
+        let edges_to = &mut self.get_vertex_mut(start)?.edges_to;
+        let old_length = edges_to.len();
+        edges_to.retain(|to| *to != end);
+        let removed = old_length - edges_to.len();
+        if removed == 0 {
+            return Ok(false);
+        }
+
+        self.adjacency_matrix = AdjacencyMatrix::default();
+        self.edge_number -= removed;
+        self.edges_changed();
+        Ok(true)
+    }
+
+    /// Remove the edge in both directions. Returns whether it was there.
+    pub fn remove_edge(
+        &mut self,
+        start: VertexIndex,
+        end: VertexIndex,
+    ) -> Result<bool, GraphError> {
+        let removed = self.remove_arc(start, end)?;
+        Ok(self.remove_arc(end, start)? || removed)
+    }
+
+    /// Remove an arc from a declared digraph and an edge otherwise.
+    pub fn disconnect(&mut self, start: VertexIndex, end: VertexIndex) -> Result<bool, GraphError> {
+        if self.directed {
+            self.remove_arc(start, end)
+        } else {
+            self.remove_edge(start, end)
+        }
+    }
+
     pub fn lookup_edge(&self, start: &VertexIndex, end: &VertexIndex) -> bool {
+        if self.has_adjacency_matrix() {
+            return match (self.position(*start), self.position(*end)) {
+                (Some(start), Some(end)) => {
+                    self.adjacency_matrix.contains(start, end) == Some(true)
+                }
+                _ => false,
+            };
+        }
+
         let start = *start as usize;
         debug_assert!(start < self.size);
         self.vertices[start].edges_to.binary_search(end).is_ok()
@@ -169,21 +517,37 @@ impl Graph {
 
     /// Remove unneccessary edges.
     /// Does so by first sorting, thus trading runtime for reduced memory footprint.
-    pub fn minimize(&mut self) {
+    /// Returns the number of removed duplicate arcs.
+    pub fn minimize(&mut self) -> usize {
         // Adjust the edge number to fit, too.
+        let old_edge_number = self.edge_number;
         self.edge_number = 0;
         for vertex in self.vertices.iter_mut() {
             vertex.edges_to.sort_unstable();
             vertex.edges_to.dedup();
             self.edge_number += vertex.edges_to.len();
         }
+        let removed = old_edge_number.saturating_sub(self.edge_number);
+        if removed > 0 {
+            self.edges_changed();
+        }
+        removed
     }
 
     pub fn set_colours(&mut self, colours: &[Colour]) -> Result<(), GraphError> {
         for (index, colour) in colours.iter().enumerate() {
             self.get_vertex_mut(index as VertexIndex)?.colour = *colour;
         }
+        self.vertices_changed();
+
+        Ok(())
+    }
 
+    /// Give the vertex the colour, unlike `recolor`, which picks a new one.
+    pub fn set_colour(&mut self, vertex: VertexIndex, colour: Colour) -> Result<(), GraphError> {
+        self.get_vertex_mut(vertex)?.colour = colour;
+        self.update_max_color(colour);
+        self.vertices_changed();
         Ok(())
     }
 
@@ -197,20 +561,25 @@ impl Graph {
 
         self.vertices = ordered_vertices;
         self.state = GraphState::Fixed;
+        self.vertices_changed();
+        self.adjacency_matrix = AdjacencyMatrix::default();
         Ok(())
     }
 
     pub fn group_colours(&mut self) {
         use GraphState::*;
+        self.adjacency_matrix = AdjacencyMatrix::default();
         match self.state {
             IndexOrdered => {
                 self.vertices.sort_by(|a, b| a.colour.cmp(&b.colour));
                 self.state = ColourGroupedOrdered;
+                self.vertices_changed();
             }
             Chaos => {
                 self.vertices
                     .sort_unstable_by(|a, b| a.colour.cmp(&b.colour));
                 self.state = ColourGrouped;
+                self.vertices_changed();
             }
             _ => (),
         }
@@ -222,11 +591,13 @@ impl Graph {
         }
 
         if self.state != GraphState::IndexOrdered {
+            self.adjacency_matrix = AdjacencyMatrix::default();
             self.vertices.sort_unstable();
             for vertex in self.vertices.iter_mut() {
                 vertex.edges_to.sort_unstable();
             }
             self.state = GraphState::IndexOrdered;
+            self.vertices_changed();
         }
     }
 
@@ -267,15 +638,240 @@ impl Graph {
 
         let sub_edge_number = subgraph.iterate_edges().count();
         subgraph.edge_number = sub_edge_number;
+        subgraph.directed = self.directed;
+
+        Ok(subgraph)
+    }
+
+    /// Induce the subgraph on the given sorted vertices and relabel
+    /// them to 0..k in this order. Keeps the colours of the vertices.
+    pub fn induce_relabeled_subgraph(
+        &self,
+        remaining_vertices: &[VertexIndex],
+    ) -> Result<Self, GraphError> {
+        let mut subgraph = Self::new_ordered(remaining_vertices.len());
+
+        for (new_index, old_index) in remaining_vertices.iter().enumerate() {
+            let vertex = self.get_vertex(*old_index)?;
+            let new_vertex = &mut subgraph.vertices[new_index];
+            new_vertex.colour = vertex.colour;
+            new_vertex.edges_to = vertex
+                .edges_to
+                .iter()
+                .filter_map(|end| remaining_vertices.binary_search(end).ok())
+                .map(|end| end as VertexIndex)
+                .collect();
+            subgraph.edge_number += new_vertex.edges_to.len();
+        }
+        subgraph.max_color = self.max_color;
+        subgraph.directed = self.directed;
 
         Ok(subgraph)
     }
 
+    /// Relabel each vertex `v` to `labels[v]`, keeping colours and edges.
+    /// The labels need to be a permutation of the vertex indices.
+    pub fn relabel(&self, labels: &[VertexIndex]) -> Result<Self, GraphError> {
+        let mut relabeled = Self::new_ordered(self.size);
+        let label = |vertex: VertexIndex| {
+            labels
+                .get(vertex as usize)
+                .copied()
+                .ok_or(GraphError(vertex))
+        };
+
+        for vertex in self.vertices.iter() {
+            let new_vertex = relabeled.get_vertex_mut(label(vertex.index)?)?;
+            new_vertex.colour = vertex.colour;
+            new_vertex.edges_to = vertex
+                .edges_to
+                .iter()
+                .map(|end| label(*end))
+                .collect::<Result<Vec<_>, _>>()?;
+            new_vertex.edges_to.sort_unstable();
+        }
+        relabeled.edge_number = self.edge_number;
+        relabeled.max_color = self.max_color;
+        relabeled.directed = self.directed;
+
+        Ok(relabeled)
+    }
+
+    /// Mark the graph as a digraph, so that `connect` adds arcs
+    /// and arcs without their reverse aren't reported as issues.
+    pub fn set_directed(&mut self, directed: bool) {
+        if self.directed != directed {
+            self.directed = directed;
+            self.edges_changed();
+        }
+    }
+
+    /// Whether the graph was declared as a digraph. Unlike
+    /// `is_directed`, this doesn't look at the arcs.
+    pub fn is_declared_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Whether the graph is declared as a digraph or
+    /// some arc is missing its reverse.
+    pub fn is_directed(&self) -> bool {
+        if self.directed {
+            return true;
+        }
+        let arcs = self.iterate_edges().collect::<HashSet<_>>();
+        arcs.iter()
+            .any(|(start, end)| !arcs.contains(&(*end, *start)))
+    }
+
+    /// Undirected graph with the same automorphisms as this directed one,
+    /// e.g. for Traces, which can't handle digraphs. Vertex `v` is split into
+    /// its out-copy `v`, its in-copy `v + n` and a middle vertex `v + 2n`,
+    /// which is the only common neighbour of both copies. Each arc `(u, v)`
+    /// becomes the edge between the out-copy of `u` and the in-copy of `v`.
+    /// Out-copies, in-copies and middle vertices get distinct colours. An
+    /// automorphism then maps the middle vertex of `v` along with the out-copy
+    /// of `v` and thus the in-copy of `v` along as well, so that its
+    /// restriction to the out-copies is an automorphism of the digraph. A
+    /// direct edge between the copies wouldn't force this, e.g. the directed
+    /// 3-cycle would become a 6-cycle, whose colour preserving automorphisms
+    /// can swap two out-copies. Loops are kept as colours instead of edges.
+    pub fn bipartite_gadget(&self) -> Result<Self, GraphError> {
+        let size = self.size as VertexIndex;
+        let has_loop = |vertex: &Vertex| vertex.edges_to.contains(&vertex.index);
+
+        let mut edges = Vec::with_capacity(2 * self.size + self.edge_number);
+        for vertex in self.vertices.iter() {
+            edges.push((vertex.index, vertex.index + 2 * size));
+            edges.push((vertex.index + size, vertex.index + 2 * size));
+            for end in vertex.edges_to.iter().filter(|end| **end != vertex.index) {
+                edges.push((vertex.index, end + size));
+            }
+        }
+        let mut gadget = Self::from_edges(3 * self.size, edges)?;
+        gadget.minimize();
+
+        // Vertices with loops can only be mapped onto each other.
+        let classes = self
+            .vertices
+            .iter()
+            .map(|vertex| (vertex.colour, has_loop(vertex)))
+            .sorted()
+            .dedup()
+            .collect_vec();
+        let mut colours = vec![0; 3 * self.size];
+        for vertex in self.vertices.iter() {
+            let class = classes
+                .binary_search(&(vertex.colour, has_loop(vertex)))
+                .expect("Colour class not found!") as Colour;
+            colours[vertex.index as usize] = 3 * class;
+            colours[(vertex.index + size) as usize] = 3 * class + 1;
+            colours[(vertex.index + 2 * size) as usize] = 3 * class + 2;
+        }
+        gadget.set_colours(&colours)?;
+        gadget.max_color = 3 * classes.len() as Colour - 1;
+
+        Ok(gadget)
+    }
+
+    /// Give each of the vertices a colour of its own, so that automorphisms
+    /// have to fix them. Colours set by `set_colours` aren't tracked by
+    /// `max_color`, so the new colours are chosen above all used ones.
+    pub fn individualize(&mut self, vertices: &[VertexIndex]) -> Result<(), GraphError> {
+        if vertices.is_empty() {
+            return Ok(());
+        }
+
+        let max_colour = self
+            .vertices
+            .iter()
+            .map(|vertex| vertex.colour)
+            .filter(|colour| *colour != DEFAULT_COLOR)
+            .max()
+            .unwrap_or(0);
+        self.update_max_color(max_colour + 1);
+
+        for vertex in vertices {
+            self.recolor(*vertex)?;
+        }
+
+        Ok(())
+    }
+
+    /// Stable hash (FNV-1a) of the size, colours and edges of the graph,
+    /// e.g. to recognize the same graph in later runs.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        fnv1a(&mut hash, self.size() as i64);
+        for vertex in self.vertices.iter() {
+            fnv1a(&mut hash, vertex.colour as i64);
+            fnv1a(&mut hash, vertex.edges_to.len() as i64);
+            vertex
+                .edges_to
+                .iter()
+                .for_each(|end| fnv1a(&mut hash, *end as i64));
+        }
+        hash
+    }
+
+    /// The colours of the graph with the number of vertices of each colour, ordered by colour.
+    pub fn colour_classes(&self) -> Vec<(Colour, usize)> {
+        self.vertices
+            .iter()
+            .map(|vertex| vertex.colour)
+            .counts()
+            .into_iter()
+            .sorted()
+            .collect()
+    }
+
+    /// Check the assumptions that the rest of DQG makes about graphs,
+    /// i.e. valid vertex indices and colours as well as undirected edges
+    /// that are stored in both directions and only once.
+    /// A loop is stored twice, as `add_edge` adds it for both directions.
+    /// Declared digraphs may have arcs without their reverse.
+    pub fn validate(&self) -> Vec<GraphIssue> {
+        let mut issues = Vec::new();
+
+        let mut indices = HashSet::with_capacity(self.vertices.len());
+        for (position, vertex) in self.vertices.iter().enumerate() {
+            let misplaced =
+                self.state == GraphState::IndexOrdered && vertex.index as usize != position;
+            if vertex.index < 0 || misplaced || !indices.insert(vertex.index) {
+                issues.push(GraphIssue::InvalidVertex(vertex.index));
+            }
+            if vertex.colour < 0 {
+                issues.push(GraphIssue::InvalidColour(vertex.index, vertex.colour));
+            }
+        }
+
+        let mut arcs: HashMap<(VertexIndex, VertexIndex), usize> =
+            HashMap::with_capacity(self.edge_number);
+        for (start, end) in self.iterate_edges() {
+            if indices.contains(&end) {
+                *arcs.entry((start, end)).or_insert(0) += 1;
+            } else {
+                issues.push(GraphIssue::DanglingEdge(start, end));
+            }
+        }
+
+        for ((start, end), count) in arcs.iter().sorted() {
+            let allowed = if start == end { 2 } else { 1 };
+            if *count > allowed {
+                issues.push(GraphIssue::DuplicateEdge(*start, *end));
+            }
+            if !self.directed && !arcs.contains_key(&(*end, *start)) {
+                issues.push(GraphIssue::MissingReverseEdge(*start, *end));
+            }
+        }
+
+        issues
+    }
+
     pub fn recolor(&mut self, vertex: VertexIndex) -> Result<(), GraphError> {
         let next_color = self.max_color;
         self.max_color = next_color + 1;
-        let vertex = self.get_vertex_mut(vertex)?;
-        vertex.colour = next_color;
+        self.get_vertex_mut(vertex)?.colour = next_color;
+        self.vertices_changed();
         Ok(())
     }
 }
@@ -310,6 +906,187 @@ impl Ord for Vertex {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_generation() -> Result<(), GraphError> {
+        let mut graph = Graph::from_edges(3, vec![(0, 1)])?;
+        let generation = graph.generation();
+        let clone = graph.clone();
+        assert_eq!(generation, clone.generation());
+        assert_ne!(generation, Graph::from_edges(3, vec![(0, 1)])?.generation());
+
+        // Neither recolouring nor sorting changes the edges.
+        graph.set_colours(&[1, 2, 1])?;
+        graph.group_colours();
+        graph.sort();
+        assert_ne!(generation, graph.generation());
+        assert!(generation.check_edges(&graph, "test").is_ok());
+        assert_eq!(clone, Graph::from_edges(3, vec![(0, 1)])?);
+
+        graph.add_edge(1, 2)?;
+        assert!(generation.check_edges(&graph, "test").is_err());
+        assert!(clone.generation().check_edges(&clone, "test").is_ok());
+
+        // Only removed duplicates change the edges.
+        let generation = graph.generation();
+        graph.minimize();
+        assert_eq!(generation, graph.generation());
+        graph.add_arc(1, 2)?;
+        let generation = graph.generation();
+        graph.minimize();
+        assert!(generation.check_edges(&graph, "test").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_from_edges() -> Result<(), GraphError> {
+        let edges = vec![(0, 1), (2, 3), (1, 3), (4, 4)];
+        let mut expected = Graph::new_ordered(5);
+        for (start, end) in edges.iter() {
+            expected.add_edge(*start, *end)?;
+        }
+
+        let graph = Graph::from_edges(5, edges.clone())?;
+        assert_eq!(expected, graph);
+        assert_eq!(8, graph.number_edges());
+
+        let adjacency_lists = vec![vec![1], vec![0, 3], vec![3], vec![2, 1], vec![4, 4]];
+        assert_eq!(expected, Graph::from_adjacency_lists(adjacency_lists)?);
+
+        assert_eq!(
+            Err(GraphError(5)),
+            Graph::from_edges(5, vec![(0, 1), (2, 5)])
+        );
+        assert_eq!(Err(GraphError(-1)), Graph::from_edges(5, vec![(-1, 1)]));
+        assert_eq!(
+            Err(GraphError(2)),
+            Graph::from_adjacency_lists(vec![vec![1], vec![2]])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_individualize() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(4);
+        graph.set_colours(&[1, 3, 1, DEFAULT_COLOR])?;
+        graph.individualize(&[0, 3])?;
+
+        let colours = graph
+            .vertices
+            .iter()
+            .map(|vertex| vertex.colour)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![4, 3, 1, 5], colours);
+        assert_eq!(Err(GraphError(4)), graph.individualize(&[4]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_bipartite_gadget() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(3);
+        graph.add_arc(0, 1)?;
+        graph.add_arc(1, 2)?;
+        graph.add_arc(2, 0)?;
+        graph.add_arc(2, 2)?;
+        assert!(graph.is_directed());
+
+        let gadget = graph.bipartite_gadget()?;
+        assert!(!gadget.is_directed());
+        assert_eq!(9, gadget.size());
+        assert_eq!(vec![4, 6], gadget.vertices[0].edges_to);
+        assert_eq!(vec![5, 7], gadget.vertices[1].edges_to);
+        assert_eq!(vec![3, 8], gadget.vertices[2].edges_to);
+        assert_eq!(vec![2, 6], gadget.vertices[3].edges_to);
+        assert_eq!(vec![0, 3], gadget.vertices[6].edges_to);
+        let colours = gadget
+            .vertices
+            .iter()
+            .map(|vertex| vertex.colour)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 0, 3, 1, 1, 4, 2, 2, 5], colours);
+
+        let mut undirected = Graph::new_ordered(2);
+        undirected.add_edge(0, 1)?;
+        assert!(!undirected.is_directed());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_relabel() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(3);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.set_colours(&[1, 2, 3])?;
+
+        let relabeled = graph.relabel(&[2, 0, 1])?;
+        assert_eq!(vec![1, 2], relabeled.vertices[0].edges_to);
+        assert_eq!(vec![0], relabeled.vertices[1].edges_to);
+        assert_eq!(vec![0], relabeled.vertices[2].edges_to);
+        assert_eq!(
+            vec![2, 3, 1],
+            relabeled.vertices.iter().map(|v| v.colour).collect_vec()
+        );
+        assert_eq!(graph.number_edges(), relabeled.number_edges());
+
+        assert_eq!(Err(GraphError(2)), graph.relabel(&[2, 0]).map(|_| ()));
+        Ok(())
+    }
+
+    #[test]
+    fn graph_validate() -> Result<(), GraphError> {
+        let mut graph = Graph::from_edges(5, vec![(0, 1), (1, 2), (4, 4)])?;
+        assert_eq!(Vec::<GraphIssue>::new(), graph.validate());
+
+        graph.add_arc(0, 2)?;
+        graph.add_arc(0, 1)?;
+        graph.add_arc(2, 7)?;
+        graph.set_colours(&[1, -2])?;
+        graph.vertices[3].index = 5;
+
+        let expected = vec![
+            GraphIssue::InvalidColour(1, -2),
+            GraphIssue::InvalidVertex(5),
+            GraphIssue::DanglingEdge(2, 7),
+            GraphIssue::DuplicateEdge(0, 1),
+            GraphIssue::MissingReverseEdge(0, 2),
+        ];
+        assert_eq!(expected, graph.validate());
+
+        Ok(())
+    }
+
+    #[test]
+    fn graph_minimize() -> Result<(), GraphError> {
+        // An edge listed in both directions, as in some CSV files.
+        let mut graph = Graph::from_edges(3, vec![(0, 1), (1, 0), (1, 2)])?;
+        assert_eq!(6, graph.number_edges());
+
+        assert_eq!(2, graph.minimize());
+        assert_eq!(4, graph.number_edges());
+        assert_eq!(vec![0, 2], graph.vertices[1].edges_to);
+        assert_eq!(Vec::<GraphIssue>::new(), graph.validate());
+
+        assert_eq!(0, graph.minimize());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_colour_classes() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(4);
+        assert_eq!(vec![(DEFAULT_COLOR, 4)], graph.colour_classes());
+
+        graph.set_colours(&[1, 3, 1, DEFAULT_COLOR])?;
+        assert_eq!(
+            vec![(1, 2), (3, 1), (DEFAULT_COLOR, 1)],
+            graph.colour_classes()
+        );
+        assert!(Graph::new_ordered(0).colour_classes().is_empty());
+        Ok(())
+    }
+
     #[test]
     fn new_graph_default() {
         let graph = Graph::new_ordered(120);
@@ -400,6 +1177,23 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_degree_and_neighbours() -> Result<(), GraphError> {
+        let mut graph = Graph::from_edges(4, vec![(0, 1), (0, 2), (0, 3), (1, 2)])?;
+        graph.sort();
+        assert_eq!(3, graph.degree(0)?);
+        assert_eq!(1, graph.degree(3)?);
+        assert_eq!(&[0, 2], graph.neighbours(1)?);
+        assert_eq!(3, graph.max_degree());
+        assert_eq!(Err(GraphError(4)), graph.degree(4));
+
+        let sparse = Graph::new_with_indices(&[2, 5, 7], true);
+        assert_eq!(0, sparse.degree(5)?);
+        assert_eq!(Err(GraphError(3)), sparse.neighbours(3));
+        assert_eq!(0, Graph::new_ordered(0).max_degree());
+        Ok(())
+    }
+
     #[test]
     fn test_induce_subgraph() -> Result<(), GraphError> {
         let mut graph = Graph::new_ordered(5);
@@ -419,4 +1213,70 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_induce_relabeled_subgraph() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(5);
+        for i in 0..4 {
+            graph.add_edge(i, i + 1)?;
+        }
+        graph.set_colours(&[1, 2, 1, 2, 2])?;
+
+        let mut expected_subgraph = Graph::new_ordered(3);
+        expected_subgraph.add_edge(1, 2)?;
+        expected_subgraph.set_colours(&[2, 2, 2])?;
+        expected_subgraph.update_max_color(graph.max_color);
+
+        assert_eq!(
+            expected_subgraph,
+            graph.induce_relabeled_subgraph(&[1, 3, 4])?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_representation() -> Result<(), GraphError> {
+        // All edges of K4 but 0-3.
+        let mut dense = Graph::from_edges(4, [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)])?;
+        dense.sort();
+        let lookups = |graph: &Graph| {
+            (0..4)
+                .flat_map(|start| (0..4).map(move |end| (start, end)))
+                .map(|(start, end)| graph.lookup_edge(&start, &end))
+                .collect_vec()
+        };
+        let list_lookups = lookups(&dense);
+        dense.choose_representation();
+        assert!(dense.has_adjacency_matrix());
+        assert_eq!(list_lookups, lookups(&dense));
+        assert!(!dense.lookup_edge(&0, &3));
+
+        dense.add_edge(0, 3)?;
+        assert!(!dense.has_adjacency_matrix());
+        assert!(dense.lookup_edge(&0, &3));
+
+        let mut sparse = Graph::from_edges(10, (0..9).map(|vertex| (vertex, vertex + 1)))?;
+        sparse.choose_representation();
+        assert!(!sparse.has_adjacency_matrix());
+        // The density of the empty graph doesn't underflow.
+        Graph::new_ordered(0).choose_representation();
+
+        // Quotient graphs are named by the orbit representatives.
+        let mut quotient = Graph::new_with_indices(&[0, 3, 7], true);
+        quotient.add_edge(0, 3)?;
+        quotient.add_edge(3, 7)?;
+        quotient.choose_representation();
+        assert!(quotient.has_adjacency_matrix());
+        assert!(quotient.lookup_edge(&7, &3));
+        assert!(!quotient.lookup_edge(&0, &7));
+        assert!(!quotient.lookup_edge(&0, &5));
+        // The matrix doesn't take part in comparisons.
+        let mut same_edges = Graph::new_with_indices(&[0, 3, 7], true);
+        same_edges.add_edge(0, 3)?;
+        same_edges.add_edge(3, 7)?;
+        assert_eq!(same_edges, quotient);
+
+        Ok(())
+    }
 }
@@ -5,12 +5,22 @@
 use custom_debug_derive::Debug;
 use std::os::raw::c_int;
 
+mod edit;
+pub use edit::GraphEdit;
+
 mod internal_graph;
-pub use internal_graph::{Graph, GraphState, Vertex};
+pub use internal_graph::{fnv1a, Generation, Graph, GraphIssue, GraphState, Vertex};
 
+#[cfg(feature = "native")]
 mod nauty_traces_graph;
+#[cfg(feature = "native")]
 pub use nauty_traces_graph::{NautyGraph, SparseNautyGraph, TracesGraph};
 
+mod refinement;
+pub use refinement::ColourRefinement;
+
+mod traversal;
+
 pub type Colour = c_int;
 pub type VertexIndex = c_int;
 
@@ -3,8 +3,8 @@ use itertools::Itertools;
 use nauty_Traces_sys::{empty_graph, SparseGraph, ADDONEEDGE, SETWORDSNEEDED};
 use std::{convert::TryInto, os::raw::c_int};
 
-use super::{Colour, Graph, GraphState, VertexIndex};
-use crate::debug::bin_fmt;
+use super::{Colour, Generation, Graph, GraphState, VertexIndex};
+use crate::{debug::bin_fmt, Error};
 
 fn encode_colours(partition: &mut [Colour]) {
     let mut last_colour = c_int::MIN; // Negative numbers should not arise or if they do, they should be bigger than this.
@@ -18,6 +18,37 @@ fn encode_colours(partition: &mut [Colour]) {
     }
 }
 
+/// Write the vertex order (lab) and the encoded colouring (ptn) of the graph.
+/// Sorts and groups the graph by colours if it isn't fixed.
+fn encode_order_and_colours(
+    graph: &mut Graph,
+    vertex_order: &mut Vec<VertexIndex>,
+    partition: &mut Vec<VertexIndex>,
+) {
+    if graph.state != GraphState::Fixed {
+        graph.sort();
+        graph.group_colours();
+    }
+
+    vertex_order.clear();
+    partition.clear();
+    for vertex in graph.vertices.iter() {
+        vertex_order.push(vertex.index);
+        partition.push(vertex.colour);
+    }
+
+    encode_colours(partition);
+}
+
+/// nauty has to run in digraph mode for arcs without their reverse and loops.
+fn needs_digraph_mode(graph: &Graph) -> bool {
+    graph.is_directed()
+        || graph
+            .vertices
+            .iter()
+            .any(|vertex| vertex.edges_to.contains(&vertex.index))
+}
+
 #[derive(Debug, Clone)]
 pub struct NautyGraph {
     /// actual graph
@@ -27,6 +58,10 @@ pub struct NautyGraph {
     pub vertex_order: Vec<VertexIndex>,
     /// ptn aka the colouring
     pub partition: Vec<VertexIndex>,
+    /// whether nauty has to run in digraph mode
+    pub digraph: bool,
+    /// of the graph when lab and ptn were last encoded
+    pub generation: Generation,
 }
 
 #[derive(Debug)]
@@ -37,6 +72,12 @@ pub struct TracesGraph {
     pub vertex_order: Vec<VertexIndex>,
     /// ptn aka the colouring
     pub partition: Vec<VertexIndex>,
+    /// whether nauty has to run in digraph mode,
+    /// never for a bipartite gadget
+    pub digraph: bool,
+    /// of the graph when lab and ptn were last encoded,
+    /// for a bipartite gadget that of the digraph
+    pub generation: Generation,
 }
 
 pub type SparseNautyGraph = TracesGraph;
@@ -50,18 +91,17 @@ impl NautyGraph {
             adjacency_matrix: empty_graph(m, n),
             vertex_order: Vec::with_capacity(n),
             partition: Vec::with_capacity(n),
+            digraph: needs_digraph_mode(graph),
+            generation: graph.generation(),
         };
 
-        if graph.state != GraphState::Fixed {
-            graph.sort();
-            graph.group_colours();
-        }
+        encode_order_and_colours(
+            graph,
+            &mut nauty_graph.vertex_order,
+            &mut nauty_graph.partition,
+        );
 
         for vertex in graph.vertices.iter() {
-            nauty_graph.vertex_order.push(vertex.index);
-
-            nauty_graph.partition.push(vertex.colour);
-
             for end in vertex.edges_to.iter() {
                 ADDONEEDGE(
                     &mut nauty_graph.adjacency_matrix,
@@ -71,12 +111,21 @@ impl NautyGraph {
                 );
             }
         }
-
-        encode_colours(&mut nauty_graph.partition);
+        nauty_graph.generation = graph.generation();
 
         nauty_graph
     }
 
+    /// Refresh only lab and ptn from the current colouring of the graph
+    /// and keep the adjacency matrix. Fails if the edges of the graph
+    /// have changed since this representation was built.
+    pub fn refresh_colours(&mut self, graph: &mut Graph) -> Result<(), Error> {
+        self.generation.check_edges(graph, "the nauty graph")?;
+        encode_order_and_colours(graph, &mut self.vertex_order, &mut self.partition);
+        self.generation = graph.generation();
+        Ok(())
+    }
+
     pub fn check_valid(&self) -> bool {
         let n = self.partition.len();
         let m = SETWORDSNEEDED(n);
@@ -99,19 +148,16 @@ impl TracesGraph {
             sparse_graph: SparseGraph::new(number_vertices, number_edges),
             vertex_order: Vec::with_capacity(number_vertices),
             partition: Vec::with_capacity(number_vertices),
+            digraph: needs_digraph_mode(graph),
+            generation: graph.generation(),
         };
 
-        if graph.state != GraphState::Fixed {
-            graph.sort();
-            graph.group_colours();
-        }
-
         // Encode order and colors
-        for vertex in graph.vertices.iter() {
-            traces_graph.vertex_order.push(vertex.index);
-            traces_graph.partition.push(vertex.colour);
-        }
-        encode_colours(&mut traces_graph.partition);
+        encode_order_and_colours(
+            graph,
+            &mut traces_graph.vertex_order,
+            &mut traces_graph.partition,
+        );
 
         // Encode graph. Vertices must be ordered with increasing indices.
         let mut edge_counter = 0usize;
@@ -125,9 +171,38 @@ impl TracesGraph {
                 edge_counter += 1;
             }
         }
+        traces_graph.generation = graph.generation();
 
         traces_graph
     }
+
+    /// The representation of the bipartite gadget of the digraph,
+    /// see `Graph::bipartite_gadget`.
+    pub fn from_digraph(digraph: &Graph) -> Result<TracesGraph, Error> {
+        let mut traces_graph = Self::from_graph(&mut digraph.bipartite_gadget()?);
+        traces_graph.generation = digraph.generation();
+        Ok(traces_graph)
+    }
+
+    /// Refresh only lab and ptn from the current colouring of the graph
+    /// and keep the sparse graph. Fails if the edges of the graph
+    /// have changed since this representation was built.
+    pub fn refresh_colours(&mut self, graph: &mut Graph) -> Result<(), Error> {
+        self.generation.check_edges(graph, "the Traces graph")?;
+        encode_order_and_colours(graph, &mut self.vertex_order, &mut self.partition);
+        self.generation = graph.generation();
+        Ok(())
+    }
+
+    /// Like `refresh_colours`, but for a representation built by `from_digraph`.
+    /// The gadget is built again from the current colouring of the digraph.
+    pub fn refresh_digraph_colours(&mut self, digraph: &Graph) -> Result<(), Error> {
+        self.generation.check_edges(digraph, "the Traces graph")?;
+        let mut gadget = digraph.bipartite_gadget()?;
+        encode_order_and_colours(&mut gadget, &mut self.vertex_order, &mut self.partition);
+        self.generation = digraph.generation();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -236,6 +311,85 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_digraph_mode() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(3);
+        graph.add_edge(0, 1)?;
+        assert!(!NautyGraph::from_graph(&mut graph).digraph);
+        assert!(!TracesGraph::from_graph(&mut graph).digraph);
+
+        graph.add_arc(2, 2)?;
+        assert!(NautyGraph::from_graph(&mut graph).digraph);
+
+        let mut digraph = Graph::new_ordered(3);
+        digraph.add_arc(0, 1)?;
+        assert!(NautyGraph::from_graph(&mut digraph).digraph);
+        assert!(TracesGraph::from_graph(&mut digraph).digraph);
+        assert!(!TracesGraph::from_digraph(&digraph).unwrap().digraph);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_colours() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.add_edge(2, 3)?;
+
+        let mut nauty_graph = NautyGraph::from_graph(&mut graph);
+        let mut traces_graph = TracesGraph::from_graph(&mut graph);
+
+        graph.set_colours(&[1, 2, 2, 1])?;
+        nauty_graph.refresh_colours(&mut graph)?;
+        traces_graph.refresh_colours(&mut graph)?;
+
+        let fresh_nauty_graph = NautyGraph::from_graph(&mut graph);
+        assert_eq!(
+            fresh_nauty_graph.adjacency_matrix,
+            nauty_graph.adjacency_matrix
+        );
+        assert_eq!(fresh_nauty_graph.vertex_order, nauty_graph.vertex_order);
+        assert_eq!(fresh_nauty_graph.partition, nauty_graph.partition);
+        assert_eq!(fresh_nauty_graph.vertex_order, traces_graph.vertex_order);
+        assert_eq!(fresh_nauty_graph.partition, traces_graph.partition);
+        assert_eq!(vec![0, 3, 1, 2], nauty_graph.vertex_order);
+        assert_eq!(vec![1, 0, 1, 0], nauty_graph.partition);
+
+        // New edges aren't in the adjacency matrix or sparse graph.
+        graph.add_edge(0, 3)?;
+        assert!(matches!(
+            nauty_graph.refresh_colours(&mut graph),
+            Err(Error::StaleGraph(_))
+        ));
+        assert!(matches!(
+            traces_graph.refresh_colours(&mut graph),
+            Err(Error::StaleGraph(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_digraph_colours() -> Result<(), Error> {
+        let mut digraph = Graph::new_ordered(3);
+        digraph.set_directed(true);
+        digraph.connect(0, 1)?;
+        digraph.connect(1, 2)?;
+
+        let mut traces_graph = TracesGraph::from_digraph(&digraph)?;
+        digraph.set_colours(&[1, 1, 2])?;
+        traces_graph.refresh_digraph_colours(&digraph)?;
+        let fresh_traces_graph = TracesGraph::from_digraph(&digraph)?;
+        assert_eq!(fresh_traces_graph.vertex_order, traces_graph.vertex_order);
+        assert_eq!(fresh_traces_graph.partition, traces_graph.partition);
+
+        digraph.connect(2, 0)?;
+        assert!(traces_graph.refresh_digraph_colours(&digraph).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn correct_traces_repr() -> Result<(), GraphError> {
         let mut graph = Graph::new_ordered(8);
@@ -0,0 +1,144 @@
+//! Colour refinement (1-dimensional Weisfeiler-Leman), which splits the
+//! colour classes by the colours of the neighbours until they are stable.
+//! Automorphisms map each vertex to one of the same refined colour, so
+//! the refined colours can be passed to nauty without changing the group.
+
+use std::str::FromStr;
+
+use super::{traversal::Positions, Colour, Graph};
+
+/// Which colours the refinement starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourRefinement {
+    /// Start from a single colour, i.e. the colours of the input are dropped.
+    Replace,
+    /// Start from the colours of the input, which the refined colours split.
+    Combine,
+}
+
+impl FromStr for ColourRefinement {
+    type Err = String;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "replace" => Ok(Self::Replace),
+            "combine" => Ok(Self::Combine),
+            _ => Err(format!("Unknown colour refinement {}", s)),
+        }
+    }
+}
+
+impl Graph {
+    /// Replace the colours of the vertices by the stable colours of the
+    /// refinement, numbered from 0 by the colour and the neighbour colours
+    /// of their first vertices. Returns the number of colours.
+    pub fn refine_colours(&mut self, refinement: ColourRefinement) -> usize {
+        let positions = Positions::new(self);
+        let neighbours = (0..self.vertices.len())
+            .map(|position| positions.neighbours(position).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut colours = match refinement {
+            ColourRefinement::Replace => vec![0; self.vertices.len()],
+            ColourRefinement::Combine => {
+                let mut input_colours = self
+                    .vertices
+                    .iter()
+                    .map(|vertex| vertex.colour)
+                    .collect::<Vec<_>>();
+                input_colours.sort_unstable();
+                input_colours.dedup();
+                self.vertices
+                    .iter()
+                    .map(|vertex| input_colours.binary_search(&vertex.colour).unwrap())
+                    .collect()
+            }
+        };
+        let mut colour_number = colours.iter().max().map_or(0, |max| max + 1);
+
+        loop {
+            // The old colour comes first, so that the classes are only split.
+            let signatures = neighbours
+                .iter()
+                .zip(colours.iter())
+                .map(|(neighbours, colour)| {
+                    let mut neighbour_colours = neighbours
+                        .iter()
+                        .map(|neighbour| colours[*neighbour])
+                        .collect::<Vec<_>>();
+                    neighbour_colours.sort_unstable();
+                    (*colour, neighbour_colours)
+                })
+                .collect::<Vec<_>>();
+            let mut classes = signatures.clone();
+            classes.sort_unstable();
+            classes.dedup();
+
+            colours = signatures
+                .iter()
+                .map(|signature| classes.binary_search(signature).unwrap())
+                .collect();
+            if classes.len() == colour_number {
+                break;
+            }
+            colour_number = classes.len();
+        }
+
+        for (vertex, colour) in self.vertices.iter_mut().zip(colours) {
+            vertex.colour = colour as Colour;
+        }
+        self.vertices_changed();
+        colour_number
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::{GraphError, DEFAULT_COLOR};
+
+    fn colours(graph: &Graph) -> Vec<Colour> {
+        graph.vertices.iter().map(|vertex| vertex.colour).collect()
+    }
+
+    #[test]
+    fn test_refine_colours() -> Result<(), GraphError> {
+        // A path 0-1-2-3 and a triangle 4-5-6.
+        let edges = [(0, 1), (1, 2), (2, 3), (4, 5), (5, 6), (6, 4)];
+        let mut graph = Graph::from_edges(7, edges.iter().copied())?;
+        graph.set_colours(&[
+            DEFAULT_COLOR,
+            DEFAULT_COLOR,
+            DEFAULT_COLOR,
+            DEFAULT_COLOR,
+            1,
+            1,
+            2,
+        ])?;
+
+        // Ends of the path, its middle and the triangle.
+        let mut replaced = graph.clone();
+        assert_eq!(3, replaced.refine_colours(ColourRefinement::Replace));
+        assert_eq!(vec![0, 1, 1, 0, 2, 2, 2], colours(&replaced));
+
+        // The input colours split the triangle first.
+        assert_eq!(4, graph.refine_colours(ColourRefinement::Combine));
+        assert_eq!(vec![2, 3, 3, 2, 0, 0, 1], colours(&graph));
+
+        // Stable colours stay the same.
+        assert_eq!(4, graph.refine_colours(ColourRefinement::Combine));
+        assert_eq!(vec![2, 3, 3, 2, 0, 0, 1], colours(&graph));
+        Ok(())
+    }
+
+    #[test]
+    fn test_refine_regular() -> Result<(), GraphError> {
+        // Colour refinement can't tell the vertices of regular graphs apart.
+        let cycle = (0..6).map(|vertex| (vertex, (vertex + 1) % 6));
+        let mut graph = Graph::from_edges(6, cycle)?;
+        assert_eq!(1, graph.refine_colours(ColourRefinement::Replace));
+        assert_eq!(vec![0; 6], colours(&graph));
+        Ok(())
+    }
+}
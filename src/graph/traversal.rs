@@ -0,0 +1,198 @@
+//! Breadth and depth first traversals and connected components.
+//! All of them are iterative, so that they don't overflow the
+//! stack on graphs with millions of vertices. They follow the
+//! arcs as stored and skip arcs to vertices outside the graph.
+
+use std::collections::{HashMap, VecDeque};
+
+use super::{Graph, GraphError, GraphState, VertexIndex};
+
+/// Positions of the vertices in the vertex list, with a map
+/// for graphs whose vertices can't be found by their index.
+pub(super) struct Positions<'a> {
+    graph: &'a Graph,
+    map: Option<HashMap<VertexIndex, usize>>,
+}
+
+impl<'a> Positions<'a> {
+    pub(super) fn new(graph: &'a Graph) -> Self {
+        let map = match graph.state {
+            GraphState::IndexOrdered | GraphState::SparseSorted => None,
+            _ => Some(
+                graph
+                    .vertices
+                    .iter()
+                    .enumerate()
+                    .map(|(position, vertex)| (vertex.index, position))
+                    .collect(),
+            ),
+        };
+        Positions { graph, map }
+    }
+
+    fn get(&self, index: VertexIndex) -> Option<usize> {
+        match self.map.as_ref() {
+            Some(map) => map.get(&index).copied(),
+            None => self.graph.position(index),
+        }
+    }
+
+    pub(super) fn neighbours(&self, position: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph.vertices[position]
+            .edges_to
+            .iter()
+            .filter_map(move |end| self.get(*end))
+    }
+}
+
+impl Graph {
+    /// The vertices reachable from the start, grouped by their
+    /// distance from it. The first layer is the start itself.
+    pub fn bfs_layers(&self, start: VertexIndex) -> Result<Vec<Vec<VertexIndex>>, GraphError> {
+        let positions = Positions::new(self);
+        let start = positions.get(start).ok_or(GraphError(start))?;
+
+        let mut visited = vec![false; self.vertices.len()];
+        visited[start] = true;
+        let mut layers = Vec::new();
+        let mut layer = vec![start];
+        while !layer.is_empty() {
+            let mut next_layer = Vec::new();
+            for position in layer.iter() {
+                for end in positions.neighbours(*position) {
+                    if !visited[end] {
+                        visited[end] = true;
+                        next_layer.push(end);
+                    }
+                }
+            }
+            layers.push(
+                layer
+                    .into_iter()
+                    .map(|position| self.vertices[position].index)
+                    .collect(),
+            );
+            layer = next_layer;
+        }
+        Ok(layers)
+    }
+
+    /// The vertices reachable from the start in depth first preorder,
+    /// visiting the neighbours in the order of the adjacency lists.
+    pub fn dfs_order(&self, start: VertexIndex) -> Result<Vec<VertexIndex>, GraphError> {
+        let positions = Positions::new(self);
+        let start = positions.get(start).ok_or(GraphError(start))?;
+
+        let mut visited = vec![false; self.vertices.len()];
+        let mut order = Vec::new();
+        // The stack holds the neighbours in reverse, so that the first is visited first.
+        let mut stack = vec![start];
+        while let Some(position) = stack.pop() {
+            if visited[position] {
+                continue;
+            }
+            visited[position] = true;
+            order.push(self.vertices[position].index);
+            let unvisited = positions
+                .neighbours(position)
+                .filter(|end| !visited[*end])
+                .collect::<Vec<_>>();
+            stack.extend(unvisited.into_iter().rev());
+        }
+        Ok(order)
+    }
+
+    /// The number of the connected component of each vertex, in the order
+    /// of the vertex list. Components are numbered from 0 in the order of
+    /// their first vertex. Arcs are only followed in their direction, so
+    /// the graph should store both directions of each edge.
+    pub fn connected_components(&self) -> Vec<usize> {
+        let positions = Positions::new(self);
+        let mut components = vec![usize::MAX; self.vertices.len()];
+        let mut number = 0;
+        let mut queue = VecDeque::new();
+        for start in 0..self.vertices.len() {
+            if components[start] != usize::MAX {
+                continue;
+            }
+            components[start] = number;
+            queue.push_back(start);
+            while let Some(position) = queue.pop_front() {
+                for end in positions.neighbours(position) {
+                    if components[end] == usize::MAX {
+                        components[end] = number;
+                        queue.push_back(end);
+                    }
+                }
+            }
+            number += 1;
+        }
+        components
+    }
+
+    pub fn number_of_components(&self) -> usize {
+        self.connected_components()
+            .into_iter()
+            .max()
+            .map_or(0, |last| last + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A path 0-1-2 with a pendant vertex 3 at 1 and an isolated edge 4-5.
+    fn example_graph() -> Result<Graph, GraphError> {
+        let mut graph = Graph::from_edges(6, vec![(0, 1), (1, 2), (1, 3), (4, 5)])?;
+        graph.sort();
+        Ok(graph)
+    }
+
+    #[test]
+    fn test_bfs_layers() -> Result<(), GraphError> {
+        let graph = example_graph()?;
+        assert_eq!(vec![vec![0], vec![1], vec![2, 3]], graph.bfs_layers(0)?);
+        assert_eq!(vec![vec![2], vec![1], vec![0, 3]], graph.bfs_layers(2)?);
+        assert_eq!(vec![vec![5], vec![4]], graph.bfs_layers(5)?);
+        assert_eq!(Err(GraphError(6)), graph.bfs_layers(6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dfs_order() -> Result<(), GraphError> {
+        let mut graph = example_graph()?;
+        graph.add_edge(2, 3)?;
+        graph.sort();
+        assert_eq!(vec![0, 1, 2, 3], graph.dfs_order(0)?);
+        assert_eq!(vec![3, 1, 0, 2], graph.dfs_order(3)?);
+        assert_eq!(Err(GraphError(-1)), graph.dfs_order(-1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_connected_components() -> Result<(), GraphError> {
+        let graph = example_graph()?;
+        assert_eq!(vec![0, 0, 0, 0, 1, 1], graph.connected_components());
+        assert_eq!(2, graph.number_of_components());
+        assert_eq!(0, Graph::new_ordered(0).number_of_components());
+
+        // Vertices named by sparse indices, with an arc to an unknown vertex.
+        let mut sparse = Graph::new_with_indices(&[7, 3, 5], false);
+        sparse.add_edge(7, 5)?;
+        sparse.add_arc(3, 9)?;
+        assert_eq!(vec![0, 1, 0], sparse.connected_components());
+        assert_eq!(vec![vec![5], vec![7]], sparse.bfs_layers(5)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deep_path() -> Result<(), GraphError> {
+        let size = 200_000;
+        let graph = Graph::from_edges(size, (1..size as VertexIndex).map(|end| (end - 1, end)))?;
+        assert_eq!(size, graph.dfs_order(0)?.len());
+        assert_eq!(size, graph.bfs_layers(0)?.len());
+        assert_eq!(1, graph.number_of_components());
+        Ok(())
+    }
+}
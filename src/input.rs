@@ -2,57 +2,293 @@
 //! graphs from stdin. Uses similar commands
 //! as dreadnaut.
 
+use flate2::read::GzDecoder;
+use itertools::Itertools;
 use std::{
     env::current_dir,
+    ffi::{OsStr, OsString},
     fs::File,
-    io::{self, BufReader, Stdin, Write},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Read, Stdin, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 use structopt::StructOpt;
+use xz2::read::XzDecoder;
 
 use crate::{
-    graph::{Graph, VertexIndex},
+    feedback::metric_from_feedback,
+    graph::{Colour, ColourRefinement, Graph, GraphEdit, VertexIndex, DEFAULT_COLOR},
     misc::CoreMetric,
-    parser::{parse_csv_input, parse_dreadnaut_input, parse_txt_input},
-    statistics::{Statistics, StatisticsLevel},
-    Error, MetricUsed, NautyTraces, Settings,
+    parser::{
+        parse_cnf_input, parse_csv_input_with, parse_dreadnaut_input, parse_dreadnaut_inputs,
+        parse_edgelist_input, parse_edit_script, parse_txt_input_with_ids, CsvOptions,
+        CsvThirdColumn,
+    },
+    predictor::ThresholdPredictor,
+    statistics::{FileStatistics, NoStatistics, Statistics, StatisticsLevel, StatisticsSink},
+    writer::QuotientFormat,
+    AutomorphismOptions, EncodingOptions, Error, Invariant, LiftFiles, MemorySize, MetricUsed,
+    NautyTraces, ProbeOptions, ReportOutput, Settings, TransversalEncoding,
 };
 
+/// Supported graph file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    Dreadnaut,
+    Csv,
+    Txt,
+    EdgeList,
+    /// DIMACS CNF, read as clause-variable incidence graph.
+    Cnf,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dre" => Ok(Self::Dreadnaut),
+            "csv" => Ok(Self::Csv),
+            "txt" => Ok(Self::Txt),
+            "edgelist" => Ok(Self::EdgeList),
+            "cnf" => Ok(Self::Cnf),
+            _ => Err(format!("Unknown input format {}", s)),
+        }
+    }
+}
+
+/// Utilities that don't search for quotients of an input graph.
+#[derive(StructOpt, Debug)]
+pub(crate) enum Command {
+    /// Compare two orbit partitions of the same graph, given as
+    /// `.orbits.csv` files (e.g. written by `--export-quotient`).
+    Diff {
+        #[structopt(parse(from_os_str))]
+        left: PathBuf,
+        #[structopt(parse(from_os_str))]
+        right: PathBuf,
+    },
+    /// Run all combinations of inputs, metrics, backends and
+    /// strategies described by a TOML config file.
+    Experiment {
+        #[structopt(parse(from_os_str))]
+        config: PathBuf,
+    },
+    /// Write the subgraph of the input graph induced by the given
+    /// orbits of its automorphism group in dreadnaut syntax, e.g.
+    /// `dqg graph.dre extract --orbit 3,7 orbits.dre`.
+    Extract {
+        /// The orbits to extract, named by any of their vertices.
+        /// Inputs with sparse vertex ids refer to the vertices by their ids.
+        #[structopt(long, use_delimiter = true, required = true)]
+        orbit: Vec<VertexIndex>,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Print the values of all metrics and the descriptiveness of the quotient
+    /// of all generators (or of each subset with `-p`) as csv, one row per
+    /// quotient, e.g. `dqg metrics graph.dre`.
+    Metrics {
+        #[structopt(parse(from_os_str))]
+        graph: PathBuf,
+        /// Write the csv to this file instead of stdout.
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Lift a property of the vertices of a descriptive quotient to the
+    /// original vertices picked by a consistent transversal, e.g.
+    /// `dqg graph.dre lift graph.orbits.csv touched.txt touched.csv`.
+    /// Each line of the property file is a quotient vertex, optionally
+    /// followed by a comma and a value.
+    Lift {
+        /// Orbit membership file of the quotient, e.g. written by `--export-quotient`.
+        #[structopt(parse(from_os_str))]
+        orbits: PathBuf,
+        #[structopt(parse(from_os_str))]
+        property: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "DQG")]
-struct CommandLineOptions {
+pub(crate) struct CommandLineOptions {
     /// Test whole powerset of the generators.
     #[structopt(short = "-p", long)]
     iter_powerset: bool,
+    /// Only test subsets with at least this many generators in the powerset.
+    #[structopt(long, default_value = "1")]
+    min_subset_size: usize,
+    /// Only test subsets with at most this many generators in the powerset.
+    #[structopt(long)]
+    max_subset_size: Option<usize>,
     /// Read a file from command line.
     #[structopt(short = "-m", long)]
     read_memory_pipe: bool,
-    /// Outputs orbits in dreadnaut format.
+    /// Outputs the orbits of the descriptive quotient in dreadnaut format
+    /// once it is found, followed by the subset of generators that induced
+    /// it if the subsets of generators are searched.
     #[structopt(short = "-o", long)]
     output_orbits: bool,
+    /// Output orbits byte-compatible with the `o` command of dreadnaut
+    /// (ranges, line wrapping, no cpu time line).
+    #[structopt(long)]
+    strict_orbits: bool,
+    /// Output the orbits grouped by the colour class of their vertices,
+    /// one line per colour.
+    #[structopt(long, conflicts_with = "strict-orbits")]
+    colour_orbits: bool,
     /// Logs all orbit sizes in a HashMap.
     #[structopt(short = "-l", long)]
     log_orbits: bool,
     /// Print formula instead of solving it.
     #[structopt(short = "-f", long)]
     print_formula: bool,
-    /// Graph is colored and colors should be
-    /// included in the nauty computation.
+    /// Include the colours in the nauty computation even if
+    /// all vertices have the same colour. By default, they are
+    /// included as soon as the graph has more than one colour.
     #[structopt(short = "-c", long)]
     colored_graph: bool,
+    /// Ignore the colours of the input graph. Doesn't affect
+    /// CNF input and individualized vertices.
+    #[structopt(long, conflicts_with = "colored-graph")]
+    no_colours: bool,
+    /// Refine the colours by the colours of the neighbours until they are
+    /// stable and use the refined colours for nauty. replace starts from a
+    /// single colour, combine from the colours of the input (unless they
+    /// are ignored). Write them with --export-dreadnaut to reuse them.
+    /// Possible values: replace, combine
+    #[structopt(long)]
+    refine_colours: Option<ColourRefinement>,
+    /// Individualize these vertices, i.e. give each of them
+    /// its own colour, before computing the automorphisms.
+    /// Comes in addition to a fix=[...] line in dreadnaut input.
+    /// Inputs with sparse vertex ids refer to the vertices by their ids.
+    #[structopt(long, use_delimiter = true)]
+    fix: Vec<VertexIndex>,
+    /// Apply the edits of this script to the input graph after reading it,
+    /// e.g. to see how small changes affect the orbits and descriptiveness.
+    /// One edit per line: `add u v` and `remove u v` for edges (arcs of
+    /// digraphs) and `colour v c` to give vertex v the colour c.
+    /// Inputs with sparse vertex ids refer to the vertices by their ids.
+    #[structopt(long, parse(from_os_str))]
+    edit_script: Option<PathBuf>,
+    /// Process all graphs of a dreadnaut input one after another
+    /// instead of only the first one.
+    #[structopt(long)]
+    all_graphs: bool,
+    /// Read the generators of the automorphism group from a cache file next
+    /// to the input file instead of computing them. Writes the cache file
+    /// if there is none yet or if it doesn't fit the graph.
+    #[structopt(long, requires = "input")]
+    cache_generators: bool,
     /// Use traces instead of nauty to compute
     /// the graphs automorphism group.
     #[structopt(short = "-t", long)]
     use_traces: bool,
+    /// Seed for all randomized parts, e.g. group sampling, to make
+    /// runs reproducible. Also seeds nauty/Traces unless --nauty-seed
+    /// is given and checks the GAP classes on one thread unless
+    /// --gap-workers is given. External tools like GAP and kissat
+    /// are not seeded by this.
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Seed for the random number generator
+    /// of nauty/Traces to make runs repeatable.
+    #[structopt(long)]
+    nauty_seed: Option<i64>,
+    /// Number of failed random Schreier tests
+    /// after which nauty stops (ignored by Traces).
+    #[structopt(long)]
+    schreier_fails: Option<i32>,
+    /// Vertex invariant for nauty (ignored by Traces).
+    /// Possible values: adjacencies, distances
+    #[structopt(long)]
+    invariant: Option<Invariant>,
+    /// Maximal search tree level at which the invariant is applied.
+    #[structopt(long, default_value = "1")]
+    invariant_level: i32,
+    /// Encoding of the transversal constraint for each orbit.
+    /// Possible values: at_least_one, pairwise, at_most_one
+    #[structopt(long, default_value = "at_least_one")]
+    transversal_encoding: TransversalEncoding,
+    /// Orbit size from which at_most_one uses the ladder instead of the pairwise encoding.
+    #[structopt(long, default_value = "8")]
+    ladder_threshold: usize,
+    /// Orbit size from which at_most_one uses the binary instead of the ladder encoding.
+    #[structopt(long, default_value = "64")]
+    binary_threshold: usize,
+    /// Only encode one direction of each undirected quotient edge.
+    #[structopt(long)]
+    no_mirrored_edges: bool,
+    /// Shorten constraints of edges to singleton orbits to unit clauses.
+    #[structopt(long)]
+    propagate_singletons: bool,
+    /// Exclude vertices without neighbours in an adjacent orbit by unit clauses.
+    #[structopt(long)]
+    degree_implied_clauses: bool,
+    /// Annotate CNF files written for external tools like kitten with
+    /// comments that map variables and clauses back to the orbits.
+    #[structopt(long)]
+    cnf_comments: bool,
     /// Use nondescriptive cores and the metric
     /// to guide the search.
-    /// Possible values: recolor, pow_gen
-    #[structopt(short = "-q", long)]
+    #[structopt(short = "-q", long, possible_values = &CoreMetric::NAMES)]
     nondescriptive_core: Option<CoreMetric>,
+    /// Search the smallest non-descriptive core of the quotient
+    /// induced by all generators by checking the subsets of its
+    /// orbits in increasing size.
+    #[structopt(long, conflicts_with = "nondescriptive-core")]
+    smallest_core: bool,
+    /// Only check subsets with at least this many orbits for cores.
+    #[structopt(long, default_value = "2")]
+    min_core_size: usize,
+    /// Only check subsets with at most this many orbits for cores.
+    #[structopt(long)]
+    max_core_size: Option<usize>,
+    /// Predict the descriptiveness of each quotient from its orbit
+    /// sizes and density and track mispredictions in the statistics.
+    #[structopt(long)]
+    predict: bool,
+    /// Don't solve quotients that are predicted to be non-descriptive.
+    /// Implies `--predict`.
+    #[structopt(long)]
+    skip_predicted: bool,
+    /// Probe each quotient with kissat limited to this many conflicts
+    /// before solving it. Needs a kissat binary in the PATH.
+    #[structopt(long)]
+    probe_conflicts: Option<u64>,
+    /// Limit the address space of the kissat binary of the probes, e.g. 2G.
+    /// kissat fails like a probe that gave up if it runs out of memory.
+    #[structopt(long, requires = "probe-conflicts")]
+    probe_memory: Option<MemorySize>,
+    /// Limit the address space of kitten, which extracts the non-descriptive
+    /// cores, e.g. 2G. Running out of memory fails the run.
+    #[structopt(long)]
+    kitten_memory: Option<MemorySize>,
+    /// Defer quotients that the probe couldn't decide to the end
+    /// of the powerset iteration instead of solving them right away.
+    #[structopt(long)]
+    defer_hard: bool,
+    /// Find up to this many distinct cores per non-descriptive
+    /// quotient in core mode and break them all at once.
+    #[structopt(long, default_value = "1")]
+    cores_per_quotient: usize,
     /// Search in the whole automorphism group instead
     /// of a set of generators.
     #[structopt(short = "-g", long)]
     search_group: bool,
+    /// Sample the given number of random group elements
+    /// and report how many induce descriptive quotients.
+    #[structopt(long)]
+    sample_group: Option<usize>,
+    /// Compute the generators with both nauty and Traces
+    /// and warn if they generate different orbits.
+    #[structopt(long)]
+    cross_check: bool,
     /// Validate each descriptiveness result
     /// with exhaustive search for consistent
     /// transversals.
@@ -63,28 +299,115 @@ struct CommandLineOptions {
     /// search in the conjugacy classes.
     #[structopt(long)]
     gap_mode: bool,
+    /// Kill GAP after the given number of seconds in GAP mode
+    /// and fall back to the search over subsets of generators.
+    #[structopt(long)]
+    gap_deadline: Option<u64>,
+    /// Check the conjugacy classes on the given number of
+    /// threads in GAP mode. Defaults to the number of cores.
+    #[structopt(long)]
+    gap_workers: Option<usize>,
+    /// Memory limit of GAP, e.g. 8G or 512M. Defaults to 16G for
+    /// the conjugacy classes without statistics and 4G otherwise.
+    #[structopt(long)]
+    gap_memory: Option<MemorySize>,
+    /// Walk down the subgroup lattice with GAP, guided by
+    /// non-descriptive cores, until the quotient is descriptive.
+    #[structopt(long)]
+    lattice_walk: bool,
     /// GIve graph size for file formats
     /// which don't contain the graph size.
     #[structopt(short = "-n", long)]
     graph_size: Option<usize>,
-    /// Use the given metric to find the "best" quotient
-    /// and use it as described by the other flags.
-    /// Possible value: least_orbits, biggest_orbit, sparsity
+    /// Format of the input graph. Overrides the file
+    /// extension and allows to pipe other formats than
+    /// dreadnaut through stdin.
+    /// Possible values: dre, csv, txt, edgelist, cnf
     #[structopt(long)]
+    format: Option<InputFormat>,
+    /// Delimiter of csv input, a single character or "tab".
+    /// Detected from the first line if not given.
+    #[structopt(long, parse(try_from_str = parse_delimiter))]
+    csv_delimiter: Option<char>,
+    /// What to do with a third column of csv input, e.g. edge weights.
+    /// edge_colours subdivides each edge by a new vertex coloured by the
+    /// value, these vertices follow the -n vertices of the graph.
+    /// Possible values: ignore, edge_colours
+    #[structopt(long, default_value = "ignore")]
+    csv_third_column: CsvThirdColumn,
+    /// Use the given metric to find the "best" quotient
+    /// and use it as described by the other flags. With -p,
+    /// the subsets of generators with the fewest orbits are checked first.
+    #[structopt(long, possible_values = &MetricUsed::NAMES)]
     metric: Option<MetricUsed>,
-    /// Evaluate a log file as printed by
-    /// the quotientPlanning tool.
+    /// Use the metric that led to the shortest plans for the same
+    /// or a similarly sized graph in this evaluation csv.
+    #[structopt(long, parse(from_os_str), conflicts_with = "metric")]
+    metric_feedback: Option<PathBuf>,
+    /// Stop the search for descriptive quotients
+    /// after the given number of seconds.
+    #[structopt(long)]
+    timeout: Option<u64>,
+    /// Keep searching the subsets of generators after the first descriptive
+    /// quotient and output the best one by the metric once the timeout is
+    /// reached or all subsets are checked.
+    #[structopt(long, requires = "iter-powerset")]
+    anytime: bool,
+    /// Write the input graph (including colours)
+    /// in dreadnaut syntax to the given file and exit.
+    #[structopt(long, parse(from_os_str))]
+    export_dreadnaut: Option<PathBuf>,
+    /// Write the quotient graph induced by all generators
+    /// as csv or txt (by file extension) to the given file and
+    /// the orbit of each vertex into a `.orbits.csv` file next to it.
+    #[structopt(long, parse(try_from_os_str = parse_quotient_path))]
+    export_quotient: Option<PathBuf>,
+    /// Write the graph as Graphviz dot file with the vertices
+    /// coloured by the orbits of all generators.
+    #[structopt(long, parse(from_os_str))]
+    export_dot: Option<PathBuf>,
+    /// Write the conflict graph of a non-descriptive core of the quotient
+    /// of all generators to this file (JSON for `.json`, dot otherwise).
     #[structopt(long, parse(from_os_str))]
-    evaluate: Option<PathBuf>,
+    export_conflict_graph: Option<PathBuf>,
+    /// Write the generators of the automorphism group to this file and exit:
+    /// as GAP group for `.g` or `.gap`, as JSON arrays with the image of
+    /// each vertex for `.json` and in cycle notation otherwise.
+    #[structopt(long, parse(from_os_str))]
+    export_generators: Option<PathBuf>,
+    /// Write how often each pair of vertices shares an orbit in the quotients
+    /// of the checked subsets of generators as csv to the given file, i.e.
+    /// how robust their symmetry is. Pairs that never share one are left
+    /// out. Combine with --anytime to check all subsets.
+    #[structopt(long, parse(from_os_str), requires = "iter-powerset")]
+    export_co_orbits: Option<PathBuf>,
+    /// Write the values of all metrics and the descriptiveness of the
+    /// quotient of all generators (or of each subset with `-p`) as csv
+    /// to the given file instead of stopping at a descriptive quotient.
+    #[structopt(long, parse(from_os_str))]
+    metrics_report: Option<PathBuf>,
+    /// Draw each orbit as a single vertex in the dot file.
+    #[structopt(long)]
+    collapse_orbits: bool,
     /// Level of detail for statistics.
     /// None if left out, basic if `-s`, full for more than one `-s`.
     #[structopt(short = "-s", parse(from_occurrences = StatisticsLevel::from))]
     statistics_level: StatisticsLevel,
+    /// Screen many graphs as fast as possible: no statistics and no checks
+    /// or reports of the input graph, e.g. its issues or colour classes.
+    #[structopt(long, conflicts_with = "statistics-level")]
+    throughput: bool,
+    /// Write the statistics to this file instead of next to the input
+    /// graph, or to `statistics.dqg` in the current directory for stdin.
+    #[structopt(long, parse(from_os_str))]
+    statistics_file: Option<PathBuf>,
     /// The input file to read from. Optional.
     /// Same path will be used for output.
     /// Reads through CLI if not specified.
     #[structopt(parse(from_os_str))]
     input: Option<PathBuf>,
+    #[structopt(subcommand)]
+    pub(crate) command: Option<Command>,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -152,46 +475,403 @@ with the next vertex or a `.` to end inputting edges.", index, index);
     Ok(should_continue)
 }
 
+/// Print at most this many issues of an invalid input graph.
+const MAX_GRAPH_ISSUE_WARNINGS: usize = 10;
+/// Report at most this many colour class sizes.
+const MAX_COLOUR_CLASS_SIZES: usize = 10;
+
+/// Warn about input graphs that violate the assumptions of DQG.
+/// They are still processed, but the results may be wrong.
+#[cfg(not(tarpaulin_include))]
+fn warn_graph_issues(graph: &Graph) {
+    let issues = graph.validate();
+    for issue in issues.iter().take(MAX_GRAPH_ISSUE_WARNINGS) {
+        eprintln!("Warning: {}", issue);
+    }
+    if issues.len() > MAX_GRAPH_ISSUE_WARNINGS {
+        eprintln!(
+            "Warning: {} more issues in the input graph",
+            issues.len() - MAX_GRAPH_ISSUE_WARNINGS
+        );
+    }
+}
+
+/// Report the colour classes of the input graph and
+/// warn if `-c` was given although there is only one.
+#[cfg(not(tarpaulin_include))]
+fn report_colour_classes(colour_classes: &[(Colour, usize)], requested: bool) {
+    let sizes = colour_classes.iter().map(|(_, size)| size);
+    let shown = sizes.clone().take(MAX_COLOUR_CLASS_SIZES).join(", ");
+    let more = if colour_classes.len() > MAX_COLOUR_CLASS_SIZES {
+        ", ..."
+    } else {
+        ""
+    };
+    eprintln!(
+        "{} colour classes of sizes {}{}",
+        colour_classes.len(),
+        shown,
+        more
+    );
+
+    if requested && colour_classes.len() <= 1 {
+        eprintln!("Warning: -c has no effect, all vertices have the same colour");
+    }
+}
+
+/// Compressions of graph files that are decompressed while reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Xz,
+}
+
+impl Compression {
+    /// Detect the compression by the extension of the file.
+    fn of_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "xz" => Some(Self::Xz),
+            _ => None,
+        }
+    }
+
+    fn decompress<R: Read + 'static>(compression: Option<Self>, input: R) -> Box<dyn BufRead> {
+        match compression {
+            Some(Self::Gzip) => Box::new(BufReader::new(GzDecoder::new(input))),
+            Some(Self::Xz) => Box::new(BufReader::new(XzDecoder::new(input))),
+            None => Box::new(BufReader::new(input)),
+        }
+    }
+}
+
+/// Open a graph file, which may be compressed (`.gz` or `.xz`).
+/// Returns the reader and the path without the compression extension.
+#[cfg(not(tarpaulin_include))]
+fn open_graph_file(path: &Path) -> Result<(Box<dyn BufRead>, PathBuf), Error> {
+    let compression = Compression::of_path(path);
+    let file = File::open(path)?;
+    let uncompressed_path = match compression {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    };
+    Ok((
+        Compression::decompress(compression, file),
+        uncompressed_path,
+    ))
+}
+
+/// The input format given by the file extension,
+/// without the extension of a compression.
+fn format_of_path(path: &Path) -> Result<InputFormat, Error> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| extension.parse().ok())
+        .ok_or_else(|| {
+            Error::ConfigError(format!(
+                "Unknown format of the graph file {}, expected a .dre, .csv, .txt, .edgelist \
+                 or .cnf file, which may be compressed as .gz or .xz",
+                path.display()
+            ))
+        })
+}
+
+/// Reject quotient exports in unknown formats before the search runs.
+#[cfg(not(tarpaulin_include))]
+fn parse_quotient_path(path: &OsStr) -> Result<PathBuf, OsString> {
+    let path = PathBuf::from(path);
+    match QuotientFormat::of_path(&path) {
+        Ok(_) => Ok(path),
+        Err(error) => Err(error.to_string().into()),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn parse_delimiter(delimiter: &str) -> Result<char, String> {
+    let mut chars = delimiter.chars();
+    match (delimiter, chars.next(), chars.next()) {
+        ("tab", _, _) => Ok('\t'),
+        (_, Some(delimiter), None) => Ok(delimiter),
+        _ => Err(format!("Invalid csv delimiter {}", delimiter)),
+    }
+}
+
+/// A graph as parsed from one of the input formats.
+struct ParsedGraph {
+    graph: Graph,
+    /// The input contained the header that selects Traces.
+    has_header: bool,
+    fixed_vertices: Vec<VertexIndex>,
+    /// Original ids of the vertices if they had to be compacted.
+    vertex_ids: Option<Vec<VertexIndex>>,
+}
+
+impl From<Graph> for ParsedGraph {
+    fn from(graph: Graph) -> Self {
+        ParsedGraph {
+            graph,
+            has_header: false,
+            fixed_vertices: Vec::new(),
+            vertex_ids: None,
+        }
+    }
+}
+
+/// Parse a graph in the given format.
+#[cfg(not(tarpaulin_include))]
+fn parse_graph_input<B: BufRead>(
+    format: InputFormat,
+    graph_size: Option<usize>,
+    csv_options: &CsvOptions,
+    input: B,
+) -> Result<ParsedGraph, Error> {
+    let graph_size = || {
+        graph_size.ok_or_else(|| {
+            Error::ConfigError(format!("{:?} input requires the graph size (-n)", format))
+        })
+    };
+
+    match format {
+        InputFormat::Dreadnaut => {
+            let (graph, has_header, fixed_vertices) = parse_dreadnaut_input(input)?;
+            Ok(ParsedGraph {
+                graph,
+                has_header,
+                fixed_vertices,
+                vertex_ids: None,
+            })
+        }
+        InputFormat::Csv => Ok(parse_csv_input_with(graph_size()?, input, csv_options)?.into()),
+        InputFormat::Txt => {
+            let (graph, vertex_ids) = parse_txt_input_with_ids(input)?;
+            if let Some(vertex_ids) = vertex_ids.as_ref() {
+                eprintln!(
+                    "Compacted the sparse vertex ids of the input to 0..{}",
+                    vertex_ids.len()
+                );
+            }
+            Ok(ParsedGraph {
+                vertex_ids,
+                ..graph.into()
+            })
+        }
+        InputFormat::EdgeList => Ok(parse_edgelist_input(graph_size()?, input)?.into()),
+        InputFormat::Cnf => Ok(parse_cnf_input(input)?.into()),
+    }
+}
+
+/// Parse the graphs of the input, i.e. all graphs of a dreadnaut
+/// file with `--all-graphs` and the only graph of any other input.
 #[cfg(not(tarpaulin_include))]
-pub fn read_graph() -> Result<(Graph, Settings), Error> {
-    let cl_options = CommandLineOptions::from_args();
-
-    if let Some(eval_path) = cl_options.evaluate {
-        let eval_file = File::open(eval_path)?;
-        let buf = BufReader::new(eval_file);
-        return Ok((
-            Graph::new_ordered(0),
-            Settings {
-                evaluate: Some(buf),
-                ..Default::default()
-            },
+fn parse_graph_inputs<B: BufRead>(
+    format: InputFormat,
+    cl_options: &CommandLineOptions,
+    csv_options: &CsvOptions,
+    input: B,
+) -> Result<Vec<ParsedGraph>, Error> {
+    if !cl_options.all_graphs {
+        return Ok(vec![parse_graph_input(
+            format,
+            cl_options.graph_size,
+            csv_options,
+            input,
+        )?]);
+    }
+    if format != InputFormat::Dreadnaut {
+        return Err(Error::ConfigError(
+            "Only dreadnaut input can contain several graphs".to_string(),
         ));
     }
 
-    let mut use_traces = cl_options.use_traces;
-    let mut graph;
+    let graphs = parse_dreadnaut_inputs(input)?;
+    eprintln!("Read {} graphs from the input", graphs.len());
+    Ok(graphs
+        .into_iter()
+        .map(|(graph, has_header, fixed_vertices)| ParsedGraph {
+            graph,
+            has_header,
+            fixed_vertices,
+            vertex_ids: None,
+        })
+        .collect())
+}
+
+/// The dense indices of the vertices with the given original ids.
+fn compact_vertices(
+    vertices: &[VertexIndex],
+    vertex_ids: &[VertexIndex],
+) -> Result<Vec<VertexIndex>, Error> {
+    vertices
+        .iter()
+        .map(|id| {
+            vertex_ids
+                .iter()
+                .position(|vertex_id| vertex_id == id)
+                .map(|index| index as VertexIndex)
+                .ok_or_else(|| Error::ConfigError(format!("No vertex with id {}", id)))
+        })
+        .collect()
+}
+
+/// Apply the edits of the script to the graph, with the vertices given by
+/// their ids if the input had sparse ones. Edits that don't change the
+/// graph, i.e. removals of missing edges, are only warned about.
+#[cfg(not(tarpaulin_include))]
+fn apply_edit_script(
+    graph: &mut Graph,
+    path: &Path,
+    vertex_ids: Option<&[VertexIndex]>,
+) -> Result<(), Error> {
+    let edits = parse_edit_script(BufReader::new(File::open(path)?))?;
+    for edit in edits.iter() {
+        let compacted = match vertex_ids {
+            Some(vertex_ids) => edit.map_vertices(|id| {
+                compact_vertices(&[id], vertex_ids).map(|vertices| vertices[0])
+            })?,
+            None => *edit,
+        };
+        if let (false, GraphEdit::RemoveEdge(start, end)) = (graph.apply_edit(compacted)?, edit) {
+            eprintln!(
+                "Warning: the edit script removes the missing edge {}-{}",
+                start, end
+            );
+        }
+    }
+    eprintln!("Applied {} edits of the edit script", edits.len());
+    Ok(())
+}
+
+/// Read a graph file in the format given by its extension, e.g. the input
+/// graph of a planning task. Formats without the graph size need it.
+#[cfg(not(tarpaulin_include))]
+pub fn read_graph_file(path: &Path, graph_size: Option<usize>) -> Result<Graph, Error> {
+    let (file_buf, path) = open_graph_file(path)?;
+    let format = format_of_path(&path)?;
+    let mut graph = parse_graph_input(format, graph_size, &CsvOptions::default(), file_buf)?.graph;
+    graph.minimize();
+    Ok(graph)
+}
+
+/// A graph to process with its settings and statistics.
+pub type GraphRun = (Graph, Settings, Box<dyn StatisticsSink>);
+
+/// Parse the command line arguments, exits on invalid ones.
+#[cfg(not(tarpaulin_include))]
+pub(crate) fn read_command_line() -> CommandLineOptions {
+    CommandLineOptions::from_args()
+}
+
+/// The parsed graphs to process. Each graph only becomes a run with its
+/// settings and statistics right before it is processed, so that its
+/// statistics don't include the time spent on the graphs before it.
+pub(crate) struct GraphInputs {
+    cl_options: CommandLineOptions,
+    parsed_graphs: Vec<ParsedGraph>,
+    is_cnf: bool,
+    out_file: PathBuf,
+    extract_orbits: Option<(Vec<VertexIndex>, PathBuf)>,
+    lift: Option<LiftFiles>,
+    metrics_output: Option<ReportOutput>,
+}
+
+impl GraphInputs {
+    /// Whether there is more than one graph to process.
+    pub fn is_batch(&self) -> bool {
+        self.parsed_graphs.len() > 1
+    }
+
+    /// The runs of the graphs in their order, each prepared once it is its turn.
+    #[cfg(not(tarpaulin_include))]
+    pub fn into_runs(self) -> impl Iterator<Item = Result<GraphRun, Error>> {
+        let batch = self.is_batch();
+        let GraphInputs {
+            cl_options,
+            parsed_graphs,
+            is_cnf,
+            out_file,
+            extract_orbits,
+            lift,
+            metrics_output,
+        } = self;
+
+        parsed_graphs
+            .into_iter()
+            .enumerate()
+            .map(move |(index, parsed)| {
+                // Each graph of a batch gets its own statistics file.
+                let mut out_file = out_file.clone();
+                if batch {
+                    out_file.set_extension(format!("{}.dqg", index + 1));
+                }
+                graph_run(
+                    &cl_options,
+                    parsed,
+                    is_cnf,
+                    out_file,
+                    extract_orbits.clone(),
+                    lift.clone(),
+                    metrics_output.clone(),
+                )
+            })
+    }
+}
+
+/// Read the graphs to process, which is a single one unless `--all-graphs`
+/// reads all graphs of a dreadnaut file.
+#[cfg(not(tarpaulin_include))]
+pub(crate) fn read_graph(mut cl_options: CommandLineOptions) -> Result<GraphInputs, Error> {
+    let mut extract_orbits = None;
+    let mut lift = None;
+    let mut metrics_output = None;
+    match cl_options.command.take() {
+        // Dispatched by `run` before reading any graph.
+        Some(Command::Diff { .. }) | Some(Command::Experiment { .. }) => {
+            return Err(Error::ConfigError(
+                "The diff and experiment commands read no graph".to_string(),
+            ))
+        }
+        Some(Command::Extract { orbit, output }) => extract_orbits = Some((orbit, output)),
+        Some(Command::Metrics { graph, output }) => {
+            if cl_options.input.replace(graph).is_some() {
+                return Err(Error::ConfigError(
+                    "Give the graph of the metrics command only after it".to_string(),
+                ));
+            }
+            metrics_output = Some(output.map_or(ReportOutput::Stdout, ReportOutput::File));
+        }
+        Some(Command::Lift {
+            orbits,
+            property,
+            output,
+        }) => {
+            lift = Some(LiftFiles {
+                orbits,
+                property,
+                output,
+            })
+        }
+        None => (),
+    }
+
+    let csv_options = CsvOptions {
+        delimiter: cl_options.csv_delimiter,
+        third_column: cl_options.csv_third_column,
+    };
+
+    let parsed_graphs;
+    // The incidence graph of a CNF formula is only meaningful with its colours.
+    let mut is_cnf = false;
     let mut out_file;
 
-    if let Some(path_to_graph_file) = cl_options.input {
+    if let Some(path_to_graph_file) = cl_options.input.as_ref() {
         // Either read the graph from a file ..
-        let file_buf = BufReader::new(File::open(&path_to_graph_file)?);
-        let (parsed_graph, has_header) = match path_to_graph_file
-            .as_path()
-            .extension()
-            .unwrap()
-            .to_str()
-            .unwrap()
-        {
-            "dre" => parse_dreadnaut_input(file_buf)?,
-            "csv" => (
-                parse_csv_input(cl_options.graph_size.unwrap(), file_buf)?,
-                false,
-            ),
-            "txt" => (parse_txt_input(file_buf)?, false),
-            _ => unimplemented!(),
+        let (file_buf, path_to_graph_file) = open_graph_file(path_to_graph_file)?;
+        let format = match cl_options.format {
+            Some(format) => format,
+            None => format_of_path(&path_to_graph_file)?,
         };
-        use_traces |= has_header;
-        graph = parsed_graph;
+        parsed_graphs = parse_graph_inputs(format, &cl_options, &csv_options, file_buf)?;
+        is_cnf = format == InputFormat::Cnf;
 
         out_file = path_to_graph_file;
         out_file.set_extension("dqg");
@@ -199,51 +879,197 @@ pub fn read_graph() -> Result<(Graph, Settings), Error> {
         // ... or from stdin.
         let stdin = io::stdin();
 
-        if cl_options.read_memory_pipe {
+        if cl_options.read_memory_pipe || cl_options.format.is_some() {
             // Stdin can either mean a memory pipe ...
             let file_buf = BufReader::new(stdin.lock());
-            let (parsed_graph, has_header) = parse_dreadnaut_input(file_buf)?;
-            use_traces |= has_header;
-            graph = parsed_graph;
+            let format = cl_options.format.unwrap_or(InputFormat::Dreadnaut);
+            parsed_graphs = parse_graph_inputs(format, &cl_options, &csv_options, file_buf)?;
+            is_cnf = format == InputFormat::Cnf;
         } else {
             // .... or the interactive command line interface.
-            graph = read_graph_empty(&stdin)?;
+            let mut graph = read_graph_empty(&stdin)?;
 
             for i in 0..graph.size() {
                 if !read_vertex(i as VertexIndex, &mut graph, &stdin)? {
                     break;
                 }
             }
+            parsed_graphs = vec![graph.into()];
         }
 
         out_file =
             current_dir().expect("Statistics feature requires current directory to be accessible!");
         out_file.push("statistics.dqg");
     }
+    if let Some(statistics_file) = cl_options.statistics_file.as_ref() {
+        out_file = statistics_file.clone();
+    }
+
+    Ok(GraphInputs {
+        cl_options,
+        parsed_graphs,
+        is_cnf,
+        out_file,
+        extract_orbits,
+        lift,
+        metrics_output,
+    })
+}
+
+/// Prepare the settings and statistics for a parsed graph.
+#[cfg(not(tarpaulin_include))]
+fn graph_run(
+    cl_options: &CommandLineOptions,
+    parsed: ParsedGraph,
+    is_cnf: bool,
+    out_file: PathBuf,
+    mut extract_orbits: Option<(Vec<VertexIndex>, PathBuf)>,
+    lift: Option<LiftFiles>,
+    metrics_output: Option<ReportOutput>,
+) -> Result<GraphRun, Error> {
+    let use_traces = cl_options.use_traces || parsed.has_header;
+    let mut colored_graph = cl_options.colored_graph || is_cnf;
+    let mut graph = parsed.graph;
+    let mut fixed_vertices = cl_options.fix.clone();
+    fixed_vertices.extend(parsed.fixed_vertices);
+    let vertex_ids = parsed.vertex_ids;
+
+    // Vertices to fix or extract are given by their ids in the input.
+    if let Some(vertex_ids) = vertex_ids.as_ref() {
+        fixed_vertices = compact_vertices(&fixed_vertices, vertex_ids)?;
+        if let Some((orbits, _)) = extract_orbits.as_mut() {
+            *orbits = compact_vertices(orbits, vertex_ids)?;
+        }
+    }
+
+    if let Some(path) = cl_options.edit_script.as_ref() {
+        apply_edit_script(&mut graph, path, vertex_ids.as_deref())?;
+    }
+
+    // Parsers keep duplicate edges, which would count twice towards the density.
+    let duplicates = graph.minimize();
+    if duplicates > 0 {
+        eprintln!("Removed {} duplicate arcs from the input graph", duplicates);
+    }
+    if !cl_options.throughput {
+        warn_graph_issues(&graph);
+    }
+    if let Some(refinement) = cl_options.refine_colours {
+        let refinement = if cl_options.no_colours {
+            ColourRefinement::Replace
+        } else {
+            refinement
+        };
+        graph.refine_colours(refinement);
+        colored_graph = true;
+    }
+    // Individualized vertices only differ by their colours.
+    colored_graph |= !fixed_vertices.is_empty();
+    let colour_classes = graph.colour_classes();
+    // Ignoring the colours of a coloured graph would yield wrong orbits.
+    colored_graph |= colour_classes.len() > 1 && !cl_options.no_colours;
+    // Individualized vertices get their own colours only later.
+    let requested = cl_options.colored_graph && fixed_vertices.is_empty();
+    if !cl_options.throughput {
+        report_colour_classes(&colour_classes, requested);
+    }
+
+    let encoding_options = EncodingOptions {
+        transversal: cl_options.transversal_encoding,
+        mirrored_edges: !cl_options.no_mirrored_edges,
+        propagate_singletons: cl_options.propagate_singletons,
+        degree_implied: cl_options.degree_implied_clauses,
+        cnf_comments: cl_options.cnf_comments,
+        kitten_memory: cl_options.kitten_memory,
+        ladder_threshold: cl_options.ladder_threshold,
+        binary_threshold: cl_options.binary_threshold,
+    };
 
     // Start the statistics after the graph reading is done.
-    let statistics = if cl_options.statistics_level == StatisticsLevel::None {
-        None
+    let statistics: Box<dyn StatisticsSink> =
+        if cl_options.statistics_level == StatisticsLevel::None || cl_options.throughput {
+            Box::new(NoStatistics)
+        } else {
+            let mut statistics = Statistics::new(cl_options.statistics_level, graph.size());
+            statistics.log_encoding_options(encoding_options.clone());
+            statistics.log_colour_classes(colour_classes.iter().map(|(_, size)| *size).collect());
+            Box::new(FileStatistics::new(statistics, out_file))
+        };
+
+    // The colours of the input, before any vertex is individualized.
+    let orbit_colours = if cl_options.colour_orbits {
+        let mut colours = vec![DEFAULT_COLOR; graph.size()];
+        for vertex in graph.vertices.iter() {
+            colours[vertex.index as usize] = vertex.colour;
+        }
+        Some(colours)
     } else {
-        Some(Statistics::new(
-            cl_options.statistics_level,
-            out_file,
-            graph.size(),
-        ))
+        None
     };
 
+    let defer_hard = cl_options.defer_hard;
     let settings = Settings {
         iter_powerset: cl_options.iter_powerset,
-        output_orbits: cl_options.output_orbits,
+        min_subset_size: cl_options.min_subset_size,
+        max_subset_size: cl_options.max_subset_size,
+        output_orbits: cl_options.output_orbits
+            || cl_options.strict_orbits
+            || cl_options.colour_orbits,
+        strict_orbits: cl_options.strict_orbits,
+        orbit_colours,
         log_orbits: cl_options.log_orbits,
         print_formula: cl_options.print_formula,
-        colored_graph: cl_options.colored_graph,
+        colored_graph,
+        fixed_vertices,
+        vertex_ids,
         nondescriptive_core: cl_options.nondescriptive_core,
+        cores_per_quotient: cl_options.cores_per_quotient,
+        smallest_core: cl_options.smallest_core,
+        min_core_size: cl_options.min_core_size,
+        max_core_size: cl_options.max_core_size,
+        predictor: if cl_options.predict || cl_options.skip_predicted {
+            Some(Box::<ThresholdPredictor>::default())
+        } else {
+            None
+        },
+        skip_predicted: cl_options.skip_predicted,
+        probe: cl_options.probe_conflicts.map(|conflicts| ProbeOptions {
+            conflicts,
+            defer_hard,
+            memory_limit: cl_options.probe_memory,
+        }),
         search_group: cl_options.search_group,
+        sample_group: cl_options.sample_group,
+        cross_check: cl_options.cross_check,
         validate: cl_options.validate,
         gap_mode: cl_options.gap_mode,
-        metric: cl_options.metric,
-        evaluate: None,
+        gap_deadline: cl_options.gap_deadline.map(Duration::from_secs),
+        gap_workers: cl_options.gap_workers,
+        gap_memory: cl_options.gap_memory,
+        lattice_walk: cl_options.lattice_walk,
+        metric: match cl_options.metric_feedback.as_ref() {
+            Some(feedback_path) => metric_from_feedback(feedback_path, &graph)?,
+            None => cl_options.metric,
+        },
+        extract_orbits,
+        lift,
+        progress: None,
+        seed: cl_options.seed,
+        timeout: cl_options.timeout.map(Duration::from_secs),
+        anytime: cl_options.anytime,
+        export_dreadnaut: cl_options.export_dreadnaut.clone(),
+        export_quotient: cl_options.export_quotient.clone(),
+        export_dot: cl_options.export_dot.clone(),
+        export_conflict_graph: cl_options.export_conflict_graph.clone(),
+        export_generators: cl_options.export_generators.clone(),
+        export_co_orbits: cl_options.export_co_orbits.clone(),
+        metrics_report: metrics_output
+            .or_else(|| cl_options.metrics_report.clone().map(ReportOutput::File)),
+        collapse_orbits: cl_options.collapse_orbits,
+        generator_cache: cl_options
+            .input
+            .clone()
+            .filter(|_| cl_options.cache_generators),
         nauyt_or_traces: if use_traces {
             NautyTraces::Traces
         } else if graph.is_sparse() {
@@ -251,8 +1077,82 @@ pub fn read_graph() -> Result<(Graph, Settings), Error> {
         } else {
             NautyTraces::Nauty
         },
-        statistics,
+        automorphism_options: AutomorphismOptions {
+            random_seed: cl_options
+                .nauty_seed
+                .or(cl_options.seed.map(|seed| seed as i64)),
+            schreier_fails: cl_options.schreier_fails,
+            invariant: cl_options.invariant,
+            invariant_level: cl_options.invariant_level,
+        },
+        encoding_options,
     };
 
-    Ok((graph, settings))
+    if settings.anytime && matches!(settings.metric, None | Some(MetricUsed::Standard)) {
+        return Err(Error::ConfigError(
+            "--anytime needs a metric that rates the quotients".to_string(),
+        ));
+    }
+
+    Ok((graph, settings, statistics))
+}
+
+#[cfg(test)]
+mod test {
+    use flate2::{write::GzEncoder, Compression as GzLevel};
+    use std::io::Cursor;
+    use xz2::write::XzEncoder;
+
+    use super::*;
+
+    #[test]
+    fn test_compressed_input() -> Result<(), Error> {
+        assert_eq!(
+            Some(Compression::Gzip),
+            Compression::of_path(Path::new("graph.txt.gz"))
+        );
+        assert_eq!(
+            Some(Compression::Xz),
+            Compression::of_path(Path::new("graph.txt.xz"))
+        );
+        assert_eq!(None, Compression::of_path(Path::new("graph.txt")));
+
+        let raw = b"0 1\n1 2\n";
+        let mut gzip = GzEncoder::new(Vec::new(), GzLevel::default());
+        gzip.write_all(raw)?;
+        let mut xz = XzEncoder::new(Vec::new(), 6);
+        xz.write_all(raw)?;
+
+        for (compression, data) in [
+            (Some(Compression::Gzip), gzip.finish()?),
+            (Some(Compression::Xz), xz.finish()?),
+            (None, raw.to_vec()),
+        ] {
+            let mut input = Compression::decompress(compression, Cursor::new(data));
+            let mut decompressed = String::new();
+            input.read_to_string(&mut decompressed)?;
+            assert_eq!("0 1\n1 2\n", decompressed);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_of_path() {
+        assert_eq!(
+            InputFormat::Txt,
+            format_of_path(Path::new("graph.txt")).unwrap()
+        );
+        assert_eq!(
+            InputFormat::EdgeList,
+            format_of_path(Path::new("dir.d/graph.edgelist")).unwrap()
+        );
+        // A compressed graph without a format, e.g. graph.gz.
+        for path in ["graph", "graph.png", "graph.dre.bak"] {
+            assert!(matches!(
+                format_of_path(Path::new(path)),
+                Err(Error::ConfigError(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,104 @@
+//! Interruption of a run by SIGINT or SIGTERM. The first signal stops the
+//! search after the current quotient, so that the partial statistics are
+//! still saved, a second one exits right away.
+//!
+//! Each search loop polls `check_interrupted`. External tools are killed
+//! while DQG waits for them, except for the DQG runs of an experiment,
+//! which receive the signals themselves to save their statistics as well.
+
+#[cfg(feature = "native")]
+use std::sync::atomic::AtomicU32;
+use std::{
+    process::{Child, ExitStatus},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crate::Error;
+#[cfg(feature = "native")]
+use crate::Outcome;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The child process that the signals are forwarded to, 0 for none.
+#[cfg(feature = "native")]
+static FORWARD_TO: AtomicU32 = AtomicU32::new(0);
+
+/// Longest pause between two checks whether a child process finished.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Install the signal handler. Only the CLI does this, library
+/// callers keep the default handling of the signals.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+pub fn install_interrupt_handler() {
+    let installed = ctrlc::set_handler(|| {
+        let forward_to = FORWARD_TO.load(Ordering::SeqCst);
+        if forward_to != 0 {
+            // Safety: kill only sends a signal.
+            unsafe { libc::kill(forward_to as libc::pid_t, libc::SIGTERM) };
+        }
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(Outcome::INTERRUPTED_EXIT_CODE);
+        }
+        eprintln!("Interrupted, stopping after the current quotient (interrupt again to exit)");
+    });
+    if let Err(error) = installed {
+        eprintln!("Couldn't install the interrupt handler: {}", error);
+    }
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails with `Error::Interrupted` once a signal was received.
+pub fn check_interrupted() -> Result<(), Error> {
+    if is_interrupted() {
+        Err(Error::Interrupted)
+    } else {
+        Ok(())
+    }
+}
+
+/// Wait for the child process like `Child::wait`, but kill it and fail with
+/// `Error::Interrupted` once a signal was received. The polling starts with
+/// short pauses, so that short runs, e.g. probes, aren't slowed down.
+#[cfg(not(tarpaulin_include))]
+pub fn wait_interruptible(child: &mut Child) -> Result<ExitStatus, Error> {
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if is_interrupted() {
+            // The child may have exited in the meantime.
+            let _ = child.kill();
+            child.wait()?;
+            return Err(Error::Interrupted);
+        }
+        thread::sleep(interval);
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+/// Forwards the signals to the child process until it is dropped.
+#[cfg(feature = "native")]
+pub struct ForwardGuard;
+
+#[cfg(feature = "native")]
+impl Drop for ForwardGuard {
+    fn drop(&mut self) {
+        FORWARD_TO.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Forward the signals to the child process, e.g. another DQG run that
+/// should stop after its current quotient as well. The child should be in
+/// its own process group, or it receives the signals of a terminal twice.
+#[cfg(feature = "native")]
+pub fn forward_interrupts(child: &Child) -> ForwardGuard {
+    FORWARD_TO.store(child.id(), Ordering::SeqCst);
+    ForwardGuard
+}
@@ -0,0 +1,866 @@
+#![warn(rust_2018_idioms)]
+//#![deny(warnings, missing_docs)]
+
+//! Project to find heuristics for
+//! descriptive quotients of graphs
+//! for certain conditions.
+//! Without the `native` feature, only the parsers, quotients and encodings
+//! are built, so large parts of the crate are unused.
+#![cfg_attr(not(feature = "native"), allow(dead_code, unused_imports))]
+
+use itertools::Itertools;
+use std::time::{Duration, Instant};
+
+mod combinatoric;
+use combinatoric::{PendingSubsets, Powerset, QueuedPowerset, QUEUE_BATCH};
+
+mod graph;
+#[cfg(feature = "native")]
+use graph::NautyGraph;
+pub use graph::{Colour, Graph, GraphError, GraphIssue, VertexIndex};
+
+#[cfg(feature = "native")]
+mod input;
+#[cfg(feature = "native")]
+pub use input::read_graph_file;
+#[cfg(feature = "native")]
+use input::{read_command_line, read_graph, Command};
+
+mod quotient;
+#[cfg(feature = "native")]
+use quotient::{compute_generators, cross_check_generators, sample_group, search_group};
+use quotient::{empty_orbits, generate_orbits, generate_orbits_of_subset, TrivialPartition};
+pub use quotient::{orbit_contiguous_labels, relabel_by_orbits, Orbits, QuotientGraph};
+
+mod encoding;
+use encoding::{
+    encode_problem, encode_problem_cached, EncodeOutcome, EncodingCache, HighLevelEncoding,
+};
+
+#[cfg_attr(feature = "native", allow(dead_code))]
+mod dpll;
+mod sat_solving;
+pub use sat_solving::ConflictGraph;
+use sat_solving::{solve, solve_conflict_graph, solve_probed, solve_validate, ProbeOutcome};
+
+mod parser;
+
+mod statistics;
+pub use statistics::{
+    FileStatistics, InMemoryStatistics, NoStatistics, Statistics, StatisticsLevel, StatisticsSink,
+};
+use statistics::{FormulaStatistics, OrbitStatistics, QuotientInvariants, QuotientStatistics};
+
+mod debug;
+pub use debug::Error;
+use debug::{orbits_nauty_style, print_generator_subset, print_orbits};
+
+#[cfg(feature = "native")]
+mod nauty_ffi;
+
+mod permutation;
+pub use permutation::Permutation;
+
+mod metric;
+
+mod metrics_report;
+use metrics_report::metrics_report;
+pub use metrics_report::ReportOutput;
+
+mod predictor;
+use predictor::{predict, skip_solving};
+pub use predictor::{Prediction, Predictor, QuotientFeatures, ThresholdPredictor};
+
+#[cfg(feature = "native")]
+mod session;
+#[cfg(feature = "native")]
+pub use session::Session;
+
+mod progress;
+pub use progress::{ProgressCallback, ProgressEvent};
+
+mod interrupt;
+#[cfg(feature = "native")]
+use interrupt::{check_interrupted, install_interrupt_handler};
+
+#[cfg(feature = "native")]
+mod generator_cache;
+
+#[cfg(feature = "native")]
+mod full_quotient;
+#[cfg(feature = "native")]
+pub use full_quotient::{is_full_quotient_descriptive, DescriptiveOutcome};
+
+mod transversal;
+use transversal::is_transversal_consistent;
+pub use transversal::{find_transversal, Transversal};
+
+mod misc;
+pub use misc::{
+    do_if_some, AutomorphismOptions, EncodingOptions, Invariant, MemorySize, MetricUsed,
+    NautyTraces, Outcome, ProbeOptions, Settings, TransversalEncoding,
+};
+
+mod external;
+
+mod evaluate;
+pub use evaluate::LogSchema;
+
+mod feedback;
+
+mod quotient_diff;
+use quotient_diff::diff_orbit_files;
+
+mod co_orbits;
+use co_orbits::write_co_orbit_file;
+pub use co_orbits::CoOrbitFrequencies;
+
+#[cfg(feature = "native")]
+mod experiment;
+#[cfg(feature = "native")]
+use experiment::run_experiment;
+
+#[cfg(feature = "native")]
+mod extract;
+#[cfg(feature = "native")]
+use extract::extract_orbits;
+
+mod lift;
+use lift::lift_files;
+pub use lift::{lift_property, parse_property, LiftFiles, LiftedVertex};
+pub use quotient_diff::{diff_orbits, QuotientDiff, Refinement};
+
+#[cfg(feature = "native")]
+mod gap;
+#[cfg(feature = "native")]
+use gap::{gap_mode, lattice_walk};
+
+#[cfg(feature = "native")]
+mod core;
+#[cfg(feature = "native")]
+use crate::core::{search_smallest_core, search_with_core};
+
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "capi")]
+mod capi;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+
+mod writer;
+#[cfg(feature = "native")]
+use writer::write_generators_file;
+use writer::{
+    write_conflict_graph_file, write_dot_file, write_dreadnaut_file, write_quotient_files,
+};
+
+/// Low-level SAT encodings of single orbits and quotient edges, to splice
+/// the descriptiveness constraints into formulas of other tools.
+pub mod sat_encoding {
+    pub use crate::encoding::{
+        encode_at_most_one, encode_at_most_one_binary, encode_at_most_one_ladder,
+        encode_at_most_one_pairwise, encode_edge_constraints, non_edge_positions, Clause, Formula,
+        LiteralAllocator, OrbitEncoding, SATEncoding, SATEncodingDictionary,
+    };
+}
+
+/// Evaluation of the logs of the quotientPlanning tool for the `dqg-eval` binary.
+pub mod evaluation {
+    pub use crate::evaluate::{
+        evaluate_log_file, evaluate_logs, expand_log_paths, write_log_csv, Log, LogSchema,
+    };
+    pub use crate::feedback::append_feedback;
+}
+
+/// Parsers for all untrusted input, exposed for the fuzz targets.
+pub mod parsers {
+    #[cfg(feature = "native")]
+    pub use crate::gap::parse_representatives;
+    #[cfg(feature = "native")]
+    pub use crate::generator_cache::parse_generator_cache;
+    pub use crate::parser::{
+        parse_cnf_input, parse_csv_input, parse_dreadnaut_input, parse_dreadnaut_inputs,
+        parse_edgelist_input, parse_txt_input,
+    };
+}
+
+/// Returns None if the quotient was deferred by the probe.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+fn compute_quotient_with_statistics(
+    generators: &mut [Permutation],
+    subset_indices: &[usize],
+    graph: &Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    encoding_cache: &mut EncodingCache,
+    probe: bool,
+) -> Result<Option<bool>, Error> {
+    let start_time = Instant::now();
+
+    time!(
+        orbit_gen_time,
+        orbits,
+        generate_orbits_of_subset(generators, subset_indices)
+    );
+
+    let mut orbit_sizes = OrbitStatistics::default();
+    if settings.log_orbits {
+        for orbit in orbits.encode_high() {
+            orbit_sizes.log_orbit(&orbit);
+        }
+    }
+
+    if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+        let subset_size = subset_indices.len();
+        let mut quotient_stats = QuotientStatistics::from_trivial_partition(
+            trivial_partition,
+            graph.size(),
+            Some(subset_indices.to_vec()),
+            subset_size,
+            start_time.elapsed(),
+            orbit_gen_time,
+        );
+        quotient_stats.orbit_sizes = orbit_sizes;
+        statistics.log(|stats| {
+            stats.log_quotient_statistic(quotient_stats);
+            stats.log_iteration()
+        });
+        if settings.output_found_orbits() {
+            print_orbits(orbits, settings, statistics);
+        }
+        return Ok(Some(true));
+    }
+
+    time!(
+        quotient_gen_time,
+        quotient_graph,
+        QuotientGraph::from_graph_orbits(graph, orbits)
+    );
+    let quotient_size = quotient_graph.quotient_graph.size();
+    let (min_orbit_size, max_orbit_size) = quotient_graph.get_orbit_sizes();
+    let invariants = QuotientInvariants::from_quotient_graph(&quotient_graph.quotient_graph);
+
+    let prediction = predict(&quotient_graph, settings);
+    if skip_solving(prediction, settings) {
+        statistics.log(|stats| {
+            stats.log_prediction(Prediction::NonDescriptive, None);
+            stats.log_iteration()
+        });
+        return Ok(Some(false));
+    }
+
+    time!(
+        encoding_time,
+        encoded,
+        encode_problem_cached(
+            &quotient_graph,
+            graph,
+            &settings.encoding_options,
+            encoding_cache,
+        )?
+    );
+
+    let mut descriptive = Ok(true);
+    let mut validated = None;
+    let mut kissat_time = Duration::ZERO;
+
+    let mut probe_outcome = None;
+    let mut formula_stats = None;
+    let trivial_encoding = encoded.trivial_reason();
+
+    let return_val = if let EncodeOutcome::Formula(formula, dict) = encoded {
+        let formula = formula.collect_vec();
+        formula_stats = Some(FormulaStatistics::from_formula(&formula));
+        let formula = formula.into_iter();
+        time!(k_time, descriptive_validated, {
+            if let Some(probe_options) = settings.probe.filter(|_| probe && !settings.validate) {
+                match solve_probed(formula, dict.variable_number(), &probe_options) {
+                    Ok(outcome) => {
+                        probe_outcome = Some(outcome);
+                        (Ok(outcome.decided().unwrap_or(false)), None)
+                    }
+                    Err(err) => (Err(err), None),
+                }
+            } else if settings.validate {
+                let sat_result = solve_validate(formula, dict);
+                match sat_result {
+                    Ok(transversal) => {
+                        if let Some(transversal) = transversal {
+                            (
+                                Ok(true),
+                                Some(is_transversal_consistent(
+                                    &transversal,
+                                    graph,
+                                    quotient_graph.encoding(),
+                                )),
+                            )
+                        } else {
+                            (Ok(false), None)
+                        }
+                    }
+                    Err(err) => (Err(err), None),
+                }
+            } else {
+                let descriptive = solve(formula);
+                (descriptive, None)
+            }
+        });
+        kissat_time = k_time;
+        descriptive = descriptive_validated.0;
+        validated = descriptive_validated.1;
+
+        matches!(descriptive, Ok(true))
+    } else {
+        // Trivially descriptive
+        true
+    };
+
+    if probe_outcome.is_some_and(|outcome| outcome.is_hard()) {
+        statistics.log(Statistics::log_hard_quotient);
+    }
+    if probe_outcome == Some(ProbeOutcome::Deferred) {
+        // Logged once the quotient is solved at the end.
+        return Ok(None);
+    }
+
+    if let (Some(prediction), Ok(descriptive)) = (prediction, &descriptive) {
+        let descriptive = *descriptive;
+        statistics.log(|stats| stats.log_prediction(prediction, Some(descriptive)));
+    }
+
+    let quotient_handling_time = start_time.elapsed();
+    let quotient_stats = QuotientStatistics {
+        subset_size: subset_indices.len(),
+        generator_subset: Some(subset_indices.to_vec()),
+        quotient_size,
+        core_size: None,
+        max_orbit_size,
+        min_orbit_size,
+        descriptive,
+        validated,
+        quotient_handling_time,
+        kissat_time,
+        orbit_gen_time,
+        quotient_gen_time,
+        encoding_time,
+        orbit_sizes,
+        invariants: Some(invariants),
+        trivial_partition: None,
+        trivial_encoding,
+        formula: formula_stats,
+    };
+    statistics.log(|stats| {
+        stats.log_quotient_statistic(quotient_stats);
+        stats.log_iteration()
+    });
+
+    if return_val && settings.output_found_orbits() {
+        print_orbits(quotient_graph.orbits, settings, statistics);
+    }
+
+    Ok(Some(return_val))
+}
+
+/// Returns None if the quotient was deferred by the probe.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+fn compute_quotient(
+    generators: &mut [Permutation],
+    subset_indices: &[usize],
+    graph: &Graph,
+    settings: &Settings,
+    encoding_cache: &mut EncodingCache,
+    probe: bool,
+) -> Result<Option<bool>, Error> {
+    let orbits = generate_orbits_of_subset(generators, subset_indices);
+
+    if TrivialPartition::detect(&orbits).is_some() {
+        if settings.output_found_orbits() {
+            print_orbits(orbits, settings, &mut NoStatistics);
+        }
+        return Ok(Some(true));
+    }
+
+    let quotient_graph = QuotientGraph::from_graph_orbits(graph, orbits);
+
+    if skip_solving(predict(&quotient_graph, settings), settings) {
+        return Ok(Some(false));
+    }
+
+    let formula = encode_problem_cached(
+        &quotient_graph,
+        graph,
+        &settings.encoding_options,
+        encoding_cache,
+    )?;
+
+    let descriptive = if let EncodeOutcome::Formula(formula, dict) = formula {
+        if let Some(probe_options) = settings.probe.filter(|_| probe && !settings.validate) {
+            match solve_probed(formula, dict.variable_number(), &probe_options)?.decided() {
+                Some(descriptive) => descriptive,
+                None => return Ok(None),
+            }
+        } else if settings.validate {
+            if let Some(transversal) = solve_validate(formula, dict)? {
+                assert!(is_transversal_consistent(
+                    &transversal,
+                    graph,
+                    quotient_graph.encoding()
+                ));
+                true
+            } else {
+                false
+            }
+        } else {
+            solve(formula)?
+        }
+    } else {
+        // Trivially descriptive
+        true
+    };
+
+    if descriptive && settings.output_found_orbits() {
+        print_orbits(quotient_graph.orbits, settings, &mut NoStatistics);
+    }
+
+    Ok(Some(descriptive))
+}
+
+/// Whether the search for descriptive quotients ran out of time.
+#[cfg(not(tarpaulin_include))]
+fn timed_out(start_time: Instant, timeout: Option<Duration>) -> bool {
+    timeout.is_some_and(|timeout| start_time.elapsed() >= timeout)
+}
+
+/// The best descriptive quotient by the metric found so far in anytime mode.
+#[cfg(feature = "native")]
+struct Incumbent {
+    subset: Vec<usize>,
+    quotient: QuotientGraph,
+}
+
+#[cfg(feature = "native")]
+impl Incumbent {
+    /// Replace the incumbent by the quotient of the subset if the metric rates it better.
+    fn update(
+        incumbent: &mut Option<Self>,
+        subset: &[usize],
+        generators: &mut [Permutation],
+        graph: &Graph,
+        metric: MetricUsed,
+    ) {
+        let orbits = generate_orbits_of_subset(generators, subset);
+        let quotient = QuotientGraph::from_graph_orbits(graph, orbits);
+        let better = match incumbent.as_ref() {
+            Some(best) => {
+                metric.compare_quotients(&quotient, &best.quotient) == std::cmp::Ordering::Less
+            }
+            None => true,
+        };
+        if better {
+            *incumbent = Some(Incumbent {
+                subset: subset.to_vec(),
+                quotient,
+            });
+        }
+    }
+}
+
+/// The subsets of generators that the powerset mode checks. With a metric that
+/// rates quotients, each batch of them is queued by the number of orbits to
+/// check the promising ones first, otherwise they are checked in the powerset order.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+fn pending_subsets(generators: &[Permutation], settings: &Settings) -> PendingSubsets {
+    let powerset = Powerset::bounded_by(generators.len(), settings);
+    if !settings.iter_powerset
+        || generators.is_empty()
+        || matches!(settings.metric, None | Some(MetricUsed::Standard))
+    {
+        return PendingSubsets::Powerset(powerset);
+    }
+
+    PendingSubsets::Queue(QueuedPowerset::new(
+        powerset,
+        generators.to_vec(),
+        QUEUE_BATCH,
+    ))
+}
+
+/// Runs DQG as configured by the command line arguments.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+pub fn run() -> Result<Outcome, Error> {
+    let mut cl_options = read_command_line();
+    // Subcommands that don't process a graph are dispatched right away.
+    match cl_options.command.take() {
+        Some(Command::Diff { left, right }) => {
+            println!("{}", diff_orbit_files(&left, &right)?);
+            return Ok(Outcome::Done);
+        }
+        Some(Command::Experiment { config }) => {
+            install_interrupt_handler();
+            return run_experiment(&config);
+        }
+        command => cl_options.command = command,
+    }
+
+    // Read the graphs from a file or via CLI and ...
+    let inputs = read_graph(cl_options)?;
+    install_interrupt_handler();
+
+    // ... process them one after another. The exit code is that of
+    // the first graph without a descriptive quotient, if any.
+    let batch = inputs.is_batch();
+    let mut outcome = Outcome::Done;
+    for (index, run) in inputs.into_runs().enumerate() {
+        check_interrupted()?;
+        let (graph, settings, statistics) = run?;
+        if batch {
+            println!("Graph {}:", index + 1);
+        }
+        let graph_outcome = run_with(graph, settings, statistics)?;
+        if outcome.exit_code() == 0 {
+            outcome = graph_outcome;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Runs DQG on the graph as configured by the settings, e.g. for a
+/// frontend that follows the progress through `Settings::progress`.
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+pub fn run_with(
+    graph: Graph,
+    settings: Settings,
+    mut statistics: Box<dyn StatisticsSink>,
+) -> Result<Outcome, Error> {
+    let progress = settings.progress.clone();
+    let report = |event| {
+        if let Some(progress) = progress.as_ref() {
+            progress.report(event);
+        }
+    };
+
+    report(ProgressEvent::ParsingDone {
+        vertices: graph.size(),
+    });
+    // Unsaved statistics of a failed run are saved when they are dropped.
+    let outcome = process_graph(graph, settings, statistics.as_mut())
+        .inspect_err(|error| statistics.log(|stats| stats.log_abort(error.to_string())))?;
+    report(ProgressEvent::Finished { outcome });
+    Ok(outcome)
+}
+
+#[cfg(feature = "native")]
+#[cfg(not(tarpaulin_include))]
+fn process_graph(
+    mut graph: Graph,
+    settings: Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<Outcome, Error> {
+    let start_time = Instant::now();
+
+    graph.individualize(&settings.fixed_vertices)?;
+
+    if let Some(files) = settings.lift.as_ref() {
+        return lift_files(&graph, &settings, files);
+    }
+
+    if let Some((orbits, path)) = settings.extract_orbits.as_ref() {
+        extract_orbits(&mut graph, &settings, statistics, orbits, path)?;
+        return Ok(Outcome::Done);
+    }
+
+    // Export the graph in dreadnaut syntax instead of searching for quotients.
+    if let Some(export_path) = settings.export_dreadnaut {
+        let traces_header = matches!(settings.nauyt_or_traces, NautyTraces::Traces);
+        write_dreadnaut_file(&export_path, &graph, traces_header)?;
+        return Ok(Outcome::Done);
+    }
+
+    // Search for a non descriptive core in a single non-descriptive quotient.
+    if settings.nondescriptive_core.is_some() {
+        return search_with_core(&mut graph, &settings, statistics);
+    }
+
+    if settings.smallest_core {
+        return search_smallest_core(&mut graph, &settings, statistics);
+    }
+
+    if let Some(samples) = settings.sample_group {
+        sample_group(&mut graph, &settings, statistics, samples)?;
+        return Ok(Outcome::Done);
+    }
+
+    if settings.search_group {
+        let nauty_graph = NautyGraph::from_graph(&mut graph);
+        assert!(nauty_graph.check_valid());
+
+        search_group(&mut graph, nauty_graph, &settings, statistics)?;
+        return Ok(Outcome::Done);
+    }
+
+    // ... compute the generators with nauty or Traces. Then ...
+    let mut generators = compute_generators(&mut graph, &settings, statistics);
+    check_interrupted()?;
+
+    statistics.log(Statistics::log_nauty_done);
+    statistics.log(|st| st.log_number_of_generators(generators.len()));
+    settings.report_progress(|| ProgressEvent::GeneratorsComputed {
+        n: generators.len(),
+    });
+
+    if settings.cross_check {
+        if let Some((nauty_orbits, traces_orbits)) = cross_check_generators(&graph, &settings) {
+            eprintln!(
+                "Warning: nauty and Traces generate different orbits.\nnauty: {}\nTraces: {}",
+                orbits_nauty_style(nauty_orbits),
+                orbits_nauty_style(traces_orbits)
+            );
+        }
+    }
+
+    // Export the generators for other tools instead of searching for quotients.
+    if let Some(export_path) = settings.export_generators.as_ref() {
+        write_generators_file(export_path, &generators)?;
+        return Ok(Outcome::Done);
+    }
+
+    // Sort the graph to allow easier lookup for edges.
+    // Without statistics, nothing is timed at all.
+    if statistics.is_collecting() {
+        time!(graph_sort_time, _t, {
+            graph.sort();
+            graph.choose_representation();
+        });
+        statistics.log(|stats| stats.log_graph_sorted(graph_sort_time));
+    } else {
+        graph.sort();
+        graph.choose_representation();
+    }
+
+    if settings.gap_mode {
+        // Without the conjugacy classes in time, search the subsets of generators instead.
+        if let Some(outcome) = gap_mode(&graph, generators.clone(), &settings, statistics)? {
+            return Ok(outcome);
+        }
+    }
+
+    if settings.lattice_walk {
+        return lattice_walk(&graph, generators, &settings, statistics);
+    }
+
+    if let Some(output) = settings.metrics_report.as_ref() {
+        return metrics_report(&graph, generators, &settings, output, start_time);
+    }
+
+    // Export the quotient induced by all generators instead of checking it.
+    if settings.export_quotient.is_some() || settings.export_dot.is_some() {
+        let orbits = if generators.is_empty() {
+            empty_orbits(graph.size())
+        } else {
+            generate_orbits(&mut generators)
+        };
+
+        if let Some(export_path) = settings.export_dot.as_ref() {
+            let orbits = orbits.encode_high();
+            write_dot_file(export_path, &graph, &orbits, settings.collapse_orbits)?;
+        }
+        if let Some(export_path) = settings.export_quotient.as_ref() {
+            let quotient_graph = QuotientGraph::from_graph_orbits(&graph, orbits);
+            write_quotient_files(export_path, &quotient_graph)?;
+        }
+        return Ok(Outcome::Done);
+    }
+
+    // Export the conflict graph of a core of the quotient induced by all generators.
+    if let Some(export_path) = settings.export_conflict_graph.as_ref() {
+        if generators.is_empty() {
+            return Ok(Outcome::Asymmetric);
+        }
+        let quotient_graph =
+            QuotientGraph::from_graph_orbits(&graph, generate_orbits(&mut generators));
+        let conflict_graph =
+            match encode_problem(&quotient_graph, &graph, &settings.encoding_options)? {
+                EncodeOutcome::Formula(formula, dict) => solve_conflict_graph(
+                    formula,
+                    &quotient_graph,
+                    &graph,
+                    dict,
+                    &settings.encoding_options,
+                )?,
+                EncodeOutcome::TriviallyDescriptive { .. } => None,
+            };
+
+        return if let Some(conflict_graph) = conflict_graph {
+            write_conflict_graph_file(export_path, &conflict_graph)?;
+            Ok(Outcome::NonDescriptiveCore)
+        } else {
+            println!("The quotient is descriptive, there is no conflict graph.");
+            Ok(Outcome::DescriptiveFound)
+        };
+    }
+
+    // Orbits repeat across subsets of generators, so parts of their encodings can be reused.
+    let mut encoding_cache = EncodingCache::default();
+    let timeout = settings.timeout;
+    let mut pending = pending_subsets(&generators, &settings);
+
+    // ... iterate over the specified subsets of generators...
+    // Conjugating a subset by an automorphism only relabels its orbits,
+    // so the induced quotient is isomorphic and conjugates are not checked.
+    let all_indices = (0..generators.len()).collect_vec();
+    let mut incumbent = None;
+    let mut co_orbits = settings
+        .export_co_orbits
+        .as_ref()
+        .map(|_| CoOrbitFrequencies::default());
+    let mut check_subset = |subset_indices: &[usize], probe: bool| -> Result<_, Error> {
+        let descriptive = if statistics.is_collecting() {
+            // ... with statistics ...
+            compute_quotient_with_statistics(
+                &mut generators,
+                subset_indices,
+                &graph,
+                &settings,
+                statistics,
+                &mut encoding_cache,
+                probe,
+            )?
+        } else {
+            // ... or without.
+            compute_quotient(
+                &mut generators,
+                subset_indices,
+                &graph,
+                &settings,
+                &mut encoding_cache,
+                probe,
+            )?
+        };
+        if descriptive == Some(true) && settings.output_found_orbits() {
+            print_generator_subset(&generators, subset_indices, &settings);
+        }
+        if let (Some(true), Some(metric)) =
+            (descriptive, settings.metric.filter(|_| settings.anytime))
+        {
+            Incumbent::update(
+                &mut incumbent,
+                subset_indices,
+                &mut generators,
+                &graph,
+                metric,
+            );
+        }
+        // Deferred subsets are only recorded once they are decided.
+        if let (Some(_), Some(co_orbits)) = (descriptive, co_orbits.as_mut()) {
+            co_orbits.record(&generate_orbits_of_subset(&mut generators, subset_indices));
+        }
+        if let Some(descriptive) = descriptive {
+            settings.report_progress(|| ProgressEvent::QuotientChecked {
+                descriptive,
+                subset: subset_indices.to_vec(),
+            });
+        }
+        Ok(descriptive)
+    };
+
+    let outcome = if all_indices.is_empty() {
+        Outcome::Asymmetric
+    } else if settings.iter_powerset {
+        let mut deferred = Vec::new();
+        let mut outcome = Outcome::ExhaustedWithoutDescriptive;
+
+        while let Some(subset_indices) = pending.next_subset() {
+            check_interrupted()?;
+            if timed_out(start_time, timeout) {
+                outcome = Outcome::Timeout;
+                break;
+            }
+
+            match check_subset(subset_indices, true)? {
+                Some(true) => {
+                    outcome = Outcome::DescriptiveFound;
+                    // In anytime mode, the search goes on for a better quotient.
+                    if !settings.anytime {
+                        break;
+                    }
+                }
+                Some(false) => (),
+                None => deferred.push(subset_indices.to_vec()),
+            }
+        }
+
+        // ... and finally the quotients the probe couldn't decide.
+        let searching = match outcome {
+            Outcome::ExhaustedWithoutDescriptive => true,
+            Outcome::DescriptiveFound => settings.anytime,
+            _ => false,
+        };
+        if searching {
+            for subset_indices in deferred {
+                check_interrupted()?;
+                if timed_out(start_time, timeout) {
+                    outcome = Outcome::Timeout;
+                    break;
+                }
+                if check_subset(&subset_indices, false)? == Some(true) {
+                    outcome = Outcome::DescriptiveFound;
+                    if !settings.anytime {
+                        break;
+                    }
+                }
+            }
+        }
+        outcome
+    } else if check_subset(&all_indices, false)? == Some(true) {
+        // A single quotient can't be deferred, so it isn't probed.
+        Outcome::DescriptiveFound
+    } else {
+        Outcome::ExhaustedWithoutDescriptive
+    };
+    let exhausted = outcome != Outcome::Timeout;
+
+    // The anytime mode outputs the best quotient so far, even after a timeout.
+    let outcome = if let Some(best) = incumbent {
+        if let Some(metric) = settings.metric {
+            eprintln!(
+                "Best descriptive quotient by {}: {}",
+                metric.name(),
+                metric.value(&best.quotient).unwrap_or_default()
+            );
+        }
+        if settings.output_orbits {
+            print_orbits(best.quotient.orbits, &settings, statistics);
+            print_generator_subset(&generators, &best.subset, &settings);
+        }
+        Outcome::DescriptiveFound
+    } else {
+        outcome
+    };
+
+    if let (Some(co_orbits), Some(export_path)) = (co_orbits, settings.export_co_orbits.as_ref()) {
+        let (pairs, stable) = co_orbits.summary();
+        eprintln!(
+            "{} vertex pairs share an orbit in any of the {} checked quotients, {} in all of them",
+            pairs,
+            co_orbits.quotients(),
+            stable
+        );
+        write_co_orbit_file(export_path, &co_orbits)?;
+    }
+
+    statistics.log(|stats| {
+        stats.exhausted = exhausted;
+        stats.log_encoding_cache_hits(encoding_cache.hits());
+        stats.log_end();
+    });
+    statistics.save()?;
+
+    Ok(outcome)
+}
@@ -0,0 +1,206 @@
+//! Lifting of properties of quotient vertices, e.g. the orbits a plan
+//! touches, back to the original vertices picked by a consistent transversal.
+//!
+//! The quotient is given by an orbit membership csv file as written by
+//! `--export-quotient`, so that the quotient vertices have the same ids
+//! as in the exported quotient graph. The property is a list of quotient
+//! vertices, one per line, each optionally followed by a comma and a value.
+//! The result is a csv file with the quotient vertex, the original vertex
+//! and the value of each listed quotient vertex.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    graph::{Graph, VertexIndex},
+    parser::parse_orbits_input,
+    quotient::Orbits,
+    quotient_diff::orbits_from_membership,
+    transversal::{find_transversal, Transversal},
+    Error, Outcome, Settings,
+};
+
+/// The files of the `lift` command.
+#[derive(Debug, Clone)]
+pub struct LiftFiles {
+    /// Orbit membership csv file of the quotient.
+    pub orbits: PathBuf,
+    /// Quotient vertices with optional values.
+    pub property: PathBuf,
+    /// Where to write the lifted property.
+    pub output: PathBuf,
+}
+
+/// A quotient vertex and the original vertex it stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiftedVertex<T> {
+    pub quotient_vertex: VertexIndex,
+    pub vertex: VertexIndex,
+    pub value: T,
+}
+
+/// The orbits of the graph given by the membership pairs together
+/// with the orbit of each quotient vertex id in the membership.
+pub fn quotient_orbits(
+    graph_size: usize,
+    membership: &[(VertexIndex, VertexIndex)],
+) -> Result<(Orbits, HashMap<VertexIndex, VertexIndex>), Error> {
+    let mut orbits = orbits_from_membership(membership);
+    if orbits.len() > graph_size {
+        return Err(Error::ConfigError(format!(
+            "Orbit membership lists vertex {} of a graph with {} vertices",
+            orbits.len() - 1,
+            graph_size
+        )));
+    }
+    // Unlisted vertices form their own orbits.
+    orbits.extend(orbits.len() as VertexIndex..graph_size as VertexIndex);
+
+    let quotient_vertices = membership
+        .iter()
+        .map(|(vertex, quotient_vertex)| (*quotient_vertex, orbits[*vertex as usize]))
+        .collect();
+    Ok((orbits, quotient_vertices))
+}
+
+/// Lift the property to the vertices that the transversal picks from the orbits
+/// of the quotient vertices. `quotient_vertices` maps the quotient vertices to
+/// their orbits, which are named like in the transversal.
+pub fn lift_property<T>(
+    transversal: &Transversal,
+    quotient_vertices: &HashMap<VertexIndex, VertexIndex>,
+    property: impl IntoIterator<Item = (VertexIndex, T)>,
+) -> Result<Vec<LiftedVertex<T>>, Error> {
+    property
+        .into_iter()
+        .map(|(quotient_vertex, value)| {
+            let vertex = quotient_vertices
+                .get(&quotient_vertex)
+                .and_then(|orbit| {
+                    transversal
+                        .binary_search_by_key(orbit, |(orbit, _)| *orbit)
+                        .ok()
+                })
+                .map(|position| transversal[position].1)
+                .ok_or_else(|| {
+                    Error::ConfigError(format!("No quotient vertex {} to lift", quotient_vertex))
+                })?;
+            Ok(LiftedVertex {
+                quotient_vertex,
+                vertex,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Parse the `quotient vertex[,value]` lines of a property file.
+pub fn parse_property(input: &str) -> Result<Vec<(VertexIndex, String)>, Error> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (quotient_vertex, value) = line.split_once(',').unwrap_or((line, ""));
+            let quotient_vertex = quotient_vertex.trim().parse().map_err(|_| {
+                Error::ConfigError(format!("Invalid quotient vertex in line {}", line))
+            })?;
+            Ok((quotient_vertex, value.trim().to_string()))
+        })
+        .collect()
+}
+
+fn write_lifted(writer: &mut impl Write, lifted: &[LiftedVertex<String>]) -> Result<(), Error> {
+    writeln!(writer, "quotient_vertex,vertex,value")?;
+    for row in lifted {
+        writeln!(
+            writer,
+            "{},{},{}",
+            row.quotient_vertex, row.vertex, row.value
+        )?;
+    }
+    Ok(())
+}
+
+/// Lift the property through a consistent transversal of the quotient and
+/// write it to the output file. The original vertices are named by their
+/// ids in the input. A non-descriptive quotient has no such transversal.
+#[cfg(not(tarpaulin_include))]
+pub fn lift_files(graph: &Graph, settings: &Settings, files: &LiftFiles) -> Result<Outcome, Error> {
+    let membership = parse_orbits_input(BufReader::new(File::open(&files.orbits)?))?;
+    let (orbits, quotient_vertices) = quotient_orbits(graph.size(), &membership)?;
+    let property = parse_property(&fs::read_to_string(&files.property)?)?;
+
+    let Some(transversal) = find_transversal(graph, orbits, &settings.encoding_options)? else {
+        eprintln!("The quotient isn't descriptive, so it has no consistent transversal");
+        return Ok(Outcome::ExhaustedWithoutDescriptive);
+    };
+    let mut lifted = lift_property(&transversal, &quotient_vertices, property)?;
+    if let Some(vertex_ids) = settings.vertex_ids.as_ref() {
+        for row in lifted.iter_mut() {
+            row.vertex = vertex_ids[row.vertex as usize];
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(&files.output)?);
+    write_lifted(&mut writer, &lifted)?;
+    writer.flush()?;
+    Ok(Outcome::Done)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quotient_orbits() -> Result<(), Error> {
+        // Quotient vertex 0 is {1, 3} and 1 is {0, 2}, vertex 4 isn't listed.
+        let membership = vec![(0, 1), (1, 0), (2, 1), (3, 0)];
+        let (orbits, quotient_vertices) = quotient_orbits(5, &membership)?;
+        assert_eq!(vec![0, 1, 0, 1, 4], orbits);
+        assert_eq!(HashMap::from([(0, 1), (1, 0)]), quotient_vertices);
+
+        assert!(quotient_orbits(3, &membership).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_property() -> Result<(), Error> {
+        let property = parse_property("1,first\n\n0\n")?;
+        assert_eq!(vec![(1, "first".to_string()), (0, String::new())], property);
+        assert!(parse_property("a,first").is_err());
+
+        let transversal = vec![(0, 2), (1, 3), (4, 4)];
+        let quotient_vertices = HashMap::from([(0, 1), (1, 0)]);
+        let lifted = lift_property(&transversal, &quotient_vertices, property)?;
+        assert_eq!(
+            vec![
+                LiftedVertex {
+                    quotient_vertex: 1,
+                    vertex: 2,
+                    value: "first".to_string()
+                },
+                LiftedVertex {
+                    quotient_vertex: 0,
+                    vertex: 3,
+                    value: String::new()
+                },
+            ],
+            lifted
+        );
+
+        let mut written = Vec::new();
+        write_lifted(&mut written, &lifted)?;
+        assert_eq!(
+            "quotient_vertex,vertex,value\n1,2,first\n0,3,\n",
+            String::from_utf8(written).unwrap()
+        );
+
+        assert!(lift_property(&transversal, &quotient_vertices, [(2, ())]).is_err());
+        Ok(())
+    }
+}
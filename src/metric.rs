@@ -2,6 +2,8 @@ use crate::quotient::QuotientGraph;
 
 pub trait Metric {
     fn compare_quotients(left: &QuotientGraph, right: &QuotientGraph) -> std::cmp::Ordering;
+    /// The raw value that the quotients are compared by.
+    fn value(quotient: &QuotientGraph) -> f64;
 }
 
 /// The quotient with the least number of orbits
@@ -13,6 +15,10 @@ impl Metric for LeastOrbits {
     fn compare_quotients(left: &QuotientGraph, right: &QuotientGraph) -> std::cmp::Ordering {
         left.quotient_graph.size().cmp(&right.quotient_graph.size())
     }
+
+    fn value(quotient: &QuotientGraph) -> f64 {
+        quotient.quotient_graph.size() as f64
+    }
 }
 
 /// The quotient with the biggest maximum orbit size
@@ -26,6 +32,10 @@ impl Metric for BiggestOrbits {
         let right_biggest = right.get_orbit_sizes().1;
         left_biggest.cmp(&right_biggest).reverse()
     }
+
+    fn value(quotient: &QuotientGraph) -> f64 {
+        quotient.get_orbit_sizes().1 as f64
+    }
 }
 
 /// The sparsest quotient
@@ -35,12 +45,14 @@ pub struct Sparsity;
 impl Metric for Sparsity {
     #[cfg(not(tarpaulin_include))]
     fn compare_quotients(left: &QuotientGraph, right: &QuotientGraph) -> std::cmp::Ordering {
-        let left_sparsity_coefficient =
-            left.quotient_graph.number_edges() as f64 / left.quotient_graph.size() as f64;
-        let right_sparsity_coefficient =
-            right.quotient_graph.number_edges() as f64 / right.quotient_graph.size() as f64;
+        let left_sparsity_coefficient = Self::value(left);
+        let right_sparsity_coefficient = Self::value(right);
         left_sparsity_coefficient
             .partial_cmp(&right_sparsity_coefficient)
             .expect("Sparsity coefficients should be comparable")
     }
+
+    fn value(quotient: &QuotientGraph) -> f64 {
+        quotient.quotient_graph.number_edges() as f64 / quotient.quotient_graph.size() as f64
+    }
 }
@@ -0,0 +1,183 @@
+//! Report of all metric values for the evaluated quotients,
+//! e.g. to learn better heuristics offline.
+
+use itertools::Itertools;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    iter::{from_fn, once},
+    path::PathBuf,
+    time::Instant,
+};
+
+use crate::{
+    combinatoric::Powerset,
+    encoding::{encode_problem, EncodeOutcome},
+    graph::Graph,
+    permutation::Permutation,
+    quotient::{generate_orbits, QuotientGraph, TrivialPartition},
+    sat_solving::solve,
+    timed_out, Error, MetricUsed, Outcome, Settings,
+};
+
+/// Where the report is written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportOutput {
+    File(PathBuf),
+    Stdout,
+}
+
+/// Metric values and descriptiveness of the quotient induced by a subset of generators.
+#[derive(Debug, PartialEq)]
+struct ReportRow {
+    /// Indices of the generators in the order computed by nauty/Traces.
+    subset: Vec<usize>,
+    values: Vec<Option<f64>>,
+    descriptive: bool,
+}
+
+impl ReportRow {
+    #[cfg(not(tarpaulin_include))]
+    fn evaluate(
+        subset: Vec<usize>,
+        generators: &mut [Permutation],
+        graph: &Graph,
+        settings: &Settings,
+    ) -> Result<Self, Error> {
+        let orbits = generate_orbits(generators);
+        let trivially_descriptive = TrivialPartition::detect(&orbits).is_some();
+        let quotient = QuotientGraph::from_graph_orbits(graph, orbits);
+
+        let values = MetricUsed::WITH_VALUES
+            .iter()
+            .map(|metric| metric.value(&quotient))
+            .collect();
+        let descriptive = if trivially_descriptive {
+            true
+        } else if let EncodeOutcome::Formula(formula, _) =
+            encode_problem(&quotient, graph, &settings.encoding_options)?
+        {
+            solve(formula)?
+        } else {
+            true
+        };
+
+        Ok(ReportRow {
+            subset,
+            values,
+            descriptive,
+        })
+    }
+}
+
+/// Write the rows as csv with one column per metric.
+/// The generator indices of a subset are separated by spaces.
+fn write_report(writer: &mut impl Write, rows: &[ReportRow]) -> Result<(), Error> {
+    let metric_names = MetricUsed::WITH_VALUES
+        .iter()
+        .map(MetricUsed::name)
+        .join(",");
+    writeln!(writer, "subset,{},descriptive", metric_names)?;
+
+    for row in rows {
+        let values = row
+            .values
+            .iter()
+            .map(|value| value.map(|value| value.to_string()).unwrap_or_default())
+            .join(",");
+        writeln!(
+            writer,
+            "{},{},{}",
+            row.subset.iter().join(" "),
+            values,
+            row.descriptive
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Evaluate all metrics for the quotient of all generators or, if the powerset
+/// is iterated, for the quotients of all subsets and write them as csv.
+/// Unlike the normal search, this doesn't stop at the first descriptive quotient.
+#[cfg(not(tarpaulin_include))]
+pub fn metrics_report(
+    graph: &Graph,
+    generators: Vec<Permutation>,
+    settings: &Settings,
+    output: &ReportOutput,
+    start_time: Instant,
+) -> Result<Outcome, Error> {
+    if generators.is_empty() {
+        return Ok(Outcome::Asymmetric);
+    }
+
+    let subsets: Box<dyn Iterator<Item = Vec<usize>>> = if settings.iter_powerset {
+        let mut powerset = Powerset::bounded_by(generators.len(), settings);
+        Box::new(from_fn(move || {
+            powerset.next_subset().map(<[usize]>::to_vec)
+        }))
+    } else {
+        Box::new(once((0..generators.len()).collect_vec()))
+    };
+
+    let mut rows = Vec::new();
+    let mut outcome = Outcome::ExhaustedWithoutDescriptive;
+    for subset in subsets {
+        if timed_out(start_time, settings.timeout) {
+            outcome = Outcome::Timeout;
+            break;
+        }
+
+        let mut subset_generators = subset
+            .iter()
+            .map(|index| generators[*index].clone())
+            .collect_vec();
+        let row = ReportRow::evaluate(subset, &mut subset_generators, graph, settings)?;
+        if row.descriptive {
+            outcome = Outcome::DescriptiveFound;
+        }
+        rows.push(row);
+    }
+
+    match output {
+        ReportOutput::File(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            write_report(&mut writer, &rows)?;
+            writer.flush()?;
+        }
+        ReportOutput::Stdout => write_report(&mut io::stdout().lock(), &rows)?,
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_report() -> Result<(), Error> {
+        let rows = vec![
+            ReportRow {
+                subset: vec![0],
+                values: vec![Some(3.), Some(2.), Some(0.5)],
+                descriptive: true,
+            },
+            ReportRow {
+                subset: vec![0, 2],
+                values: vec![Some(2.), None, Some(1.)],
+                descriptive: false,
+            },
+        ];
+
+        let mut output = Vec::new();
+        write_report(&mut output, &rows)?;
+        assert_eq!(
+            "subset,least_orbits,biggest_orbit,sparsity,descriptive\n0,3,2,0.5,true\n0 2,2,,1,false\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+}
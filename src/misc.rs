@@ -1,10 +1,16 @@
-use std::{fs::File, io::BufReader, str::FromStr};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
 
 use crate::debug::MetricError;
-use crate::statistics::Statistics;
+use crate::lift::LiftFiles;
+use crate::metrics_report::ReportOutput;
+use crate::predictor::Predictor;
+use crate::progress::{ProgressCallback, ProgressEvent};
 use crate::{
+    graph::{Colour, VertexIndex},
     metric::{BiggestOrbits, LeastOrbits, Metric, Sparsity},
     quotient::QuotientGraph,
+    Error,
 };
 
 #[cfg(not(tarpaulin_include))]
@@ -18,7 +24,39 @@ where
     }
 }
 
-#[derive(Debug)]
+/// Outcome of a run that is reported as the exit code of the process
+/// so that scripts don't have to scrape the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The requested task doesn't decide descriptiveness (e.g. exports).
+    Done,
+    DescriptiveFound,
+    ExhaustedWithoutDescriptive,
+    /// The core search stopped with a non-descriptive core left.
+    NonDescriptiveCore,
+    /// The graph has no non-trivial automorphisms.
+    Asymmetric,
+    Timeout,
+}
+
+impl Outcome {
+    pub const PARSE_ERROR_EXIT_CODE: i32 = 2;
+    pub const OTHER_ERROR_EXIT_CODE: i32 = 1;
+    /// Like shells report processes killed by SIGINT.
+    pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Done | Self::DescriptiveFound => 0,
+            Self::ExhaustedWithoutDescriptive => 3,
+            Self::NonDescriptiveCore => 4,
+            Self::Asymmetric => 5,
+            Self::Timeout => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NautyTraces {
     /// Calls dense nauty
     Nauty,
@@ -34,6 +72,185 @@ impl Default for NautyTraces {
     }
 }
 
+/// Vertex invariants that nauty can use to refine the partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invariant {
+    Adjacencies,
+    Distances,
+}
+
+impl FromStr for Invariant {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "adjacencies" => Ok(Self::Adjacencies),
+            "distances" => Ok(Self::Distances),
+            _ => Err(Error::ConfigError(format!(
+                "Unknown invariant {} (expected one of adjacencies, distances)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options for the randomized parts and the refinement of nauty/Traces.
+/// These are logged into the statistics to make experiments repeatable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutomorphismOptions {
+    /// Seed for the random number generator of nauty/Traces.
+    pub random_seed: Option<i64>,
+    /// Number of failed random Schreier tests after which
+    /// nauty assumes the group to be complete. Not used by Traces.
+    pub schreier_fails: Option<i32>,
+    /// Vertex invariant used by nauty. Traces doesn't support invariants.
+    pub invariant: Option<Invariant>,
+    /// Maximal search tree level at which the invariant is applied.
+    pub invariant_level: i32,
+}
+
+/// Conflict limited probe that is run before the full SAT call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeOptions {
+    /// Number of conflicts after which the probe gives up.
+    pub conflicts: u64,
+    /// Defer quotients that the probe couldn't decide to the end of the
+    /// iteration instead of solving them right away with the full budget.
+    pub defer_hard: bool,
+    /// Limit of the address space of the kissat binary.
+    pub memory_limit: Option<MemorySize>,
+}
+
+/// Amount of memory for an external tool, e.g. `16G`, given in bytes
+/// or with the binary suffixes `K`, `M` and `G` (in either case).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemorySize(u64);
+
+impl MemorySize {
+    const UNITS: [(u64, char); 3] = [(1 << 30, 'G'), (1 << 20, 'M'), (1 << 10, 'K')];
+
+    pub const fn gibibytes(gibibytes: u64) -> Self {
+        MemorySize(gibibytes << 30)
+    }
+
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for MemorySize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid memory size {}, expected e.g. 512M or 16G", s);
+        let (number, factor) = match s.chars().last().map(|unit| unit.to_ascii_uppercase()) {
+            Some(unit) if unit.is_ascii_alphabetic() => {
+                let (factor, _) = Self::UNITS
+                    .iter()
+                    .find(|(_, suffix)| *suffix == unit)
+                    .ok_or_else(invalid)?;
+                (&s[..s.len() - 1], *factor)
+            }
+            _ => (s, 1),
+        };
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(factor))
+            .filter(|bytes| *bytes > 0)
+            .map(MemorySize)
+            .ok_or_else(invalid)
+    }
+}
+
+/// The largest unit that divides the size, as understood by GAP's `-o`.
+impl fmt::Display for MemorySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The factors are powers of two.
+        match Self::UNITS
+            .iter()
+            .find(|(factor, _)| self.0 & (factor - 1) == 0)
+        {
+            Some((factor, suffix)) => write!(f, "{}{}", self.0 / factor, suffix),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// How the transversal encoding restricts the picked vertices of an orbit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransversalEncoding {
+    /// Only require at least one vertex per orbit. Picking more
+    /// vertices only adds constraints, so this is sufficient.
+    #[default]
+    AtLeastOne,
+    /// Additionally forbid picking two vertices of the same orbit pairwise.
+    Pairwise,
+    /// Additionally forbid picking two vertices of the same orbit, pairwise
+    /// for small orbits and with the ladder or binary encoding for larger
+    /// ones, depending on the thresholds of the encoding options.
+    AtMostOne,
+}
+
+impl FromStr for TransversalEncoding {
+    type Err = MetricError;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "at_least_one" => Ok(Self::AtLeastOne),
+            "pairwise" => Ok(Self::Pairwise),
+            "at_most_one" => Ok(Self::AtMostOne),
+            _ => Err(MetricError(s.to_string())),
+        }
+    }
+}
+
+/// Options for the SAT encoding of the descriptiveness problem.
+/// The default reproduces the original encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingOptions {
+    /// Encoding of the transversal constraint for each orbit.
+    pub transversal: TransversalEncoding,
+    /// Encode the constraints of a quotient edge for both directions.
+    /// Undirected edges otherwise only yield the clauses of one direction,
+    /// as the other direction produces the same clauses mirrored. Both
+    /// directions are always encoded for graphs declared as digraphs.
+    pub mirrored_edges: bool,
+    /// Shorten the constraints of edges to singleton orbits to unit clauses,
+    /// as their only vertex is always picked.
+    pub propagate_singletons: bool,
+    /// Exclude vertices without any neighbour in the other orbit of a
+    /// quotient edge with a single unit clause instead of one binary
+    /// clause per vertex of the other orbit.
+    pub degree_implied: bool,
+    /// Annotate CNF files written for external tools with comments.
+    pub cnf_comments: bool,
+    /// Limit of the address space of kitten, which extracts the cores.
+    pub kitten_memory: Option<MemorySize>,
+    /// Orbits with at least this many vertices use the ladder encoding
+    /// for at most one picked vertex instead of the pairwise one.
+    pub ladder_threshold: usize,
+    /// Orbits with at least this many vertices use the binary encoding
+    /// for at most one picked vertex instead of the ladder one.
+    pub binary_threshold: usize,
+}
+
+impl Default for EncodingOptions {
+    fn default() -> Self {
+        Self {
+            transversal: TransversalEncoding::default(),
+            mirrored_edges: true,
+            propagate_singletons: false,
+            degree_implied: false,
+            cnf_comments: false,
+            kitten_memory: None,
+            ladder_threshold: 8,
+            binary_threshold: 64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MetricUsed {
     LeastOrbits,
@@ -43,6 +260,13 @@ pub enum MetricUsed {
 }
 
 impl MetricUsed {
+    /// All metrics that rate quotients by a value.
+    pub const WITH_VALUES: [MetricUsed; 3] = [
+        MetricUsed::LeastOrbits,
+        MetricUsed::BiggestOrbits,
+        MetricUsed::Sparsity,
+    ];
+
     #[cfg(not(tarpaulin_include))]
     pub fn compare_quotients(
         &self,
@@ -58,24 +282,60 @@ impl MetricUsed {
             Self::Standard => Ordering::Less,
         }
     }
+
+    pub const ALL: [MetricUsed; 4] = [
+        MetricUsed::LeastOrbits,
+        MetricUsed::BiggestOrbits,
+        MetricUsed::Sparsity,
+        MetricUsed::Standard,
+    ];
+
+    /// The names of all metrics, e.g. for the possible values on the command line.
+    pub const NAMES: [&'static str; 4] = [
+        Self::ALL[0].name(),
+        Self::ALL[1].name(),
+        Self::ALL[2].name(),
+        Self::ALL[3].name(),
+    ];
+
+    /// Name of the metric as accepted on the command line. The
+    /// quotientPlanning tool logs the metrics by the same names.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::LeastOrbits => "least_orbits",
+            Self::BiggestOrbits => "biggest_orbit",
+            Self::Sparsity => "sparsity",
+            Self::Standard => "standard",
+        }
+    }
+
+    /// Value of the quotient that this metric compares quotients by.
+    /// None for the standard metric, which doesn't rate quotients.
+    pub fn value(&self, quotient: &QuotientGraph) -> Option<f64> {
+        match self {
+            Self::LeastOrbits => Some(LeastOrbits::value(quotient)),
+            Self::BiggestOrbits => Some(BiggestOrbits::value(quotient)),
+            Self::Sparsity => Some(Sparsity::value(quotient)),
+            Self::Standard => None,
+        }
+    }
 }
 
 impl FromStr for MetricUsed {
     type Err = MetricError;
 
-    #[cfg(not(tarpaulin_include))]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "least_orbits" {
-            Ok(Self::LeastOrbits)
-        } else if s == "biggest_orbit" {
-            Ok(Self::BiggestOrbits)
-        } else if s == "sparsity" {
-            Ok(Self::Sparsity)
-        } else if s == "standard" {
-            Ok(Self::Standard)
-        } else {
-            Err(MetricError(s.to_string()))
-        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|metric| metric.name() == s)
+            .ok_or_else(|| {
+                MetricError(format!(
+                    "{} (expected one of {})",
+                    s,
+                    Self::NAMES.join(", ")
+                ))
+            })
     }
 }
 
@@ -86,24 +346,59 @@ impl Default for MetricUsed {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CoreMetric {
     Recolor,
     PowerGenerators,
     MergeGenerators,
+    /// Interleave the other strategies in time slices
+    /// and adopt the first one that makes progress.
+    Auto,
+}
+
+impl CoreMetric {
+    pub const ALL: [CoreMetric; 4] = [
+        CoreMetric::Recolor,
+        CoreMetric::PowerGenerators,
+        CoreMetric::MergeGenerators,
+        CoreMetric::Auto,
+    ];
+
+    /// The names of all core metrics, e.g. for the possible values on the command line.
+    pub const NAMES: [&'static str; 4] = [
+        Self::ALL[0].name(),
+        Self::ALL[1].name(),
+        Self::ALL[2].name(),
+        Self::ALL[3].name(),
+    ];
+
+    /// Name of the core metric as accepted on the command line
+    /// and as strategy of experiments.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Recolor => "recolor",
+            Self::PowerGenerators => "power_generators",
+            Self::MergeGenerators => "merge_generators",
+            Self::Auto => "auto",
+        }
+    }
 }
 
 impl FromStr for CoreMetric {
     type Err = MetricError;
 
-    #[cfg(not(tarpaulin_include))]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "recolor" => Ok(Self::Recolor),
-            "pow_gen" => Ok(Self::PowerGenerators),
-            "merge_gen" => Ok(Self::MergeGenerators),
-            _ => Err(MetricError(s.to_string())),
-        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|metric| metric.name() == s)
+            .ok_or_else(|| {
+                MetricError(format!(
+                    "{} (expected one of {})",
+                    s,
+                    Self::NAMES.join(", ")
+                ))
+            })
     }
 }
 
@@ -111,8 +406,17 @@ impl FromStr for CoreMetric {
 pub struct Settings {
     /// Iterate the whole powerset.
     pub iter_powerset: bool,
+    /// Size bounds of the subsets in the powerset iteration.
+    pub min_subset_size: usize,
+    pub max_subset_size: Option<usize>,
     /// Outputs orbits in dreadnaut format.
     pub output_orbits: bool,
+    /// Output orbits exactly like the `o` command
+    /// of dreadnaut instead of the nauty summary.
+    pub strict_orbits: bool,
+    /// Colours of the input vertices, indexed by the vertex,
+    /// if the orbits are printed grouped by their colour.
+    pub orbit_colours: Option<Vec<Colour>>,
     /// Log orbit sizes.
     pub log_orbits: bool,
     /// Print formula instead of solving it.
@@ -120,12 +424,34 @@ pub struct Settings {
     /// Graph is colored and colors should be
     /// included in the nauty computation.
     pub colored_graph: bool,
+    /// Vertices that are individualized before
+    /// the automorphisms are computed.
+    pub fixed_vertices: Vec<VertexIndex>,
+    /// Original ids of the vertices of an input with sparse ids,
+    /// indexed by the vertex. Orbits are printed with these ids,
+    /// except in the strict dreadnaut style.
+    pub vertex_ids: Option<Vec<VertexIndex>>,
     /// Search for the smallest non-descriptive quotient
     /// core in the first non-descriptive quotient graph.
     pub nondescriptive_core: Option<CoreMetric>,
+    /// Attack up to this many distinct cores of each
+    /// non-descriptive quotient at once in core mode.
+    pub cores_per_quotient: usize,
+    /// Search the smallest non-descriptive core of the quotient
+    /// induced by all generators among the subsets of its orbits.
+    pub smallest_core: bool,
+    /// Size bounds of the subsets of orbits checked for cores.
+    pub min_core_size: usize,
+    pub max_core_size: Option<usize>,
     /// Search in the whole automorphism group instead
     /// of a set of generators.
     pub search_group: bool,
+    /// Sample this many random group elements instead
+    /// of enumerating the whole automorphism group.
+    pub sample_group: Option<usize>,
+    /// Compute the generators with both nauty and Traces
+    /// and warn if they generate different orbits.
+    pub cross_check: bool,
     /// Validate each descriptiveness result
     /// with exhaustive search for consistent
     /// transversals.
@@ -134,20 +460,150 @@ pub struct Settings {
     /// This means that DQG use GAP to
     /// search in the conjugacy classes.
     pub gap_mode: bool,
+    /// Kill GAP after this duration and search
+    /// the subsets of generators instead.
+    pub gap_deadline: Option<Duration>,
+    /// Check the conjugacy classes on this many threads
+    /// instead of one per available core.
+    pub gap_workers: Option<usize>,
+    /// Memory limit of GAP instead of the default of each GAP mode.
+    pub gap_memory: Option<MemorySize>,
+    /// Walk down the subgroup lattice (computed by GAP)
+    /// until a descriptive subgroup is found.
+    pub lattice_walk: bool,
     /// Use the given metric to find the "best" quotient
-    /// and use it as described by the other flags.
+    /// and use it as described by the other flags. With `iter_powerset`,
+    /// the subsets with the fewest orbits are checked first.
     pub metric: Option<MetricUsed>,
-    /// Evaluate a log file as printed by
-    /// the quotientPlanning tool.
-    pub evaluate: Option<BufReader<File>>,
+    /// Write the subgraph induced by the orbits of these
+    /// vertices to this file instead of searching.
+    pub extract_orbits: Option<(Vec<VertexIndex>, PathBuf)>,
+    /// Lift a property of the quotient vertices to the original
+    /// vertices instead of searching.
+    pub lift: Option<LiftFiles>,
+    /// Stop the search for descriptive quotients
+    /// after this duration.
+    pub timeout: Option<Duration>,
+    /// Keep searching after the first descriptive quotient
+    /// and output the best one by the metric at the end.
+    pub anytime: bool,
+    /// Write the graph in dreadnaut syntax
+    /// to this file instead of processing it.
+    pub export_dreadnaut: Option<PathBuf>,
+    /// Write the quotient graph induced by all generators
+    /// and its orbit membership to this file.
+    pub export_quotient: Option<PathBuf>,
+    /// Write the graph with the vertices coloured
+    /// by their orbits as dot file.
+    pub export_dot: Option<PathBuf>,
+    /// Write the conflict graph of a non-descriptive core
+    /// of the quotient induced by all generators to this file.
+    pub export_conflict_graph: Option<PathBuf>,
+    /// Write the generators of the automorphism group
+    /// to this file instead of searching for quotients.
+    pub export_generators: Option<PathBuf>,
+    /// Write how often each pair of vertices shares an orbit
+    /// in the quotients of the checked subsets as csv.
+    pub export_co_orbits: Option<PathBuf>,
+    /// Write the values of all metrics and the descriptiveness
+    /// of each evaluated quotient as csv.
+    pub metrics_report: Option<ReportOutput>,
+    /// Draw each orbit as a single vertex in the dot file.
+    pub collapse_orbits: bool,
+    /// Read the generators from a cache file next to this input
+    /// file if there is a valid one, otherwise write it.
+    pub generator_cache: Option<PathBuf>,
     ///  Call nauty or traces.
     pub nauyt_or_traces: NautyTraces,
-    /// Statistics object if used
-    pub statistics: Option<Statistics>,
+    /// Random and invariant options for nauty/Traces.
+    pub automorphism_options: AutomorphismOptions,
+    /// Options for the SAT encoding of each quotient.
+    pub encoding_options: EncodingOptions,
+    /// Consulted before solving each quotient.
+    pub predictor: Option<Box<dyn Predictor>>,
+    /// Don't solve quotients that are predicted to be non-descriptive.
+    pub skip_predicted: bool,
+    /// Probe each quotient with a conflict limit before solving it.
+    pub probe: Option<ProbeOptions>,
+    /// Receives the progress events of the run.
+    pub progress: Option<ProgressCallback>,
+    /// Seed for all randomized parts of DQG, which then also
+    /// avoid results that depend on the thread scheduling.
+    pub seed: Option<u64>,
 }
 
 impl Settings {
-    pub fn get_stats(&mut self) -> &mut Option<Statistics> {
-        &mut self.statistics
+    /// Report the event if anyone listens.
+    pub fn report_progress(&self, event: impl FnOnce() -> ProgressEvent) {
+        if let Some(progress) = self.progress.as_ref() {
+            progress.report(event());
+        }
+    }
+
+    /// Whether the orbits of a descriptive quotient are printed as soon as it
+    /// is found. In anytime mode, only the best one is printed at the end.
+    pub fn output_found_orbits(&self) -> bool {
+        self.output_orbits && !self.anytime
+    }
+
+    /// Whether the results of a run must not depend on chance.
+    pub fn is_deterministic(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    /// A random number generator seeded with the seed, if any.
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_metric_names() {
+        for metric in MetricUsed::ALL {
+            assert_eq!(Some(metric), metric.name().parse().ok());
+        }
+        for metric in CoreMetric::ALL {
+            assert_eq!(Some(metric), metric.name().parse().ok());
+        }
+        assert_eq!(
+            Some(MetricUsed::BiggestOrbits),
+            "biggest_orbit".parse().ok()
+        );
+        assert!("pow_gen".parse::<CoreMetric>().is_err());
+    }
+
+    #[test]
+    fn test_invariant_names() {
+        assert_eq!(Some(Invariant::Adjacencies), "adjacencies".parse().ok());
+        assert_eq!(Some(Invariant::Distances), "distances".parse().ok());
+        assert_eq!(
+            "Invalid configuration: Unknown invariant foo (expected one of adjacencies, distances)",
+            "foo".parse::<Invariant>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_memory_size() {
+        assert_eq!(Ok(MemorySize::gibibytes(16)), "16G".parse());
+        assert_eq!(Ok(MemorySize(512 << 20)), "512m".parse());
+        assert_eq!(Ok(MemorySize(1000)), "1000".parse());
+        assert!("".parse::<MemorySize>().is_err());
+        assert!("0G".parse::<MemorySize>().is_err());
+        assert!("16T".parse::<MemorySize>().is_err());
+        assert!("G".parse::<MemorySize>().is_err());
+        assert!("99999999999G".parse::<MemorySize>().is_err());
+
+        assert_eq!("16G", MemorySize::gibibytes(16).to_string());
+        assert!("1.5G".parse::<MemorySize>().is_err());
+        assert_eq!("1536M", MemorySize(1536 << 20).to_string());
+        assert_eq!("3K", MemorySize(3072).to_string());
+        assert_eq!("1000", MemorySize(1000).to_string());
     }
 }
@@ -0,0 +1,277 @@
+//! Safe wrappers around the calls into nauty and Traces.
+//!
+//! Both libraries report automorphisms through C callbacks. A panic must never
+//! unwind through their C frames, so each callback runs inside a `CallbackGuard`.
+//! The guard catches the panic, ignores all further callbacks of the same call
+//! and resumes the panic once the library returned to Rust.
+
+use libffi::high::{ClosureMut2, ClosureMut3, ClosureMut6};
+use nauty_Traces_sys::{
+    allgroup, densenauty, groupautomproc, grouplevelproc, groupptr, makecosetreps, optionblk,
+    sparsenauty, statsblk, Traces, TracesOptions, TracesStats, TRUE,
+};
+use std::{
+    any::Any,
+    os::raw::c_int,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    slice::from_raw_parts,
+};
+
+use crate::graph::{NautyGraph, SparseNautyGraph, TracesGraph};
+
+/// Keeps panics of a callback on the Rust side of an FFI call.
+#[derive(Default)]
+struct CallbackGuard {
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl CallbackGuard {
+    /// Run the callback unless an earlier one panicked.
+    fn call(&mut self, callback: impl FnOnce()) {
+        if self.is_poisoned() {
+            return;
+        }
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(callback)) {
+            self.panic = Some(payload);
+        }
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.panic.is_some()
+    }
+
+    /// Resume the caught panic, if any. Must only be called
+    /// after the library call returned.
+    fn finish(self) {
+        if let Some(payload) = self.panic {
+            resume_unwind(payload);
+        }
+    }
+}
+
+/// Check that lab and ptn describe the same number of vertices as the orbits buffer.
+fn assert_sizes(vertex_order: &[c_int], partition: &[c_int], orbits: &[c_int]) {
+    assert_eq!(vertex_order.len(), partition.len());
+    assert_eq!(vertex_order.len(), orbits.len());
+}
+
+/// Run dense nauty and pass each generator to `on_generator`.
+/// Invariants: `orbits` has one entry per vertex and the adjacency matrix
+/// has `n * m` words. The user automorphism procedure of the options is
+/// replaced for the duration of the call.
+pub fn dense_nauty(
+    graph: &mut NautyGraph,
+    orbits: &mut [c_int],
+    options: &mut optionblk,
+    stats: &mut statsblk,
+    mut on_generator: impl FnMut(&[c_int]),
+) {
+    assert!(graph.check_valid());
+    assert_sizes(&graph.vertex_order, &graph.partition, orbits);
+    let (n, m) = graph.graph_repr_sizes();
+    let mut guard = CallbackGuard::default();
+
+    // Limit how long the closure can reference the guard so that we can finish it afterwards.
+    {
+        let mut userautomproc =
+            |_count, generator_ptr: *mut c_int, _orbits, _numorbits, _stabvertex, n: c_int| {
+                // Safety: nauty passes a permutation of all n vertices.
+                let generator = unsafe { from_raw_parts(generator_ptr, n as usize) };
+                guard.call(|| on_generator(generator));
+            };
+        let userautomproc = ClosureMut6::new(&mut userautomproc);
+        options.userautomproc = Some(*userautomproc.code_ptr());
+
+        // Safety: The buffers have the sizes nauty expects (checked above)
+        // and, like the closure, outlive the call.
+        unsafe {
+            densenauty(
+                graph.adjacency_matrix.as_mut_ptr(),
+                graph.vertex_order.as_mut_ptr(),
+                graph.partition.as_mut_ptr(),
+                orbits.as_mut_ptr(),
+                options,
+                stats,
+                m as c_int,
+                n as c_int,
+                std::ptr::null_mut(),
+            );
+        }
+        // The closure is dropped at the end of this block.
+        options.userautomproc = None;
+    }
+
+    guard.finish();
+}
+
+/// Run sparse nauty and pass each generator to `on_generator`.
+/// Invariants: `orbits` has one entry per vertex. The user automorphism
+/// procedure of the options is replaced for the duration of the call.
+pub fn sparse_nauty(
+    graph: &mut SparseNautyGraph,
+    orbits: &mut [c_int],
+    options: &mut optionblk,
+    stats: &mut statsblk,
+    mut on_generator: impl FnMut(&[c_int]),
+) {
+    assert_sizes(&graph.vertex_order, &graph.partition, orbits);
+    let mut guard = CallbackGuard::default();
+
+    {
+        let mut userautomproc =
+            |_count, generator_ptr: *mut c_int, _orbits, _numorbits, _stabvertex, n: c_int| {
+                // Safety: nauty passes a permutation of all n vertices.
+                let generator = unsafe { from_raw_parts(generator_ptr, n as usize) };
+                guard.call(|| on_generator(generator));
+            };
+        let userautomproc = ClosureMut6::new(&mut userautomproc);
+        options.userautomproc = Some(*userautomproc.code_ptr());
+
+        // Safety: The buffers have the sizes nauty expects (checked above)
+        // and, like the closure, outlive the call.
+        unsafe {
+            sparsenauty(
+                &mut (&mut graph.sparse_graph).into(),
+                graph.vertex_order.as_mut_ptr(),
+                graph.partition.as_mut_ptr(),
+                orbits.as_mut_ptr(),
+                options,
+                stats,
+                std::ptr::null_mut(),
+            );
+        }
+        options.userautomproc = None;
+    }
+
+    guard.finish();
+}
+
+/// Run Traces and pass each generator to `on_generator`.
+/// Invariants: `orbits` has one entry per vertex. The user automorphism
+/// procedure of the options is replaced for the duration of the call.
+pub fn traces(
+    graph: &mut TracesGraph,
+    orbits: &mut [c_int],
+    options: &mut TracesOptions,
+    stats: &mut TracesStats,
+    mut on_generator: impl FnMut(&[c_int]),
+) {
+    assert_sizes(&graph.vertex_order, &graph.partition, orbits);
+    let mut guard = CallbackGuard::default();
+
+    {
+        let mut userautomproc = |_count, generator_ptr: *mut c_int, n: c_int| {
+            // Safety: Traces passes a permutation of all n vertices.
+            let generator = unsafe { from_raw_parts(generator_ptr, n as usize) };
+            guard.call(|| on_generator(generator));
+        };
+        let userautomproc = ClosureMut3::new(&mut userautomproc);
+        options.userautomproc = Some(*userautomproc.code_ptr());
+
+        // Safety: The buffers have the sizes Traces expects (checked above)
+        // and, like the closure, outlive the call.
+        unsafe {
+            Traces(
+                &mut (&mut graph.sparse_graph).into(),
+                graph.vertex_order.as_mut_ptr(),
+                graph.partition.as_mut_ptr(),
+                orbits.as_mut_ptr(),
+                options,
+                stats,
+                std::ptr::null_mut(),
+            );
+        }
+        options.userautomproc = None;
+    }
+
+    guard.finish();
+}
+
+/// Run dense nauty, let it store the whole automorphism group
+/// and pass each group element to `on_element`.
+/// Invariants: as for `dense_nauty`. The user procedures of the options
+/// are replaced by the group procedures of nauty.
+pub fn dense_nauty_all_group(
+    graph: &mut NautyGraph,
+    orbits: &mut [c_int],
+    options: &mut optionblk,
+    stats: &mut statsblk,
+    mut on_element: impl FnMut(&[c_int]),
+) {
+    assert!(graph.check_valid());
+    assert_sizes(&graph.vertex_order, &graph.partition, orbits);
+    let (n, m) = graph.graph_repr_sizes();
+
+    options.userautomproc = Some(groupautomproc);
+    options.userlevelproc = Some(grouplevelproc);
+
+    // Safety: The buffers have the sizes nauty expects (checked above).
+    // The group procedures are part of nauty itself.
+    unsafe {
+        densenauty(
+            graph.adjacency_matrix.as_mut_ptr(),
+            graph.vertex_order.as_mut_ptr(),
+            graph.partition.as_mut_ptr(),
+            orbits.as_mut_ptr(),
+            options,
+            stats,
+            m as c_int,
+            n as c_int,
+            std::ptr::null_mut(),
+        );
+    }
+
+    let mut guard = CallbackGuard::default();
+    {
+        let mut handle_element = |element_ptr: *mut c_int, n: c_int| {
+            // Safety: nauty passes a permutation of all n vertices.
+            let element = unsafe { from_raw_parts(element_ptr, n as usize) };
+            guard.call(|| on_element(element));
+        };
+        let handle_element = ClosureMut2::new(&mut handle_element);
+
+        // Safety: The group was stored by the densenauty call above
+        // and the closure outlives the call to allgroup.
+        unsafe {
+            let group = groupptr(TRUE);
+            if group.is_null() {
+                panic!("The group ptr is null!");
+            }
+            makecosetreps(group);
+            allgroup(group, Some(*handle_element.code_ptr()));
+        }
+    }
+
+    guard.finish();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn callback_guard_poisons() {
+        let mut calls = 0;
+        let mut guard = CallbackGuard::default();
+
+        guard.call(|| calls += 1);
+        assert!(!guard.is_poisoned());
+
+        guard.call(|| panic!("callback failed"));
+        assert!(guard.is_poisoned());
+
+        // Callbacks after the panic are skipped.
+        guard.call(|| calls += 1);
+        assert_eq!(1, calls);
+
+        let resumed = catch_unwind(AssertUnwindSafe(|| guard.finish()));
+        assert!(resumed.is_err());
+    }
+
+    #[test]
+    fn callback_guard_finishes_quietly() {
+        let mut guard = CallbackGuard::default();
+        guard.call(|| {});
+        guard.finish();
+    }
+}
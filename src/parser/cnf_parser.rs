@@ -0,0 +1,214 @@
+//! Parser for formulas in DIMACS CNF that builds the
+//! clause-variable incidence graph of the formula.
+//! The symmetries of this graph are the (phase and
+//! permutation) symmetries of the formula.
+
+use nom::error::VerboseErrorKind;
+use std::io::BufRead;
+
+use crate::{
+    graph::{Colour, Graph, VertexIndex},
+    parse_single_line, Error,
+};
+
+use super::{Input, ParseResult};
+
+/// Colour of the literal vertices in the incidence graph.
+const LITERAL_COLOUR: Colour = 1;
+/// Colour of the clause vertices in the incidence graph.
+const CLAUSE_COLOUR: Colour = 2;
+
+/// Parse the header `p cnf #variables #clauses`.
+fn parse_cnf_header(input: Input<'_>) -> ParseResult<'_, (usize, usize)> {
+    use nom::{
+        bytes::complete::tag,
+        character::complete::{space0, space1, u64},
+        error::context,
+        sequence::tuple,
+    };
+
+    let mut header = context(
+        "CNF header",
+        tuple((
+            tag("p"),
+            space1,
+            tag("cnf"),
+            space1,
+            u64,
+            space1,
+            u64,
+            space0,
+        )),
+    );
+    let (rest, (_, _, _, _, variables, _, clauses, _)) = header(input)?;
+    Ok((rest, (variables as usize, clauses as usize)))
+}
+
+/// Parse the literals in a single line. Clauses end
+/// with a `0` and might span multiple lines.
+fn parse_literals(input: Input<'_>) -> ParseResult<'_, Vec<i32>> {
+    use nom::{
+        character::complete::{i32, space0, space1},
+        multi::separated_list0,
+        sequence::delimited,
+    };
+
+    delimited(space0, separated_list0(space1, i32), space0)(input)
+}
+
+/// Vertex of a literal in the incidence graph. The positive literal
+/// of variable v is vertex 2(v-1) and the negative one 2(v-1)+1.
+/// The literal must be non-zero and its variable must be declared,
+/// which the header check keeps in the range of vertex indices.
+fn literal_vertex(literal: i32) -> VertexIndex {
+    let variable = (literal.unsigned_abs() - 1) as VertexIndex;
+    if literal > 0 {
+        2 * variable
+    } else {
+        2 * variable + 1
+    }
+}
+
+/// Read a DIMACS CNF formula and build its incidence graph. The
+/// vertices 0..2n are the literals, each connected to its negation,
+/// and the following vertices are the clauses, connected to their
+/// literals. Literals and clauses have different colours.
+pub fn parse_cnf_input<B: BufRead>(input: B) -> Result<Graph, Error> {
+    use nom::combinator::eof;
+
+    let mut graph = None;
+    let mut number_variables = 0;
+    let mut clause_vertex = 0;
+    let mut clause = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('c') {
+            continue;
+        }
+        // Some benchmark sets end with a `%` line.
+        if trimmed.starts_with('%') {
+            break;
+        }
+
+        let graph = match graph.as_mut() {
+            Some(graph) => graph,
+            None => {
+                parse_single_line!(header, parse_cnf_header(trimmed));
+                let (variables, clauses) = header;
+                // All literal and clause vertices need an index.
+                variables
+                    .checked_mul(2)
+                    .and_then(|literals| literals.checked_add(clauses))
+                    .filter(|vertices| *vertices <= VertexIndex::MAX as usize)
+                    .ok_or_else(|| {
+                        Error::ParseError(vec![VerboseErrorKind::Context(
+                            "Too many variables and clauses in CNF header",
+                        )])
+                    })?;
+                number_variables = variables as VertexIndex;
+                clause_vertex = 2 * number_variables;
+
+                let mut colours = vec![LITERAL_COLOUR; 2 * variables];
+                colours.append(&mut vec![CLAUSE_COLOUR; clauses]);
+                let mut incidence_graph = Graph::new_ordered(colours.len());
+                incidence_graph.set_colours(&colours)?;
+                incidence_graph.update_max_color(CLAUSE_COLOUR);
+
+                for variable in 0..number_variables {
+                    incidence_graph.add_edge(2 * variable, 2 * variable + 1)?;
+                }
+
+                graph.get_or_insert(incidence_graph);
+                continue;
+            }
+        };
+
+        parse_single_line!(literals, parse_literals(trimmed));
+        for literal in literals {
+            if literal == 0 {
+                for literal in clause.drain(..) {
+                    graph.add_edge(clause_vertex, literal)?;
+                }
+                clause_vertex += 1;
+            } else if literal.unsigned_abs() > number_variables as u32 {
+                return Err(Error::ParseError(vec![VerboseErrorKind::Context(
+                    "Literal of undeclared variable",
+                )]));
+            } else {
+                clause.push(literal_vertex(literal));
+            }
+        }
+    }
+
+    let mut graph = graph
+        .ok_or_else(|| Error::ParseError(vec![VerboseErrorKind::Context("Missing CNF header")]))?;
+    // A literal might occur more than once in a clause.
+    graph.minimize();
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cnf_header() -> Result<(), Error> {
+        let (_, parsed) = parse_cnf_header("p cnf 3 12")?;
+        assert_eq!((3, 12), parsed);
+        assert!(parse_cnf_header("p dnf 3 12").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_literals() -> Result<(), Error> {
+        let (_, parsed) = parse_literals(" 1 -2  3 0")?;
+        assert_eq!(vec![1, -2, 3, 0], parsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cnf_input() -> Result<(), Error> {
+        let cnf = "c a small formula
+p cnf 2 2
+1 -2 0
+2
+ 1 1 0
+";
+        let buf = BufReader::new(cnf.as_bytes());
+        let parsed = parse_cnf_input(buf)?;
+
+        let mut graph = Graph::new_ordered(6);
+        graph.set_colours(&[1, 1, 1, 1, 2, 2])?;
+        graph.update_max_color(2);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(2, 3)?;
+        graph.add_edge(4, 0)?;
+        graph.add_edge(4, 3)?;
+        graph.add_edge(5, 2)?;
+        graph.add_edge(5, 0)?;
+        graph.minimize();
+
+        assert_eq!(graph, parsed);
+
+        let too_many_variables = "p cnf 1 1\n1 2 0\n";
+        let buf = BufReader::new(too_many_variables.as_bytes());
+        assert!(parse_cnf_input(buf).is_err());
+
+        let minimal_literal = "p cnf 1 1\n-2147483648 0\n";
+        let buf = BufReader::new(minimal_literal.as_bytes());
+        assert!(parse_cnf_input(buf).is_err());
+
+        let oversized = "p cnf 1073741824 0\n";
+        let buf = BufReader::new(oversized.as_bytes());
+        assert!(parse_cnf_input(buf).is_err());
+
+        Ok(())
+    }
+}
@@ -1,47 +1,181 @@
 //! Parser for graphs encoded in csv files.
+//! The delimiter (comma, semicolon or tab) and a header line are
+//! detected from the first line. A third column, e.g. edge weights,
+//! is either ignored or mapped to edge colours.
 
-use std::io::BufRead;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::BufRead,
+    str::FromStr,
+};
 
 use crate::{
-    get_line_recognize,
-    graph::{Graph, VertexIndex},
+    graph::{Colour, Graph, VertexIndex},
     parse_single_line, Error,
 };
 
 use super::{Input, ParseResult};
 
-fn parse_edge(input: Input<'_>) -> ParseResult<'_, (VertexIndex, VertexIndex)> {
+/// Delimiters that are detected, in this order.
+const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// Colour of the vertices of the graph if the third column is mapped to edge colours.
+const VERTEX_COLOUR: Colour = 0;
+
+/// What to do with a third column of a csv file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsvThirdColumn {
+    #[default]
+    Ignore,
+    /// Subdivide each edge by a new vertex with one colour per distinct value
+    /// of the column, so that the automorphisms respect the edge colours.
+    EdgeColours,
+}
+
+impl FromStr for CsvThirdColumn {
+    type Err = String;
+
+    #[cfg(not(tarpaulin_include))]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(Self::Ignore),
+            "edge_colours" => Ok(Self::EdgeColours),
+            _ => Err(format!("Unknown csv column mapping {}", s)),
+        }
+    }
+}
+
+/// How to read a csv file. The delimiter is detected if None.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvOptions {
+    pub delimiter: Option<char>,
+    pub third_column: CsvThirdColumn,
+}
+
+fn parse_field(input: Input<'_>) -> ParseResult<'_, VertexIndex> {
+    use nom::{bytes::complete::take_while, character::complete::i32, sequence::delimited};
+
+    let spaces = || take_while(|c| c == ' ');
+    delimited(spaces(), i32, spaces())(input)
+}
+
+/// Start, end and the optional third column of a row.
+fn parse_edge(
+    input: Input<'_>,
+    delimiter: char,
+) -> ParseResult<'_, (VertexIndex, VertexIndex, Option<&str>)> {
     use nom::{
-        character::complete::{char, i32},
-        sequence::separated_pair,
+        bytes::complete::take_till,
+        character::complete::char,
+        combinator::{map, opt},
+        sequence::{preceded, separated_pair, tuple},
     };
 
-    separated_pair(i32, char(','), i32)(input)
+    map(
+        tuple((
+            separated_pair(parse_field, char(delimiter), parse_field),
+            opt(preceded(
+                char(delimiter),
+                take_till(move |c| c == delimiter || c == '\r' || c == '\n'),
+            )),
+        )),
+        |((start, end), third)| (start, end, third.map(str::trim)),
+    )(input)
 }
 
-fn parse_column_header(input: Input<'_>) -> ParseResult<'_, ()> {
-    use nom::{character::complete::not_line_ending, combinator::value};
+/// The first supported delimiter in the line.
+fn detect_delimiter(line: &str) -> Option<char> {
+    DELIMITERS
+        .iter()
+        .copied()
+        .find(|delimiter| line.contains(*delimiter))
+}
 
-    value((), not_line_ending)(input)
+/// A header doesn't start with a vertex.
+fn is_header(line: &str, delimiter: char) -> bool {
+    let first_field = line.split(delimiter).next().unwrap_or_default();
+    first_field.trim().parse::<VertexIndex>().is_err()
 }
 
 pub fn parse_csv_input<B: BufRead>(graph_size: usize, input: B) -> Result<Graph, Error> {
-    use nom::combinator::eof;
+    parse_csv_input_with(graph_size, input, &CsvOptions::default())
+}
 
-    let mut graph = Graph::new_ordered(graph_size);
-    let mut lines = input.lines();
+/// Same as `parse_csv_input`, but with the given delimiter and mapping of the third column.
+/// With edge colours, the vertices that subdivide the edges follow the `graph_size` vertices.
+pub fn parse_csv_input_with<B: BufRead>(
+    graph_size: usize,
+    input: B,
+    options: &CsvOptions,
+) -> Result<Graph, Error> {
+    use nom::combinator::eof;
 
-    get_line_recognize!(lines, parse_column_header);
+    let mut edges = Vec::new();
+    let mut delimiter = options.delimiter;
+    let mut first_line = true;
 
-    for line in lines {
+    for line in input.lines() {
         let line = line?;
-        parse_single_line!(start_end, parse_edge(&line));
-        let (start, end) = start_end;
-        graph
-            .add_edge(start, end)
-            .expect("Edge to non existing vertex! Graph too small!");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let delimiter = match delimiter {
+            Some(delimiter) => delimiter,
+            None => *delimiter.insert(detect_delimiter(&line).ok_or_else(|| {
+                Error::ConfigError(format!("No comma, semicolon or tab in csv line {}", line))
+            })?),
+        };
+        if std::mem::take(&mut first_line) && is_header(&line, delimiter) {
+            continue;
+        }
+
+        parse_single_line!(row, parse_edge(&line, delimiter));
+        let (start, end, third) = row;
+        edges.push((start, end, third.map(str::to_string)));
+    }
+
+    match options.third_column {
+        CsvThirdColumn::Ignore => Graph::from_edges(
+            graph_size,
+            edges.into_iter().map(|(start, end, _)| (start, end)),
+        )
+        .map_err(Error::from),
+        CsvThirdColumn::EdgeColours => subdivide_coloured_edges(graph_size, edges),
+    }
+}
+
+/// Each edge with a third column becomes a path over a new
+/// vertex with the colour of the value in the third column.
+fn subdivide_coloured_edges(
+    graph_size: usize,
+    edges: Vec<(VertexIndex, VertexIndex, Option<String>)>,
+) -> Result<Graph, Error> {
+    let colours = edges
+        .iter()
+        .filter_map(|(_, _, value)| value.as_ref())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| (value.clone(), VERTEX_COLOUR + 1 + index as Colour))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut vertex_colours = vec![VERTEX_COLOUR; graph_size];
+    let mut plain_edges = Vec::with_capacity(edges.len());
+    for (start, end, value) in edges {
+        match value {
+            Some(value) => {
+                let middle = vertex_colours.len() as VertexIndex;
+                vertex_colours.push(colours[&value]);
+                plain_edges.push((start, middle));
+                plain_edges.push((middle, end));
+            }
+            None => plain_edges.push((start, end)),
+        }
     }
 
+    let mut graph = Graph::from_edges(vertex_colours.len(), plain_edges)?;
+    graph.set_colours(&vertex_colours)?;
+    graph.update_max_color(VERTEX_COLOUR + colours.len() as Colour);
     Ok(graph)
 }
 
@@ -55,17 +189,27 @@ mod test {
 
     #[test]
     fn test_parse_edge() -> Result<(), Error> {
-        let edge = "123,46783";
-        let parsed = parse_edge(edge)?.1;
-        assert_eq!((123, 46783), parsed);
+        let (_, parsed) = parse_edge("123,46783", ',')?;
+        assert_eq!((123, 46783, None), parsed);
+
+        let (_, parsed) = parse_edge("1 ; 2; 0.5", ';')?;
+        assert_eq!((1, 2, Some("0.5")), parsed);
+
+        let (_, parsed) = parse_edge("1\t2\tred", '\t')?;
+        assert_eq!((1, 2, Some("red")), parsed);
 
         Ok(())
     }
 
     #[test]
-    fn test_parse_column_header() -> Result<(), Error> {
-        let header = "node_1,node_2\n";
-        Ok(parse_column_header(header)?.1)
+    fn test_detect_header() {
+        assert_eq!(Some(','), detect_delimiter("node_1,node_2"));
+        assert_eq!(Some(';'), detect_delimiter("0;3"));
+        assert_eq!(Some('\t'), detect_delimiter("0\t3"));
+        assert_eq!(None, detect_delimiter("0 3"));
+
+        assert!(is_header("node_1,node_2", ','));
+        assert!(!is_header("0, 3", ','));
     }
 
     #[test]
@@ -91,4 +235,50 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_csv_input_without_header() -> Result<(), Error> {
+        let csv = "0;3;1.5\n\n1;2;7\n";
+        let parsed = parse_csv_input(4, BufReader::new(csv.as_bytes()))?;
+
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 3)?;
+        graph.add_edge(1, 2)?;
+
+        assert_eq!(graph, parsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_input_edge_colours() -> Result<(), Error> {
+        let csv = "from\tto\tweight\n0\t1\theavy\n1\t2\tlight\n2\t0\theavy\n";
+        let options = CsvOptions {
+            delimiter: Some('\t'),
+            third_column: CsvThirdColumn::EdgeColours,
+        };
+        let parsed = parse_csv_input_with(3, BufReader::new(csv.as_bytes()), &options)?;
+
+        // 3, 4 and 5 subdivide the edges, heavy before light.
+        let mut graph = Graph::new_ordered(6);
+        graph.set_colours(&[0, 0, 0, 1, 2, 1])?;
+        graph.update_max_color(2);
+        graph.add_edge(0, 3)?;
+        graph.add_edge(3, 1)?;
+        graph.add_edge(1, 4)?;
+        graph.add_edge(4, 2)?;
+        graph.add_edge(2, 5)?;
+        graph.add_edge(5, 0)?;
+
+        assert_eq!(graph, parsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_input_too_small() {
+        let csv = "node_1,node_2\n0,3\n";
+        let buf = BufReader::new(csv.as_bytes());
+        assert!(parse_csv_input(3, buf).is_err());
+    }
 }
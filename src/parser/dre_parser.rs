@@ -2,7 +2,10 @@
 //! E.g., these can be generated from planning
 //! problems by this tool: <https://home.in.tum.de/~mansour/cv-and-website/tools/quotientPlan.zip>
 
-use std::{io::BufRead, iter::Peekable};
+use std::{
+    io::{BufRead, Lines},
+    iter::Peekable,
+};
 
 use nom::error::ParseError;
 
@@ -56,11 +59,28 @@ where
     Ok(("", ()))
 }
 
+/// Parse the directive that declares the following graph as a digraph (`d`)
+/// or as undirected (`-d`). Without it, graphs are undirected.
+fn parse_directedness(input: Input<'_>) -> ParseResult<'_, bool> {
+    use nom::{branch::alt, bytes::complete::tag, combinator::value, error::context};
+
+    let mut directive = context(
+        "Directedness directive",
+        alt((value(false, tag("-d")), value(true, tag("d")))),
+    );
+    directive(input)
+}
+
 /// Parse the start line for th graph that contains the size.
 fn parse_graph_size(input: Input<'_>) -> ParseResult<'_, usize> {
-    use nom::{bytes::complete::tag, character::complete::u64, error::context, sequence::tuple};
+    use nom::{
+        bytes::complete::tag, character::complete::u64, combinator::verify, error::context,
+        sequence::tuple,
+    };
 
-    let mut size_header = context("Graph size header", tuple((tag("n="), u64, tag(" g"))));
+    // Vertex indices have to fit into `VertexIndex`.
+    let size = verify(u64, |size| *size <= VertexIndex::MAX as u64);
+    let mut size_header = context("Graph size header", tuple((tag("n="), size, tag(" g"))));
     let (rest, (_, graph_size, _)) = size_header(input)?;
     Ok((rest, graph_size as usize))
 }
@@ -85,7 +105,12 @@ fn parse_vertex_edges(
         sequence::pair,
     };
 
-    let (input, index) = context("lines starts with vector index", parse_vertex_index)(input)?;
+    let (input, index) = context(
+        "lines starts with vector index",
+        verify(parse_vertex_index, |index| {
+            *index >= 0 && *index < graph_size as VertexIndex
+        }),
+    )(input)?;
     let (input, _) = pair(tag(":"), space0)(input)?;
 
     let (rest, edges) = context(
@@ -93,7 +118,7 @@ fn parse_vertex_edges(
         separated_list1(
             space1,
             verify(parse_vertex_index, |end_index| {
-                *end_index < graph_size as VertexIndex && *end_index != index
+                *end_index >= 0 && *end_index < graph_size as VertexIndex && *end_index != index
             }),
         ),
     )(input)?;
@@ -123,7 +148,7 @@ fn parse_colouring(graph_size: usize, input: Input<'_>) -> ParseResult<'_, (Vec<
     use nom::{
         bytes::complete::tag,
         character::complete::{multispace1, space0},
-        combinator::opt,
+        combinator::{opt, verify},
         multi::{separated_list0, separated_list1},
         sequence::tuple,
     };
@@ -133,7 +158,10 @@ fn parse_colouring(graph_size: usize, input: Input<'_>) -> ParseResult<'_, (Vec<
 
     let sep = |sep_tag| tuple((space0, tag(sep_tag), space0));
 
-    let single_colour = separated_list1(sep(","), parse_vertex_index);
+    let coloured_vertex = verify(parse_vertex_index, |vertex| {
+        *vertex >= 0 && (*vertex as usize) < graph_size
+    });
+    let single_colour = separated_list1(sep(","), coloured_vertex);
     let mut colour_list = separated_list0(sep("|"), single_colour);
 
     let (input, _) = tag("f=[")(input)?;
@@ -150,15 +178,80 @@ fn parse_colouring(graph_size: usize, input: Input<'_>) -> ParseResult<'_, (Vec<
     Ok((rest, (colours, colour_counter)))
 }
 
-pub fn parse_dreadnaut_input<B: BufRead>(input: B) -> Result<(Graph, bool), Error> {
-    use nom::combinator::eof;
+/// Parse the optional list of fixed vertices after the colouring: `fix=[v1,v2,...,vn]`.
+/// These vertices are individualized before the automorphisms are computed.
+fn parse_fixed_vertices(graph_size: usize, input: Input<'_>) -> ParseResult<'_, Vec<VertexIndex>> {
+    use nom::{
+        bytes::complete::tag,
+        character::complete::{multispace0, space0},
+        combinator::verify,
+        multi::separated_list0,
+        sequence::{delimited, tuple},
+    };
+
+    let fixed_vertex = verify(parse_vertex_index, |vertex| {
+        *vertex >= 0 && (*vertex as usize) < graph_size
+    });
+    let mut fixed_list = separated_list0(tuple((space0, tag(","), space0)), fixed_vertex);
+
+    let (input, _) = tuple((space0, tag("fix=[")))(input)?;
+    let (input, fixed) = fixed_list(input)?;
+    let (rest, _) = delimited(space0, tag("]"), multispace0)(input)?;
+
+    Ok((rest, fixed))
+}
 
+/// A graph in dreadnaut syntax, whether it had the
+/// Traces header and the vertices that should be fixed.
+pub type DreadnautGraph = (Graph, bool, Vec<VertexIndex>);
+
+/// Parse a graph in dreadnaut syntax. Returns whether the input
+/// contained the Traces header and the vertices that should be fixed.
+/// Only the first graph of inputs with several graphs is parsed.
+pub fn parse_dreadnaut_input<B: BufRead>(input: B) -> Result<DreadnautGraph, Error> {
+    parse_dreadnaut_graph(&mut input.lines().peekable())
+}
+
+/// Parse all graphs of a dreadnaut session that defines several
+/// graphs one after another, e.g. a bundle of benchmark instances.
+/// Each graph may have its own Traces header and fixed vertices.
+pub fn parse_dreadnaut_inputs<B: BufRead>(input: B) -> Result<Vec<DreadnautGraph>, Error> {
     let mut lines = input.lines().peekable();
+    let mut graphs = Vec::new();
+    loop {
+        graphs.push(parse_dreadnaut_graph(&mut lines)?);
+
+        // Blank lines separate the graphs.
+        let is_blank =
+            |line: &std::io::Result<String>| line.as_ref().is_ok_and(|line| line.trim().is_empty());
+        while lines.next_if(is_blank).is_some() {}
+        if lines.peek().is_none() {
+            return Ok(graphs);
+        }
+    }
+}
+
+fn parse_dreadnaut_graph<B: BufRead>(
+    lines: &mut Peekable<Lines<B>>,
+) -> Result<DreadnautGraph, Error> {
+    use nom::combinator::eof;
 
-    let header = parse_header(&mut lines).is_ok();
+    let header = parse_header(lines).is_ok();
+    let is_directive = |line: &std::io::Result<String>| {
+        line.as_ref()
+            .is_ok_and(|line| matches!(line.trim(), "d" | "-d"))
+    };
+    let directed = if let Some(directive_line) = lines.next_if(is_directive) {
+        let directive_line = directive_line?;
+        parse_single_line!(directed, parse_directedness(directive_line.trim()));
+        directed
+    } else {
+        false
+    };
     get_line_parse!(lines, graph_size, parse_graph_size);
 
     let mut graph = Graph::new_ordered(graph_size);
+    graph.set_directed(directed);
 
     loop {
         get_line!(line, lines);
@@ -166,12 +259,12 @@ pub fn parse_dreadnaut_input<B: BufRead>(input: B) -> Result<(Graph, bool), Erro
         let (vertex, edges) = vertex_edges;
 
         for end in edges {
-            graph.add_edge(vertex, end)?;
+            graph.connect(vertex, end)?;
         }
 
         parse_single_line!(should_continue, parse_continue_after_edge_line(res));
 
-        if !should_continue || vertex as usize >= graph_size - 1 {
+        if !should_continue || vertex as usize + 1 >= graph_size {
             break;
         }
     }
@@ -182,7 +275,19 @@ pub fn parse_dreadnaut_input<B: BufRead>(input: B) -> Result<(Graph, bool), Erro
     graph.set_colours(&colours)?;
     graph.update_max_color(max_colour);
 
-    Ok((graph, header))
+    let is_fix_line = |line: &std::io::Result<String>| {
+        line.as_ref()
+            .is_ok_and(|line| line.trim_start().starts_with("fix="))
+    };
+    let fixed = if let Some(fix_line) = lines.next_if(is_fix_line) {
+        let fix_line = fix_line?;
+        parse_single_line!(fixed, parse_fixed_vertices(graph_size, &fix_line));
+        fixed
+    } else {
+        Vec::new()
+    };
+
+    Ok((graph, header, fixed))
 }
 
 #[cfg(test)]
@@ -293,9 +398,10 @@ f=[0|1, 2] x o
         expected_graph.set_colours(&vec![1, 2, 2, DEFAULT_COLOR])?;
         expected_graph.update_max_color(3);
 
-        let (parsed_graph, has_header) = parse_dreadnaut_input(test_buf)?;
+        let (parsed_graph, has_header, fixed) = parse_dreadnaut_input(test_buf)?;
         assert_eq!(expected_graph, parsed_graph);
         assert!(has_header);
+        assert!(fixed.is_empty());
 
         Ok(())
     }
@@ -318,10 +424,129 @@ f=[0|1, 2]
         expected_graph.set_colours(&vec![1, 2, 2, DEFAULT_COLOR])?;
         expected_graph.update_max_color(3);
 
-        let (parsed_graph, has_header) = parse_dreadnaut_input(test_buf)?;
+        let (parsed_graph, has_header, _) = parse_dreadnaut_input(test_buf)?;
         assert_eq!(expected_graph, parsed_graph);
         assert!(!has_header);
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_fixed_vertices() -> Result<(), Error> {
+        let test_file = "n=3 g
+0:1 2.
+f=[0|1,2]
+fix=[2, 1]
+";
+        let test_buf = BufReader::new(test_file.as_bytes());
+        let (_, _, fixed) = parse_dreadnaut_input(test_buf)?;
+        assert_eq!(vec![2, 1], fixed);
+
+        assert_eq!(
+            Vec::<VertexIndex>::new(),
+            parse_fixed_vertices(3, "fix=[]")?.1
+        );
+        assert!(parse_fixed_vertices(3, "fix=[3]").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dreadnaut_inputs() -> Result<(), Error> {
+        let test_file = "n=2 g
+0:1.
+f=[0|1]
+
+At
+
+-a
+-m
+n=3 g
+0:1;
+1:2.
+f=[0,1,2] x o
+fix=[1]
+   
+";
+        let graphs = parse_dreadnaut_inputs(BufReader::new(test_file.as_bytes()))?;
+        assert_eq!(2, graphs.len());
+        assert_eq!(
+            parse_dreadnaut_input(BufReader::new(test_file.as_bytes()))?,
+            graphs[0]
+        );
+
+        let (graph, has_header, fixed) = &graphs[1];
+        assert_eq!(3, graph.size());
+        assert_eq!(4, graph.number_edges());
+        assert!(has_header);
+        assert_eq!(&vec![1], fixed);
+
+        // Anything after a graph has to be another graph.
+        assert!(
+            parse_dreadnaut_inputs(BufReader::new("n=2 g\n0:1.\nf=[0|1]\nq\n".as_bytes())).is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_directed_dreadnaut_input() -> Result<(), Error> {
+        let test_file = "At
+
+-a
+-m
+d
+n=3 g
+0:1;
+1:2.
+f=[0,1,2] x o
+";
+        let mut expected_graph = Graph::new_ordered(3);
+        expected_graph.set_directed(true);
+        expected_graph.add_arc(0, 1)?;
+        expected_graph.add_arc(1, 2)?;
+        expected_graph.set_colours(&[1, 1, 1])?;
+        expected_graph.update_max_color(2);
+
+        let (parsed_graph, has_header, _) =
+            parse_dreadnaut_input(BufReader::new(test_file.as_bytes()))?;
+        assert_eq!(expected_graph, parsed_graph);
+        assert!(has_header);
+        assert!(parsed_graph.validate().is_empty());
+
+        let undirected = parse_dreadnaut_input(BufReader::new(
+            "-d
+n=2 g
+0:1.
+f=[]
+"
+            .as_bytes(),
+        ))?
+        .0;
+        assert!(!undirected.is_declared_directed());
+        assert_eq!(2, undirected.number_edges());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dreadnaut_input_malformed() {
+        let malformed_files = [
+            // Colour for a vertex that doesn't exist
+            "n=2 g\n0:1.\nf=[0|2]\n",
+            // Negative vertex index
+            "n=2 g\n-1:1.\nf=[0|1]\n",
+            // Empty graph with an edge line
+            "n=0 g\n0:1.\nf=[]\n",
+            // Edge to a vertex that doesn't exist
+            "n=2 g\n0:5.\nf=[0|1]\n",
+            // Fixed vertex that doesn't exist
+            "n=2 g\n0:1.\nf=[0|1]\nfix=[2]\n",
+        ];
+
+        for file in malformed_files {
+            let test_buf = BufReader::new(file.as_bytes());
+            assert!(parse_dreadnaut_input(test_buf).is_err());
+        }
+    }
 }
@@ -0,0 +1,78 @@
+//! Parser for raw edge lists without any header,
+//! i.e. one whitespace separated `u v` pair per line.
+
+use std::io::BufRead;
+
+use crate::{
+    graph::{Graph, VertexIndex},
+    parse_single_line, Error,
+};
+
+use super::{Input, ParseResult};
+
+fn parse_edge(input: Input<'_>) -> ParseResult<'_, (VertexIndex, VertexIndex)> {
+    use nom::{
+        character::complete::{i32, space0, space1},
+        sequence::{delimited, separated_pair},
+    };
+
+    delimited(space0, separated_pair(i32, space1, i32), space0)(input)
+}
+
+pub fn parse_edgelist_input<B: BufRead>(graph_size: usize, input: B) -> Result<Graph, Error> {
+    use nom::combinator::eof;
+
+    let mut edges = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        parse_single_line!(start_end, parse_edge(&line));
+        edges.push(start_end);
+    }
+
+    Graph::from_edges(graph_size, edges).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_edge() -> Result<(), Error> {
+        let (_, parsed) = parse_edge("12 7")?;
+        assert_eq!((12, 7), parsed);
+
+        let (_, parsed) = parse_edge(" 3\t 4 ")?;
+        assert_eq!((3, 4), parsed);
+
+        assert!(parse_edge("3,4").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_edgelist_input() -> Result<(), Error> {
+        let edgelist = "0 3
+0 1
+
+1 2
+";
+        let buf = BufReader::new(edgelist.as_bytes());
+        let parsed = parse_edgelist_input(4, buf)?;
+
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 3)?;
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+
+        assert_eq!(graph, parsed);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Parser for edit scripts that modify the input graph after loading,
+//! one edit per line:
+//!
+//! ```text
+//! # comments and empty lines are skipped
+//! add 0 1
+//! remove 2 3
+//! colour 4 1
+//! ```
+
+use std::io::BufRead;
+
+use crate::{
+    graph::{GraphEdit, VertexIndex},
+    parse_single_line, Error,
+};
+
+use super::{Input, ParseResult};
+
+fn parse_vertex_pair(input: Input<'_>) -> ParseResult<'_, (VertexIndex, VertexIndex)> {
+    use nom::{
+        character::complete::{i32, space1},
+        sequence::{preceded, tuple},
+    };
+
+    tuple((preceded(space1, i32), preceded(space1, i32)))(input)
+}
+
+fn parse_edit(input: Input<'_>) -> ParseResult<'_, GraphEdit> {
+    use nom::{
+        branch::alt,
+        bytes::complete::tag,
+        character::complete::space0,
+        combinator::{map, verify},
+        sequence::{delimited, preceded},
+    };
+
+    let add = map(preceded(tag("add"), parse_vertex_pair), |(start, end)| {
+        GraphEdit::AddEdge(start, end)
+    });
+    let remove = map(
+        preceded(tag("remove"), parse_vertex_pair),
+        |(start, end)| GraphEdit::RemoveEdge(start, end),
+    );
+    let colour = map(
+        preceded(
+            tag("colour"),
+            verify(parse_vertex_pair, |(_, colour)| *colour >= 0),
+        ),
+        |(vertex, colour)| GraphEdit::Recolour(vertex, colour),
+    );
+    delimited(space0, alt((add, remove, colour)), space0)(input)
+}
+
+/// Parse the edits of an edit script in the order of the script.
+pub fn parse_edit_script<B: BufRead>(input: B) -> Result<Vec<GraphEdit>, Error> {
+    use nom::combinator::eof;
+
+    let mut edits = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        parse_single_line!(edit, parse_edit(&line));
+        edits.push(edit);
+    }
+
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_edit() -> Result<(), Error> {
+        let (_, parsed) = parse_edit("add 0 1")?;
+        assert_eq!(GraphEdit::AddEdge(0, 1), parsed);
+
+        let (_, parsed) = parse_edit(" remove\t3  4 ")?;
+        assert_eq!(GraphEdit::RemoveEdge(3, 4), parsed);
+
+        let (_, parsed) = parse_edit("colour 2 5")?;
+        assert_eq!(GraphEdit::Recolour(2, 5), parsed);
+
+        assert!(parse_edit("colour 2 -1").is_err());
+        assert!(parse_edit("add0 1").is_err());
+        assert!(parse_edit("delete 0 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_edit_script() -> Result<(), Error> {
+        let script = "# what if 0 and 3 were adjacent
+add 0 3
+
+remove 1 2
+colour 3 1
+";
+        let parsed = parse_edit_script(BufReader::new(script.as_bytes()))?;
+        assert_eq!(
+            vec![
+                GraphEdit::AddEdge(0, 3),
+                GraphEdit::RemoveEdge(1, 2),
+                GraphEdit::Recolour(3, 1)
+            ],
+            parsed
+        );
+
+        let incomplete = "add 0\n";
+        assert!(parse_edit_script(BufReader::new(incomplete.as_bytes())).is_err());
+
+        Ok(())
+    }
+}
@@ -1,12 +1,20 @@
+mod cnf_parser;
 mod csv_parser;
 mod dre_parser;
+mod edgelist_parser;
+mod edit_parser;
 mod mus_parser;
+mod orbits_parser;
 mod txt_parser;
 
-pub use csv_parser::parse_csv_input;
-pub use dre_parser::parse_dreadnaut_input;
+pub use cnf_parser::parse_cnf_input;
+pub use csv_parser::{parse_csv_input, parse_csv_input_with, CsvOptions, CsvThirdColumn};
+pub use dre_parser::{parse_dreadnaut_input, parse_dreadnaut_inputs};
+pub use edgelist_parser::parse_edgelist_input;
+pub use edit_parser::parse_edit_script;
 pub use mus_parser::{BinInput, BinParseError, BinParseResult, _parse_mus};
-pub use txt_parser::parse_txt_input;
+pub use orbits_parser::parse_orbits_input;
+pub use txt_parser::{parse_txt_input, parse_txt_input_with_ids};
 
 pub type Input<'a> = &'a str;
 pub type ParseError<'a> = nom::error::VerboseError<Input<'a>>;
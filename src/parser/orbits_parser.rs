@@ -0,0 +1,66 @@
+//! Parser for the orbit membership of each vertex as
+//! written next to exported quotient graphs.
+
+use std::io::BufRead;
+
+use crate::{get_line_recognize, graph::VertexIndex, parse_single_line, Error};
+
+use super::{Input, ParseResult};
+
+fn parse_column_header(input: Input<'_>) -> ParseResult<'_, ()> {
+    use nom::{bytes::complete::tag, combinator::value};
+
+    value((), tag("vertex,orbit"))(input)
+}
+
+fn parse_membership(input: Input<'_>) -> ParseResult<'_, (VertexIndex, VertexIndex)> {
+    use nom::{
+        character::complete::{char, i32},
+        combinator::verify,
+        sequence::separated_pair,
+    };
+
+    let index = || verify(i32, |index| *index >= 0);
+    separated_pair(index(), char(','), index())(input)
+}
+
+/// Parse the `vertex,orbit` pairs of an orbit membership csv file.
+pub fn parse_orbits_input<B: BufRead>(input: B) -> Result<Vec<(VertexIndex, VertexIndex)>, Error> {
+    use nom::combinator::eof;
+
+    let mut lines = input.lines();
+    let mut membership = Vec::new();
+
+    get_line_recognize!(lines, parse_column_header);
+
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        parse_single_line!(vertex_orbit, parse_membership(&line));
+        membership.push(vertex_orbit);
+    }
+
+    Ok(membership)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_orbits_input() -> Result<(), Error> {
+        let orbits = "vertex,orbit\n0,0\n1,1\n2,1\n\n3,0\n";
+        let parsed = parse_orbits_input(BufReader::new(orbits.as_bytes()))?;
+        assert_eq!(vec![(0, 0), (1, 1), (2, 1), (3, 0)], parsed);
+
+        let negative = "vertex,orbit\n0,-1\n";
+        assert!(parse_orbits_input(BufReader::new(negative.as_bytes())).is_err());
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
 //! Parser for graphs in a specific format.
 //! The supported format is based of data from
 //! https://snap.stanford.edu/data/ .
+//! The vertex ids of SNAP files are often sparse, i.e. some are
+//! larger than the number of nodes in the header. They are then
+//! compacted to dense indices and the original ids are kept.
 
-use std::io::BufRead;
+use std::{collections::HashMap, io::BufRead};
 
 use crate::{
     get_line_parse, get_line_recognize,
@@ -16,11 +19,14 @@ fn parse_size_comment(input: Input<'_>) -> ParseResult<'_, usize> {
     use nom::{
         bytes::complete::tag,
         character::complete::{char, u64},
-        combinator::map,
+        combinator::{map, verify},
         sequence::{preceded, terminated, tuple},
     };
 
-    let size_parser = preceded(tag(" Nodes: "), u64);
+    let size_parser = preceded(
+        tag(" Nodes: "),
+        verify(u64, |size| *size <= VertexIndex::MAX as u64),
+    );
     let edges_parser = tuple((tag(" Edges: "), u64));
     let comment_parser = preceded(char('#'), terminated(size_parser, edges_parser));
 
@@ -47,7 +53,42 @@ fn parse_edge(input: Input<'_>) -> ParseResult<'_, (VertexIndex, VertexIndex)> {
     pair(terminated(i32, multispace1), i32)(input)
 }
 
+/// Map the vertex ids of the edges to dense indices in the order of the ids.
+/// Vertices that the header counts but no edge mentions are isolated and get
+/// made up ids after the largest one. Returns the original id of each index.
+fn compact_ids(graph_size: usize, edges: &mut [(VertexIndex, VertexIndex)]) -> Vec<VertexIndex> {
+    let mut ids = edges
+        .iter()
+        .flat_map(|(start, end)| [*start, *end])
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let indices = ids
+        .iter()
+        .enumerate()
+        .map(|(index, id)| (*id, index as VertexIndex))
+        .collect::<HashMap<_, _>>();
+    for (start, end) in edges.iter_mut() {
+        *start = indices[start];
+        *end = indices[end];
+    }
+
+    let largest_id = ids.last().copied().unwrap_or(-1);
+    let isolated = graph_size.saturating_sub(ids.len());
+    ids.extend((1..=isolated).map(|offset| largest_id.saturating_add(offset as VertexIndex)));
+    ids
+}
+
 pub fn parse_txt_input<B: BufRead>(input: B) -> Result<Graph, Error> {
+    parse_txt_input_with_ids(input).map(|(graph, _)| graph)
+}
+
+/// Same as `parse_txt_input`, but also returns the original
+/// vertex ids if they had to be compacted.
+pub fn parse_txt_input_with_ids<B: BufRead>(
+    input: B,
+) -> Result<(Graph, Option<Vec<VertexIndex>>), Error> {
     use nom::combinator::eof;
 
     let mut lines = input.lines().peekable();
@@ -57,19 +98,24 @@ pub fn parse_txt_input<B: BufRead>(input: B) -> Result<Graph, Error> {
     get_line_parse!(lines, graph_size, parse_size_comment);
     get_line_recognize!(lines, parse_meaningless_comment);
 
-    let mut graph = Graph::new_ordered(graph_size);
+    let mut edges = Vec::new();
 
     for line in lines {
         let line = line?;
         parse_single_line!(start_end, parse_edge(&line));
-        let (start, end) = start_end;
-
-        graph
-            .add_edge(start, end)
-            .expect("Edge to non existing vertex! Graph too small!");
+        edges.push(start_end);
     }
 
-    Ok(graph)
+    let sparse = edges.iter().any(|(start, end)| {
+        [*start, *end]
+            .iter()
+            .any(|id| *id < 0 || *id as usize >= graph_size)
+    });
+    let ids = sparse.then(|| compact_ids(graph_size, &mut edges));
+    let graph_size = ids.as_ref().map_or(graph_size, Vec::len);
+
+    let graph = Graph::from_edges(graph_size, edges)?;
+    Ok((graph, ids))
 }
 
 #[cfg(test)]
@@ -119,4 +165,33 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_txt_input_sparse_ids() -> Result<(), Error> {
+        let txt = "# Undirected graph
+# Sparse ids
+# Nodes: 5 Edges: 3
+# FromNodeId	ToNodeId
+10	3
+3	42
+7	42
+";
+        let buf = BufReader::new(txt.as_bytes());
+        let (parsed, ids) = parse_txt_input_with_ids(buf)?;
+
+        // 3, 7, 10, 42 and one isolated vertex.
+        let mut graph = Graph::new_ordered(5);
+        graph.add_edge(2, 0)?;
+        graph.add_edge(0, 3)?;
+        graph.add_edge(1, 3)?;
+
+        assert_eq!(graph, parsed);
+        assert_eq!(Some(vec![3, 7, 10, 42, 43]), ids);
+
+        let dense = "#\n#\n# Nodes: 2 Edges: 1\n#\n0\t1\n";
+        let (_, ids) = parse_txt_input_with_ids(BufReader::new(dense.as_bytes()))?;
+        assert_eq!(None, ids);
+
+        Ok(())
+    }
 }
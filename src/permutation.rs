@@ -1,4 +1,4 @@
-use std::convert::TryInto;
+use std::{convert::TryInto, fmt, mem, str::FromStr};
 
 use itertools::Itertools;
 #[cfg(test)]
@@ -9,6 +9,28 @@ use crate::graph::VertexIndex;
 #[derive(Debug)]
 pub struct IncompatiblePermutationSizes;
 
+/// Error for images or cycles that aren't a bijection of the points `0..n`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NotBijective;
+
+impl fmt::Display for NotBijective {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a bijection")
+    }
+}
+
+/// Error for text that is not a valid permutation in cycle notation.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidCycleNotation(pub String);
+
+impl fmt::Display for InvalidCycleNotation {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cycle notation: {}", self.0)
+    }
+}
+
 #[derive(Debug, Clone, PartialOrd, Ord)]
 pub struct Permutation {
     pub raw: Vec<VertexIndex>,
@@ -23,24 +45,49 @@ impl PartialEq for Permutation {
 
 impl Eq for Permutation {}
 
+/// Whether each of the points `0..size` occurs at most once and no other point occurs.
+/// For exactly `size` points, this means that they are a bijection of `0..size`.
+fn are_distinct_points<'a>(points: impl IntoIterator<Item = &'a VertexIndex>, size: usize) -> bool {
+    let mut seen = vec![false; size];
+    points.into_iter().all(|point| {
+        (0..size as VertexIndex).contains(point) && !mem::replace(&mut seen[*point as usize], true)
+    })
+}
+
 impl Permutation {
-    /// Builds a new permutation from the given vector but doesn't
-    /// compute the cycles explicitly.
-    pub fn new(raw: Vec<VertexIndex>) -> Self {
-        Permutation { raw, cycles: None }
+    /// Builds a new permutation from the given images but doesn't
+    /// compute the cycles explicitly. Fails unless the images are
+    /// a bijection of `0..n`, e.g. for malformed external input.
+    pub fn new(raw: Vec<VertexIndex>) -> Result<Self, NotBijective> {
+        if !are_distinct_points(&raw, raw.len()) {
+            return Err(NotBijective);
+        }
+        Ok(Permutation { raw, cycles: None })
     }
 
-    /// Builds a new permutation from the given vector and
+    /// Builds a new permutation from the given images and
     /// compute the cycles explicitly.
-    pub fn new_with_cycles(raw: Vec<VertexIndex>) -> Self {
-        let mut new = Permutation { raw, cycles: None };
+    pub fn new_with_cycles(raw: Vec<VertexIndex>) -> Result<Self, NotBijective> {
+        let mut new = Self::new(raw)?;
         new.compute_cycles();
-        new
+        Ok(new)
+    }
+
+    pub fn identity(size: usize) -> Self {
+        Permutation {
+            raw: (0..size as VertexIndex).collect(),
+            cycles: Some(Vec::new()),
+        }
     }
 
     /// Builds a new permutation form the given cycles.
     /// The cycles need not contain single element cycles as well.
-    pub fn from_cycles(cycles: Vec<Vec<VertexIndex>>, size: usize) -> Self {
+    /// Fails if a point occurs twice or isn't in `0..size`.
+    pub fn from_cycles(cycles: Vec<Vec<VertexIndex>>, size: usize) -> Result<Self, NotBijective> {
+        if !are_distinct_points(cycles.iter().flatten(), size) {
+            return Err(NotBijective);
+        }
+
         let mut raw = (0..size as VertexIndex).collect_vec();
 
         for cycle in cycles.iter().filter(|cycle| cycle.len() > 1) {
@@ -49,10 +96,10 @@ impl Permutation {
 
         let cycles = cycles.into_iter().filter(|cycle| cycle.len() > 1).collect();
 
-        Permutation {
+        Ok(Permutation {
             raw,
             cycles: Some(cycles),
-        }
+        })
     }
 
     /// Standard composition of permutations where the right
@@ -142,6 +189,10 @@ impl Permutation {
         self.raw.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
     /// Computes the order of the permutation. This is the same as the size
     /// of the subgroup generated by this permutation.
     /// The order is the least common multiple of all cycle lengths as each
@@ -226,6 +277,10 @@ impl Permutation {
     }
 
     fn compute_cycles(&mut self) {
+        self.cycles = Some(self.collect_cycles());
+    }
+
+    fn collect_cycles(&self) -> Vec<Vec<VertexIndex>> {
         let mut cycles = Vec::new();
 
         for (index, value) in self.raw.iter().enumerate() {
@@ -238,7 +293,66 @@ impl Permutation {
             }
         }
 
-        self.cycles = Some(cycles);
+        cycles
+    }
+
+    /// Parses a permutation in cycle notation, e.g. `(0 1 2)(3 4)`.
+    /// Elements within a cycle are separated by whitespace or commas and
+    /// the identity is written as `()`. Without a given size, the permutation
+    /// is only as big as its biggest moved element requires.
+    pub fn from_cycle_notation(
+        input: &str,
+        size: Option<usize>,
+    ) -> Result<Self, InvalidCycleNotation> {
+        let invalid = || InvalidCycleNotation(input.to_string());
+        let trimmed = input.trim();
+
+        if !trimmed.starts_with('(') || !trimmed.ends_with(')') {
+            return Err(invalid());
+        }
+
+        let mut cycles = Vec::new();
+        for cycle in trimmed[1..trimmed.len() - 1].split(')') {
+            let cycle = cycle.trim_start().strip_prefix('(').unwrap_or(cycle);
+            if cycle.contains('(') {
+                return Err(invalid());
+            }
+
+            let cycle: Vec<VertexIndex> = cycle
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|element| !element.is_empty())
+                .map(|element| element.parse::<VertexIndex>())
+                .try_collect()
+                .map_err(|_| invalid())?;
+            cycles.push(cycle);
+        }
+
+        let needed_size = cycles
+            .iter()
+            .flatten()
+            .max()
+            .filter(|max| **max >= 0)
+            .map_or(0, |max| *max as usize + 1);
+        Self::from_cycles(cycles, size.unwrap_or(needed_size)).map_err(|_| invalid())
+    }
+
+    /// Parses the images of the points separated by whitespace, e.g. `1 0 2`,
+    /// as written by `images_string`. None if they aren't a permutation of
+    /// the given size.
+    pub fn from_images_str(images: &str, size: usize) -> Option<Self> {
+        let raw: Vec<VertexIndex> = images
+            .split_whitespace()
+            .map(|image| image.parse().ok())
+            .collect::<Option<_>>()?;
+        if raw.len() != size {
+            return None;
+        }
+        Self::new(raw).ok()
+    }
+
+    /// The images of the points separated by spaces, e.g. `1 0 2`.
+    pub fn images_string(&self) -> String {
+        self.raw.iter().join(" ")
     }
 
     pub fn is_identity(&self) -> bool {
@@ -255,7 +369,34 @@ where
     <T as TryInto<VertexIndex>>::Error: std::fmt::Debug,
 {
     fn from(raw: Vec<T>) -> Self {
-        Permutation::new(raw.into_iter().map(|t| t.try_into()).try_collect().unwrap())
+        Permutation::new(raw.into_iter().map(|t| t.try_into()).try_collect().unwrap()).unwrap()
+    }
+}
+
+/// Formats the permutation in cycle notation without fixed points,
+/// e.g. `(0 1 2)(3 4)`, and the identity as `()`.
+impl fmt::Display for Permutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Cached cycles might not be normalized (see `from_cycles`).
+        let cycles = self.collect_cycles();
+
+        if cycles.is_empty() {
+            return write!(f, "()");
+        }
+
+        for cycle in cycles {
+            write!(f, "({})", cycle.iter().join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Permutation {
+    type Err = InvalidCycleNotation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_cycle_notation(s, None)
     }
 }
 
@@ -298,7 +439,7 @@ mod test {
     #[test]
     fn compute_cycles_test() {
         let raw = vec![0, 1, 3, 2];
-        let mut perm = Permutation::new(raw.clone());
+        let mut perm = Permutation::new(raw.clone()).unwrap();
         perm.compute_cycles();
         assert_eq!(raw, perm.raw);
         assert_eq!(vec![vec![2, 3]], perm.cycles.unwrap());
@@ -306,22 +447,22 @@ mod test {
 
     #[test]
     fn get_subgroup_size() {
-        let mut perm = Permutation::new_with_cycles(vec![4, 0, 1, 5, 7, 3, 2, 6]); //(0 4 7 6 2 1) (3 5)
+        let mut perm = Permutation::new_with_cycles(vec![4, 0, 1, 5, 7, 3, 2, 6]).unwrap(); //(0 4 7 6 2 1) (3 5)
         let subgroup_size = perm.get_order();
         assert_eq!(6, subgroup_size);
 
-        let mut perm2 = Permutation::new(vec![1, 2, 0, 4, 3]); // (0 1 2) (3 4)
+        let mut perm2 = Permutation::new(vec![1, 2, 0, 4, 3]).unwrap(); // (0 1 2) (3 4)
         let subgroup_size2 = perm2.get_order();
         assert_eq!(6, subgroup_size2);
 
-        let mut perm3 = Permutation::new(vec![1, 2, 0, 4, 3, 8, 5, 6, 7]); // (0 1 2) (3 4) (5 8 7 6)
+        let mut perm3 = Permutation::new(vec![1, 2, 0, 4, 3, 8, 5, 6, 7]).unwrap(); // (0 1 2) (3 4) (5 8 7 6)
         let subgroup_size3 = perm3.get_order();
         assert_eq!(12, subgroup_size3);
     }
 
     #[test]
     fn evaluate_test() {
-        let perm = Permutation::new(vec![0, 2, 1]);
+        let perm = Permutation::new(vec![0, 2, 1]).unwrap();
         assert_eq!(0, perm.evaluate(&0).unwrap());
         assert_eq!(2, perm.evaluate(&1).unwrap());
         assert_eq!(1, perm.evaluate(&2).unwrap());
@@ -330,7 +471,7 @@ mod test {
 
     #[test]
     fn apply_test() {
-        let perm = Permutation::new(vec![4, 2, 1, 0, 3]);
+        let perm = Permutation::new(vec![4, 2, 1, 0, 3]).unwrap();
         let data = vec![0, 2, 3, 4, 5, 1];
         let permuted_data: Vec<i32> = perm._apply(data.into_iter()).collect();
         assert_eq!(vec![4, 1, 0, 3, 5, 2], permuted_data);
@@ -339,47 +480,117 @@ mod test {
     #[test]
     fn from_cycles_test() {
         let cycles = vec![vec![1, 2, 3], vec![0], vec![5, 6], vec![4]];
-        let perm = Permutation::from_cycles(cycles, 7);
-        let expected_perm = Permutation::new_with_cycles(vec![0, 2, 3, 1, 4, 6, 5]);
+        let perm = Permutation::from_cycles(cycles, 7).unwrap();
+        let expected_perm = Permutation::new_with_cycles(vec![0, 2, 3, 1, 4, 6, 5]).unwrap();
         assert_eq!(expected_perm, perm);
+        assert_eq!(
+            Permutation::new(vec![0, 1, 2]),
+            Ok(Permutation::identity(3))
+        );
+    }
+
+    #[test]
+    fn test_not_bijective() {
+        // Each of them made `get_cycle` loop forever or panic.
+        assert_eq!(Err(NotBijective), Permutation::new(vec![1, 1, 0]));
+        assert_eq!(Err(NotBijective), Permutation::new(vec![1, 3, 0]));
+        assert_eq!(Err(NotBijective), Permutation::new(vec![-1, 0]));
+        assert_eq!(
+            Err(NotBijective),
+            Permutation::from_cycles(vec![vec![0, 1], vec![1, 2]], 3)
+        );
+        assert_eq!(
+            Err(NotBijective),
+            Permutation::from_cycles(vec![vec![0, 1], vec![1]], 3)
+        );
+        assert_eq!(
+            Err(NotBijective),
+            Permutation::from_cycles(vec![vec![0, 3]], 3)
+        );
+        assert!(Permutation::new(Vec::new()).is_ok());
+        assert_eq!(
+            Err(InvalidCycleNotation("(-1)".to_string())),
+            "(-1)".parse::<Permutation>()
+        );
     }
 
     #[test]
     fn test_nth_power() {
-        let mut base = Permutation::new(vec![2, 7, 3, 4, 5, 6, 0, 1]);
-        let expected_double = Permutation::new(vec![3, 1, 4, 5, 6, 0, 2, 7]);
+        let mut base = Permutation::new(vec![2, 7, 3, 4, 5, 6, 0, 1]).unwrap();
+        let expected_double = Permutation::new(vec![3, 1, 4, 5, 6, 0, 2, 7]).unwrap();
 
         base.nth_power(2);
         assert_eq!(expected_double, base);
 
-        let expected_quadrupel = Permutation::new(vec![5, 1, 6, 0, 2, 3, 4, 7]);
+        let expected_quadrupel = Permutation::new(vec![5, 1, 6, 0, 2, 3, 4, 7]).unwrap();
         base.nth_power(2);
         assert_eq!(expected_quadrupel, base);
     }
 
     #[test]
     fn test_nth_power_of() {
-        let base = Permutation::new(vec![2, 7, 3, 4, 5, 6, 0, 1]);
+        let base = Permutation::new(vec![2, 7, 3, 4, 5, 6, 0, 1]).unwrap();
 
         let once = base.nth_power_of(1);
         assert_eq!(base, once);
 
-        let expected_double = Permutation::new(vec![3, 1, 4, 5, 6, 0, 2, 7]);
+        let expected_double = Permutation::new(vec![3, 1, 4, 5, 6, 0, 2, 7]).unwrap();
         assert_eq!(expected_double, base.nth_power_of(2));
 
-        let expected_tripple = Permutation::new(vec![4, 7, 5, 6, 0, 2, 3, 1]);
+        let expected_tripple = Permutation::new(vec![4, 7, 5, 6, 0, 2, 3, 1]).unwrap();
         assert_eq!(expected_tripple, base.nth_power_of(3));
 
-        let expected_quadrupel = Permutation::new(vec![5, 1, 6, 0, 2, 3, 4, 7]);
+        let expected_quadrupel = Permutation::new(vec![5, 1, 6, 0, 2, 3, 4, 7]).unwrap();
         assert_eq!(expected_quadrupel, base.nth_power_of(4));
 
-        let expected_quintuple = Permutation::new(vec![6, 7, 0, 2, 3, 4, 5, 1]);
+        let expected_quintuple = Permutation::new(vec![6, 7, 0, 2, 3, 4, 5, 1]).unwrap();
         assert_eq!(expected_quintuple, base.nth_power_of(5));
 
-        let expected_ident = Permutation::from_cycles(vec![], 8);
+        let expected_ident = Permutation::from_cycles(vec![], 8).unwrap();
         assert_eq!(expected_ident, base.nth_power_of(6));
 
         assert_eq!(base, base.nth_power_of(7));
         assert_eq!(base, base.nth_power_of_mod(7));
     }
+
+    #[test]
+    fn test_images_round_trip() {
+        let permutation = Permutation::new(vec![1, 0, 2]).unwrap();
+        assert_eq!("1 0 2", permutation.images_string());
+        assert_eq!(
+            Some(permutation),
+            Permutation::from_images_str(" 1\t0 2 ", 3)
+        );
+        assert_eq!(None, Permutation::from_images_str("1 0 2", 4));
+        assert_eq!(None, Permutation::from_images_str("1 1 2", 3));
+        assert_eq!(None, Permutation::from_images_str("1 x 2", 3));
+    }
+
+    #[test]
+    fn test_cycle_notation_round_trip() {
+        let perm = Permutation::new(vec![1, 2, 0, 4, 3, 5]).unwrap();
+        assert_eq!("(0 1 2)(3 4)", perm.to_string());
+
+        let parsed: Permutation = perm.to_string().parse().unwrap();
+        assert_eq!(Permutation::new(vec![1, 2, 0, 4, 3]).unwrap(), parsed);
+        let parsed = Permutation::from_cycle_notation(&perm.to_string(), Some(6)).unwrap();
+        assert_eq!(perm, parsed);
+
+        let perm = Permutation::from_cycles(vec![vec![4, 2, 3]], 5).unwrap();
+        assert_eq!("(2 3 4)", perm.to_string());
+        let parsed: Permutation = "(2, 3, 4)".parse().unwrap();
+        assert_eq!(perm, parsed);
+
+        let identity = Permutation::from_cycles(vec![], 3).unwrap();
+        assert_eq!("()", identity.to_string());
+        let parsed = Permutation::from_cycle_notation("()", Some(3)).unwrap();
+        assert_eq!(identity, parsed);
+
+        assert!(" (1 0) (2 3) ".parse::<Permutation>().is_ok());
+        assert!("(0 1".parse::<Permutation>().is_err());
+        assert!("(0 1)x(2 3)".parse::<Permutation>().is_err());
+        assert!("(0 1)(1 2)".parse::<Permutation>().is_err());
+        assert!("(0 -1)".parse::<Permutation>().is_err());
+        assert!(Permutation::from_cycle_notation("(0 4)", Some(3)).is_err());
+    }
 }
@@ -0,0 +1,116 @@
+//! Cheap predictions of the descriptiveness of a quotient
+//! that are consulted before the SAT solver is called.
+
+use std::fmt;
+
+use crate::{quotient::QuotientGraph, Settings};
+
+/// Features of a quotient graph that a predictor can use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotientFeatures {
+    pub orbits: usize,
+    pub max_orbit_size: usize,
+    /// Ratio of quotient edges to the possible edges between the orbits.
+    pub density: f64,
+}
+
+impl QuotientFeatures {
+    pub fn from_quotient(quotient: &QuotientGraph) -> Self {
+        let orbits = quotient.quotient_graph.size();
+        let possible_edges = orbits * orbits.saturating_sub(1);
+        let density = if possible_edges == 0 {
+            0.
+        } else {
+            quotient.quotient_graph.number_edges() as f64 / possible_edges as f64
+        };
+
+        QuotientFeatures {
+            orbits,
+            max_orbit_size: quotient.get_orbit_sizes().1,
+            density,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prediction {
+    Descriptive,
+    NonDescriptive,
+    /// Not confident enough to predict anything.
+    Unsure,
+}
+
+/// Predicts the descriptiveness of quotients from their features.
+pub trait Predictor: fmt::Debug {
+    fn predict(&self, features: &QuotientFeatures) -> Prediction;
+}
+
+/// Predicts quotients with big orbits and many edges between them to be
+/// non-descriptive, as their transversals have to satisfy many constraints.
+/// Quotients with only singleton orbits are always descriptive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdPredictor {
+    pub max_orbit_size: usize,
+    pub density: f64,
+}
+
+impl Default for ThresholdPredictor {
+    fn default() -> Self {
+        ThresholdPredictor {
+            max_orbit_size: 8,
+            density: 0.5,
+        }
+    }
+}
+
+impl Predictor for ThresholdPredictor {
+    fn predict(&self, features: &QuotientFeatures) -> Prediction {
+        if features.max_orbit_size <= 1 {
+            Prediction::Descriptive
+        } else if features.max_orbit_size >= self.max_orbit_size && features.density >= self.density
+        {
+            Prediction::NonDescriptive
+        } else {
+            Prediction::Unsure
+        }
+    }
+}
+
+/// The confident prediction of the configured predictor for the quotient, if any.
+pub fn predict(quotient: &QuotientGraph, settings: &Settings) -> Option<Prediction> {
+    let predictor = settings.predictor.as_ref()?;
+    match predictor.predict(&QuotientFeatures::from_quotient(quotient)) {
+        Prediction::Unsure => None,
+        prediction => Some(prediction),
+    }
+}
+
+/// Whether the SAT call can be skipped for a quotient with this prediction.
+/// Only non-descriptive predictions are trusted, as a descriptive quotient
+/// ends the search and should therefore be proven.
+pub fn skip_solving(prediction: Option<Prediction>, settings: &Settings) -> bool {
+    settings.skip_predicted && prediction == Some(Prediction::NonDescriptive)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_threshold_predictor() {
+        let predictor = ThresholdPredictor::default();
+        let features = |max_orbit_size, density| QuotientFeatures {
+            orbits: 4,
+            max_orbit_size,
+            density,
+        };
+
+        assert_eq!(Prediction::Descriptive, predictor.predict(&features(1, 1.)));
+        assert_eq!(
+            Prediction::NonDescriptive,
+            predictor.predict(&features(8, 0.5))
+        );
+        assert_eq!(Prediction::Unsure, predictor.predict(&features(8, 0.25)));
+        assert_eq!(Prediction::Unsure, predictor.predict(&features(2, 1.)));
+    }
+}
@@ -0,0 +1,97 @@
+//! Typed progress events of a run, so that a GUI or notebook
+//! frontend can show live progress without parsing stdout.
+
+use std::{
+    fmt,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+};
+
+use crate::Outcome;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The input graph with this many vertices is ready.
+    ParsingDone {
+        vertices: usize,
+    },
+    GeneratorsComputed {
+        n: usize,
+    },
+    /// The quotient induced by the generators with these indices was checked.
+    QuotientChecked {
+        descriptive: bool,
+        subset: Vec<usize>,
+    },
+    /// Core mode found a non-descriptive core with this many orbits.
+    CoreFound {
+        size: usize,
+    },
+    Finished {
+        outcome: Outcome,
+    },
+}
+
+/// Receives the progress events of a run. It is called from the
+/// thread that does the work, so it should return quickly.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    /// A callback that sends the events to the returned receiver.
+    /// Events are dropped once the receiver is gone.
+    pub fn channel() -> (Self, Receiver<ProgressEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let callback = ProgressCallback::new(move |event| {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(event);
+            }
+        });
+        (callback, receiver)
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_progress_channel() {
+        let (callback, receiver) = ProgressCallback::channel();
+        callback.report(ProgressEvent::GeneratorsComputed { n: 3 });
+        callback.clone().report(ProgressEvent::Finished {
+            outcome: Outcome::Asymmetric,
+        });
+
+        assert_eq!(
+            vec![
+                ProgressEvent::GeneratorsComputed { n: 3 },
+                ProgressEvent::Finished {
+                    outcome: Outcome::Asymmetric
+                },
+            ],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+
+        drop(receiver);
+        callback.report(ProgressEvent::CoreFound { size: 2 });
+    }
+}
@@ -0,0 +1,335 @@
+//! Python bindings for the main pipeline, built with the `python` feature,
+//! e.g. with `maturin develop`. The functions mirror the steps of a run:
+//!
+//! ```python
+//! import dqg
+//! graph = dqg.read_graph("example.dre")
+//! generators = dqg.compute_generators(graph)
+//! orbits = dqg.orbits(graph, generators)
+//! transversal = dqg.find_transversal(graph, orbits)
+//! core = dqg.smallest_core(graph, orbits) if transversal is None else None
+//! ```
+//!
+//! Generators are lists with the image of each vertex, orbits map each vertex
+//! to the smallest vertex of its orbit and transversals are lists of
+//! (orbit, vertex) pairs, just like in the library.
+
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::{PyKeyboardInterrupt, PyRuntimeError, PyValueError},
+    prelude::*,
+};
+
+use crate::{
+    encoding::{OrbitEncoding, QuotientGraphEncoding},
+    full_quotient::{is_full_quotient_descriptive, DescriptiveOutcome},
+    graph::{Colour, Graph, VertexIndex},
+    input::read_graph_file,
+    permutation::Permutation,
+    quotient::{compute_generators as compute_generators_of, empty_orbits, generate_orbits},
+    quotient::{Orbits, QuotientGraph},
+    statistics::NoStatistics,
+    transversal::{find_transversal as find_transversal_of, Transversal},
+    EncodingOptions, Error, NautyTraces, Settings,
+};
+
+fn to_py_err(error: Error) -> PyErr {
+    let message = format!("{:?}", error);
+    match error {
+        Error::GraphError(_)
+        | Error::ParseError(_)
+        | Error::CLIParseError(_)
+        | Error::MetricError(_)
+        | Error::ConfigError(_) => PyValueError::new_err(message),
+        Error::Interrupted => PyKeyboardInterrupt::new_err(message),
+        _ => PyRuntimeError::new_err(message),
+    }
+}
+
+/// An input graph with optionally coloured vertices.
+#[pyclass(name = "Graph")]
+#[derive(Clone)]
+pub struct PyGraph {
+    graph: Graph,
+}
+
+#[pymethods]
+impl PyGraph {
+    /// Undirected graph with the vertices 0..size and the given edges.
+    #[new]
+    #[pyo3(signature = (size, edges, colours = None))]
+    fn new(
+        size: usize,
+        edges: Vec<(VertexIndex, VertexIndex)>,
+        colours: Option<Vec<Colour>>,
+    ) -> PyResult<Self> {
+        let mut graph = Graph::from_edges(size, edges).map_err(|e| to_py_err(e.into()))?;
+        if let Some(colours) = colours {
+            graph
+                .set_colours(&colours)
+                .map_err(|e| to_py_err(e.into()))?;
+        }
+        graph.minimize();
+        Ok(PyGraph { graph })
+    }
+
+    #[getter]
+    fn size(&self) -> usize {
+        self.graph.size()
+    }
+
+    /// Both directions of each edge.
+    fn edges(&self) -> Vec<(VertexIndex, VertexIndex)> {
+        self.graph.iterate_edges().collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Graph(size={}, edges={})",
+            self.graph.size(),
+            self.graph.number_edges()
+        )
+    }
+}
+
+impl PyGraph {
+    /// A sorted copy to compute on, as the lookups need sorted edges.
+    fn sorted(&self) -> Graph {
+        let mut graph = self.graph.clone();
+        graph.sort();
+        graph
+    }
+}
+
+/// Read a graph file in any of the input formats, chosen by its extension.
+#[pyfunction]
+#[pyo3(signature = (path, graph_size = None))]
+fn read_graph(py: Python<'_>, path: PathBuf, graph_size: Option<usize>) -> PyResult<PyGraph> {
+    py.allow_threads(|| read_graph_file(&path, graph_size))
+        .map(|graph| PyGraph { graph })
+        .map_err(to_py_err)
+}
+
+fn parse_backend(backend: Option<&str>, graph: &Graph) -> PyResult<NautyTraces> {
+    match backend {
+        None if graph.is_sparse() => Ok(NautyTraces::SparseNauty),
+        None | Some("nauty") => Ok(NautyTraces::Nauty),
+        Some("sparse-nauty") => Ok(NautyTraces::SparseNauty),
+        Some("traces") => Ok(NautyTraces::Traces),
+        Some(other) => Err(PyValueError::new_err(format!(
+            "Unknown backend {}, expected nauty, sparse-nauty or traces",
+            other
+        ))),
+    }
+}
+
+/// Generators of the automorphism group, computed by nauty or Traces.
+/// The backend defaults to dense or sparse nauty, like the CLI does.
+#[pyfunction]
+#[pyo3(signature = (graph, colored = true, backend = None))]
+fn compute_generators(
+    py: Python<'_>,
+    graph: &PyGraph,
+    colored: bool,
+    backend: Option<&str>,
+) -> PyResult<Vec<Vec<VertexIndex>>> {
+    let backend = parse_backend(backend, &graph.graph)?;
+    let mut graph = graph.graph.clone();
+    let generators = py.allow_threads(|| {
+        // Settings aren't Sync, so they are built without the GIL.
+        let settings = Settings {
+            colored_graph: colored,
+            nauyt_or_traces: backend,
+            ..Default::default()
+        };
+        compute_generators_of(&mut graph, &settings, &mut NoStatistics)
+    });
+    Ok(generators
+        .into_iter()
+        .map(|generator| generator.raw)
+        .collect())
+}
+
+fn to_orbits(graph: &Graph, generators: Vec<Vec<VertexIndex>>) -> PyResult<Orbits> {
+    let mut permutations = generators
+        .into_iter()
+        .map(|raw| {
+            if raw.len() != graph.size() {
+                return Err(PyValueError::new_err(format!(
+                    "Generator with {} images for a graph with {} vertices",
+                    raw.len(),
+                    graph.size()
+                )));
+            }
+            Permutation::new(raw)
+                .map_err(|e| PyValueError::new_err(format!("Invalid generator: {}", e)))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(if permutations.is_empty() {
+        empty_orbits(graph.size())
+    } else {
+        generate_orbits(&mut permutations)
+    })
+}
+
+/// The orbits are canonical as computed by `orbits`, i.e. each
+/// vertex is mapped to the smallest vertex of its orbit.
+fn check_orbits(graph: &Graph, orbits: &[VertexIndex]) -> PyResult<()> {
+    let valid = orbits.len() == graph.size()
+        && orbits.iter().enumerate().all(|(vertex, orbit)| {
+            (0..=vertex as VertexIndex).contains(orbit) && orbits[*orbit as usize] == *orbit
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(PyValueError::new_err(
+            "The orbits need to map each vertex of the graph to the smallest vertex of its orbit",
+        ))
+    }
+}
+
+/// The orbits of the group generated by the generators.
+#[pyfunction]
+fn orbits(graph: &PyGraph, generators: Vec<Vec<VertexIndex>>) -> PyResult<Orbits> {
+    to_orbits(&graph.graph, generators)
+}
+
+/// The edges of the quotient graph induced by the orbits, between the
+/// smallest vertices of the orbits.
+#[pyfunction]
+fn quotient_edges(graph: &PyGraph, orbits: Orbits) -> PyResult<Vec<(VertexIndex, VertexIndex)>> {
+    check_orbits(&graph.graph, &orbits)?;
+    let quotient = QuotientGraph::from_graph_orbits(&graph.sorted(), orbits);
+    Ok(quotient.quotient_graph.iterate_edges().collect())
+}
+
+/// A consistent transversal of the quotient induced by the orbits,
+/// or None if the quotient isn't descriptive.
+#[pyfunction]
+fn find_transversal(
+    py: Python<'_>,
+    graph: &PyGraph,
+    orbits: Orbits,
+) -> PyResult<Option<Transversal>> {
+    check_orbits(&graph.graph, &orbits)?;
+    let graph = graph.sorted();
+    py.allow_threads(|| find_transversal_of(&graph, orbits, &EncodingOptions::default()))
+        .map_err(to_py_err)
+}
+
+/// Whether the quotient induced by the orbits is descriptive.
+#[pyfunction]
+fn is_descriptive(py: Python<'_>, graph: &PyGraph, orbits: Orbits) -> PyResult<bool> {
+    find_transversal(py, graph, orbits).map(|transversal| transversal.is_some())
+}
+
+/// The smallest set of orbits, with their vertices, whose quotient has no consistent
+/// transversal, or None if the quotient is descriptive. Only sets with at most
+/// `max_size` orbits are checked.
+#[pyfunction]
+#[pyo3(signature = (graph, orbits, max_size = None))]
+fn smallest_core(
+    py: Python<'_>,
+    graph: &PyGraph,
+    orbits: Orbits,
+    max_size: Option<usize>,
+) -> PyResult<Option<Vec<OrbitEncoding>>> {
+    check_orbits(&graph.graph, &orbits)?;
+    let graph = graph.sorted();
+    let core = py
+        .allow_threads(|| {
+            let quotient = QuotientGraph::from_graph_orbits(&graph, orbits);
+            let max_size = max_size.unwrap_or_else(|| quotient.iter_orbits().count());
+            quotient.search_non_descriptive_core(&graph, 1..=max_size, true)
+        })
+        .map_err(to_py_err)?;
+    Ok(core.map(|QuotientGraphEncoding(_, core)| core))
+}
+
+/// The result of `check_full_quotient`.
+#[pyclass(name = "FullQuotient", get_all)]
+pub struct PyFullQuotient {
+    /// Whether the quotient is descriptive, which asymmetric graphs trivially are.
+    descriptive: bool,
+    /// None for asymmetric graphs.
+    orbits: Option<Orbits>,
+    /// A consistent transversal of a descriptive quotient.
+    transversal: Option<Transversal>,
+    /// The orbits without a consistent transversal of a non-descriptive quotient.
+    core: Option<Vec<OrbitEncoding>>,
+}
+
+#[pymethods]
+impl PyFullQuotient {
+    fn __repr__(&self) -> String {
+        format!(
+            "FullQuotient(descriptive={}, orbits={:?}, transversal={:?}, core={:?})",
+            self.descriptive, self.orbits, self.transversal, self.core
+        )
+    }
+}
+
+/// Check the quotient of the full automorphism group in one call.
+#[pyfunction]
+fn check_full_quotient(py: Python<'_>, graph: &PyGraph) -> PyResult<PyFullQuotient> {
+    let outcome = py
+        .allow_threads(|| is_full_quotient_descriptive(&graph.graph))
+        .map_err(to_py_err)?;
+    Ok(match outcome {
+        DescriptiveOutcome::Asymmetric => PyFullQuotient {
+            descriptive: true,
+            orbits: None,
+            transversal: None,
+            core: None,
+        },
+        DescriptiveOutcome::Descriptive {
+            orbits,
+            transversal,
+        } => PyFullQuotient {
+            descriptive: true,
+            orbits: Some(orbits),
+            transversal: Some(transversal),
+            core: None,
+        },
+        DescriptiveOutcome::NonDescriptive { orbits, core } => PyFullQuotient {
+            descriptive: false,
+            orbits: Some(orbits),
+            transversal: None,
+            core: Some(core),
+        },
+    })
+}
+
+#[pymodule]
+fn dqg(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGraph>()?;
+    module.add_class::<PyFullQuotient>()?;
+    module.add_function(wrap_pyfunction!(read_graph, module)?)?;
+    module.add_function(wrap_pyfunction!(compute_generators, module)?)?;
+    module.add_function(wrap_pyfunction!(orbits, module)?)?;
+    module.add_function(wrap_pyfunction!(quotient_edges, module)?)?;
+    module.add_function(wrap_pyfunction!(find_transversal, module)?)?;
+    module.add_function(wrap_pyfunction!(is_descriptive, module)?)?;
+    module.add_function(wrap_pyfunction!(smallest_core, module)?)?;
+    module.add_function(wrap_pyfunction!(check_full_quotient, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_orbits() {
+        let graph = Graph::new_ordered(3);
+        assert!(check_orbits(&graph, &[0, 0, 2]).is_ok());
+        assert!(check_orbits(&graph, &[0, 1, 0]).is_ok());
+
+        // Not the smallest vertex of the orbit, or not mapped onto itself.
+        for orbits in [[1, 0, 2], [2, 2, 0], [0, 0, 1], [0, 1, 3]] {
+            assert!(check_orbits(&graph, &orbits).is_err());
+        }
+        assert!(check_orbits(&graph, &[0, 0]).is_err());
+    }
+}
@@ -3,46 +3,86 @@
 
 use custom_debug_derive::Debug;
 use itertools::{Either, Itertools, MinMaxResult};
-use libffi::high::{ClosureMut2, ClosureMut3, ClosureMut6};
+#[cfg(feature = "native")]
 use nauty_Traces_sys::{
-    allgroup, densenauty, groupautomproc, grouplevelproc, groupptr, makecosetreps, optionblk,
-    orbjoin, sparsenauty, statsblk, Traces, TracesStats, FALSE, TRUE,
+    adjacencies, adjacencies_sg, distances, distances_sg, optionblk, ran_init, schreier_fails,
+    statsblk, TracesStats, FALSE, TRUE,
+};
+use rand::{rngs::StdRng, Rng};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::RangeInclusive,
+    os::raw::{c_int, c_long},
+    sync::OnceLock,
+    usize,
 };
-use std::{os::raw::c_int, slice::from_raw_parts, usize};
 
 use crate::{
     debug::print_generator,
-    do_if_some,
-    encoding::QuotientGraphEncoding,
-    graph::{Graph, NautyGraph, SparseNautyGraph, TracesGraph, Vertex, VertexIndex, DEFAULT_COLOR},
+    encoding::{
+        encode_problem, EncodeOutcome, HighLevelEncoding, OrbitEncoding, QuotientGraphEncoding,
+    },
+    graph::{Generation, Graph, GraphError, Vertex, VertexIndex, DEFAULT_COLOR},
+    interrupt::{check_interrupted, is_interrupted},
     permutation::Permutation,
-    Error, NautyTraces, Settings,
+    sat_solving::solve,
+    statistics::{NoStatistics, StatisticsSink},
+    AutomorphismOptions, Error, Invariant, NautyTraces, Settings,
+};
+#[cfg(feature = "native")]
+use crate::{
+    generator_cache::{
+        generator_cache_key, generator_cache_path, load_generators, store_generators,
+        CachedGenerators,
+    },
+    graph::{NautyGraph, SparseNautyGraph, TracesGraph},
+    nauty_ffi::{dense_nauty, dense_nauty_all_group, sparse_nauty, traces},
 };
 
 pub type Orbits = Vec<VertexIndex>;
 
+#[cfg(feature = "native")]
+/// Seed the random number generator of nauty/Traces and set
+/// the number of failed random Schreier tests nauty accepts.
+#[cfg(not(tarpaulin_include))]
+fn apply_random_options(automorphism_options: &AutomorphismOptions) {
+    // Safety: Both only set global parameters of the nauty library.
+    if let Some(seed) = automorphism_options.random_seed {
+        unsafe { ran_init(seed as c_long) };
+    }
+    if let Some(fails) = automorphism_options.schreier_fails {
+        unsafe { schreier_fails(fails as c_int) };
+    }
+}
+
+#[cfg(feature = "native")]
 /// Call nauty with the given graph representation
 /// and compute the generators of the automorphism group
 /// for the graph. Return the generators.
 pub fn compute_generators_with_nauty(
-    nauty_graph: Either<NautyGraph, SparseNautyGraph>,
-    settings: &mut Settings,
+    nauty_graph: Either<&mut NautyGraph, &mut SparseNautyGraph>,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
 ) -> Vec<Permutation> {
     let mut generators = Vec::new();
-    let (n, m);
+    let n;
     let mut options;
 
     match nauty_graph {
         Either::Left(ref dense_nauty_graph) => {
-            let nm = dense_nauty_graph.graph_repr_sizes();
-            n = nm.0;
-            m = nm.1;
+            n = dense_nauty_graph.graph_repr_sizes().0;
             options = optionblk::default();
+            if dense_nauty_graph.digraph {
+                options.digraph = TRUE;
+            }
         }
         Either::Right(ref sparse_nauty_graph) => {
             n = sparse_nauty_graph.partition.len();
-            m = 0;
             options = optionblk::default_sparse();
+            if sparse_nauty_graph.digraph {
+                options.digraph = TRUE;
+            }
         }
     }
 
@@ -52,144 +92,351 @@ pub fn compute_generators_with_nauty(
         options.defaultptn = FALSE;
     }
 
+    let automorphism_options = settings.automorphism_options.clone();
+    if let Some(invariant) = automorphism_options.invariant {
+        options.invarproc = Some(match (invariant, nauty_graph.is_right()) {
+            (Invariant::Adjacencies, false) => adjacencies,
+            (Invariant::Distances, false) => distances,
+            (Invariant::Adjacencies, true) => adjacencies_sg,
+            (Invariant::Distances, true) => distances_sg,
+        });
+        options.maxinvarlevel = automorphism_options.invariant_level;
+    }
+    apply_random_options(&automorphism_options);
+
     let mut stats = statsblk::default();
     let mut orbits = vec![0_i32; n];
 
-    // Limit how long the closure can reference generators so that we can return it afterwards.
-    {
-        // Callback that copies the current generator.
-        let mut userautomproc =
-            |_count, generator_ptr: *mut c_int, _orbits, _numorbits, _stabvertex, n: c_int| {
-                let mut generator = Vec::with_capacity(n as usize);
-                let generator_raw = unsafe { from_raw_parts(generator_ptr, n as usize) };
-
-                for vertex in generator_raw {
-                    generator.push(*vertex);
-                }
-
-                generators.push(Permutation::new(generator));
-            };
-        let userautomproc = ClosureMut6::new(&mut userautomproc);
-
-        options.userautomproc = Some(*userautomproc.code_ptr());
-
-        // Safety: Call to nauty library function that computes
-        // the automorphism group generator through useratomproc.
-        match nauty_graph {
-            Either::Left(mut dense_nauty_graph) => unsafe {
-                densenauty(
-                    dense_nauty_graph.adjacency_matrix.as_mut_ptr(),
-                    dense_nauty_graph.vertex_order.as_mut_ptr(),
-                    dense_nauty_graph.partition.as_mut_ptr(),
-                    orbits.as_mut_ptr(),
-                    &mut options,
-                    &mut stats,
-                    m as c_int,
-                    n as c_int,
-                    std::ptr::null_mut(),
-                );
-            },
-            Either::Right(mut sparse_nauty_graph) => unsafe {
-                sparsenauty(
-                    &mut (&mut sparse_nauty_graph.sparse_graph).into(),
-                    sparse_nauty_graph.vertex_order.as_mut_ptr(),
-                    sparse_nauty_graph.partition.as_mut_ptr(),
-                    orbits.as_mut_ptr(),
-                    &mut options,
-                    &mut stats,
-                    std::ptr::null_mut(),
-                );
-            },
-        }
+    // Callback that copies the current generator.
+    let on_generator = |generator: &[c_int]| {
+        generators
+            .push(Permutation::new(generator.to_vec()).expect("Invalid generator from nauty!"))
+    };
+    match nauty_graph {
+        Either::Left(dense_nauty_graph) => dense_nauty(
+            dense_nauty_graph,
+            &mut orbits,
+            &mut options,
+            &mut stats,
+            on_generator,
+        ),
+        Either::Right(sparse_nauty_graph) => sparse_nauty(
+            sparse_nauty_graph,
+            &mut orbits,
+            &mut options,
+            &mut stats,
+            on_generator,
+        ),
     }
 
-    do_if_some(settings.get_stats(), |statsistics| {
-        statsistics.log_group_size(stats.grpsize1, stats.grpsize2)
+    statistics.log(|statsistics| {
+        statsistics.log_group_size(stats.grpsize1, stats.grpsize2);
+        statsistics.log_automorphism_options(automorphism_options);
     });
 
     generators
 }
 
+#[cfg(feature = "native")]
 /// Call Traces with the given graph representation
 /// and compute the generators of the automorphism group
 /// for the graph. Return the generators.
 pub fn compute_generators_with_traces(
-    mut traces_graph: TracesGraph,
-    settings: &mut Settings,
+    traces_graph: &mut TracesGraph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Vec<Permutation> {
+    let use_colours = settings.colored_graph;
+    traces_generators(traces_graph, settings, statistics, use_colours)
+}
+
+#[cfg(feature = "native")]
+/// Compute the generators of the digraph from its bipartite gadget (see
+/// `Graph::bipartite_gadget`) with Traces. The gadget's colours are always
+/// used and its generators are restricted to the out-copies of the vertices.
+pub fn compute_digraph_generators_with_traces(
+    gadget: &mut TracesGraph,
+    digraph_size: usize,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Vec<Permutation> {
+    let mut generators = traces_generators(gadget, settings, statistics, true);
+    for generator in generators.iter_mut() {
+        generator.raw.truncate(digraph_size);
+    }
+    generators
+}
+
+#[cfg(feature = "native")]
+fn traces_generators(
+    traces_graph: &mut TracesGraph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    use_colours: bool,
 ) -> Vec<Permutation> {
     let n = traces_graph.vertex_order.len();
     let mut generators = Vec::new();
 
-    // Limit how long the closure can reference generators so that we can return it afterwards.
-    {
-        // Callback that copies the current generator.
-        let mut userautomproc = |_count, generator_ptr: *mut c_int, n: c_int| {
-            let mut generator = Vec::with_capacity(n as usize);
-            let generator_raw = unsafe { from_raw_parts(generator_ptr, n as usize) };
+    // Traces has no invariants and its own Schreier method.
+    let automorphism_options = AutomorphismOptions {
+        schreier_fails: None,
+        invariant: None,
+        ..settings.automorphism_options.clone()
+    };
+    apply_random_options(&automorphism_options);
 
-            for vertex in generator_raw {
-                generator.push(*vertex);
-            }
+    let mut options = nauty_Traces_sys::TracesOptions::default();
+    if use_colours {
+        options.defaultptn = FALSE;
+    }
 
-            generators.push(Permutation::new(generator));
-        };
-        let userautomproc = ClosureMut3::new(&mut userautomproc);
+    let mut stats = TracesStats::default();
+    let mut orbits = vec![0_i32; n];
 
-        let mut options = nauty_Traces_sys::TracesOptions {
-            userautomproc: Some(*userautomproc.code_ptr()),
-            ..Default::default()
-        };
-        if settings.colored_graph {
-            options.defaultptn = FALSE;
+    // Callback that copies the current generator.
+    traces(
+        traces_graph,
+        &mut orbits,
+        &mut options,
+        &mut stats,
+        |generator| {
+            generators
+                .push(Permutation::new(generator.to_vec()).expect("Invalid generator from Traces!"))
+        },
+    );
+
+    statistics.log(|statsistics| {
+        statsistics.log_group_size(stats.grpsize1, stats.grpsize2);
+        statsistics.log_automorphism_options(automorphism_options);
+    });
+
+    generators
+}
+
+#[cfg(feature = "native")]
+/// The nauty or Traces representation of a graph that can be reused
+/// for repeated generator computations in which only the colours change.
+pub enum AutomorphismGraph {
+    Nauty(NautyGraph),
+    SparseNauty(SparseNautyGraph),
+    Traces(TracesGraph),
+    /// The bipartite gadget of a digraph and the size of the digraph.
+    TracesDigraph(TracesGraph, usize),
+}
+
+#[cfg(feature = "native")]
+impl AutomorphismGraph {
+    pub fn from_graph(graph: &mut Graph, settings: &Settings) -> Self {
+        Self::from_graph_with(graph, settings.nauyt_or_traces)
+    }
+
+    /// Like `from_graph`, but for the given backend instead of the configured one.
+    pub fn from_graph_with(graph: &mut Graph, backend: NautyTraces) -> Self {
+        match backend {
+            NautyTraces::Nauty => {
+                let nauty_graph = NautyGraph::from_graph(graph);
+
+                debug_assert!(nauty_graph.check_valid());
+                AutomorphismGraph::Nauty(nauty_graph)
+            }
+            NautyTraces::SparseNauty => {
+                AutomorphismGraph::SparseNauty(SparseNautyGraph::from_graph(graph))
+            }
+            NautyTraces::Traces if graph.is_directed() => AutomorphismGraph::TracesDigraph(
+                TracesGraph::from_digraph(graph).expect("Digraph with invalid vertices!"),
+                graph.size(),
+            ),
+            NautyTraces::Traces => AutomorphismGraph::Traces(TracesGraph::from_graph(graph)),
         }
+    }
 
-        let mut stats = TracesStats::default();
-        let mut orbits = vec![0_i32; n];
-
-        // Safety: Call to Traces library function that computes
-        // the automorphism group generators through useratomproc.
-        unsafe {
-            Traces(
-                &mut (&mut traces_graph.sparse_graph).into(),
-                traces_graph.vertex_order.as_mut_ptr(),
-                traces_graph.partition.as_mut_ptr(),
-                orbits.as_mut_ptr(),
-                &mut options,
-                &mut stats,
-                std::ptr::null_mut(),
-            );
+    /// Refresh only the vertex order and colouring from the graph,
+    /// as nauty and Traces overwrite them during each call.
+    /// Fails if the edges of the graph changed in the meantime.
+    pub fn refresh_colours(&mut self, graph: &mut Graph) -> Result<(), Error> {
+        match self {
+            AutomorphismGraph::Nauty(nauty_graph) => nauty_graph.refresh_colours(graph),
+            AutomorphismGraph::SparseNauty(sparse_nauty_graph) => {
+                sparse_nauty_graph.refresh_colours(graph)
+            }
+            AutomorphismGraph::Traces(traces_graph) => traces_graph.refresh_colours(graph),
+            AutomorphismGraph::TracesDigraph(traces_graph, _) => {
+                traces_graph.refresh_digraph_colours(graph)
+            }
         }
+    }
 
-        do_if_some(settings.get_stats(), |statsistics| {
-            statsistics.log_group_size(stats.grpsize1, stats.grpsize2)
-        });
+    pub fn compute_generators(
+        &mut self,
+        settings: &Settings,
+        statistics: &mut dyn StatisticsSink,
+    ) -> Vec<Permutation> {
+        match self {
+            AutomorphismGraph::Nauty(nauty_graph) => {
+                compute_generators_with_nauty(Either::Left(nauty_graph), settings, statistics)
+            }
+            AutomorphismGraph::SparseNauty(sparse_nauty_graph) => compute_generators_with_nauty(
+                Either::Right(sparse_nauty_graph),
+                settings,
+                statistics,
+            ),
+            AutomorphismGraph::Traces(traces_graph) => {
+                compute_generators_with_traces(traces_graph, settings, statistics)
+            }
+            AutomorphismGraph::TracesDigraph(traces_graph, digraph_size) => {
+                compute_digraph_generators_with_traces(
+                    traces_graph,
+                    *digraph_size,
+                    settings,
+                    statistics,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+/// Split the vertices into their colour classes if no edge
+/// connects two vertices of different colours. Returns None
+/// if there is only one colour class.
+fn independent_colour_classes(graph: &Graph) -> Option<Vec<Vec<VertexIndex>>> {
+    let mut colours = vec![DEFAULT_COLOR; graph.size()];
+    for vertex in graph.vertices.iter() {
+        colours[vertex.index as usize] = vertex.colour;
     }
 
+    let independent = graph
+        .iterate_edges()
+        .all(|(start, end)| colours[start as usize] == colours[end as usize]);
+    if !independent {
+        return None;
+    }
+
+    let classes = (0..graph.size() as VertexIndex)
+        .into_group_map_by(|vertex| colours[*vertex as usize])
+        .into_values()
+        .collect_vec();
+
+    if classes.len() > 1 {
+        Some(classes)
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "native")]
+/// Lift a permutation of the relabeled colour class to the whole graph.
+fn lift_class_generator(
+    class: &[VertexIndex],
+    generator: &Permutation,
+    graph_size: usize,
+) -> Permutation {
+    let mut raw = (0..graph_size as VertexIndex).collect_vec();
+    for (vertex, image) in class.iter().zip(generator.raw.iter()) {
+        raw[*vertex as usize] = class[*image as usize];
+    }
+    Permutation::new(raw).expect("Lifted generator isn't a permutation!")
+}
+
+#[cfg(feature = "native")]
+/// Compute the generators for each colour class on its own, if the classes
+/// are unions of connected components. The automorphism group is then the
+/// direct product of the groups of the classes.
+#[cfg(not(tarpaulin_include))]
+fn compute_generators_by_colour_classes(
+    graph: &Graph,
+    classes: Vec<Vec<VertexIndex>>,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Vec<Permutation> {
+    let mut generators = Vec::new();
+    let mut group_size = 1.;
+
+    for class in classes.into_iter().filter(|class| class.len() > 1) {
+        let mut class_graph = graph
+            .induce_relabeled_subgraph(&class)
+            .expect("Colour class not in graph!");
+        let class_generators = compute_generators_uncached(&mut class_graph, settings, statistics);
+        generators.extend(
+            class_generators
+                .iter()
+                .map(|generator| lift_class_generator(&class, generator, graph.size())),
+        );
+        statistics.log(|stats| group_size *= stats.get_group_size());
+    }
+
+    statistics.log(|stats| stats.log_group_size(group_size, 0));
+
     generators
 }
 
-pub fn compute_generators(graph: &mut Graph, settings: &mut Settings) -> Vec<Permutation> {
-    match settings.nauyt_or_traces {
-        NautyTraces::Nauty => {
-            let nauty_graph = NautyGraph::from_graph(graph);
+#[cfg(feature = "native")]
+/// Compute the generators, or read them from the generator cache of the
+/// input if there is one for the graph. Newly computed ones are cached.
+pub fn compute_generators(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Vec<Permutation> {
+    let Some(input) = settings.generator_cache.as_ref() else {
+        return compute_generators_uncached(graph, settings, statistics);
+    };
 
-            debug_assert!(nauty_graph.check_valid());
-            compute_generators_with_nauty(Either::Left(nauty_graph), settings)
-        }
-        NautyTraces::SparseNauty => {
-            let sparse_nauty_graph = SparseNautyGraph::from_graph(graph);
-            compute_generators_with_nauty(Either::Right(sparse_nauty_graph), settings)
-        }
-        NautyTraces::Traces => {
-            let traces_graph = TracesGraph::from_graph(graph);
-            compute_generators_with_traces(traces_graph, settings)
+    let key = generator_cache_key(graph, settings.colored_graph, settings.nauyt_or_traces);
+    let path = generator_cache_path(input, key);
+    if let Some(cached) = load_generators(&path, graph, settings.colored_graph) {
+        statistics.log(|stats| {
+            if let Some(group_size) = cached.group_size {
+                stats.log_group_size(group_size, 0);
+            }
+            stats.log_cached_generators();
+        });
+        return cached.generators;
+    }
+
+    let generators = compute_generators_uncached(graph, settings, statistics);
+    let mut group_size = None;
+    statistics.log(|stats| group_size = Some(stats.get_group_size()));
+    let cached = CachedGenerators {
+        generators,
+        group_size,
+    };
+    if let Err(error) = store_generators(&path, graph.size(), &cached) {
+        eprintln!(
+            "Couldn't write the generator cache file {}: {}",
+            path.display(),
+            error
+        );
+    }
+    cached.generators
+}
+
+#[cfg(feature = "native")]
+fn compute_generators_uncached(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Vec<Permutation> {
+    if settings.colored_graph {
+        if let Some(classes) = independent_colour_classes(graph) {
+            return compute_generators_by_colour_classes(graph, classes, settings, statistics);
         }
     }
+
+    AutomorphismGraph::from_graph(graph, settings).compute_generators(settings, statistics)
 }
 
+#[cfg(feature = "native")]
 #[cfg(not(tarpaulin_include))]
-pub fn search_group(graph: &mut Graph, mut nauty_graph: NautyGraph, settings: &mut Settings) {
-    let generators = compute_generators_with_nauty(Either::Left(nauty_graph.clone()), settings);
+pub fn search_group(
+    graph: &mut Graph,
+    mut nauty_graph: NautyGraph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+) -> Result<(), Error> {
+    let mut generator_graph = nauty_graph.clone();
+    let generators =
+        compute_generators_with_nauty(Either::Left(&mut generator_graph), settings, statistics);
 
     for generator in generators {
         print!("Generator: ");
@@ -197,90 +444,207 @@ pub fn search_group(graph: &mut Graph, mut nauty_graph: NautyGraph, settings: &m
     }
 
     // First, call nauty to compute the group.
-    let (n, m) = nauty_graph.graph_repr_sizes();
+    let (n, _) = nauty_graph.graph_repr_sizes();
     let mut options = optionblk::default();
 
     if settings.colored_graph {
         options.defaultptn = FALSE;
     }
+    if nauty_graph.digraph {
+        options.digraph = TRUE;
+    }
 
     let mut stats = statsblk::default();
     let mut orbits = vec![0_i32; n];
 
-    // Set custom group generating methods.
-    options.userautomproc = Some(groupautomproc);
-    options.userlevelproc = Some(grouplevelproc);
-
-    unsafe {
-        densenauty(
-            nauty_graph.adjacency_matrix.as_mut_ptr(),
-            nauty_graph.vertex_order.as_mut_ptr(),
-            nauty_graph.partition.as_mut_ptr(),
-            orbits.as_mut_ptr(),
-            &mut options,
-            &mut stats,
-            m as c_int,
-            n as c_int,
-            std::ptr::null_mut(),
-        );
-    }
-
     // Don't forget to sort. Otherwise, the encoding will be wrong.
     graph.sort();
 
     // Then search in the group.
-    let mut handle_automorphism = |autom_ptr: *mut c_int, n: c_int| {
-        let mut automorphism = Vec::with_capacity(n as usize);
-        let automorphism_raw = unsafe { from_raw_parts(autom_ptr, n as usize) };
-
-        for vertex in automorphism_raw {
-            automorphism.push(*vertex);
+    let handle_automorphism = |automorphism: &[c_int]| {
+        // nauty can't be stopped, so the remaining automorphisms are skipped.
+        if is_interrupted() {
+            return;
+        }
+        let mut automorphism = automorphism.to_vec();
+        let mut orbits = empty_orbits(graph.size());
+        apply_generator(&mut automorphism, &mut orbits);
+        let automorphism =
+            Permutation::new_with_cycles(automorphism).expect("Invalid automorphism from nauty!");
+        if let Some(trivial_partition) = TrivialPartition::detect(&orbits) {
+            print!("Automorphism induced {}: ", trivial_partition);
+            print_generator(automorphism);
+            return;
         }
 
-        let quotient = QuotientGraph::from_automorphism(graph, &mut automorphism);
-        let formula = crate::encoding::encode_problem(&quotient, graph);
+        let quotient = QuotientGraph::from_graph_orbits(graph, orbits);
+        let formula = crate::encoding::encode_problem(&quotient, graph, &settings.encoding_options)
+            .expect("Graph changed after building the quotient!");
 
-        if let Some((formula, _)) = formula {
+        if let EncodeOutcome::Formula(formula, _) = formula {
             let descriptive = crate::sat_solving::solve(formula);
 
             if let Ok(true) = descriptive {
                 print!("Descriptive induced by ");
-                print_generator(Permutation::new_with_cycles(automorphism));
+                print_generator(automorphism);
             } else {
                 print!("Nondescriptive induced by ");
-                print_generator(Permutation::new_with_cycles(automorphism));
+                print_generator(automorphism);
             }
         } else {
             print!("Automorphism induced trivially descriptive: ");
-            print_generator(Permutation::new_with_cycles(automorphism));
+            print_generator(automorphism);
         }
     };
-    let handle_automorphism = ClosureMut2::new(&mut handle_automorphism);
+    dense_nauty_all_group(
+        &mut nauty_graph,
+        &mut orbits,
+        &mut options,
+        &mut stats,
+        handle_automorphism,
+    );
+    check_interrupted()
+}
 
-    unsafe {
-        let group = groupptr(TRUE);
-        if group.is_null() {
-            panic!("The group ptr is null!");
+#[cfg(feature = "native")]
+/// Generates pseudo random group elements from a set of generators
+/// with the product replacement algorithm (rattle variant).
+struct ProductReplacement {
+    state: Vec<Permutation>,
+    accumulator: Permutation,
+    rng: StdRng,
+}
+
+#[cfg(feature = "native")]
+impl ProductReplacement {
+    /// The state should at least contain this many elements.
+    const MIN_STATE_SIZE: usize = 10;
+    /// Number of steps to mix the state before the first element is returned.
+    const WARMUP_STEPS: usize = 50;
+
+    fn new(generators: Vec<Permutation>, rng: StdRng) -> Self {
+        let size = generators
+            .first()
+            .expect("Can't sample from a group without generators")
+            .len();
+        let state_size = generators.len().max(Self::MIN_STATE_SIZE);
+        let state = generators.into_iter().cycle().take(state_size).collect();
+
+        let mut product_replacement = ProductReplacement {
+            state,
+            accumulator: Permutation::identity(size),
+            rng,
+        };
+
+        for _ in 0..Self::WARMUP_STEPS {
+            product_replacement.next_element();
         }
-        makecosetreps(group);
-        allgroup(group, Some(*handle_automorphism.code_ptr()));
+
+        product_replacement
+    }
+
+    fn next_element(&mut self) -> Permutation {
+        let first = self.rng.gen_range(0..self.state.len());
+        let mut second = self.rng.gen_range(0..self.state.len() - 1);
+        if second >= first {
+            second += 1;
+        }
+
+        let replaced = Permutation::compose(&self.state[first], &self.state[second]).unwrap();
+        self.accumulator = Permutation::compose(&self.accumulator, &replaced).unwrap();
+        self.state[first] = replaced;
+
+        self.accumulator.clone()
     }
 }
 
-// Apply a generator to the current orbits and combine those,
-// the the generator connects. Does not change the generator
-// (the &mut is for FFI reasons only, will not write into it).
+#[cfg(feature = "native")]
+/// Estimate how many elements of the automorphism group induce
+/// descriptive quotients by checking random group elements.
+#[cfg(not(tarpaulin_include))]
+pub fn sample_group(
+    graph: &mut Graph,
+    settings: &Settings,
+    statistics: &mut dyn StatisticsSink,
+    samples: usize,
+) -> Result<(), Error> {
+    let generators = compute_generators(graph, settings, statistics);
+
+    if generators.is_empty() {
+        println!("Trivial automorphism group, nothing to sample.");
+        return Ok(());
+    }
+
+    // Don't forget to sort. Otherwise, the encoding will be wrong.
+    graph.sort();
+
+    let mut random_elements = ProductReplacement::new(generators, settings.rng());
+    let mut descriptive_counter = 0usize;
+    let mut trivial_counter = 0usize;
+
+    for _ in 0..samples {
+        check_interrupted()?;
+        let mut element = random_elements.next_element();
+        let mut orbits = empty_orbits(graph.size());
+        apply_generator(&mut element.raw, &mut orbits);
+
+        if TrivialPartition::detect(&orbits).is_some() {
+            descriptive_counter += 1;
+            trivial_counter += 1;
+            continue;
+        }
+
+        let quotient = QuotientGraph::from_graph_orbits(graph, orbits);
+
+        let descriptive = if let EncodeOutcome::Formula(formula, _) =
+            encode_problem(&quotient, graph, &settings.encoding_options)?
+        {
+            solve(formula)?
+        } else {
+            true
+        };
+
+        if descriptive {
+            descriptive_counter += 1;
+        }
+    }
+
+    println!(
+        "Sampled {} group elements, {} induced descriptive quotients ({:.2}%), {} of them trivially.",
+        samples,
+        descriptive_counter,
+        100. * descriptive_counter as f64 / samples.max(1) as f64,
+        trivial_counter
+    );
+
+    Ok(())
+}
+
+/// Apply a generator to the current orbits and combine those the generator
+/// connects, so that each vertex is mapped to the smallest vertex of its
+/// orbit. Same as `orbjoin` of nauty, which isn't available without the
+/// `native` feature. Does not change the generator.
 fn apply_generator(generator: &mut [VertexIndex], orbits: &mut Orbits) {
     debug_assert_eq!(generator.len(), orbits.len());
 
-    // Safety: Call to nauty library function that reads from the generator
-    // and combines orbits accordingly. There probably is no nicer way to do this.
-    unsafe {
-        orbjoin(
-            orbits.as_mut_ptr(),
-            generator.as_mut_ptr(),
-            generator.len() as c_int,
-        );
+    let root = |orbits: &Orbits, mut vertex: VertexIndex| {
+        while orbits[vertex as usize] != vertex {
+            vertex = orbits[vertex as usize];
+        }
+        vertex
+    };
+    for (vertex, image) in generator.iter().enumerate() {
+        let left = root(orbits, orbits[vertex]);
+        let right = root(orbits, orbits[*image as usize]);
+        if left < right {
+            orbits[right as usize] = left;
+        } else if right < left {
+            orbits[left as usize] = right;
+        }
+    }
+    // Each vertex points to a smaller one, so one pass in order flattens the trees.
+    for vertex in 0..orbits.len() {
+        orbits[vertex] = orbits[orbits[vertex] as usize];
     }
 }
 
@@ -294,6 +658,15 @@ pub fn empty_orbits(number_vertices: usize) -> Orbits {
     orbits
 }
 
+/// Number of orbits, i.e. of the vertices that represent their orbit.
+pub fn count_orbits(orbits: &[VertexIndex]) -> usize {
+    orbits
+        .iter()
+        .enumerate()
+        .filter(|(vertex, orbit)| *vertex as VertexIndex == **orbit)
+        .count()
+}
+
 fn get_orbit(orbits: &[VertexIndex], vertex: VertexIndex) -> VertexIndex {
     *orbits
         .get(vertex as usize)
@@ -302,17 +675,177 @@ fn get_orbit(orbits: &[VertexIndex], vertex: VertexIndex) -> VertexIndex {
 
 // Generate the orbits of a quotient graph from the generators of the original graph.
 pub fn generate_orbits(generators: &mut [Permutation]) -> Orbits {
+    let mut orbits = Vec::new();
+    generate_orbits_into(generators, &mut orbits);
+    orbits
+}
+
+/// Same as `generate_orbits` for the generators with the given indices.
+pub fn generate_orbits_of_subset(generators: &mut [Permutation], subset: &[usize]) -> Orbits {
+    let number_of_vertices = generators[subset[0]].len();
+    let mut orbits = (0..number_of_vertices as VertexIndex).collect_vec();
+
+    for index in subset {
+        apply_generator(&mut generators[*index].raw, &mut orbits);
+    }
+    orbits
+}
+
+/// Same as `generate_orbits`, but overwrites the given orbits to reuse their allocation.
+pub fn generate_orbits_into(generators: &mut [Permutation], orbits: &mut Orbits) {
     let number_of_vertices = generators
         .first()
         .expect("Empty subset can't be used to generate orbits")
         .len();
-    let mut orbits = empty_orbits(number_of_vertices);
+    orbits.clear();
+    orbits.extend(0..number_of_vertices as VertexIndex);
 
     for generator in generators {
-        apply_generator(&mut generator.raw, &mut orbits);
+        apply_generator(&mut generator.raw, orbits);
     }
+}
 
+/// Whether both orbit vectors partition the vertices in the same way,
+/// regardless of which vertex represents an orbit.
+pub fn same_partition(left: &[VertexIndex], right: &[VertexIndex]) -> bool {
+    let mut left_to_right = HashMap::new();
+    let mut right_to_left = HashMap::new();
+    left.len() == right.len()
+        && left.iter().zip(right.iter()).all(|(left, right)| {
+            *left_to_right.entry(left).or_insert(right) == right
+                && *right_to_left.entry(right).or_insert(left) == left
+        })
+}
+
+#[cfg(feature = "native")]
+/// Compute the generators with nauty and with Traces and compare the orbits they generate.
+/// Returns the orbits of nauty and of Traces if they differ.
+#[cfg(not(tarpaulin_include))]
+pub fn cross_check_generators(graph: &Graph, settings: &Settings) -> Option<(Orbits, Orbits)> {
+    let nauty = if graph.is_sparse() {
+        NautyTraces::SparseNauty
+    } else {
+        NautyTraces::Nauty
+    };
+
+    // Each backend reorders its own copy of the graph.
+    let [nauty_orbits, traces_orbits] = [nauty, NautyTraces::Traces].map(|backend| {
+        let mut graph = graph.clone();
+        let mut generators = AutomorphismGraph::from_graph_with(&mut graph, backend)
+            .compute_generators(settings, &mut NoStatistics);
+        if generators.is_empty() {
+            empty_orbits(graph.size())
+        } else {
+            generate_orbits(&mut generators)
+        }
+    });
+
+    (!same_partition(&nauty_orbits, &traces_orbits)).then_some((nauty_orbits, traces_orbits))
+}
+
+/// Labels that make the members of each orbit contiguous. The orbits are
+/// sorted by increasing size (ties by representative), their members by index.
+/// The permutation maps each vertex to its new label.
+pub fn orbit_contiguous_labels(orbits: &[VertexIndex]) -> Permutation {
+    let mut vertices = (0..orbits.len() as VertexIndex).collect_vec();
+    let orbit_sizes = orbits.iter().counts();
+    vertices.sort_by_key(|vertex| {
+        let orbit = orbits[*vertex as usize];
+        (orbit_sizes[&orbit], orbit, *vertex)
+    });
+
+    let mut labels = vec![0; orbits.len()];
+    for (label, vertex) in vertices.into_iter().enumerate() {
+        labels[vertex as usize] = label as VertexIndex;
+    }
+    Permutation::new(labels).expect("Labels aren't a permutation!")
+}
+
+/// Relabel the graph and its orbits with `orbit_contiguous_labels`,
+/// e.g. for better locality or to present the orbits as blocks.
+/// Returns the permutation used as well.
+pub fn relabel_by_orbits(
+    graph: &Graph,
+    orbits: &[VertexIndex],
+) -> Result<(Graph, Orbits, Permutation), GraphError> {
+    let labels = orbit_contiguous_labels(orbits);
+    let relabeled_graph = graph.relabel(&labels.raw)?;
+
+    // The members keep their relative order, so the representative
+    // (smallest member) of an orbit stays the smallest.
+    let mut relabeled_orbits = vec![0; orbits.len()];
+    for (vertex, orbit) in orbits.iter().enumerate() {
+        relabeled_orbits[labels.raw[vertex] as usize] = labels.raw[*orbit as usize];
+    }
+
+    Ok((relabeled_graph, relabeled_orbits, labels))
+}
+
+/// Orbit partitions whose quotient graphs are descriptive
+/// without building or encoding them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrivialPartition {
+    /// All vertices are in one orbit, i.e. the group acts vertex-transitively.
+    /// The quotient is a single vertex.
+    SingleOrbit,
+    /// Every vertex is its own orbit, i.e. the quotient is the graph itself.
+    Identity,
+}
+
+impl TrivialPartition {
+    pub fn detect(orbits: &[VertexIndex]) -> Option<Self> {
+        let first = *orbits.first()?;
+        if orbits.iter().all(|orbit| *orbit == first) {
+            Some(Self::SingleOrbit)
+        } else if orbits
+            .iter()
+            .enumerate()
+            .all(|(vertex, orbit)| vertex as VertexIndex == *orbit)
+        {
+            Some(Self::Identity)
+        } else {
+            None
+        }
+    }
+
+    pub fn quotient_size(&self, graph_size: usize) -> usize {
+        match self {
+            Self::SingleOrbit => 1,
+            Self::Identity => graph_size,
+        }
+    }
+
+    pub fn orbit_size(&self, graph_size: usize) -> usize {
+        match self {
+            Self::SingleOrbit => graph_size,
+            Self::Identity => 1,
+        }
+    }
+}
+
+impl fmt::Display for TrivialPartition {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SingleOrbit => write!(f, "vertex-transitive (single orbit)"),
+            Self::Identity => write!(f, "identity partition (only singleton orbits)"),
+        }
+    }
+}
+
+/// Compute the minimal and maximal orbit size.
+/// Vertices not part of any orbit (i.e. marked with -1) are ignored.
+fn compute_orbit_sizes(orbits: &[VertexIndex]) -> (usize, usize) {
+    let mut counter = vec![0usize; orbits.len()];
     orbits
+        .iter()
+        .filter(|orbit| **orbit >= 0)
+        .for_each(|orbit| counter[*orbit as usize] += 1);
+    match counter.iter().filter(|size| **size > 0).minmax() {
+        MinMaxResult::NoElements => (0, 0),
+        MinMaxResult::OneElement(m) => (*m, *m),
+        MinMaxResult::MinMax(min, max) => (*min, *max),
+    }
 }
 
 /// Represents a quotient graph where the vertices are
@@ -321,18 +854,20 @@ pub fn generate_orbits(generators: &mut [Permutation]) -> Orbits {
 #[derive(Debug)]
 pub struct QuotientGraph {
     pub quotient_graph: Graph,
+    /// Must not be changed after construction, as the encoding is cached.
     #[debug(skip)]
     pub orbits: Orbits,
+    /// Minimal and maximal orbit size, computed once on construction.
+    orbit_sizes: (usize, usize),
+    /// The quotient edges and the orbits grouped by their id, computed on first use.
+    #[debug(skip)]
+    encoding: OnceLock<QuotientGraphEncoding>,
+    /// Of the graph that the quotient was built from.
+    #[debug(skip)]
+    graph_generation: Generation,
 }
 
 impl QuotientGraph {
-    #[cfg(not(tarpaulin_include))]
-    fn from_automorphism(graph: &Graph, automorphism: &mut [VertexIndex]) -> Self {
-        let mut orbits = empty_orbits(graph.size());
-        apply_generator(automorphism, &mut orbits);
-        Self::from_graph_orbits(graph, orbits)
-    }
-
     /// Generates the quotient graph where each orbit is represented
     /// by the vertex with the smallest index in the orbit.
     pub fn from_graph_orbits(graph: &Graph, orbits: Orbits) -> Self {
@@ -349,6 +884,8 @@ impl QuotientGraph {
             quotient_graph = Graph::new_with_indices(&unique_orbits, true);
             // Add edges between the orbits if single vertices in these are
             // connected by an edge. Doesn't add edges within the same orbit.
+            // Undirected edges are iterated in both directions, so adding
+            // arcs keeps them undirected and digraphs keep their arcs.
             graph.iterate_edges().for_each(|(start, end)| {
                 let start_orbit = get_orbit(&orbits, start);
                 let end_orbit = get_orbit(&orbits, end);
@@ -361,74 +898,134 @@ impl QuotientGraph {
 
             // Edges between orbits might be generated more often than once.
             quotient_graph.minimize();
+            // Quotients of sparse graphs can still be dense.
+            quotient_graph.choose_representation();
         } else {
             quotient_graph = Graph::new_ordered(1);
             quotient_graph
                 .set_vertex(Vertex::new(0, DEFAULT_COLOR))
                 .expect("Single vertex could not be added!");
         }
+        quotient_graph.set_directed(graph.is_declared_directed());
+
+        let orbit_sizes = compute_orbit_sizes(&orbits);
 
         QuotientGraph {
             quotient_graph,
             orbits,
+            orbit_sizes,
+            encoding: OnceLock::new(),
+            graph_generation: graph.generation(),
         }
     }
 
+    /// Fail if the edges of the graph changed since the quotient was built from it,
+    /// e.g. before encoding. Recolouring or sorting the graph keeps the quotient.
+    pub fn check_graph(&self, graph: &Graph) -> Result<(), Error> {
+        self.graph_generation
+            .check_edges(graph, "the quotient graph")
+    }
+
     pub fn get_orbit_sizes(&self) -> (usize, usize) {
-        let mut counter = vec![0usize; self.orbits.len()];
-        self.orbits
-            .iter()
-            .for_each(|orbit| counter[*orbit as usize] += 1);
-        match counter.iter().filter(|size| **size > 0).minmax() {
-            MinMaxResult::NoElements => (0, 0),
-            MinMaxResult::OneElement(m) => (*m, *m),
-            MinMaxResult::MinMax(min, max) => (*min, *max),
+        self.orbit_sizes
+    }
+
+    /// The high level encoding of the quotient, computed once and shared by
+    /// the encoding, validation and core search of this quotient instead of
+    /// being encoded again by each of them. Use `encode_high` for a copy.
+    pub fn encoding(&self) -> &QuotientGraphEncoding {
+        self.encoding.get_or_init(|| {
+            QuotientGraphEncoding(self.quotient_graph.encode_high(), self.group_orbits())
+        })
+    }
+
+    /// The orbits as (orbit id, vertices) ordered by id, like `Orbits::encode_high`.
+    pub fn grouped_orbits(&self) -> &[OrbitEncoding] {
+        &self.encoding().1
+    }
+
+    /// Group the orbits by bucketing the vertices instead of sorting them.
+    fn group_orbits(&self) -> Vec<OrbitEncoding> {
+        let mut buckets = vec![Vec::new(); self.orbits.len()];
+        for (vertex, orbit) in self.orbits.iter().enumerate() {
+            if *orbit >= 0 {
+                buckets[*orbit as usize].push(vertex as VertexIndex);
+            }
         }
+        buckets
+            .into_iter()
+            .enumerate()
+            .filter(|(_, vertices)| !vertices.is_empty())
+            .map(|(orbit, vertices)| (orbit as VertexIndex, vertices))
+            .collect()
     }
 
+    /// Iterate over the orbits as (orbit id, vertices) ordered by id.
+    pub fn iter_orbits(&self) -> impl Iterator<Item = (VertexIndex, &[VertexIndex])> + '_ {
+        self.grouped_orbits()
+            .iter()
+            .map(|(orbit, vertices)| (*orbit, vertices.as_slice()))
+    }
+
+    /// Search the smallest non-descriptive core with a number of orbits in the range by
+    /// checking the subsets of orbits of each size in increasing order. The subsets of one
+    /// size are checked in parallel, so that the core found among those of the smallest
+    /// size depends on the scheduling unless `deterministic` is set.
+    /// Fails if the solver fails or once the search was interrupted.
     #[cfg(not(tarpaulin_include))]
-    pub fn search_non_descriptive_core(self, graph: &Graph) -> Option<QuotientGraphEncoding> {
-        use crate::encoding::{
-            EdgeEncoding, HighLevelEncoding, SATEncoding, SATEncodingDictionary,
-        };
+    pub fn search_non_descriptive_core(
+        self,
+        graph: &Graph,
+        sizes: RangeInclusive<usize>,
+        deterministic: bool,
+    ) -> Result<Option<QuotientGraphEncoding>, Error> {
+        use crate::encoding::{EdgeEncoding, SATEncoding, SATEncodingDictionary};
         use rayon::prelude::*;
-        let QuotientGraphEncoding(quotient_edges, orbits) = self.encode_high();
+        let QuotientGraphEncoding(quotient_edges, orbits) = self.encoding();
 
-        orbits
-            .iter()
-            .cloned()
-            .combinations(4) // From observations it seemed that such cores are mostly of size 4.
-            .par_bridge()
-            .find_map_any(|orbit_subset| {
-                let mut dict = SATEncodingDictionary::default();
-                let edge_subset = quotient_edges
-                    .iter()
-                    .filter(|edge| {
-                        let (start, end) = edge.get_edge();
-                        orbit_subset.iter().any(|(orbit, _)| *orbit == start)
-                            && orbit_subset.iter().any(|(orbit, _)| *orbit == end)
-                    })
-                    .copied()
-                    .collect::<Vec<EdgeEncoding>>();
-
-                let descriptive_constraint_encoding =
-                    QuotientGraphEncoding(edge_subset.clone(), orbit_subset.clone())
-                        .encode_sat(&mut dict, graph);
-
-                let transversal_encoding = orbit_subset
-                    .iter()
-                    .flat_map(|orbit| orbit.encode_sat(&mut dict, graph));
-
-                if !crate::solve(
-                    transversal_encoding.chain(descriptive_constraint_encoding.into_iter()),
-                )
-                .unwrap()
-                {
-                    Some(QuotientGraphEncoding(edge_subset, orbit_subset))
+        let check_subset = |orbit_subset: Vec<OrbitEncoding>| {
+            if is_interrupted() {
+                return Some(Err(Error::Interrupted));
+            }
+            let mut dict = SATEncodingDictionary::default();
+            let edge_subset = quotient_edges
+                .iter()
+                .filter(|edge| {
+                    let (start, end) = edge.get_edge();
+                    orbit_subset.iter().any(|(orbit, _)| *orbit == start)
+                        && orbit_subset.iter().any(|(orbit, _)| *orbit == end)
+                })
+                .copied()
+                .collect::<Vec<EdgeEncoding>>();
+
+            let descriptive_constraint_encoding =
+                QuotientGraphEncoding(edge_subset.clone(), orbit_subset.clone())
+                    .encode_sat(&mut dict, graph);
+
+            let transversal_encoding = orbit_subset
+                .iter()
+                .flat_map(|orbit| orbit.encode_sat(&mut dict, graph));
+
+            match crate::solve(
+                transversal_encoding.chain(descriptive_constraint_encoding.into_iter()),
+            ) {
+                Ok(false) => Some(Ok(QuotientGraphEncoding(edge_subset, orbit_subset))),
+                Ok(true) => None,
+                Err(error) => Some(Err(error)),
+            }
+        };
+
+        let max_size = (*sizes.end()).min(orbits.len());
+        (*sizes.start()..=max_size)
+            .find_map(|size| {
+                let orbit_subsets = orbits.iter().cloned().combinations(size);
+                if deterministic {
+                    orbit_subsets.into_iter().find_map(check_subset)
                 } else {
-                    None
+                    orbit_subsets.par_bridge().find_map_any(check_subset)
                 }
             })
+            .transpose()
     }
 
     pub fn induced_subquotient(&self, orbit_subset: &[VertexIndex]) -> Result<Self, Error> {
@@ -439,9 +1036,14 @@ impl QuotientGraph {
             }
         });
 
+        let orbit_sizes = compute_orbit_sizes(&sub_orbits);
+
         Ok(QuotientGraph {
             quotient_graph: self.quotient_graph.induce_subgraph(orbit_subset, true)?,
             orbits: sub_orbits,
+            orbit_sizes,
+            encoding: OnceLock::new(),
+            graph_generation: self.graph_generation,
         })
     }
 }
@@ -451,6 +1053,68 @@ mod test {
     use super::*;
     use crate::{graph::GraphError, Error};
 
+    #[test]
+    fn test_same_partition() {
+        assert!(same_partition(&[0, 1, 0, 3], &[2, 1, 2, 3]));
+        assert!(!same_partition(&[0, 1, 0, 3], &[0, 0, 0, 3]));
+        assert!(!same_partition(&[0, 0, 0, 3], &[0, 1, 0, 3]));
+        assert!(!same_partition(&[0, 1], &[0, 1, 2]));
+    }
+
+    #[test]
+    fn test_iter_orbits() {
+        let mut graph = Graph::new_ordered(5);
+        graph.add_edge(0, 1).unwrap();
+        graph.add_edge(1, 2).unwrap();
+        let orbits = vec![0, 1, 0, 3, 3];
+        let quotient_graph = QuotientGraph::from_graph_orbits(&graph, orbits.clone());
+
+        assert_eq!(orbits.encode_high(), quotient_graph.grouped_orbits());
+        // The encoding is computed once and shared.
+        assert!(std::ptr::eq(
+            quotient_graph.encoding(),
+            quotient_graph.encoding()
+        ));
+        assert_eq!(
+            quotient_graph.quotient_graph.encode_high(),
+            quotient_graph.encoding().0
+        );
+        let expected: Vec<(VertexIndex, &[VertexIndex])> =
+            vec![(0, &[0, 2]), (1, &[1]), (3, &[3, 4])];
+        assert_eq!(expected, quotient_graph.iter_orbits().collect::<Vec<_>>());
+
+        // Removed orbits are skipped.
+        let subquotient = quotient_graph.induced_subquotient(&[0, 3]).unwrap();
+        assert_eq!(
+            subquotient.orbits.encode_high(),
+            subquotient.grouped_orbits()
+        );
+    }
+
+    #[test]
+    fn test_relabel_by_orbits() -> Result<(), Error> {
+        // A path of four vertices with the reflection and
+        // a pendant vertex that is its own orbit.
+        let mut graph = Graph::new_ordered(5);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.add_edge(2, 3)?;
+        graph.add_edge(1, 4)?;
+        graph.add_edge(2, 4)?;
+        let orbits = vec![0, 1, 1, 0, 4];
+
+        let (relabeled, relabeled_orbits, labels) = relabel_by_orbits(&graph, &orbits)?;
+        assert_eq!(vec![1, 3, 4, 2, 0], labels.raw);
+        assert_eq!(vec![0, 1, 1, 3, 3], relabeled_orbits);
+        assert!(relabeled.lookup_edge(&1, &3));
+        assert!(relabeled.lookup_edge(&3, &4));
+        assert!(relabeled.lookup_edge(&4, &2));
+        assert!(relabeled.lookup_edge(&0, &3));
+        assert!(relabeled.lookup_edge(&0, &4));
+        assert!(!relabeled.lookup_edge(&1, &2));
+        Ok(())
+    }
+
     #[test]
     fn test_from_graph_orbits() -> Result<(), Error> {
         let mut graph = Graph::new_ordered(8);
@@ -471,6 +1135,7 @@ mod test {
 
         let quotient = QuotientGraph::from_graph_orbits(&graph, orbits.clone());
         assert_eq!(orbits, quotient.orbits);
+        assert_eq!((1, 3), quotient.get_orbit_sizes());
 
         let mut expected_vert0 = Vertex::new(0, DEFAULT_COLOR);
         expected_vert0.add_edge(1);
@@ -504,6 +1169,33 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_directed_quotient_graph() -> Result<(), GraphError> {
+        // The directed 4-cycle by its rotation by two.
+        let mut graph = Graph::new_ordered(4);
+        graph.set_directed(true);
+        for vertex in 0..4 {
+            graph.connect(vertex, (vertex + 1) % 4)?;
+        }
+
+        let quotient = QuotientGraph::from_graph_orbits(&graph, vec![0, 1, 0, 1]);
+        assert!(quotient.quotient_graph.is_declared_directed());
+        assert!(quotient.quotient_graph.lookup_edge(&0, &1));
+        assert!(quotient.quotient_graph.lookup_edge(&1, &0));
+
+        // Both arcs of the in-star 0 -> 1 <- 2 map to the same quotient arc.
+        let mut star = Graph::new_ordered(3);
+        star.set_directed(true);
+        star.connect(0, 1)?;
+        star.connect(2, 1)?;
+        let quotient = QuotientGraph::from_graph_orbits(&star, vec![0, 1, 0]);
+        assert_eq!(1, quotient.quotient_graph.number_edges());
+        assert!(quotient.quotient_graph.lookup_edge(&0, &1));
+        assert!(!quotient.quotient_graph.lookup_edge(&1, &0));
+
+        Ok(())
+    }
+
     #[test]
     fn test_apply_generator() {
         let mut orbits = empty_orbits(7);
@@ -512,6 +1204,11 @@ mod test {
         apply_generator(&mut generator, &mut orbits);
 
         assert_eq!(orbits, [0, 1, 2, 3, 2, 5, 5]);
+
+        // The cycle merges the orbit of 3 into that of 1 before 2 joins them.
+        let mut orbits = empty_orbits(4);
+        apply_generator(&mut [0, 3, 1, 2], &mut orbits);
+        assert_eq!(orbits, [0, 1, 1, 1]);
     }
 
     #[test]
@@ -524,9 +1221,127 @@ mod test {
         assert_eq!(orbits, vec![0, 1, 2, 1, 4, 0, 1, 0]);
     }
 
+    #[test]
+    fn test_conjugate_quotients_agree() -> Result<(), Error> {
+        // Cube with the top 0-1-2-3 and the bottom 4-5-6-7.
+        let mut graph = Graph::new_ordered(8);
+        for (start, end) in [
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (3, 0),
+            (4, 5),
+            (5, 6),
+            (6, 7),
+            (7, 4),
+        ] {
+            graph.add_edge(start, end)?;
+        }
+        for vertex in 0..4 {
+            graph.add_edge(vertex, vertex + 4)?;
+        }
+        graph.sort();
+
+        let rotation = Permutation::new(vec![1, 2, 3, 0, 5, 6, 7, 4]).unwrap();
+        let inverse_rotation = Permutation::new(vec![3, 0, 1, 2, 7, 4, 5, 6]).unwrap();
+        let reflection = Permutation::new(vec![0, 3, 2, 1, 4, 7, 6, 5]).unwrap();
+        let conjugate = Permutation::compose(
+            &Permutation::compose(&rotation, &reflection).unwrap(),
+            &inverse_rotation,
+        )
+        .unwrap();
+
+        // The conjugate's orbits are the rotated orbits of the reflection, ...
+        let orbits = generate_orbits(&mut [reflection]);
+        let conjugate_orbits = generate_orbits(&mut [conjugate]);
+        assert_eq!(vec![0, 1, 2, 1, 4, 5, 6, 5], orbits);
+        assert_eq!(vec![0, 1, 0, 3, 4, 5, 4, 7], conjugate_orbits);
+
+        // ... so the quotients are isomorphic and agree on descriptiveness.
+        let descriptive = |orbits| {
+            let quotient = QuotientGraph::from_graph_orbits(&graph, orbits);
+            match encode_problem(&quotient, &graph, &Default::default())? {
+                EncodeOutcome::Formula(formula, _) => solve(formula),
+                EncodeOutcome::TriviallyDescriptive { .. } => Ok(true),
+            }
+        };
+        assert_eq!(descriptive(orbits)?, descriptive(conjugate_orbits)?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_independent_colour_classes() -> Result<(), GraphError> {
+        let mut graph = Graph::new_ordered(5);
+        graph.add_edge(0, 2)?;
+        graph.add_edge(1, 3)?;
+        graph.add_edge(3, 4)?;
+        graph.set_colours(&[1, 2, 1, 2, 2])?;
+
+        let mut classes = independent_colour_classes(&graph).unwrap();
+        classes.sort();
+        assert_eq!(vec![vec![0, 2], vec![1, 3, 4]], classes);
+
+        let class_generator = Permutation::new(vec![2, 1, 0]).unwrap();
+        assert_eq!(
+            Permutation::new(vec![0, 4, 2, 3, 1]).unwrap(),
+            lift_class_generator(&classes[1], &class_generator, 5)
+        );
+
+        graph.add_edge(0, 1)?;
+        assert!(independent_colour_classes(&graph).is_none());
+
+        let mut single_colour = Graph::new_ordered(3);
+        single_colour.add_edge(0, 1)?;
+        assert!(independent_colour_classes(&single_colour).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trivial_partition() {
+        assert_eq!(
+            Some(TrivialPartition::SingleOrbit),
+            TrivialPartition::detect(&[0, 0, 0, 0])
+        );
+        assert_eq!(
+            Some(TrivialPartition::Identity),
+            TrivialPartition::detect(&empty_orbits(4))
+        );
+        assert_eq!(None, TrivialPartition::detect(&[0, 1, 0, 3]));
+        assert_eq!(None, TrivialPartition::detect(&[]));
+
+        assert_eq!(1, TrivialPartition::SingleOrbit.quotient_size(4));
+        assert_eq!(4, TrivialPartition::SingleOrbit.orbit_size(4));
+        assert_eq!(4, TrivialPartition::Identity.quotient_size(4));
+        assert_eq!(1, TrivialPartition::Identity.orbit_size(4));
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_product_replacement() {
+        let generator = Permutation::new(vec![1, 2, 0, 3]).unwrap();
+        let group = [
+            generator.nth_power_of(1),
+            generator.nth_power_of(2),
+            generator.nth_power_of(3),
+        ];
+
+        let settings = Settings {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let mut random_elements = ProductReplacement::new(vec![generator], settings.rng());
+        for _ in 0..20 {
+            assert!(group.contains(&random_elements.next_element()));
+        }
+    }
+
+    #[cfg(feature = "native")]
     #[test]
     fn test_compute_generators_with_dense_nauty() -> Result<(), GraphError> {
-        let mut settings = Settings {
+        let settings = Settings {
             colored_graph: true,
             ..Default::default()
         };
@@ -551,35 +1366,107 @@ mod test {
         graph.order(&order)?;
 
         // Test dense nauty
-        let nauty_graph = NautyGraph::from_graph(&mut graph);
+        let mut nauty_graph = NautyGraph::from_graph(&mut graph);
         assert!(nauty_graph.check_valid());
         let expected_generators: Vec<Permutation> = vec![
             vec![5, 1, 2, 6, 4, 0, 3, 7].into(),
             vec![0, 3, 2, 1, 4, 7, 6, 5].into(),
         ];
-        let generators = compute_generators_with_nauty(Either::Left(nauty_graph), &mut settings);
+        let generators = compute_generators_with_nauty(
+            Either::Left(&mut nauty_graph),
+            &settings,
+            &mut NoStatistics,
+        );
         assert_eq!(expected_generators, generators);
 
         // Test sparse nauty
-        let sparse_nauty_graph = SparseNautyGraph::from_graph(&mut graph);
+        let mut sparse_nauty_graph = SparseNautyGraph::from_graph(&mut graph);
         let expected_generators: Vec<Permutation> = vec![
             vec![0, 3, 2, 1, 4, 7, 6, 5].into(),
             vec![5, 1, 2, 6, 4, 0, 3, 7].into(),
         ];
-        let generators =
-            compute_generators_with_nauty(Either::Right(sparse_nauty_graph), &mut settings);
+        let generators = compute_generators_with_nauty(
+            Either::Right(&mut sparse_nauty_graph),
+            &settings,
+            &mut NoStatistics,
+        );
         assert_eq!(expected_generators, generators);
 
         // Test traces
-        let traces_graph = TracesGraph::from_graph(&mut graph);
+        let mut traces_graph = TracesGraph::from_graph(&mut graph);
         let expected_generators: Vec<Permutation> = vec![
             vec![7, 3, 2, 6, 4, 0, 1, 5].into(),
             vec![5, 1, 2, 6, 4, 0, 3, 7].into(),
         ];
-        let generators = compute_generators_with_traces(traces_graph, &mut settings);
+        let generators =
+            compute_generators_with_traces(&mut traces_graph, &settings, &mut NoStatistics);
 
         assert_eq!(expected_generators, generators);
 
         Ok(())
     }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_digraph_generators_with_traces_agree_with_nauty() -> Result<(), Error> {
+        // The directed 3-cycle only has its rotations, but no reflections.
+        let mut graph = Graph::new_ordered(3);
+        for vertex in 0..3 {
+            graph.add_arc(vertex, (vertex + 1) % 3)?;
+        }
+
+        let mut gadget = TracesGraph::from_digraph(&graph)?;
+        let traces_generators = compute_digraph_generators_with_traces(
+            &mut gadget,
+            3,
+            &Default::default(),
+            &mut NoStatistics,
+        );
+
+        let mut nauty_graph = NautyGraph::from_graph(&mut graph);
+        let mut options = optionblk {
+            digraph: TRUE,
+            ..optionblk::default()
+        };
+        let mut stats = statsblk::default();
+        let mut orbits = vec![0; 3];
+        let mut nauty_generators = Vec::new();
+        dense_nauty(
+            &mut nauty_graph,
+            &mut orbits,
+            &mut options,
+            &mut stats,
+            |generator| nauty_generators.push(generator.to_vec()),
+        );
+
+        // Enumerate the (small) group spanned by the generators.
+        let group = |generators: Vec<Vec<VertexIndex>>| {
+            let mut elements = vec![(0..3).collect::<Vec<VertexIndex>>()];
+            let mut index = 0;
+            while index < elements.len() {
+                for generator in generators.iter() {
+                    let element = elements[index]
+                        .iter()
+                        .map(|image| generator[*image as usize])
+                        .collect_vec();
+                    if !elements.contains(&element) {
+                        elements.push(element);
+                    }
+                }
+                index += 1;
+            }
+            elements.sort();
+            elements
+        };
+        let traces_group = group(
+            traces_generators
+                .into_iter()
+                .map(|generator| generator.raw)
+                .collect(),
+        );
+        assert_eq!(group(nauty_generators), traces_group);
+        assert_eq!(3, traces_group.len());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,255 @@
+//! Comparison of two orbit partitions of the same graph,
+//! e.g. computed with different metrics or backends.
+
+use std::{collections::HashMap, fmt, fs::File, io::BufReader, path::Path};
+
+use crate::{
+    debug::orbits_nauty_style,
+    graph::VertexIndex,
+    parser::parse_orbits_input,
+    quotient::{count_orbits, Orbits},
+    Error,
+};
+
+/// How two orbit partitions relate to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refinement {
+    Equal,
+    /// Every left orbit is part of a right orbit.
+    LeftFiner,
+    /// Every right orbit is part of a left orbit.
+    RightFiner,
+    Incomparable,
+}
+
+impl fmt::Display for Refinement {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equal => write!(f, "equal"),
+            Self::LeftFiner => write!(f, "left refines right"),
+            Self::RightFiner => write!(f, "right refines left"),
+            Self::Incomparable => write!(f, "incomparable"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct QuotientDiff {
+    pub refinement: Refinement,
+    pub left_orbits: usize,
+    pub right_orbits: usize,
+    /// Finest partition that both partitions refine.
+    pub common_coarsening: Orbits,
+    /// Vertices whose left orbit has other members than their right orbit.
+    pub differing_vertices: Vec<VertexIndex>,
+}
+
+impl fmt::Display for QuotientDiff {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Refinement: {}", self.refinement)?;
+        writeln!(
+            f,
+            "Orbits: {} left, {} right, {} in the common coarsening",
+            self.left_orbits,
+            self.right_orbits,
+            count_orbits(&self.common_coarsening)
+        )?;
+        writeln!(
+            f,
+            "Common coarsening: {}",
+            orbits_nauty_style(self.common_coarsening.clone())
+        )?;
+        write!(
+            f,
+            "Differently assigned vertices ({}):",
+            self.differing_vertices.len()
+        )?;
+        for vertex in self.differing_vertices.iter() {
+            write!(f, " {}", vertex)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name each orbit by its smallest member, independent of the original labels.
+fn normalize(labels: &[VertexIndex]) -> Orbits {
+    let mut representatives = HashMap::new();
+    labels
+        .iter()
+        .enumerate()
+        .map(|(vertex, label)| {
+            *representatives
+                .entry(*label)
+                .or_insert(vertex as VertexIndex)
+        })
+        .collect()
+}
+
+/// Whether every orbit of `finer` is contained in an orbit of `coarser`.
+fn refines(finer: &[VertexIndex], coarser: &[VertexIndex]) -> bool {
+    let mut containing_orbit = HashMap::new();
+    finer
+        .iter()
+        .zip(coarser)
+        .all(|(fine, coarse)| containing_orbit.entry(*fine).or_insert(*coarse) == coarse)
+}
+
+fn find(parents: &mut [VertexIndex], vertex: VertexIndex) -> VertexIndex {
+    let parent = parents[vertex as usize];
+    if parent == vertex {
+        vertex
+    } else {
+        let root = find(parents, parent);
+        parents[vertex as usize] = root;
+        root
+    }
+}
+
+/// Join of both partitions, i.e. the transitive closure of being
+/// in the same orbit in either of them.
+fn common_coarsening(left: &[VertexIndex], right: &[VertexIndex]) -> Orbits {
+    let mut parents = (0..left.len() as VertexIndex).collect::<Vec<_>>();
+
+    for (vertex, orbits) in left.iter().zip(right).enumerate() {
+        for orbit in [*orbits.0, *orbits.1] {
+            let vertex_root = find(&mut parents, vertex as VertexIndex);
+            let orbit_root = find(&mut parents, orbit);
+            // Keep the smallest vertex as root to name the orbits by it.
+            let (root, child) = (vertex_root.min(orbit_root), vertex_root.max(orbit_root));
+            parents[child as usize] = root;
+        }
+    }
+
+    (0..left.len() as VertexIndex)
+        .map(|vertex| find(&mut parents, vertex))
+        .collect()
+}
+
+fn members(orbits: &[VertexIndex]) -> HashMap<VertexIndex, Vec<VertexIndex>> {
+    let mut members: HashMap<VertexIndex, Vec<VertexIndex>> = HashMap::new();
+    for (vertex, orbit) in orbits.iter().enumerate() {
+        members
+            .entry(*orbit)
+            .or_default()
+            .push(vertex as VertexIndex);
+    }
+    members
+}
+
+/// Compare two orbit partitions of the same graph. The orbit labels don't have to match.
+/// If one partition is shorter, the missing vertices are treated as singleton orbits.
+pub fn diff_orbits(left: &[VertexIndex], right: &[VertexIndex]) -> QuotientDiff {
+    let size = left.len().max(right.len());
+    let pad = |orbits: &[VertexIndex]| {
+        let mut padded = normalize(orbits);
+        padded.extend(orbits.len() as VertexIndex..size as VertexIndex);
+        padded
+    };
+    let (left, right) = (pad(left), pad(right));
+
+    let refinement = match (refines(&left, &right), refines(&right, &left)) {
+        (true, true) => Refinement::Equal,
+        (true, false) => Refinement::LeftFiner,
+        (false, true) => Refinement::RightFiner,
+        (false, false) => Refinement::Incomparable,
+    };
+
+    let (left_members, right_members) = (members(&left), members(&right));
+    let differing_vertices = (0..size as VertexIndex)
+        .filter(|vertex| {
+            left_members[&left[*vertex as usize]] != right_members[&right[*vertex as usize]]
+        })
+        .collect();
+
+    QuotientDiff {
+        refinement,
+        left_orbits: count_orbits(&left),
+        right_orbits: count_orbits(&right),
+        common_coarsening: common_coarsening(&left, &right),
+        differing_vertices,
+    }
+}
+
+/// Orbits from the `vertex,orbit` pairs of an orbit membership file.
+/// Vertices that aren't listed form their own orbits.
+pub fn orbits_from_membership(membership: &[(VertexIndex, VertexIndex)]) -> Orbits {
+    let size = membership
+        .iter()
+        .map(|(vertex, _)| *vertex as usize + 1)
+        .max()
+        .unwrap_or(0);
+    // Unlisted vertices get labels that can't clash with the listed orbits.
+    let mut labels = (0..size as VertexIndex)
+        .map(|vertex| -vertex - 1)
+        .collect::<Vec<_>>();
+    for (vertex, orbit) in membership {
+        labels[*vertex as usize] = *orbit;
+    }
+    normalize(&labels)
+}
+
+/// Compare the orbit partitions from two orbit membership csv files.
+#[cfg(not(tarpaulin_include))]
+pub fn diff_orbit_files(left: &Path, right: &Path) -> Result<QuotientDiff, Error> {
+    let left = parse_orbits_input(BufReader::new(File::open(left)?))?;
+    let right = parse_orbits_input(BufReader::new(File::open(right)?))?;
+    Ok(diff_orbits(
+        &orbits_from_membership(&left),
+        &orbits_from_membership(&right),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff_orbits_refinement() {
+        // {0,1},{2,3},{4} and {0,1,2,3},{4} with different labels.
+        let fine = vec![5, 5, 7, 7, 9];
+        let coarse = vec![1, 1, 1, 1, 0];
+
+        let diff = diff_orbits(&fine, &coarse);
+        assert_eq!(Refinement::LeftFiner, diff.refinement);
+        assert_eq!(3, diff.left_orbits);
+        assert_eq!(2, diff.right_orbits);
+        assert_eq!(vec![0, 0, 0, 0, 4], diff.common_coarsening);
+        assert_eq!(vec![0, 1, 2, 3], diff.differing_vertices);
+
+        assert_eq!(
+            Refinement::RightFiner,
+            diff_orbits(&coarse, &fine).refinement
+        );
+
+        let same = diff_orbits(&fine, &[0, 0, 2, 2, 4]);
+        assert_eq!(Refinement::Equal, same.refinement);
+        assert!(same.differing_vertices.is_empty());
+    }
+
+    #[test]
+    fn test_diff_orbits_incomparable() {
+        // {0,1},{2,3},{4} and {0},{1,2},{3,4}
+        let left = vec![0, 0, 2, 2, 4];
+        let right = vec![0, 1, 1, 3, 3];
+
+        let diff = diff_orbits(&left, &right);
+        assert_eq!(Refinement::Incomparable, diff.refinement);
+        assert_eq!(vec![0, 0, 0, 0, 0], diff.common_coarsening);
+        assert_eq!(vec![0, 1, 2, 3, 4], diff.differing_vertices);
+    }
+
+    #[test]
+    fn test_diff_orbits_padding() {
+        let diff = diff_orbits(&[0, 0], &[0, 0, 2]);
+        assert_eq!(Refinement::Equal, diff.refinement);
+        assert_eq!(vec![0, 0, 2], diff.common_coarsening);
+    }
+
+    #[test]
+    fn test_orbits_from_membership() {
+        let membership = vec![(0, 1), (2, 1), (3, 0)];
+        assert_eq!(vec![0, 1, 0, 3], orbits_from_membership(&membership));
+    }
+}
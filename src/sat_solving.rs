@@ -1,6 +1,5 @@
 use std::{
-    collections::HashMap,
-    convert::TryInto,
+    collections::{HashMap, HashSet},
     fs::File,
     process::{Command, Stdio},
     sync::Arc,
@@ -8,38 +7,111 @@ use std::{
 
 use flussab_cnf::cnf::Parser;
 use itertools::Itertools;
+#[cfg(feature = "native")]
 use kissat_rs::{Assignment, Solver};
-use num::ToPrimitive;
 
+#[cfg(not(feature = "native"))]
+use crate::dpll::{Assignment, Solver};
 use crate::{
-    debug::write_formula_dimacs,
+    debug::{write_formula_dimacs, write_formula_dimacs_commented, DimacsComments},
     encoding::{
-        encode_problem, Clause, HighLevelEncoding, QuotientGraphEncoding, SATEncodingDictionary,
+        encode_problem, Clause, EdgeEncoding, HighLevelEncoding, OrbitEncoding,
+        QuotientGraphEncoding, SATEncodingDictionary,
     },
+    external::limit_memory,
     graph::{Graph, VertexIndex},
+    interrupt::wait_interruptible,
     parser::_parse_mus,
     quotient::QuotientGraph,
-    Error,
+    EncodingOptions, Error, MemorySize, ProbeOptions,
 };
 
 pub fn solve(formula: impl Iterator<Item = Clause>) -> Result<bool, Error> {
     Solver::decide_formula(formula).map_err(Error::from)
 }
 
+/// Result of solving a formula with a conflict limited probe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// Decided within the conflict limit.
+    Decided(bool),
+    /// The probe gave up and the formula was decided with the full budget.
+    Escalated(bool),
+    /// The probe gave up and the formula is left for later.
+    Deferred,
+}
+
+impl ProbeOutcome {
+    pub fn is_hard(&self) -> bool {
+        !matches!(self, Self::Decided(_))
+    }
+
+    pub fn decided(&self) -> Option<bool> {
+        match self {
+            Self::Decided(result) | Self::Escalated(result) => Some(*result),
+            Self::Deferred => None,
+        }
+    }
+}
+
+/// Try to decide the formula with kissat within the given number of conflicts.
+/// Returns None if kissat gave up. Needs a kissat binary in the PATH.
+#[cfg(not(tarpaulin_include))]
+fn probe(
+    formula: &[Clause],
+    variable_number: usize,
+    options: &ProbeOptions,
+) -> Result<Option<bool>, Error> {
+    let mut kissat = limit_memory(&mut Command::new("kissat"), options.memory_limit)
+        .arg("-q")
+        .arg(format!("--conflicts={}", options.conflicts))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let mut stdin = kissat
+        .stdin
+        .take()
+        .expect("Failed to open stdin to kissat!");
+    write_formula_dimacs(&mut stdin, formula, variable_number)?;
+    // Close stdin so that kissat starts solving.
+    drop(stdin);
+
+    // 10 for Satisfiable, 20 for Unsatisfiable, 0 if the limit was hit
+    match wait_interruptible(&mut kissat)?.code() {
+        Some(10) => Ok(Some(true)),
+        Some(20) => Ok(Some(false)),
+        _ => Ok(None),
+    }
+}
+
+/// Solve the formula, but first probe it with a conflict limit.
+/// If the probe gives up, the formula is either solved with the
+/// full budget or deferred, depending on the options.
+#[cfg(not(tarpaulin_include))]
+pub fn solve_probed(
+    formula: impl Iterator<Item = Clause>,
+    variable_number: usize,
+    options: &ProbeOptions,
+) -> Result<ProbeOutcome, Error> {
+    let formula = formula.collect_vec();
+    if let Some(result) = probe(&formula, variable_number, options)? {
+        Ok(ProbeOutcome::Decided(result))
+    } else if options.defer_hard {
+        Ok(ProbeOutcome::Deferred)
+    } else {
+        solve(formula.into_iter()).map(ProbeOutcome::Escalated)
+    }
+}
+
 fn get_transversal(
     assignment: HashMap<i32, Option<Assignment>>,
-    dict: SATEncodingDictionary,
+    dict: &SATEncodingDictionary,
 ) -> Vec<(VertexIndex, VertexIndex)> {
+    // Auxiliary variables of the transversal encoding belong to no orbit
+    // and aren't part of the dictionary's pairs.
     let mut picked = dict
-        .destroy()
-        .into_iter()
-        .enumerate()
-        .filter(|(literal, _)| {
-            matches!(
-                assignment.get(&(literal.to_i32().unwrap())),
-                Some(Some(Assignment::True))
-            )
-        })
+        .iter()
+        .filter(|(literal, _)| matches!(assignment.get(literal), Some(Some(Assignment::True))))
         .map(|(_, orbit_vertex)| orbit_vertex)
         .collect_vec();
     picked.sort_unstable_by(|(orbit1, _), (orbit2, _)| orbit1.cmp(orbit2));
@@ -52,13 +124,13 @@ pub fn solve_validate(
     dict: SATEncodingDictionary,
 ) -> Result<Option<Vec<(VertexIndex, VertexIndex)>>, Error> {
     let assignment = Solver::solve_formula(formula).map_err(Error::from)?;
-    Ok(assignment.map(|assignment| get_transversal(assignment, dict)))
+    Ok(assignment.map(|assignment| get_transversal(assignment, &dict)))
 }
 
 fn _get_core_orbits_indexed(
     clause_indices: &[usize],
     formula: &[Clause],
-    dict: SATEncodingDictionary,
+    dict: &SATEncodingDictionary,
 ) -> Vec<VertexIndex> {
     let core_formula = clause_indices
         .iter()
@@ -72,22 +144,19 @@ fn _get_core_orbits_indexed(
     get_core_orbits(&core_formula, dict)
 }
 
-fn get_core_orbits(core_formula: &[Clause], dict: SATEncodingDictionary) -> Vec<VertexIndex> {
+fn get_core_orbits(core_formula: &[Clause], dict: &SATEncodingDictionary) -> Vec<VertexIndex> {
     let mut core_orbits = Vec::new();
-    let raw_dict = dict.destroy();
 
     for clause in core_formula {
         for variable in clause {
-            let orbit = raw_dict
-                .get::<usize>(
-                    variable
-                        .abs()
-                        .try_into()
-                        .expect("Could not transform literal to usize!"),
-                )
-                .expect("Variable not in dict!")
-                .0;
-            core_orbits.push(orbit);
+            assert!(
+                (variable.unsigned_abs() as usize) < dict.variable_number(),
+                "Variable not in dict!"
+            );
+            // Auxiliary variables of the transversal encoding belong to no orbit.
+            if let Some((orbit, _)) = dict.lookup_literal(*variable) {
+                core_orbits.push(orbit);
+            }
         }
     }
 
@@ -103,6 +172,7 @@ pub fn _solve_mus(
     quotient_graph: &QuotientGraph,
     graph: &Graph,
     dict: SATEncodingDictionary,
+    options: &EncodingOptions,
 ) -> Result<Option<QuotientGraphEncoding>, Error> {
     let formula_collected = formula.collect_vec();
 
@@ -127,13 +197,15 @@ pub fn _solve_mus(
         // 20 for Unsatisfiable
         if mus_out.status.code() == Some(20) {
             let core = _parse_mus(&mus_out.stdout)?;
-            let core_orbits = _get_core_orbits_indexed(&core, &formula_arc, dict);
+            let core_orbits = _get_core_orbits_indexed(&core, &formula_arc, &dict);
             dbg!(&core_orbits);
             let sub_quotient = quotient_graph.induced_subquotient(&core_orbits)?;
 
             // Make sure that the found orbits are in fact a non-descriptive core.
             // I don't really doubt picmus, but who knows what kind of MUS it finds.
-            let (formula, _) = encode_problem(&sub_quotient, graph).unwrap();
+            let (formula, _) = encode_problem(&sub_quotient, graph, options)?
+                .formula()
+                .unwrap();
             assert!(matches!(solve(formula), Ok(false)));
 
             Ok(Some(sub_quotient.encode_high()))
@@ -143,34 +215,37 @@ pub fn _solve_mus(
     }
 }
 
+/// Run kitten on the formula, with its address space limited if there is
+/// a limit, and return the core it found or None if it is satisfiable.
+/// Fails if kitten decides neither, e.g. because it ran out of memory.
 #[cfg(not(tarpaulin_include))]
-pub fn solve_mus_kitten(
-    formula: impl Iterator<Item = Clause>,
-    quotient_graph: &QuotientGraph,
-    graph: &Graph,
-    dict: SATEncodingDictionary,
-) -> Result<Option<QuotientGraphEncoding>, Error> {
+fn kitten_core(
+    formula: &[Clause],
+    variable_number: usize,
+    comments: Option<&DimacsComments<'_>>,
+    memory_limit: Option<MemorySize>,
+) -> Result<Option<Vec<Clause>>, Error> {
     use flussab_cnf::cnf::Config;
 
-    let formula_collected = formula.collect_vec();
-
-    if Solver::decide_formula(formula_collected.iter().cloned())? {
-        Ok(None)
+    let mut dqg_file = File::create("./dqg.cnf")?;
+    if let Some(comments) = comments {
+        write_formula_dimacs_commented(&mut dqg_file, formula, variable_number, comments)?;
     } else {
-        let mut dqg_file = File::create("./dqg.cnf")?;
-        let variable_number = dict.variable_number();
-        write_formula_dimacs(&mut dqg_file, &formula_collected, variable_number)?;
-
-        let mut kitten = Command::new("./kitten")
-            .arg("-O25")
-            .arg("./dqg.cnf")
-            .arg("./core.cnf")
-            .stdout(Stdio::piped())
-            .spawn()?;
-        let kitten_exit = kitten.wait()?;
+        write_formula_dimacs(&mut dqg_file, formula, variable_number)?;
+    }
 
-        // 20 for Unsatisfiable
-        if kitten_exit.code() == Some(20) {
+    let mut kitten = limit_memory(&mut Command::new("./kitten"), memory_limit)
+        .arg("-O25")
+        .arg("./dqg.cnf")
+        .arg("./core.cnf")
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let kitten_exit = wait_interruptible(&mut kitten)?;
+
+    // 10 for Satisfiable, 20 for Unsatisfiable
+    match kitten_exit.code() {
+        Some(10) => Ok(None),
+        Some(20) => {
             let core_file = File::open("./core.cnf")?;
             let conf = Config::ignore_header(Default::default(), true);
             let mut core_parser = Parser::from_read(core_file, conf).unwrap();
@@ -184,27 +259,184 @@ pub fn solve_mus_kitten(
                 }
             }
 
-            let core_orbits = get_core_orbits(&core, dict);
-            let sub_quotient = quotient_graph.induced_subquotient(&core_orbits)?;
+            Ok(Some(core))
+        }
+        _ => Err(Error::ExternalToolError("kitten", kitten_exit)),
+    }
+}
 
-            // Make sure that the found orbits are in fact a non-descriptive core.
-            // I don't really doubt picmus, but who knows what kind of MUS it finds.
-            let (formula, _) = encode_problem(&sub_quotient, graph).unwrap();
-            assert!(matches!(solve(formula), Ok(false)));
+/// Remove the clauses of a core from the formula so that
+/// the next core has to be a different one. Keeps the number
+/// of leading transversal clauses up to date.
+fn block_core(formula: &mut Vec<Clause>, core: &[Clause], transversal_clauses: &mut usize) {
+    let sorted = |clause: &Clause| clause.iter().copied().sorted().collect_vec();
+    let core_clauses = core.iter().map(sorted).collect::<HashSet<_>>();
+    let leading = *transversal_clauses;
+    let mut index = 0;
+    formula.retain(|clause| {
+        let keep = !core_clauses.contains(&sorted(clause));
+        if !keep && index < leading {
+            *transversal_clauses -= 1;
+        }
+        index += 1;
+        keep
+    });
+}
 
-            Ok(Some(sub_quotient.encode_high()))
-        } else {
-            Ok(None)
+#[cfg(not(tarpaulin_include))]
+pub fn solve_mus_kitten(
+    formula: impl Iterator<Item = Clause>,
+    quotient_graph: &QuotientGraph,
+    graph: &Graph,
+    dict: SATEncodingDictionary,
+    options: &EncodingOptions,
+) -> Result<Option<QuotientGraphEncoding>, Error> {
+    let cores = solve_mus_kitten_multiple(formula, quotient_graph, graph, dict, options, 1)?;
+    Ok(cores.into_iter().next())
+}
+
+/// The orbits of a non-descriptive core and the quotient edges
+/// whose descriptiveness constraints are part of the core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictGraph {
+    pub orbits: Vec<OrbitEncoding>,
+    /// Each edge only once from its smaller orbit.
+    pub edges: Vec<(VertexIndex, VertexIndex)>,
+}
+
+/// Find a non-descriptive core of the quotient and the conflict graph between its orbits.
+/// Returns None if the quotient is descriptive.
+#[cfg(not(tarpaulin_include))]
+pub fn solve_conflict_graph(
+    formula: impl Iterator<Item = Clause>,
+    quotient_graph: &QuotientGraph,
+    graph: &Graph,
+    mut dict: SATEncodingDictionary,
+    options: &EncodingOptions,
+) -> Result<Option<ConflictGraph>, Error> {
+    let formula = formula.collect_vec();
+    let core = match kitten_core(
+        &formula,
+        dict.variable_number(),
+        None,
+        options.kitten_memory,
+    )? {
+        Some(core) => core,
+        None => return Ok(None),
+    };
+    let sorted = |clause: &Clause| clause.iter().copied().sorted().collect_vec();
+    let core_clauses = core.iter().map(sorted).collect::<HashSet<_>>();
+
+    // Encode each quotient edge on its own to see whether its clauses are part of the core.
+    let QuotientGraphEncoding(quotient_edges, orbits) = quotient_graph.encoding();
+    let mut edges = Vec::new();
+    for edge in quotient_edges.iter().filter(|edge| edge.0 < edge.1) {
+        let edge_orbits = orbits
+            .iter()
+            .filter(|(orbit, _)| *orbit == edge.0 || *orbit == edge.1)
+            .cloned()
+            .collect_vec();
+        let edge_encoding =
+            QuotientGraphEncoding(vec![*edge, EdgeEncoding(edge.1, edge.0)], edge_orbits)
+                .encode_sat_with(&mut dict, graph, options, None);
+        if edge_encoding
+            .iter()
+            .any(|clause| core_clauses.contains(&sorted(clause)))
+        {
+            edges.push(edge.get_edge());
+        }
+    }
+
+    let core_orbits = get_core_orbits(&core, &dict);
+    let orbits = orbits
+        .iter()
+        .filter(|(orbit, _)| core_orbits.binary_search(orbit).is_ok())
+        .cloned()
+        .collect();
+
+    Ok(Some(ConflictGraph { orbits, edges }))
+}
+
+/// Find up to `max_cores` distinct non-descriptive cores of the quotient.
+/// Each core is blocked by removing its clauses before searching for the next one,
+/// so the search stops early once the remaining formula becomes satisfiable.
+/// Returns no cores if the quotient is descriptive.
+#[cfg(not(tarpaulin_include))]
+pub fn solve_mus_kitten_multiple(
+    formula: impl Iterator<Item = Clause>,
+    quotient_graph: &QuotientGraph,
+    graph: &Graph,
+    dict: SATEncodingDictionary,
+    options: &EncodingOptions,
+    max_cores: usize,
+) -> Result<Vec<QuotientGraphEncoding>, Error> {
+    let mut formula_collected = formula.collect_vec();
+    let mut cores: Vec<QuotientGraphEncoding> = Vec::new();
+
+    if Solver::decide_formula(formula_collected.iter().cloned())? {
+        return Ok(cores);
+    }
+
+    let variable_number = dict.variable_number();
+    let mut transversal_clauses = dict.transversal_clauses();
+    let pairs = if options.cnf_comments {
+        dict.pairs()
+    } else {
+        Vec::new()
+    };
+
+    while cores.len() < max_cores.max(1) {
+        let comments = DimacsComments {
+            pairs: &pairs,
+            transversal_clauses,
+        };
+        let comments = options.cnf_comments.then_some(&comments);
+        let core = match kitten_core(
+            &formula_collected,
+            variable_number,
+            comments,
+            options.kitten_memory,
+        )? {
+            Some(core) => core,
+            None => break,
+        };
+
+        let core_orbits = get_core_orbits(&core, &dict);
+        let sub_quotient = quotient_graph.induced_subquotient(&core_orbits)?;
+
+        // Make sure that the found orbits are in fact a non-descriptive core.
+        // I don't really doubt picmus, but who knows what kind of MUS it finds.
+        let (formula, _) = encode_problem(&sub_quotient, graph, options)?
+            .formula()
+            .unwrap();
+        assert!(matches!(solve(formula), Ok(false)));
+
+        // Different clause sets can still cover the same orbits.
+        let core_encoding = sub_quotient.encode_high();
+        if cores.iter().all(|found| found.1 != core_encoding.1) {
+            cores.push(core_encoding);
         }
+        block_core(&mut formula_collected, &core, &mut transversal_clauses);
     }
+
+    Ok(cores)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{encoding::encode_problem, graph::Graph, quotient::QuotientGraph};
+    use crate::{encoding::encode_problem, graph::Graph, quotient::QuotientGraph, EncodingOptions};
 
     use super::*;
 
+    #[test]
+    fn test_probe_outcome() {
+        assert!(!ProbeOutcome::Decided(true).is_hard());
+        assert!(ProbeOutcome::Escalated(false).is_hard());
+        assert!(ProbeOutcome::Deferred.is_hard());
+        assert_eq!(Some(false), ProbeOutcome::Escalated(false).decided());
+        assert_eq!(None, ProbeOutcome::Deferred.decided());
+    }
+
     #[test]
     fn test_non_descriptive() -> Result<(), Error> {
         //0-1-2-3, where 1 and 2 are in the same (fake) orbit.
@@ -219,9 +451,9 @@ mod test {
         let fake_orbits = vec![0, 1, 1, 3];
         let quotient = QuotientGraph::from_graph_orbits(&graph, fake_orbits);
 
-        let formula = encode_problem(&quotient, &graph);
+        let formula = encode_problem(&quotient, &graph, &EncodingOptions::default())?;
 
-        let result = solve(formula.unwrap().0);
+        let result = solve(formula.formula().unwrap().0);
         assert!(result.is_ok());
         assert_eq!(false, result.unwrap());
 
@@ -248,7 +480,7 @@ mod test {
         assert_eq!(4, dict.lookup_pairing(2, 2));
 
         let expected_transversal = vec![(0, 0), (2, 3)];
-        assert_eq!(expected_transversal, get_transversal(assignment, dict));
+        assert_eq!(expected_transversal, get_transversal(assignment, &dict));
     }
 
     #[test]
@@ -276,7 +508,19 @@ mod test {
 
         assert_eq!(
             expected_orbits,
-            _get_core_orbits_indexed(&core, &formula, dict)
+            _get_core_orbits_indexed(&core, &formula, &dict)
         );
     }
+
+    #[test]
+    fn test_block_core() {
+        let mut formula = vec![vec![1, 2], vec![-1, -2], vec![3, 4], vec![-1], vec![-2]];
+        // Core clauses with a different literal order.
+        let core = vec![vec![2, 1], vec![-1], vec![-2]];
+
+        let mut transversal_clauses = 2;
+        block_core(&mut formula, &core, &mut transversal_clauses);
+        assert_eq!(vec![vec![-1, -2], vec![3, 4]], formula);
+        assert_eq!(1, transversal_clauses);
+    }
 }
@@ -0,0 +1,185 @@
+//! Session to check many graphs in one process (e.g. in batch mode)
+//! without allocating the same buffers anew for each graph.
+
+use std::{mem, time::Instant};
+
+use crate::{
+    combinatoric::Powerset,
+    debug::print_orbits,
+    encoding::{encode_problem_reusing, EncodeOutcome, EncodingCache, SATEncodingDictionary},
+    graph::Graph,
+    interrupt::check_interrupted,
+    permutation::Permutation,
+    predictor::{predict, skip_solving},
+    progress::ProgressEvent,
+    quotient::{compute_generators, generate_orbits_into, Orbits, QuotientGraph, TrivialPartition},
+    sat_solving::solve,
+    statistics::NoStatistics,
+    timed_out, Error, Outcome, Settings,
+};
+
+/// Buffers that are reused across quotients and graphs.
+/// nauty itself keeps its dynamic workspace between calls,
+/// so only the buffers of this crate are owned here.
+#[derive(Debug, Default)]
+pub struct Session {
+    orbits: Orbits,
+    dict: SATEncodingDictionary,
+    encoding_cache: EncodingCache,
+}
+
+impl Session {
+    /// Search for a descriptive quotient of the given graph like a single run does,
+    /// but without statistics.
+    #[cfg(not(tarpaulin_include))]
+    pub fn check_graph(
+        &mut self,
+        graph: &mut Graph,
+        settings: &Settings,
+    ) -> Result<Outcome, Error> {
+        let outcome = self.search_graph(graph, settings)?;
+        settings.report_progress(|| ProgressEvent::Finished { outcome });
+        Ok(outcome)
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    fn search_graph(&mut self, graph: &mut Graph, settings: &Settings) -> Result<Outcome, Error> {
+        let start_time = Instant::now();
+        let mut generators = compute_generators(graph, settings, &mut NoStatistics);
+        graph.sort();
+        graph.choose_representation();
+        self.encoding_cache.clear();
+        settings.report_progress(|| ProgressEvent::GeneratorsComputed {
+            n: generators.len(),
+        });
+
+        if generators.is_empty() {
+            return Ok(Outcome::Asymmetric);
+        }
+
+        if !settings.iter_powerset {
+            let descriptive = self.check_generators(graph, &mut generators, settings)?;
+            settings.report_progress(|| ProgressEvent::QuotientChecked {
+                descriptive,
+                subset: (0..generators.len()).collect(),
+            });
+            return Ok(if descriptive {
+                Outcome::DescriptiveFound
+            } else {
+                Outcome::ExhaustedWithoutDescriptive
+            });
+        }
+
+        let mut powerset = Powerset::bounded_by(generators.len(), settings);
+        let mut subset = Vec::with_capacity(generators.len());
+        while let Some(subset_indices) = powerset.next_subset() {
+            check_interrupted()?;
+            if timed_out(start_time, settings.timeout) {
+                return Ok(Outcome::Timeout);
+            }
+            subset.clear();
+            subset.extend(
+                subset_indices
+                    .iter()
+                    .map(|index| generators[*index].clone()),
+            );
+            let descriptive = self.check_generators(graph, &mut subset, settings)?;
+            settings.report_progress(|| ProgressEvent::QuotientChecked {
+                descriptive,
+                subset: subset_indices.to_vec(),
+            });
+            if descriptive {
+                return Ok(Outcome::DescriptiveFound);
+            }
+        }
+        Ok(Outcome::ExhaustedWithoutDescriptive)
+    }
+
+    /// Whether the quotient induced by the generators is descriptive.
+    /// The graph needs to be sorted. Call `clear` before switching to another
+    /// graph, as the cached encodings are only valid for one graph and the
+    /// check fails with `Error::StaleGraph` otherwise.
+    pub fn check_generators(
+        &mut self,
+        graph: &Graph,
+        generators: &mut [Permutation],
+        settings: &Settings,
+    ) -> Result<bool, Error> {
+        generate_orbits_into(generators, &mut self.orbits);
+
+        if TrivialPartition::detect(&self.orbits).is_some() {
+            if settings.output_orbits {
+                print_orbits(self.orbits.clone(), settings, &mut NoStatistics);
+            }
+            return Ok(true);
+        }
+
+        let quotient_graph = QuotientGraph::from_graph_orbits(graph, mem::take(&mut self.orbits));
+
+        let descriptive = if skip_solving(predict(&quotient_graph, settings), settings) {
+            false
+        } else if let EncodeOutcome::Formula(formula, mut dict) = encode_problem_reusing(
+            &quotient_graph,
+            graph,
+            &settings.encoding_options,
+            &mut self.encoding_cache,
+            mem::take(&mut self.dict),
+        )? {
+            let descriptive = solve(formula)?;
+            dict.clear();
+            self.dict = dict;
+            descriptive
+        } else {
+            // Trivially descriptive
+            true
+        };
+
+        if descriptive && settings.output_orbits {
+            print_orbits(quotient_graph.orbits.clone(), settings, &mut NoStatistics);
+        }
+        self.orbits = quotient_graph.orbits;
+
+        Ok(descriptive)
+    }
+
+    /// Forget everything that is specific to the last graph but keep the allocations.
+    pub fn clear(&mut self) {
+        self.dict.clear();
+        self.encoding_cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_session_multiple_graphs() {
+        let settings = Settings::default();
+        let mut session = Session::default();
+
+        // A path of three vertices with the reflection is descriptive.
+        let mut path = Graph::new_ordered(3);
+        path.add_edge(0, 1).unwrap();
+        path.add_edge(1, 2).unwrap();
+        path.sort();
+        let mut reflection = vec![Permutation::new(vec![2, 1, 0]).unwrap()];
+        assert!(session
+            .check_generators(&path, &mut reflection, &settings)
+            .unwrap());
+
+        // A cycle of six vertices with the rotation by three is not.
+        session.clear();
+        let mut cycle = Graph::new_ordered(6);
+        for vertex in 0..6 {
+            cycle.add_edge(vertex, (vertex + 1) % 6).unwrap();
+        }
+        cycle.sort();
+        let mut rotation = vec![Permutation::new(vec![3, 4, 5, 0, 1, 2]).unwrap()];
+        assert!(!session
+            .check_generators(&cycle, &mut rotation, &settings)
+            .unwrap());
+        assert_eq!(vec![0, 1, 2, 0, 1, 2], session.orbits);
+        assert_eq!(1, session.dict.variable_number());
+    }
+}
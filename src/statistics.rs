@@ -1,21 +1,27 @@
 //! Statistics about different parts of the program.
 
 use custom_debug_derive::Debug;
+use itertools::Itertools;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    thread,
     time::{Duration, Instant},
 };
 
 use crate::{
     debug::{opt_fmt, result_fmt},
-    encoding::OrbitEncoding,
-    Error,
+    encoding::{Clause, OrbitEncoding, TrivialReason},
+    external::peak_external_memory,
+    graph::{Graph, VertexIndex},
+    predictor::Prediction,
+    quotient::TrivialPartition,
+    AutomorphismOptions, EncodingOptions, Error,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatisticsLevel {
     None,
     Basic,
@@ -54,8 +60,149 @@ impl OrbitStatistics {
     }
 }
 
+/// Counts how often each vertex was part of a non-descriptive core
+/// in core mode. Orbit numbers change between iterations, so vertices
+/// are counted instead. Vertices that keep appearing in cores point
+/// to the structurally problematic regions of the graph.
+#[derive(Default)]
+pub struct CoreFrequencies {
+    pub number_of_cores: usize,
+    /// Number of quotients the cores were found in.
+    pub number_of_quotients: usize,
+    pub vertex_frequencies: HashMap<VertexIndex, usize>,
+}
+
+impl CoreFrequencies {
+    pub fn log_core(&mut self, core: &[OrbitEncoding]) {
+        self.number_of_cores += 1;
+        for (_, members) in core {
+            for member in members {
+                *self.vertex_frequencies.entry(*member).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Log all cores found in the same quotient.
+    pub fn log_cores(&mut self, cores: &[Vec<OrbitEncoding>]) {
+        self.number_of_quotients += 1;
+        for core in cores {
+            self.log_core(core);
+        }
+    }
+
+    /// Vertices sorted by how often they appeared in cores, most frequent first.
+    pub fn sorted_frequencies(&self) -> Vec<(VertexIndex, usize)> {
+        let mut frequencies = self
+            .vertex_frequencies
+            .iter()
+            .map(|(vertex, frequency)| (*vertex, *frequency))
+            .collect::<Vec<_>>();
+        frequencies.sort_unstable_by(|(vertex_a, frequency_a), (vertex_b, frequency_b)| {
+            frequency_b.cmp(frequency_a).then(vertex_a.cmp(vertex_b))
+        });
+        frequencies
+    }
+}
+
+/// How the predictor did on the quotients it was confident about.
+#[derive(Debug, Default)]
+pub struct PredictionStatistics {
+    pub confident: usize,
+    /// Quotients whose SAT call was skipped due to the prediction.
+    pub skipped: usize,
+    /// Solved quotients whose descriptiveness differed from the prediction.
+    pub mispredicted: usize,
+}
+
+impl PredictionStatistics {
+    /// Log a confident prediction and the solved descriptiveness,
+    /// which is None if solving was skipped.
+    pub fn log_prediction(&mut self, prediction: Prediction, descriptive: Option<bool>) {
+        self.confident += 1;
+        match descriptive {
+            None => self.skipped += 1,
+            Some(descriptive) if descriptive != (prediction == Prediction::Descriptive) => {
+                self.mispredicted += 1
+            }
+            Some(_) => (),
+        }
+    }
+}
+
+/// The symmetry left in an iteration of core mode, so that
+/// the loss of symmetry caused by breaking cores is visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreIteration {
+    /// Name of the core strategy, as auto mode interleaves them.
+    pub strategy: &'static str,
+    pub generators: usize,
+    pub orbits: usize,
+    pub max_orbit_size: usize,
+}
+
+impl CoreIteration {
+    pub fn new(strategy: &'static str, generators: usize, orbits: &[VertexIndex]) -> Self {
+        let orbit_sizes = orbits.iter().filter(|orbit| **orbit >= 0).counts();
+        CoreIteration {
+            strategy,
+            generators,
+            orbits: orbit_sizes.len(),
+            max_orbit_size: orbit_sizes.values().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+/// Cheap invariants of a quotient graph to correlate its
+/// structure with the behaviour of the SAT solver.
+#[derive(Debug, PartialEq)]
+pub struct QuotientInvariants {
+    pub edges: usize,
+    pub density: f64,
+    pub components: usize,
+    pub min_degree: usize,
+    pub max_degree: usize,
+    pub mean_degree: f64,
+}
+
+impl QuotientInvariants {
+    /// Quotient graphs have no loops and store each edge in both directions.
+    pub fn from_quotient_graph(quotient_graph: &Graph) -> Self {
+        let size = quotient_graph.vertices.len();
+        let degrees = quotient_graph
+            .vertices
+            .iter()
+            .map(|vertex| vertex.edges_to.len())
+            .collect::<Vec<_>>();
+        let edges = degrees.iter().sum::<usize>() / 2;
+        let density = if size > 1 {
+            (2 * edges) as f64 / (size * (size - 1)) as f64
+        } else {
+            0.0
+        };
+
+        QuotientInvariants {
+            edges,
+            density,
+            components: quotient_graph.number_of_components(),
+            min_degree: degrees.iter().copied().min().unwrap_or(0),
+            max_degree: degrees.iter().copied().max().unwrap_or(0),
+            mean_degree: if size > 0 {
+                (2 * edges) as f64 / size as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct QuotientStatistics {
+    /// Indices of the generators (in the order computed by nauty/Traces)
+    /// that induced this quotient. None if the quotient was not induced
+    /// by a subset of these generators (e.g. GAP representatives).
+    #[debug(with = "opt_fmt")]
+    pub generator_subset: Option<Vec<usize>>,
+    pub subset_size: usize,
     pub quotient_size: usize,
     #[debug(with = "opt_fmt")]
     pub core_size: Option<usize>,
@@ -71,6 +218,98 @@ pub struct QuotientStatistics {
     pub quotient_gen_time: Duration,
     pub encoding_time: Duration,
     pub orbit_sizes: OrbitStatistics,
+    /// Not computed for quotients decided by their orbit partition alone.
+    #[debug(with = "opt_fmt")]
+    pub invariants: Option<QuotientInvariants>,
+    /// Set if the quotient was decided by its orbit partition alone.
+    #[debug(with = "opt_fmt")]
+    pub trivial_partition: Option<TrivialPartition>,
+    /// Set if the quotient was encoded without any constraints.
+    #[debug(with = "opt_fmt")]
+    pub trivial_encoding: Option<TrivialReason>,
+    /// None if the quotient wasn't encoded or the formula wasn't collected.
+    #[debug(with = "opt_fmt")]
+    pub formula: Option<FormulaStatistics>,
+}
+
+/// Size and shape of the CNF formula of a quotient, to correlate
+/// with the solver time without exporting the formula.
+#[derive(Debug, Default, PartialEq)]
+pub struct FormulaStatistics {
+    /// Number of variables that occur in the clauses.
+    pub variables: usize,
+    pub clauses: usize,
+    /// Number of clauses of each length, indexed by the length.
+    pub clause_lengths: Vec<usize>,
+    pub clause_variable_ratio: f64,
+}
+
+impl FormulaStatistics {
+    pub fn from_formula(formula: &[Clause]) -> Self {
+        let mut variables = HashSet::new();
+        let mut clause_lengths = Vec::new();
+        for clause in formula {
+            if clause_lengths.len() <= clause.len() {
+                clause_lengths.resize(clause.len() + 1, 0);
+            }
+            clause_lengths[clause.len()] += 1;
+            variables.extend(clause.iter().map(|literal| literal.unsigned_abs()));
+        }
+
+        FormulaStatistics {
+            variables: variables.len(),
+            clauses: formula.len(),
+            clause_lengths,
+            clause_variable_ratio: if variables.is_empty() {
+                0.0
+            } else {
+                formula.len() as f64 / variables.len() as f64
+            },
+        }
+    }
+
+    /// An empty clause makes the formula trivially unsatisfiable
+    /// and points to a broken encoding.
+    pub fn is_degenerate(&self) -> bool {
+        self.clause_lengths.first().is_some_and(|empty| *empty > 0)
+    }
+}
+
+impl QuotientStatistics {
+    /// Statistics of a quotient that was decided by its trivial
+    /// orbit partition, i.e. without building or encoding it.
+    #[cfg(not(tarpaulin_include))]
+    pub fn from_trivial_partition(
+        trivial_partition: TrivialPartition,
+        graph_size: usize,
+        generator_subset: Option<Vec<usize>>,
+        subset_size: usize,
+        quotient_handling_time: Duration,
+        orbit_gen_time: Duration,
+    ) -> Self {
+        let orbit_size = trivial_partition.orbit_size(graph_size);
+
+        QuotientStatistics {
+            generator_subset,
+            subset_size,
+            quotient_size: trivial_partition.quotient_size(graph_size),
+            core_size: None,
+            max_orbit_size: orbit_size,
+            min_orbit_size: orbit_size,
+            descriptive: Ok(true),
+            validated: None,
+            quotient_handling_time,
+            kissat_time: Duration::ZERO,
+            orbit_gen_time,
+            quotient_gen_time: Duration::ZERO,
+            encoding_time: Duration::ZERO,
+            orbit_sizes: Default::default(),
+            invariants: None,
+            trivial_partition: Some(trivial_partition),
+            trivial_encoding: None,
+            formula: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -78,9 +317,14 @@ pub struct Statistics {
     // Meta information
     #[debug(skip)]
     level: StatisticsLevel,
-    #[debug(skip)]
-    out_file: PathBuf,
     pub exhausted: bool,
+    /// Why the run ended early, None if it ran to the end.
+    #[debug(with = "opt_fmt")]
+    aborted: Option<String>,
+    /// GAP missed its deadline and the generator subsets were searched instead.
+    gap_fallback: bool,
+    /// The generators were read from the generator cache of the input.
+    cached_generators: bool,
     // Timings
     #[debug(skip)]
     pub start_time: Instant,
@@ -92,15 +336,41 @@ pub struct Statistics {
     end_time: Option<Duration>,
     #[debug(with = "opt_fmt")]
     graph_sort_time: Option<Duration>,
+    /// Largest resident set size in KiB of the external tools, e.g. GAP
+    /// and kissat, that ran before the end of the run.
+    #[debug(with = "opt_fmt")]
+    peak_external_memory: Option<u64>,
     // Graph statistics
     graph_size: usize,
+    /// Number of vertices of each colour.
+    colour_class_sizes: Vec<usize>,
     group_size: f64,
+    #[debug(with = "opt_fmt")]
+    automorphism_options: Option<AutomorphismOptions>,
+    #[debug(with = "opt_fmt")]
+    encoding_options: Option<EncodingOptions>,
     iteration_counter: usize,
     descriptive_found: bool,
     #[debug(with = "opt_fmt")]
     number_of_generators: Option<usize>,
     max_orbit_size: usize,
     max_quotient_graph_size: usize,
+    encoding_cache_hits: usize,
+    /// Quotients that the conflict limited probe couldn't decide.
+    hard_quotients: usize,
+    /// Quotients that were decided right after the orbit generation,
+    /// without building or encoding them.
+    identity_quotients: usize,
+    single_orbit_quotients: usize,
+    /// Quotients that were encoded without any constraints.
+    no_edge_quotients: usize,
+    complete_edge_quotients: usize,
+    /// Quotients whose formula contained an empty clause.
+    degenerate_formulas: usize,
+    predictions: PredictionStatistics,
+    core_frequencies: CoreFrequencies,
+    /// The symmetry left in each iteration of core mode.
+    core_iterations: Vec<CoreIteration>,
     #[debug(with = "opt_fmt")]
     max_quotient_handling_time: Option<Duration>,
     #[debug(with = "opt_fmt")]
@@ -110,31 +380,56 @@ pub struct Statistics {
 
 impl Statistics {
     #[cfg(not(tarpaulin_include))]
-    pub fn new(level: StatisticsLevel, out_file: PathBuf, graph_size: usize) -> Self {
+    pub fn new(level: StatisticsLevel, graph_size: usize) -> Self {
         debug_assert!(level != StatisticsLevel::None);
 
         Statistics {
             level,
-            out_file,
             start_time: Instant::now(),
             exhausted: false,
+            aborted: None,
+            gap_fallback: false,
+            cached_generators: false,
             nauty_done_time: None,
             gap_done_time: None,
             end_time: None,
             graph_sort_time: None,
+            peak_external_memory: None,
             graph_size,
+            colour_class_sizes: Vec::new(),
             group_size: 0.,
+            automorphism_options: None,
+            encoding_options: None,
             iteration_counter: 0,
             descriptive_found: false,
             number_of_generators: None,
             max_orbit_size: 0,
             max_quotient_graph_size: 0,
+            encoding_cache_hits: 0,
+            hard_quotients: 0,
+            identity_quotients: 0,
+            single_orbit_quotients: 0,
+            no_edge_quotients: 0,
+            complete_edge_quotients: 0,
+            degenerate_formulas: 0,
+            predictions: PredictionStatistics::default(),
+            core_frequencies: CoreFrequencies::default(),
+            core_iterations: Vec::new(),
             max_quotient_handling_time: None,
             max_kissat_time: None,
             quotient_statistics: Vec::new(),
         }
     }
 
+    /// Mark the statistics as partial, e.g. because the run failed.
+    pub fn log_abort(&mut self, reason: String) {
+        self.aborted = Some(reason);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.is_some()
+    }
+
     #[cfg(not(tarpaulin_include))]
     pub fn log_iteration(&mut self) {
         self.iteration_counter += 1;
@@ -147,6 +442,23 @@ impl Statistics {
         self.group_size = base * 10f64.pow(mantisse);
     }
 
+    /// Log the options that were effectively passed to nauty/Traces.
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_automorphism_options(&mut self, options: AutomorphismOptions) {
+        self.automorphism_options = Some(options);
+    }
+
+    /// Log the options used to encode the quotients into SAT.
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_encoding_options(&mut self, options: EncodingOptions) {
+        self.encoding_options = Some(options);
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn get_group_size(&self) -> f64 {
+        self.group_size
+    }
+
     #[cfg(not(tarpaulin_include))]
     pub fn log_nauty_done(&mut self) {
         self.nauty_done_time = Some(self.start_time.elapsed());
@@ -162,6 +474,16 @@ impl Statistics {
         self.gap_done_time = Some(duration);
     }
 
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_gap_fallback(&mut self) {
+        self.gap_fallback = true;
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_cached_generators(&mut self) {
+        self.cached_generators = true;
+    }
+
     #[cfg(not(tarpaulin_include))]
     pub fn log_graph_sorted(&mut self, duration: Duration) {
         self.graph_sort_time = Some(duration);
@@ -175,6 +497,12 @@ impl Statistics {
     #[cfg(not(tarpaulin_include))]
     pub fn log_end(&mut self) {
         self.end_time = Some(self.start_time.elapsed());
+        self.peak_external_memory = peak_external_memory();
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_colour_classes(&mut self, colour_class_sizes: Vec<usize>) {
+        self.colour_class_sizes = colour_class_sizes;
     }
 
     #[cfg(not(tarpaulin_include))]
@@ -182,6 +510,31 @@ impl Statistics {
         self.number_of_generators = Some(number_of_generators);
     }
 
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_prediction(&mut self, prediction: Prediction, descriptive: Option<bool>) {
+        self.predictions.log_prediction(prediction, descriptive);
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_encoding_cache_hits(&mut self, hits: usize) {
+        self.encoding_cache_hits = hits;
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_hard_quotient(&mut self) {
+        self.hard_quotients += 1;
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_cores(&mut self, cores: &[Vec<OrbitEncoding>]) {
+        self.core_frequencies.log_cores(cores);
+    }
+
+    #[cfg(not(tarpaulin_include))]
+    pub fn log_core_iteration(&mut self, iteration: CoreIteration) {
+        self.core_iterations.push(iteration);
+    }
+
     #[cfg(not(tarpaulin_include))]
     pub fn log_quotient_statistic(&mut self, quotient_statistic: QuotientStatistics) {
         self.descriptive_found |= matches!(quotient_statistic.descriptive, Ok(true));
@@ -200,6 +553,24 @@ impl Statistics {
             Some(quotient_statistic.kissat_time)
         };
 
+        match quotient_statistic.trivial_partition {
+            Some(TrivialPartition::Identity) => self.identity_quotients += 1,
+            Some(TrivialPartition::SingleOrbit) => self.single_orbit_quotients += 1,
+            None => (),
+        }
+        match quotient_statistic.trivial_encoding {
+            Some(TrivialReason::NoQuotientEdges) => self.no_edge_quotients += 1,
+            Some(TrivialReason::CompleteQuotientEdges) => self.complete_edge_quotients += 1,
+            None => (),
+        }
+        if quotient_statistic
+            .formula
+            .as_ref()
+            .is_some_and(FormulaStatistics::is_degenerate)
+        {
+            self.degenerate_formulas += 1;
+        }
+
         if self.level == StatisticsLevel::Full {
             self.quotient_statistics.push(quotient_statistic);
         }
@@ -214,8 +585,261 @@ impl Statistics {
     }
 
     #[cfg(not(tarpaulin_include))]
-    pub fn save_statistics(&self) -> Result<(), Error> {
-        let mut statistics_file = File::create(&self.out_file)?;
+    pub fn save_statistics(&self, out_file: &Path) -> Result<(), Error> {
+        let mut statistics_file = File::create(out_file)?;
         write!(statistics_file, "Raw Statistics: {:#?}", self).map_err(Error::from)
     }
 }
+
+/// Where the statistics of a run go. Everything that logs
+/// statistics gets the sink passed explicitly.
+pub trait StatisticsSink {
+    /// The statistics to log into, None if nothing is logged.
+    fn statistics(&mut self) -> Option<&mut Statistics>;
+
+    /// Persist the statistics at the end of a run.
+    fn save(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl dyn StatisticsSink + '_ {
+    /// Log into the statistics if they are collected at all.
+    pub fn log(&mut self, log: impl FnOnce(&mut Statistics)) {
+        if let Some(statistics) = self.statistics() {
+            log(statistics);
+        }
+    }
+
+    pub fn is_collecting(&mut self) -> bool {
+        self.statistics().is_some()
+    }
+}
+
+/// Collects no statistics at all.
+#[derive(Debug, Default)]
+pub struct NoStatistics;
+
+impl StatisticsSink for NoStatistics {
+    fn statistics(&mut self) -> Option<&mut Statistics> {
+        None
+    }
+}
+
+/// Keeps the statistics in memory, e.g. for library callers that inspect them.
+#[derive(Debug)]
+pub struct InMemoryStatistics(pub Statistics);
+
+impl StatisticsSink for InMemoryStatistics {
+    fn statistics(&mut self) -> Option<&mut Statistics> {
+        Some(&mut self.0)
+    }
+}
+
+/// Writes the statistics to a file once they are saved. Statistics
+/// that are dropped unsaved because the run was aborted or panicked
+/// are written as well, so that the partial statistics aren't lost.
+#[derive(Debug)]
+pub struct FileStatistics {
+    pub statistics: Statistics,
+    pub out_file: PathBuf,
+    saved: bool,
+}
+
+impl FileStatistics {
+    pub fn new(statistics: Statistics, out_file: PathBuf) -> Self {
+        FileStatistics {
+            statistics,
+            out_file,
+            saved: false,
+        }
+    }
+}
+
+impl StatisticsSink for FileStatistics {
+    fn statistics(&mut self) -> Option<&mut Statistics> {
+        Some(&mut self.statistics)
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.saved = true;
+        self.statistics.save_statistics(&self.out_file)
+    }
+}
+
+impl Drop for FileStatistics {
+    fn drop(&mut self) {
+        if self.saved {
+            return;
+        }
+        if thread::panicking() {
+            self.statistics.log_abort("panic".to_string());
+        }
+        // Modes that don't save any statistics didn't abort.
+        if self.statistics.is_aborted() {
+            self.statistics.log_end();
+            if let Err(error) = self.save() {
+                eprintln!("Couldn't save the partial statistics: {:?}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::quotient::QuotientGraph;
+
+    #[test]
+    fn test_formula_statistics() {
+        let formula = vec![vec![1, 2], vec![-1, -2], vec![3], vec![-1, 2, -3]];
+        let stats = FormulaStatistics::from_formula(&formula);
+        assert_eq!(3, stats.variables);
+        assert_eq!(4, stats.clauses);
+        assert_eq!(vec![0, 1, 2, 1], stats.clause_lengths);
+        assert!((stats.clause_variable_ratio - 4.0 / 3.0).abs() < f64::EPSILON);
+        assert!(!stats.is_degenerate());
+
+        assert!(FormulaStatistics::from_formula(&[vec![1], vec![]]).is_degenerate());
+        assert_eq!(
+            FormulaStatistics::default(),
+            FormulaStatistics::from_formula(&[])
+        );
+    }
+
+    #[test]
+    fn test_statistics_sinks() {
+        let sink: &mut dyn StatisticsSink = &mut NoStatistics;
+        assert!(!sink.is_collecting());
+        sink.log(|_| panic!("Nothing should be logged!"));
+
+        let mut in_memory = InMemoryStatistics(Statistics::new(StatisticsLevel::Basic, 4));
+        let sink: &mut dyn StatisticsSink = &mut in_memory;
+        assert!(sink.is_collecting());
+        sink.log(Statistics::log_iteration);
+        sink.log(Statistics::log_iteration);
+        assert!(sink.save().is_ok());
+        assert_eq!(2, in_memory.0.iteration_counter);
+    }
+
+    #[test]
+    fn test_trivial_quotient_counts() {
+        let mut statistics = Statistics::new(StatisticsLevel::Basic, 4);
+        for trivial_partition in [
+            TrivialPartition::Identity,
+            TrivialPartition::Identity,
+            TrivialPartition::SingleOrbit,
+        ] {
+            statistics.log_quotient_statistic(QuotientStatistics::from_trivial_partition(
+                trivial_partition,
+                4,
+                None,
+                1,
+                Duration::ZERO,
+                Duration::ZERO,
+            ));
+        }
+
+        let mut no_edges = QuotientStatistics::from_trivial_partition(
+            TrivialPartition::Identity,
+            4,
+            None,
+            1,
+            Duration::ZERO,
+            Duration::ZERO,
+        );
+        no_edges.trivial_partition = None;
+        no_edges.trivial_encoding = Some(TrivialReason::NoQuotientEdges);
+        statistics.log_quotient_statistic(no_edges);
+
+        assert_eq!(2, statistics.identity_quotients);
+        assert_eq!(1, statistics.single_orbit_quotients);
+        assert_eq!(1, statistics.no_edge_quotients);
+        assert_eq!(0, statistics.complete_edge_quotients);
+        assert_eq!(4, statistics.max_quotient_graph_size);
+    }
+
+    #[test]
+    fn test_core_iteration() {
+        let iteration = CoreIteration::new("recolor", 2, &[0, 0, 2, 0, 4, 4]);
+        assert_eq!(
+            CoreIteration {
+                strategy: "recolor",
+                generators: 2,
+                orbits: 3,
+                max_orbit_size: 3,
+            },
+            iteration
+        );
+        assert_eq!(0, CoreIteration::new("recolor", 0, &[]).max_orbit_size);
+    }
+
+    #[test]
+    fn test_quotient_invariants() {
+        // Two disjoint paths of three vertices, whose middle
+        // vertices are joined, with the swap of the path ends.
+        let mut graph = Graph::new_ordered(7);
+        for (start, end) in [(0, 1), (1, 2), (3, 4), (4, 5), (1, 4)] {
+            graph.add_edge(start, end).unwrap();
+        }
+        let orbits = vec![0, 1, 0, 3, 4, 3, 6];
+        let quotient = QuotientGraph::from_graph_orbits(&graph, orbits);
+
+        let invariants = QuotientInvariants::from_quotient_graph(&quotient.quotient_graph);
+        assert_eq!(3, invariants.edges);
+        assert_eq!(2, invariants.components);
+        assert_eq!(0, invariants.min_degree);
+        assert_eq!(2, invariants.max_degree);
+        assert_eq!(0.3, invariants.density);
+        assert_eq!(1.2, invariants.mean_degree);
+    }
+
+    #[test]
+    fn test_prediction_statistics() {
+        let mut predictions = PredictionStatistics::default();
+        predictions.log_prediction(Prediction::NonDescriptive, None);
+        predictions.log_prediction(Prediction::NonDescriptive, Some(true));
+        predictions.log_prediction(Prediction::Descriptive, Some(true));
+
+        assert_eq!(3, predictions.confident);
+        assert_eq!(1, predictions.skipped);
+        assert_eq!(1, predictions.mispredicted);
+    }
+
+    #[test]
+    fn test_core_frequencies() {
+        let mut frequencies = CoreFrequencies::default();
+        frequencies.log_core(&[(0, vec![0, 1]), (2, vec![2, 3])]);
+        frequencies.log_core(&[(1, vec![1, 4])]);
+        frequencies.log_core(&[(1, vec![1, 3])]);
+
+        assert_eq!(3, frequencies.number_of_cores);
+        assert_eq!(
+            vec![(1, 3), (3, 2), (0, 1), (2, 1), (4, 1)],
+            frequencies.sorted_frequencies()
+        );
+
+        frequencies.log_cores(&[vec![(0, vec![0, 1])], vec![(4, vec![4, 5])]]);
+        assert_eq!(5, frequencies.number_of_cores);
+        assert_eq!(1, frequencies.number_of_quotients);
+    }
+
+    #[test]
+    fn test_file_statistics_flush_on_abort() {
+        let out_file = std::env::temp_dir().join(format!("dqg_aborted_{}", std::process::id()));
+
+        // Statistics of a mode that doesn't save them aren't written.
+        drop(FileStatistics::new(
+            Statistics::new(StatisticsLevel::Basic, 3),
+            out_file.clone(),
+        ));
+        assert!(!out_file.exists());
+
+        let mut statistics = Statistics::new(StatisticsLevel::Basic, 3);
+        statistics.log_abort("Interrupted by a signal".to_string());
+        drop(FileStatistics::new(statistics, out_file.clone()));
+        let written = std::fs::read_to_string(&out_file).unwrap();
+        std::fs::remove_file(&out_file).unwrap();
+        assert!(written.contains("aborted: \"Interrupted by a signal\""));
+    }
+}
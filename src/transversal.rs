@@ -1,12 +1,18 @@
 use crate::{
-    encoding::QuotientGraphEncoding,
+    encoding::{encode_problem, EncodeOutcome, QuotientGraphEncoding},
     graph::{Graph, VertexIndex},
+    quotient::{Orbits, QuotientGraph},
+    sat_solving::solve_validate,
+    EncodingOptions, Error,
 };
 
+/// The picked vertex of each orbit as (orbit, vertex), ordered by orbit.
+pub type Transversal = Vec<(VertexIndex, VertexIndex)>;
+
 pub fn is_transversal_consistent(
     transversal: &[(VertexIndex, VertexIndex)],
     graph: &Graph,
-    quotient: QuotientGraphEncoding,
+    quotient: &QuotientGraphEncoding,
 ) -> bool {
     for edge in quotient.0.iter() {
         let start = transversal[transversal
@@ -26,6 +32,27 @@ pub fn is_transversal_consistent(
     true
 }
 
+/// A consistent transversal of the quotient induced by the orbits,
+/// or None if the quotient isn't descriptive.
+#[cfg(not(tarpaulin_include))]
+pub fn find_transversal(
+    graph: &Graph,
+    orbits: Orbits,
+    options: &EncodingOptions,
+) -> Result<Option<Transversal>, Error> {
+    let quotient_graph = QuotientGraph::from_graph_orbits(graph, orbits);
+    match encode_problem(&quotient_graph, graph, options)? {
+        EncodeOutcome::Formula(formula, dict) => solve_validate(formula, dict),
+        // Without any constraints, every transversal is consistent.
+        EncodeOutcome::TriviallyDescriptive { .. } => Ok(Some(
+            quotient_graph
+                .iter_orbits()
+                .map(|(orbit, vertices)| (orbit, vertices[0]))
+                .collect(),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -55,19 +82,19 @@ mod test {
         assert!(is_transversal_consistent(
             &transversal1_1,
             &graph,
-            quotient1.clone()
+            &quotient1
         ));
         let transversal1_2 = vec![(0, 5), (2, 6)];
         assert!(is_transversal_consistent(
             &transversal1_2,
             &graph,
-            quotient1.clone()
+            &quotient1
         ));
         let transversal1_3 = vec![(0, 0), (2, 6)];
         assert!(!is_transversal_consistent(
             &transversal1_3,
             &graph,
-            quotient1.clone()
+            &quotient1
         ));
 
         let quotient2: QuotientGraphEncoding = QuotientGraphEncoding(
@@ -83,19 +110,19 @@ mod test {
         assert!(is_transversal_consistent(
             &transversal2_1,
             &graph,
-            quotient2.clone()
+            &quotient2
         ));
         let transversal2_2 = vec![(0, 5), (1, 6), (2, 2), (4, 4)];
         assert!(is_transversal_consistent(
             &transversal2_2,
             &graph,
-            quotient2.clone()
+            &quotient2
         ));
         let transversal2_3 = vec![(0, 0), (1, 6), (2, 2), (4, 4)];
         assert!(!is_transversal_consistent(
             &transversal2_3,
             &graph,
-            quotient2.clone()
+            &quotient2
         ));
 
         Ok(())
@@ -133,11 +160,7 @@ mod test {
                 for pick4 in [4, 6] {
                     for pick5 in [5, 7] {
                         let transversal = vec![(0, pick0), (1, pick1), (4, pick4), (5, pick5)];
-                        assert!(!is_transversal_consistent(
-                            &transversal,
-                            &graph,
-                            quotient.clone()
-                        ));
+                        assert!(!is_transversal_consistent(&transversal, &graph, &quotient));
                     }
                 }
             }
@@ -0,0 +1,104 @@
+//! Bindings for the web demo, built with the `wasm` feature but without
+//! `native`, e.g. `wasm-pack build -- --no-default-features --features wasm`.
+//! The demo takes a graph in dreadnaut syntax and orbits as `vertex,orbit`
+//! pairs, like those written next to exported quotients, and explains why
+//! their quotient is descriptive or not.
+
+use itertools::Itertools;
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    encoding::HighLevelEncoding,
+    graph::VertexIndex,
+    lift::quotient_orbits,
+    parser::{parse_dreadnaut_input, parse_orbits_input},
+    quotient::QuotientGraph,
+    transversal::find_transversal,
+    EncodingOptions, Error,
+};
+
+/// Explain whether the quotient of the graph induced by the orbits is
+/// descriptive: by the vertex that a consistent transversal picks from
+/// each orbit, or by the smallest set of orbits without one.
+#[wasm_bindgen]
+pub fn explain_descriptiveness(graph: &str, orbits: &str) -> Result<String, JsValue> {
+    explain(graph, orbits).map_err(|error| JsValue::from_str(&format!("{:?}", error)))
+}
+
+fn explain(graph: &str, orbits: &str) -> Result<String, Error> {
+    let (mut graph, _, _) = parse_dreadnaut_input(graph.as_bytes())?;
+    graph.minimize();
+    graph.sort();
+    let membership = parse_orbits_input(orbits.as_bytes())?;
+    let (orbits, _) = quotient_orbits(graph.size(), &membership)?;
+
+    let mut explanation = String::new();
+    if let Some(transversal) =
+        find_transversal(&graph, orbits.clone(), &EncodingOptions::default())?
+    {
+        writeln!(
+            explanation,
+            "The quotient is descriptive. These vertices induce it:"
+        )
+        .unwrap();
+        for (orbit, vertex) in transversal {
+            writeln!(explanation, "orbit {}: vertex {}", orbit, vertex).unwrap();
+        }
+        return Ok(explanation);
+    }
+
+    let quotient = QuotientGraph::from_graph_orbits(&graph, orbits.clone());
+    let orbit_number = orbits.encode_high().len();
+    let core = quotient
+        .search_non_descriptive_core(&graph, 1..=orbit_number, true)?
+        .expect("Non-descriptive quotient without core!");
+    writeln!(
+        explanation,
+        "The quotient isn't descriptive. No choice of one vertex from each of these orbits induces the edges between them:"
+    )
+    .unwrap();
+    for (orbit, vertices) in core.1.iter() {
+        writeln!(
+            explanation,
+            "orbit {}: {}",
+            orbit,
+            vertices.iter().join(" ")
+        )
+        .unwrap();
+    }
+    let edges = core
+        .0
+        .iter()
+        .map(|edge| edge.get_edge())
+        .map(|(start, end): (VertexIndex, VertexIndex)| format!("{}-{}", start, end))
+        .join(" ");
+    writeln!(explanation, "quotient edges: {}", edges).unwrap();
+    Ok(explanation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_explain() -> Result<(), Error> {
+        // A path 0-1-2-3 with the orbits of its flip.
+        let path = "n=4 g\n0:1;\n1:2;\n2:3.\nf=[]\n";
+        let flip = "vertex,orbit\n0,0\n1,1\n2,1\n3,0\n";
+        let explanation = explain(path, flip)?;
+        assert!(explanation.starts_with("The quotient is descriptive."));
+        assert!(explanation.contains("orbit 1: vertex"));
+
+        // The quotient of the 6-cycle by its rotation by three is a triangle,
+        // but no three vertices of the cycle form one.
+        let cycle = "n=6 g\n0:1;\n1:2;\n2:3;\n3:4;\n4:5;\n5:0.\nf=[]\n";
+        let rotation = "vertex,orbit\n0,0\n1,1\n2,2\n3,0\n4,1\n5,2\n";
+        let explanation = explain(cycle, rotation)?;
+        assert!(explanation.starts_with("The quotient isn't descriptive."));
+        assert!(explanation.contains("orbit 2: 2 5"));
+
+        assert!(explain(path, "vertex,orbit\n4,0\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Writer for the conflict graph of a non-descriptive core,
+//! either in the dot language or as JSON.
+
+use itertools::Itertools;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{sat_solving::ConflictGraph, Error};
+
+/// One vertex per core orbit, labeled with the orbit and its size.
+pub fn write_conflict_dot(
+    writer: &mut impl Write,
+    conflict_graph: &ConflictGraph,
+) -> Result<(), Error> {
+    writeln!(writer, "graph conflicts {{")?;
+    for (orbit, members) in conflict_graph.orbits.iter() {
+        writeln!(
+            writer,
+            "{} [label=\"{} ({})\"];",
+            orbit,
+            orbit,
+            members.len()
+        )?;
+    }
+    for (start, end) in conflict_graph.edges.iter() {
+        writeln!(writer, "{} -- {};", start, end)?;
+    }
+    writeln!(writer, "}}").map_err(Error::from)
+}
+
+/// The orbits with their vertices and the edges as pairs of orbits.
+pub fn write_conflict_json(
+    writer: &mut impl Write,
+    conflict_graph: &ConflictGraph,
+) -> Result<(), Error> {
+    let orbits = conflict_graph
+        .orbits
+        .iter()
+        .map(|(orbit, members)| {
+            format!(
+                "{{\"orbit\": {}, \"vertices\": [{}]}}",
+                orbit,
+                members.iter().join(", ")
+            )
+        })
+        .join(", ");
+    let edges = conflict_graph
+        .edges
+        .iter()
+        .map(|(start, end)| format!("[{}, {}]", start, end))
+        .join(", ");
+    writeln!(
+        writer,
+        "{{\"orbits\": [{}], \"edges\": [{}]}}",
+        orbits, edges
+    )
+    .map_err(Error::from)
+}
+
+/// Write the conflict graph as JSON if the file ends with `.json` and in the dot language otherwise.
+#[cfg(not(tarpaulin_include))]
+pub fn write_conflict_graph_file(path: &Path, conflict_graph: &ConflictGraph) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        write_conflict_json(&mut writer, conflict_graph)?;
+    } else {
+        write_conflict_dot(&mut writer, conflict_graph)?;
+    }
+    writer.flush().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn conflict_graph() -> ConflictGraph {
+        ConflictGraph {
+            orbits: vec![(0, vec![0, 3]), (1, vec![1, 2]), (4, vec![4])],
+            edges: vec![(0, 1), (1, 4)],
+        }
+    }
+
+    #[test]
+    fn test_write_conflict_dot() -> Result<(), Error> {
+        let mut output = Vec::new();
+        write_conflict_dot(&mut output, &conflict_graph())?;
+        assert_eq!(
+            "graph conflicts {
+0 [label=\"0 (2)\"];
+1 [label=\"1 (2)\"];
+4 [label=\"4 (1)\"];
+0 -- 1;
+1 -- 4;
+}
+",
+            String::from_utf8(output).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_conflict_json() -> Result<(), Error> {
+        let mut output = Vec::new();
+        write_conflict_json(&mut output, &conflict_graph())?;
+        assert_eq!(
+            "{\"orbits\": [{\"orbit\": 0, \"vertices\": [0, 3]}, {\"orbit\": 1, \"vertices\": [1, 2]}, \
+{\"orbit\": 4, \"vertices\": [4]}], \"edges\": [[0, 1], [1, 4]]}\n",
+            String::from_utf8(output).unwrap()
+        );
+        Ok(())
+    }
+}
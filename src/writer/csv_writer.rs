@@ -0,0 +1,67 @@
+//! Writer for graphs as csv edge lists.
+
+use std::io::Write;
+
+use super::exported_edges;
+use crate::{graph::Graph, Error};
+
+/// Write the graph as csv edge list with a column header.
+/// Each edge is only written once from its smaller end,
+/// unless the graph is declared as a digraph.
+pub fn write_csv_output(writer: &mut impl Write, graph: &Graph) -> Result<(), Error> {
+    writeln!(writer, "node_1,node_2")?;
+    for (start, end) in exported_edges(graph) {
+        writeln!(writer, "{},{}", start, end)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parser::parse_csv_input;
+
+    #[test]
+    fn test_write_csv_output() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 3)?;
+        graph.add_edge(1, 0)?;
+        graph.add_edge(2, 1)?;
+
+        let mut output = Vec::new();
+        write_csv_output(&mut output, &graph)?;
+        assert_eq!(
+            "node_1,node_2\n0,1\n0,3\n1,2\n",
+            String::from_utf8(output.clone()).unwrap()
+        );
+
+        let parsed = parse_csv_input(4, BufReader::new(&output[..]))?;
+        assert_eq!(graph.number_edges(), parsed.number_edges());
+        for (start, end) in graph.iterate_edges() {
+            assert!(parsed.lookup_edge(&start, &end));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_csv_output_digraph() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(3);
+        graph.set_directed(true);
+        graph.connect(1, 0)?;
+        graph.connect(1, 2)?;
+        graph.connect(2, 1)?;
+
+        let mut output = Vec::new();
+        write_csv_output(&mut output, &graph)?;
+        assert_eq!(
+            "node_1,node_2\n1,0\n1,2\n2,1\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+}
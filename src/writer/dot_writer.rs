@@ -0,0 +1,226 @@
+//! Writer for graphs in the dot language of Graphviz
+//! with the vertices coloured by their orbits.
+
+use itertools::Itertools;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use super::exported_edges;
+use crate::{
+    encoding::OrbitEncoding,
+    graph::{Graph, VertexIndex},
+    Error,
+};
+
+/// Colours for the orbits, reused from the start if there are more orbits.
+const PALETTE: [&str; 12] = [
+    "red",
+    "green",
+    "blue",
+    "black",
+    "yellow",
+    "orange",
+    "purple",
+    "cyan",
+    "magenta",
+    "brown",
+    "gray",
+    "darkgreen",
+];
+
+fn orbit_colour(position: usize) -> &'static str {
+    PALETTE[position % PALETTE.len()]
+}
+
+/// Write the vertices of the given orbits and the edges between them
+/// in the dot language. Each orbit gets its own colour.
+/// Collapsed orbits are drawn as a single vertex labeled with the orbit
+/// and its size and connected to all orbits that share an edge with it.
+/// Graphs declared as digraphs are drawn with arcs.
+pub fn write_dot_output(
+    writer: &mut impl Write,
+    graph: &Graph,
+    orbits: &[OrbitEncoding],
+    collapse_orbits: bool,
+) -> Result<(), Error> {
+    // Position of the orbit of each drawn vertex.
+    let orbit_positions: HashMap<VertexIndex, usize> = orbits
+        .iter()
+        .enumerate()
+        .flat_map(|(position, (_, members))| members.iter().map(move |member| (*member, position)))
+        .collect();
+
+    let directed = graph.is_declared_directed();
+    let (graph_keyword, edge_operator) = if directed {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+    writeln!(writer, "{} graphname {{", graph_keyword)?;
+
+    if collapse_orbits {
+        for (position, (orbit, members)) in orbits.iter().enumerate() {
+            writeln!(
+                writer,
+                "{} [color={:?}, label=\"{} ({})\"];",
+                orbit,
+                orbit_colour(position),
+                orbit,
+                members.len()
+            )?;
+        }
+
+        let quotient_edges = exported_edges(graph)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let start_position = *orbit_positions.get(&start)?;
+                let end_position = *orbit_positions.get(&end)?;
+                let (start_orbit, end_orbit) = (orbits[start_position].0, orbits[end_position].0);
+                if directed {
+                    Some((start_orbit, end_orbit))
+                } else {
+                    Some((start_orbit.min(end_orbit), start_orbit.max(end_orbit)))
+                }
+            })
+            .sorted()
+            .dedup();
+        for (start, end) in quotient_edges {
+            writeln!(writer, "{} {} {};", start, edge_operator, end)?;
+        }
+    } else {
+        for (position, (_, members)) in orbits.iter().enumerate() {
+            for vertex in members {
+                writeln!(writer, "{} [color={:?}];", vertex, orbit_colour(position))?;
+            }
+        }
+
+        for (start, end) in exported_edges(graph) {
+            if orbit_positions.contains_key(&start) && orbit_positions.contains_key(&end) {
+                writeln!(writer, "{} {} {};", start, edge_operator, end)?;
+            }
+        }
+    }
+
+    writeln!(writer, "}}").map_err(Error::from)
+}
+
+/// Write the graph with the given orbits as dot file.
+#[cfg(not(tarpaulin_include))]
+pub fn write_dot_file(
+    path: &Path,
+    graph: &Graph,
+    orbits: &[OrbitEncoding],
+    collapse_orbits: bool,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_dot_output(&mut writer, graph, orbits, collapse_orbits)?;
+    writer.flush().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_graph() -> Result<Graph, Error> {
+        // 0 -- 1 -- 2 -- 3 with the orbits {0,3} and {1,2}
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(1, 2)?;
+        graph.add_edge(2, 3)?;
+        Ok(graph)
+    }
+
+    #[test]
+    fn test_write_dot_output() -> Result<(), Error> {
+        let graph = test_graph()?;
+        let orbits = vec![(0, vec![0, 3]), (1, vec![1, 2])];
+
+        let mut output = Vec::new();
+        write_dot_output(&mut output, &graph, &orbits, false)?;
+        assert_eq!(
+            "graph graphname {
+0 [color=\"red\"];
+3 [color=\"red\"];
+1 [color=\"green\"];
+2 [color=\"green\"];
+0 -- 1;
+1 -- 2;
+2 -- 3;
+}
+",
+            String::from_utf8(output).unwrap()
+        );
+
+        // Only draw the core orbit {1,2}.
+        let mut output = Vec::new();
+        write_dot_output(&mut output, &graph, &orbits[1..], false)?;
+        assert_eq!(
+            "graph graphname {
+1 [color=\"red\"];
+2 [color=\"red\"];
+1 -- 2;
+}
+",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_dot_output_collapsed() -> Result<(), Error> {
+        let graph = test_graph()?;
+        let orbits = vec![(0, vec![0, 3]), (1, vec![1, 2])];
+
+        let mut output = Vec::new();
+        write_dot_output(&mut output, &graph, &orbits, true)?;
+        assert_eq!(
+            "graph graphname {
+0 [color=\"red\", label=\"0 (2)\"];
+1 [color=\"green\", label=\"1 (2)\"];
+0 -- 1;
+1 -- 1;
+}
+",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_dot_output_digraph() -> Result<(), Error> {
+        // The directed 4-cycle 0 -> 1 -> 2 -> 3 -> 0 with the orbits {0,2} and {1,3}
+        let mut graph = Graph::new_ordered(4);
+        graph.set_directed(true);
+        for vertex in 0..4 {
+            graph.connect(vertex, (vertex + 1) % 4)?;
+        }
+        let orbits = vec![(0, vec![0, 2]), (1, vec![1, 3])];
+
+        let mut output = Vec::new();
+        write_dot_output(&mut output, &graph, &orbits, true)?;
+        assert_eq!(
+            "digraph graphname {
+0 [color=\"red\", label=\"0 (2)\"];
+1 [color=\"green\", label=\"1 (2)\"];
+0 -> 1;
+1 -> 0;
+}
+",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_orbit_colour_cycles() {
+        assert_eq!(orbit_colour(0), orbit_colour(PALETTE.len()));
+        assert_ne!(orbit_colour(0), orbit_colour(1));
+    }
+}
@@ -0,0 +1,185 @@
+//! Writer for graphs in dreadnaut syntax.
+//! The output can be read by this tool as well
+//! as by standalone dreadnaut/Traces.
+
+use itertools::Itertools;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{graph::Graph, Error};
+
+/// Write the header that tells dreadnaut to use Traces.
+fn write_header(writer: &mut impl Write) -> Result<(), Error> {
+    writeln!(writer, "At")?;
+    writeln!(writer)?;
+    writeln!(writer, "-a")?;
+    writeln!(writer, "-m").map_err(Error::from)
+}
+
+/// Write the edges as `s:e1 e2 ... en;` lines and end the last one with a `.`.
+/// Each edge is only written from its smaller end, while the arcs of a
+/// declared digraph are all written from their start.
+fn write_edges(writer: &mut impl Write, graph: &Graph) -> Result<(), Error> {
+    let directed = graph.is_declared_directed();
+    let edge_lines = graph
+        .vertices
+        .iter()
+        .sorted()
+        .filter_map(|vertex| {
+            let ends = vertex
+                .edges_to
+                .iter()
+                .filter(|end| directed || **end > vertex.index)
+                .sorted()
+                .dedup()
+                .collect_vec();
+            if ends.is_empty() {
+                None
+            } else {
+                Some((vertex.index, ends))
+            }
+        })
+        .collect_vec();
+
+    if edge_lines.is_empty() {
+        return writeln!(writer, ".").map_err(Error::from);
+    }
+
+    let last = edge_lines.len() - 1;
+    for (line, (start, ends)) in edge_lines.into_iter().enumerate() {
+        write!(writer, "{}:{}", start, ends.into_iter().join(" "))?;
+        writeln!(writer, "{}", if line == last { "." } else { ";" })?;
+    }
+
+    Ok(())
+}
+
+/// Write the colouring as `f=[c11,...,c1n|...|cp1,...,cpk]`.
+/// The cells are ordered by increasing colour values.
+fn write_colouring(writer: &mut impl Write, graph: &Graph) -> Result<(), Error> {
+    let cells = graph
+        .vertices
+        .iter()
+        .sorted_by_key(|vertex| (vertex.colour, vertex.index))
+        .chunk_by(|vertex| vertex.colour);
+
+    let cells = cells
+        .into_iter()
+        .map(|(_, cell)| cell.map(|vertex| vertex.index).join(","))
+        .collect_vec();
+
+    // A single cell is the default partition.
+    let cells = if cells.len() > 1 {
+        cells.join("|")
+    } else {
+        String::new()
+    };
+
+    writeln!(writer, "f=[{}] x o", cells).map_err(Error::from)
+}
+
+/// Write the graph with its colouring in dreadnaut syntax.
+/// If `traces_header` is set, the output starts with the
+/// header that selects Traces instead of nauty.
+pub fn write_dreadnaut_output(
+    writer: &mut impl Write,
+    graph: &Graph,
+    traces_header: bool,
+) -> Result<(), Error> {
+    if traces_header {
+        write_header(writer)?;
+    }
+
+    if graph.is_declared_directed() {
+        writeln!(writer, "d")?;
+    }
+    writeln!(writer, "n={} g", graph.size())?;
+    write_edges(writer, graph)?;
+    write_colouring(writer, graph)
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn write_dreadnaut_file(path: &Path, graph: &Graph, traces_header: bool) -> Result<(), Error> {
+    let mut dre_file = BufWriter::new(File::create(path)?);
+    write_dreadnaut_output(&mut dre_file, graph, traces_header)?;
+    dre_file.flush().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parser::parse_dreadnaut_input;
+
+    #[test]
+    fn test_write_dreadnaut_output() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(4);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(0, 2)?;
+        graph.add_edge(2, 3)?;
+        graph.set_colours(&[2, 1, 1, 2])?;
+
+        let mut output = Vec::new();
+        write_dreadnaut_output(&mut output, &graph, true)?;
+        let expected = "At\n\n-a\n-m\nn=4 g\n0:1 2;\n2:3.\nf=[1,2|0,3] x o\n";
+        assert_eq!(expected, String::from_utf8(output.clone()).unwrap());
+
+        let (parsed_graph, has_header, _) = parse_dreadnaut_input(BufReader::new(&output[..]))?;
+        assert!(has_header);
+        assert_eq!(graph.size(), parsed_graph.size());
+        for (start, end) in graph.iterate_edges() {
+            assert!(parsed_graph.lookup_edge(&start, &end));
+        }
+        assert_eq!(graph.number_edges(), parsed_graph.number_edges());
+        let parsed_colours = parsed_graph
+            .vertices
+            .iter()
+            .map(|vertex| vertex.colour)
+            .collect_vec();
+        assert_eq!(vec![2, 1, 1, 2], parsed_colours);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_directed_graph() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(3);
+        graph.set_directed(true);
+        graph.connect(2, 0)?;
+        graph.connect(0, 1)?;
+
+        let mut output = Vec::new();
+        write_dreadnaut_output(&mut output, &graph, false)?;
+        assert_eq!(
+            "d\nn=3 g\n0:1;\n2:0.\nf=[] x o\n",
+            String::from_utf8(output.clone()).unwrap()
+        );
+
+        let (parsed_graph, _, _) = parse_dreadnaut_input(BufReader::new(&output[..]))?;
+        assert!(parsed_graph.is_declared_directed());
+        assert!(parsed_graph.lookup_edge(&2, &0));
+        assert!(!parsed_graph.lookup_edge(&0, &2));
+        assert_eq!(2, parsed_graph.number_edges());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_uncoloured_graph() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(3);
+        graph.add_edge(1, 2)?;
+
+        let mut output = Vec::new();
+        write_dreadnaut_output(&mut output, &graph, false)?;
+        assert_eq!(
+            "n=3 g\n1:2.\nf=[] x o\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+}
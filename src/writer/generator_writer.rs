@@ -0,0 +1,95 @@
+//! Writer for the generators of the automorphism group, so that other tools
+//! can use them: as GAP group, as JSON arrays or in cycle notation.
+
+use itertools::Itertools;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{gap::write_group, permutation::Permutation, Error};
+
+/// The image of each vertex for each generator, as JSON array of arrays.
+pub fn write_generators_json(
+    writer: &mut impl Write,
+    generators: &[Permutation],
+) -> Result<(), Error> {
+    let generators = generators
+        .iter()
+        .map(|generator| format!("[{}]", generator.raw.iter().join(", ")))
+        .join(", ");
+    writeln!(writer, "[{}]", generators).map_err(Error::from)
+}
+
+/// One generator per line in cycle notation, with the vertices numbered from 0.
+pub fn write_generators_cycles(
+    writer: &mut impl Write,
+    generators: &[Permutation],
+) -> Result<(), Error> {
+    for generator in generators {
+        writeln!(writer, "{}", generator)?;
+    }
+    Ok(())
+}
+
+/// Write the generators as GAP group for `.g` and `.gap` files,
+/// as JSON for `.json` files and in cycle notation otherwise.
+#[cfg(not(tarpaulin_include))]
+pub fn write_generators_file(path: &Path, generators: &[Permutation]) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        // GAP can't build a group from an empty list.
+        Some("g" | "gap") if generators.is_empty() => writeln!(writer, "g:=Group(());;")?,
+        Some("g" | "gap") => write_group(&mut writer, generators.to_vec())?,
+        Some("json") => write_generators_json(&mut writer, generators)?,
+        _ => write_generators_cycles(&mut writer, generators)?,
+    }
+    writer.flush().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn generators() -> Vec<Permutation> {
+        vec![
+            Permutation::new(vec![1, 0, 2, 3]).unwrap(),
+            Permutation::new(vec![0, 2, 3, 1]).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write_generators_json() -> Result<(), Error> {
+        let mut output = Vec::new();
+        write_generators_json(&mut output, &generators())?;
+        assert_eq!(
+            "[[1, 0, 2, 3], [0, 2, 3, 1]]\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        let mut output = Vec::new();
+        write_generators_json(&mut output, &[])?;
+        assert_eq!("[]\n", String::from_utf8(output).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_generators_gap() -> Result<(), Error> {
+        let mut output = Vec::new();
+        write_group(&mut output, generators())?;
+        assert_eq!(
+            "g:=Group([(1,2),\n(2,3,4),\n]);;\n",
+            String::from_utf8(output).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_generators_cycles() -> Result<(), Error> {
+        let mut output = Vec::new();
+        write_generators_cycles(&mut output, &generators())?;
+        assert_eq!("(0 1)\n(1 2 3)\n", String::from_utf8(output).unwrap());
+        Ok(())
+    }
+}
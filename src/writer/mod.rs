@@ -0,0 +1,35 @@
+//! Writers that export graphs into the
+//! file formats understood by the parsers.
+use itertools::Itertools;
+
+use crate::graph::{Graph, VertexIndex};
+
+mod conflict_writer;
+mod csv_writer;
+mod dot_writer;
+mod dre_writer;
+#[cfg(feature = "native")]
+mod generator_writer;
+mod quotient_writer;
+mod txt_writer;
+
+pub use conflict_writer::write_conflict_graph_file;
+pub use csv_writer::write_csv_output;
+pub use dot_writer::write_dot_file;
+pub use dre_writer::write_dreadnaut_file;
+#[cfg(feature = "native")]
+pub use generator_writer::write_generators_file;
+pub use quotient_writer::{write_quotient_files, QuotientFormat};
+pub use txt_writer::write_txt_output;
+
+/// All edges to export, each edge of an undirected graph only once
+/// from its smaller end, but every arc of a declared digraph.
+fn exported_edges(graph: &Graph) -> Vec<(VertexIndex, VertexIndex)> {
+    let directed = graph.is_declared_directed();
+    graph
+        .iterate_edges()
+        .filter(|(start, end)| directed || start < end)
+        .sorted()
+        .dedup()
+        .collect_vec()
+}
@@ -0,0 +1,160 @@
+//! Export of quotient graphs as edge lists between
+//! orbits together with the orbit membership of each vertex.
+
+use itertools::Itertools;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use super::{write_csv_output, write_txt_output};
+use crate::{
+    graph::{Graph, VertexIndex},
+    quotient::QuotientGraph,
+    Error,
+};
+
+/// File formats of exported quotient graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotientFormat {
+    Csv,
+    Txt,
+}
+
+impl QuotientFormat {
+    /// The format given by the extension of the path.
+    pub fn of_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => Ok(Self::Csv),
+            Some("txt") => Ok(Self::Txt),
+            _ => Err(Error::ConfigError(format!(
+                "Can't export a quotient to {}, expected a .csv or .txt file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Relabel the orbits to consecutive ids in the order of their
+/// representatives so that the parsers can read the quotient again.
+/// Returns the relabeled quotient graph and the orbit id of each vertex.
+fn relabel_orbits(quotient_graph: &QuotientGraph) -> (Graph, Vec<(VertexIndex, VertexIndex)>) {
+    let representatives = quotient_graph
+        .orbits
+        .iter()
+        .filter(|orbit| **orbit >= 0)
+        .copied()
+        .sorted()
+        .dedup()
+        .collect_vec();
+    let orbit_id = |orbit: VertexIndex| {
+        representatives
+            .binary_search(&orbit)
+            .expect("Orbit without representative!") as VertexIndex
+    };
+
+    let mut relabeled = Graph::new_ordered(representatives.len());
+    relabeled.set_directed(quotient_graph.quotient_graph.is_declared_directed());
+    for (start, end) in quotient_graph.quotient_graph.iterate_edges() {
+        relabeled
+            .add_arc(orbit_id(start), orbit_id(end))
+            .expect("Orbit ids out of range!");
+    }
+    relabeled.minimize();
+
+    let membership = quotient_graph
+        .orbits
+        .iter()
+        .enumerate()
+        .filter(|(_, orbit)| **orbit >= 0)
+        .map(|(vertex, orbit)| (vertex as VertexIndex, orbit_id(*orbit)))
+        .collect_vec();
+
+    (relabeled, membership)
+}
+
+/// Write the orbit id of each vertex as csv.
+fn write_orbit_membership(
+    writer: &mut impl Write,
+    membership: &[(VertexIndex, VertexIndex)],
+) -> Result<(), Error> {
+    writeln!(writer, "vertex,orbit")?;
+    for (vertex, orbit) in membership {
+        writeln!(writer, "{},{}", vertex, orbit)?;
+    }
+
+    Ok(())
+}
+
+/// Write the quotient graph in the format given by the extension
+/// of the path (csv or txt). The orbit membership is written next to
+/// it into a file with the extension `orbits.csv`.
+#[cfg(not(tarpaulin_include))]
+pub fn write_quotient_files(path: &Path, quotient_graph: &QuotientGraph) -> Result<(), Error> {
+    let format = QuotientFormat::of_path(path)?;
+    let (relabeled, membership) = relabel_orbits(quotient_graph);
+
+    let mut quotient_file = BufWriter::new(File::create(path)?);
+    match format {
+        QuotientFormat::Csv => write_csv_output(&mut quotient_file, &relabeled)?,
+        QuotientFormat::Txt => write_txt_output(&mut quotient_file, &relabeled)?,
+    }
+    quotient_file.flush()?;
+
+    let mut orbit_file = BufWriter::new(File::create(path.with_extension("orbits.csv"))?);
+    write_orbit_membership(&mut orbit_file, &membership)?;
+    orbit_file.flush().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quotient_format() {
+        assert_eq!(
+            Some(QuotientFormat::Csv),
+            QuotientFormat::of_path(Path::new("quotient.csv")).ok()
+        );
+        assert_eq!(
+            Some(QuotientFormat::Txt),
+            QuotientFormat::of_path(Path::new("dir/quotient.txt")).ok()
+        );
+        assert!(QuotientFormat::of_path(Path::new("quotient.dre")).is_err());
+        assert!(QuotientFormat::of_path(Path::new("quotient")).is_err());
+    }
+
+    #[test]
+    fn test_relabel_orbits() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(6);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(0, 2)?;
+        graph.add_edge(3, 4)?;
+        graph.add_edge(3, 5)?;
+        graph.add_edge(0, 3)?;
+
+        let orbits = vec![0, 1, 1, 0, 1, 1];
+        let quotient_graph = QuotientGraph::from_graph_orbits(&graph, orbits);
+        let (relabeled, membership) = relabel_orbits(&quotient_graph);
+
+        let mut expected = Graph::new_ordered(2);
+        expected.add_edge(0, 1)?;
+        assert_eq!(expected.size(), relabeled.size());
+        assert!(relabeled.lookup_edge(&0, &1));
+        assert!(relabeled.lookup_edge(&1, &0));
+        assert_eq!(
+            vec![(0, 0), (1, 1), (2, 1), (3, 0), (4, 1), (5, 1)],
+            membership
+        );
+
+        let mut output = Vec::new();
+        write_orbit_membership(&mut output, &membership[..2])?;
+        assert_eq!(
+            "vertex,orbit\n0,0\n1,1\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+//! Writer for graphs in the SNAP based txt format
+//! from https://snap.stanford.edu/data/ .
+
+use std::io::Write;
+
+use super::exported_edges;
+use crate::{graph::Graph, Error};
+
+/// Write the graph with the comment header expected by the txt parser.
+/// Each edge is only written once from its smaller end,
+/// unless the graph is declared as a digraph.
+pub fn write_txt_output(writer: &mut impl Write, graph: &Graph) -> Result<(), Error> {
+    let edges = exported_edges(graph);
+
+    if graph.is_declared_directed() {
+        writeln!(writer, "# Directed graph (each arc is saved once)")?;
+    } else {
+        writeln!(
+            writer,
+            "# Undirected graph (each unordered pair of nodes is saved once)"
+        )?;
+    }
+    writeln!(writer, "# Written by DQG")?;
+    writeln!(writer, "# Nodes: {} Edges: {}", graph.size(), edges.len())?;
+    writeln!(writer, "# FromNodeId\tToNodeId")?;
+    for (start, end) in edges {
+        writeln!(writer, "{}\t{}", start, end)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parser::parse_txt_input;
+
+    #[test]
+    fn test_write_txt_output() -> Result<(), Error> {
+        let mut graph = Graph::new_ordered(6);
+        graph.add_edge(0, 1)?;
+        graph.add_edge(2, 3)?;
+        graph.add_edge(1, 4)?;
+        graph.add_edge(2, 5)?;
+
+        let mut output = Vec::new();
+        write_txt_output(&mut output, &graph)?;
+
+        let parsed = parse_txt_input(BufReader::new(&output[..]))?;
+        assert_eq!(graph, parsed);
+
+        Ok(())
+    }
+}